@@ -1,11 +1,22 @@
 //! contains the QuickJsRuntimeBuilder which may be used to instantiate a new QuickjsRuntimeFacade
 
 use crate::facades::QuickJsRuntimeFacade;
+#[cfg(feature = "console")]
+use crate::features::console::ConsoleLevel;
+use crate::quickjs_utils::dates;
 use crate::quickjsrealmadapter::QuickJsRealmAdapter;
 use crate::quickjsruntimeadapter::QuickJsRuntimeAdapter;
+use crate::quickjsvalueadapter::QuickJsValueAdapter;
 
-use crate::jsutils::modules::{CompiledModuleLoader, NativeModuleLoader, ScriptModuleLoader};
+use crate::jsutils::helper_tasks::HelperTaskQueuePolicy;
+use crate::jsutils::modules::{
+    BytecodeCache, CompiledModuleLoader, FileSystemModuleLoader, NativeModuleLoader,
+    ScriptModuleLoader,
+};
+use crate::jsutils::promises::ResolvingPromiseStatsListener;
 use crate::jsutils::{JsError, ScriptPreProcessor};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 pub type EsRuntimeInitHooks =
@@ -24,18 +35,45 @@ pub struct QuickJsRuntimeBuilder {
     pub(crate) script_module_loaders: Vec<Box<dyn ScriptModuleLoader + Send>>,
     pub(crate) native_module_loaders: Vec<Box<dyn NativeModuleLoader + Send>>,
     pub(crate) compiled_module_loaders: Vec<Box<dyn CompiledModuleLoader + Send>>,
+    #[allow(clippy::type_complexity)]
+    pub(crate) native_modules: Vec<(
+        String,
+        Box<
+            dyn Fn(&QuickJsRealmAdapter) -> Result<Vec<(String, QuickJsValueAdapter)>, JsError>
+                + Send,
+        >,
+    )>,
     pub(crate) opt_memory_limit_bytes: Option<u64>,
     pub(crate) opt_gc_threshold: Option<u64>,
     pub(crate) opt_max_stack_size: Option<u64>,
     pub(crate) opt_gc_interval: Option<Duration>,
+    pub(crate) opt_helper_thread_pool_size: Option<usize>,
+    pub(crate) opt_helper_thread_count: Option<usize>,
+    pub(crate) opt_helper_task_queue_max: Option<usize>,
+    pub(crate) helper_task_queue_policy: HelperTaskQueuePolicy,
+    pub(crate) module_cache_enabled: bool,
+    pub(crate) opt_module_root: Option<String>,
+    pub(crate) resolving_promise_stats_enabled: bool,
+    pub(crate) resolving_promise_stats_listener: Option<Box<dyn ResolvingPromiseStatsListener>>,
     pub(crate) runtime_init_hooks: EsRuntimeInitHooks,
     pub(crate) script_pre_processors: Vec<Box<dyn ScriptPreProcessor + Send>>,
+    pub(crate) opt_bytecode_cache: Option<Box<dyn BytecodeCache + Send>>,
     #[allow(clippy::type_complexity)]
     pub(crate) interrupt_handler: Option<Box<dyn Fn(&QuickJsRuntimeAdapter) -> bool + Send>>,
+    #[allow(clippy::type_complexity)]
+    pub(crate) promise_rejection_handler: Option<
+        Box<dyn Fn(&QuickJsRealmAdapter, QuickJsValueAdapter, QuickJsValueAdapter, bool) + Send>,
+    >,
+    #[cfg(feature = "console")]
+    #[allow(clippy::type_complexity)]
+    pub(crate) console_handler:
+        Option<Box<dyn Fn(&QuickJsRealmAdapter, ConsoleLevel, Vec<QuickJsValueAdapter>) + Send>>,
 }
 
 impl QuickJsRuntimeBuilder {
     /// build an EsRuntime
+    /// # Panics
+    /// panics if the helper thread options are invalid, e.g. a helper_thread_pool_size, helper_thread_count or helper_task_queue_max of 0
     pub fn build(self) -> QuickJsRuntimeFacade {
         log::debug!("QuickJsRuntimeBuilder.build");
         QuickJsRuntimeFacade::new(self)
@@ -47,13 +85,26 @@ impl QuickJsRuntimeBuilder {
             script_module_loaders: vec![],
             native_module_loaders: vec![],
             compiled_module_loaders: vec![],
+            native_modules: vec![],
             opt_memory_limit_bytes: None,
             opt_gc_threshold: None,
             opt_max_stack_size: None,
             opt_gc_interval: None,
+            opt_helper_thread_pool_size: None,
+            opt_helper_thread_count: None,
+            opt_helper_task_queue_max: None,
+            helper_task_queue_policy: HelperTaskQueuePolicy::Block,
+            module_cache_enabled: false,
+            opt_module_root: None,
+            resolving_promise_stats_enabled: false,
+            resolving_promise_stats_listener: None,
             runtime_init_hooks: vec![],
             script_pre_processors: vec![],
+            opt_bytecode_cache: None,
             interrupt_handler: None,
+            promise_rejection_handler: None,
+            #[cfg(feature = "console")]
+            console_handler: None,
         }
     }
 
@@ -88,7 +139,79 @@ impl QuickJsRuntimeBuilder {
         self
     }
 
+    /// add a FileSystemModuleLoader which loads .mjs and .js modules from the given root dirs
+    /// # Example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// let rt = QuickJsRuntimeBuilder::new()
+    ///     .file_system_module_loader(vec!["./scripts", "./lib"])
+    ///     .build();
+    /// ```
+    pub fn file_system_module_loader<P: Into<PathBuf>, I: IntoIterator<Item = P>>(
+        self,
+        roots: I,
+    ) -> Self {
+        self.script_module_loader(FileSystemModuleLoader::new(roots))
+    }
+
+    /// add a native module which can be imported by name, the factory is called to create the exports when the module is imported in a realm
+    /// # Example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::jsutils::Script;
+    /// let rt = QuickJsRuntimeBuilder::new()
+    ///     .native_module("host:math", |realm| {
+    ///         let double = realm.create_function("double", |realm, _this, args| realm.create_i32(args[0].to_i32() * 2), 1)?;
+    ///         Ok(vec![("double".to_string(), double)])
+    ///     })
+    ///     .build();
+    /// rt.eval_module_sync(None, Script::new("math.mjs", "import {double} from 'host:math';\nconsole.log(double(21));")).expect("module failed");
+    /// ```
+    pub fn native_module<
+        F: Fn(&QuickJsRealmAdapter) -> Result<Vec<(String, QuickJsValueAdapter)>, JsError>
+            + Send
+            + 'static,
+    >(
+        mut self,
+        name: &str,
+        factory: F,
+    ) -> Self {
+        self.native_modules
+            .push((name.to_string(), Box::new(factory)));
+        self
+    }
+
+    /// cache compiled script modules by their normalized path so loaders are only called once per module and modules are only compiled once
+    /// every realm also remembers the imports which could not be resolved by any loader so loaders are not asked again
+    /// the cache may be invalidated with QuickJsRealmAdapter::invalidate_cached_module, QuickJsRuntimeFacade::invalidate_cached_module or clear_module_cache
+    pub fn module_cache(mut self, enabled: bool) -> Self {
+        self.module_cache_enabled = enabled;
+        self
+    }
+
+    /// set a root path for modules, relative and absolute imports which resolve to a path outside of the root throw a ReferenceError
+    /// relative imports are always resolved against the path of the importing module before the module loaders are called
+    pub fn module_root(mut self, root: &str) -> Self {
+        self.opt_module_root = Some(root.to_string());
+        self
+    }
+
     /// add a ScriptPreProcessor which will be called for all scripts which are evaluated and compiled
+    /// this includes the source of modules loaded by a ScriptModuleLoader, pre processors are called in the order they were added
+    /// # Example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::jsutils::{JsError, Script};
+    /// let rt = QuickJsRuntimeBuilder::new()
+    ///     .script_pre_processor(|script: &mut Script| -> Result<(), JsError> {
+    ///         let code = script.get_code().replace("__VERSION__", "'1.2.3'");
+    ///         script.set_code(code);
+    ///         Ok(())
+    ///     })
+    ///     .build();
+    /// let res = rt.eval_sync(None, Script::new("version.js", "__VERSION__;")).expect("script failed");
+    /// assert_eq!(res.get_str(), "1.2.3");
+    /// ```
     pub fn script_pre_processor<S: ScriptPreProcessor + Send + 'static>(
         mut self,
         processor: S,
@@ -97,6 +220,13 @@ impl QuickJsRuntimeBuilder {
         self
     }
 
+    /// set a BytecodeCache which is consulted before modules loaded by a ScriptModuleLoader are compiled
+    /// newly compiled modules are put in the cache
+    pub fn bytecode_cache<C: BytecodeCache + Send + 'static>(mut self, cache: C) -> Self {
+        self.opt_bytecode_cache = Some(Box::new(cache));
+        self
+    }
+
     /// add a module loader which can load native functions and proxy classes
     /// # Example
     /// ```rust
@@ -158,18 +288,23 @@ impl QuickJsRuntimeBuilder {
     }
 
     /// set max memory the runtime may use
+    /// when a script exceeds the limit it throws a catchable "InternalError: out of memory", which surfaces as a JsError when not caught
     pub fn memory_limit(mut self, bytes: u64) -> Self {
         self.opt_memory_limit_bytes = Some(bytes);
         self
     }
 
-    /// number of allocations before gc is run
+    /// set the number of bytes which may be allocated before the garbage collector is run automatically
+    /// the QuickJS default is 256 KiB, after every automatic gc run the threshold is raised to 1.5 times the memory in use at that point
+    /// see also [QuickJsRuntimeFacade::set_gc_threshold](crate::facades::QuickJsRuntimeFacade::set_gc_threshold)
     pub fn gc_threshold(mut self, size: u64) -> Self {
         self.opt_gc_threshold = Some(size);
         self
     }
 
-    /// set a max stack size
+    /// set a max stack size in bytes, the QuickJS default is 256 KiB, 0 disables the check
+    /// a script which exceeds the limit (e.g. by unbounded recursion) throws a catchable "InternalError: stack overflow" instead of crashing the process
+    /// the size should stay well below the stack size of the event loop thread (2 MiB by default)
     pub fn max_stack_size(mut self, size: u64) -> Self {
         self.opt_max_stack_size = Some(size);
         self
@@ -181,7 +316,73 @@ impl QuickJsRuntimeBuilder {
         self
     }
 
+    /// set the max number of threads used to run helper tasks (e.g. the producers of resolving promises), additional tasks are queued
+    /// the helper thread pool is shared by all runtimes in the process, the setting is ignored (and an error is logged) once any runtime has run a helper task
+    /// a size of 0 makes [QuickJsRuntimeBuilder::build] panic
+    /// a runtime built with [QuickJsRuntimeBuilder::helper_thread_count] or [QuickJsRuntimeBuilder::helper_task_queue_max] runs its producers in its own pool instead,
+    /// the shared pool is then only used for async producers (e.g. new_resolving_promise_async)
+    /// blocking producers which wait for other helper tasks may deadlock if the pool is too small
+    /// see also [crate::jsutils::helper_tasks::set_helper_thread_pool_size]
+    pub fn helper_thread_pool_size(mut self, size: usize) -> Self {
+        self.opt_helper_thread_pool_size = Some(size);
+        self
+    }
+
+    /// give this runtime its own helper thread pool with at most count threads, these are used to run the producers of resolving promises
+    /// this takes precedence over [QuickJsRuntimeBuilder::helper_thread_pool_size] for the producers of this runtime, a count of 0 makes [QuickJsRuntimeBuilder::build] panic
+    /// see also [crate::jsutils::helper_tasks::HelperPool]
+    pub fn helper_thread_count(mut self, count: usize) -> Self {
+        self.opt_helper_thread_count = Some(count);
+        self
+    }
+
+    /// give this runtime its own helper thread pool in which at most max tasks may wait for a thread, a max of 0 makes [QuickJsRuntimeBuilder::build] panic
+    /// what happens when the queue is full is determined by the [HelperTaskQueuePolicy], by default adding a task blocks until there is room
+    pub fn helper_task_queue_max(mut self, max: usize) -> Self {
+        self.opt_helper_task_queue_max = Some(max);
+        self
+    }
+
+    /// count the promises which are resolved from rust (e.g. by new_resolving_promise), see [QuickJsRuntimeFacade::resolving_promise_stats]
+    pub fn resolving_promise_stats(mut self) -> Self {
+        self.resolving_promise_stats_enabled = true;
+        self
+    }
+
+    /// count the promises which are resolved from rust and notify a listener when they are created or settled
+    pub fn resolving_promise_stats_listener<L: ResolvingPromiseStatsListener + 'static>(
+        mut self,
+        listener: L,
+    ) -> Self {
+        self.resolving_promise_stats_enabled = true;
+        self.resolving_promise_stats_listener = Some(Box::new(listener));
+        self
+    }
+
+    /// set what happens when a task is added to the helper thread pool of this runtime while its queue is full
+    /// see also [QuickJsRuntimeBuilder::helper_task_queue_max]
+    pub fn helper_task_queue_policy(mut self, policy: HelperTaskQueuePolicy) -> Self {
+        self.helper_task_queue_policy = policy;
+        self
+    }
+
     /// add an interrupt handler, this will be called several times during script execution and may be used to cancel a running script
+    /// the handler is polled by QuickJS every few thousand bytecode instructions (and during long running builtins), returning true aborts the running script with an uncatchable InternalError
+    /// the handler is called from the runtime's event loop thread, to abort a script from another thread share an AtomicBool (or similar) with the handler
+    /// # Example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::jsutils::Script;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let cancelled = Arc::new(AtomicBool::new(true));
+    /// let cancelled2 = cancelled.clone();
+    /// let rt = QuickJsRuntimeBuilder::new()
+    ///     .set_interrupt_handler(move |_q_js_rt| cancelled2.load(Ordering::SeqCst))
+    ///     .build();
+    /// assert!(rt.eval_sync(None, Script::new("loop.js", "while(true){}")).is_err());
+    /// ```
     pub fn set_interrupt_handler<I: Fn(&QuickJsRuntimeAdapter) -> bool + Send + 'static>(
         mut self,
         interrupt_handler: I,
@@ -189,6 +390,65 @@ impl QuickJsRuntimeBuilder {
         self.interrupt_handler = Some(Box::new(interrupt_handler));
         self
     }
+
+    /// set a handler which is called when a promise is rejected without a rejection handler, or when a rejection handler is added to an already rejected promise
+    /// the handler receives the realm, the promise, the rejection reason and a bool indicating if the rejection was handled
+    /// when no handler is set unhandled rejections are logged as errors
+    /// # Example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::jsutils::Script;
+    /// use std::sync::{Arc, Mutex};
+    /// let rejections = Arc::new(Mutex::new(vec![]));
+    /// let rejections2 = rejections.clone();
+    /// let rt = QuickJsRuntimeBuilder::new()
+    ///     .set_promise_rejection_handler(move |_realm, _promise, reason, handled| {
+    ///         if !handled {
+    ///             rejections2.lock().unwrap().push(reason.to_string().unwrap());
+    ///         }
+    ///     })
+    ///     .build();
+    /// rt.eval_sync(None, Script::new("reject.js", "Promise.reject('oops');")).expect("script failed");
+    /// assert_eq!(*rejections.lock().unwrap(), vec!["oops".to_string()]);
+    /// ```
+    pub fn set_promise_rejection_handler<
+        H: Fn(&QuickJsRealmAdapter, QuickJsValueAdapter, QuickJsValueAdapter, bool) + Send + 'static,
+    >(
+        mut self,
+        handler: H,
+    ) -> Self {
+        self.promise_rejection_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// set a handler which is called for console.log(), console.info() etc. instead of writing the messages to the log crate
+    /// the handler receives the realm, the level and the arguments of the call
+    /// # Example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::features::console::format_args_q;
+    /// use quickjs_runtime::jsutils::Script;
+    /// use std::sync::{Arc, Mutex};
+    /// let messages = Arc::new(Mutex::new(vec![]));
+    /// let messages2 = messages.clone();
+    /// let rt = QuickJsRuntimeBuilder::new()
+    ///     .set_console_handler(move |realm, _level, args| {
+    ///         messages2.lock().unwrap().push(format_args_q(realm, &args));
+    ///     })
+    ///     .build();
+    /// rt.eval_sync(None, Script::new("hello.js", "console.log('hello %s', 'world');")).expect("script failed");
+    /// assert_eq!(*messages.lock().unwrap(), vec!["hello world".to_string()]);
+    /// ```
+    #[cfg(feature = "console")]
+    pub fn set_console_handler<
+        H: Fn(&QuickJsRealmAdapter, ConsoleLevel, Vec<QuickJsValueAdapter>) + Send + 'static,
+    >(
+        mut self,
+        handler: H,
+    ) -> Self {
+        self.console_handler = Some(Box::new(handler));
+        self
+    }
 }
 
 impl Default for QuickJsRuntimeBuilder {
@@ -234,6 +494,24 @@ impl QuickJsRuntimeBuilder {
         })
     }
 
+    /// set a clock function which returns the current time in milliseconds since the epoch
+    /// Date.now(), new Date() and performance.now() (if present) will observe that time in all realms of the runtime
+    /// # Example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::jsutils::Script;
+    /// let rt = QuickJsRuntimeBuilder::new().set_clock(|| 1317826087123.0).build();
+    /// let res = rt.eval_sync(None, Script::new("clock.js", "new Date().toISOString()")).unwrap();
+    /// assert_eq!(res.get_str(), "2011-10-05T14:48:07.123Z");
+    /// ```
+    pub fn set_clock<C: Fn() -> f64 + Send + Sync + 'static>(self, clock: C) -> Self {
+        let clock = Arc::new(clock);
+        self.realm_adapter_init_hook(move |_rt, realm| {
+            let clock = clock.clone();
+            dates::set_clock_q(realm, move || clock())
+        })
+    }
+
     pub fn compiled_module_loader<S: CompiledModuleLoader + Send + 'static>(
         mut self,
         module_loader: S,
@@ -247,8 +525,11 @@ impl QuickJsRuntimeBuilder {
 pub mod tests {
     use crate::builder::QuickJsRuntimeBuilder;
     use crate::jsutils::modules::ScriptModuleLoader;
-    use crate::jsutils::Script;
+    use crate::jsutils::{JsError, Script};
+    use crate::quickjs_utils::dates;
     use crate::quickjsrealmadapter::QuickJsRealmAdapter;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
 
     #[test]
     fn test_module_loader() {
@@ -284,4 +565,172 @@ pub mod tests {
             Err(e) => panic!("script failed {}", e),
         }
     }
+
+    #[test]
+    fn test_clock() {
+        let millis = Arc::new(AtomicU64::new(1_317_826_087_123_f64.to_bits()));
+        let clock_millis = millis.clone();
+        let rt = QuickJsRuntimeBuilder::new()
+            .set_clock(move || f64::from_bits(clock_millis.load(Ordering::SeqCst)))
+            .build();
+
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_clock.js",
+                    "let a = Date.now(); for (let i = 0; i < 100000; i++) {} let b = Date.now(); JSON.stringify([a, b, new Date().getTime(), new Date(0).getTime(), new Date() instanceof Date, typeof Date()]);",
+                ),
+            )
+            .expect("script failed");
+        assert_eq!(
+            res.get_str(),
+            "[1317826087123,1317826087123,1317826087123,0,true,\"string\"]"
+        );
+
+        millis.store(1_000_f64.to_bits(), Ordering::SeqCst);
+        let now = rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            dates::now_q(q_js_rt.get_main_realm()).expect("now failed")
+        });
+        assert_eq!(now, 1_000_f64);
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let rt = QuickJsRuntimeBuilder::new()
+            .memory_limit(4 * 1024 * 1024)
+            .build();
+        let err = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_memory_limit.js",
+                    "let arr = []; for (let i = 0; i < 10000000; i++) { arr.push({i}); }",
+                ),
+            )
+            .expect_err("script should run out of memory");
+        assert!(err.get_message().contains("out of memory"), "{}", err);
+
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_memory_limit2.js",
+                    "let msg; try { new Array(100000000).fill(0); msg = 'allocated'; } catch (e) { msg = '' + e; } msg;",
+                ),
+            )
+            .expect("script failed");
+        assert!(res.get_str().contains("out of memory"), "{}", res.get_str());
+
+        // the runtime is still usable
+        let res = rt
+            .eval_sync(None, Script::new("test_memory_limit3.js", "1 + 2;"))
+            .expect("script failed");
+        assert_eq!(res.get_i32(), 3);
+    }
+
+    #[test]
+    fn test_max_stack_size() {
+        let rt = QuickJsRuntimeBuilder::new()
+            .max_stack_size(128 * 1024)
+            .build();
+        let err = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_max_stack_size.js",
+                    "function recurse(a) { return recurse(a + 1) + 1; } recurse(0);",
+                ),
+            )
+            .expect_err("stack should overflow");
+        assert!(err.get_message().contains("stack overflow"), "{}", err);
+
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_max_stack_size2.js",
+                    "function recurse2(a) { return recurse2(a + 1) + 1; } let msg; try { recurse2(0); } catch (e) { msg = e.name + ': ' + e.message; } msg;",
+                ),
+            )
+            .expect("script failed");
+        assert!(
+            res.get_str().contains("stack overflow"),
+            "{}",
+            res.get_str()
+        );
+
+        // the runtime is still usable
+        let res = rt
+            .eval_sync(None, Script::new("test_max_stack_size3.js", "1 + 2;"))
+            .expect("script failed");
+        assert_eq!(res.get_i32(), 3);
+    }
+
+    #[test]
+    fn test_script_pre_processor() {
+        struct LetToVar {}
+        impl ScriptModuleLoader for LetToVar {
+            fn normalize_path(
+                &self,
+                _realm: &QuickJsRealmAdapter,
+                _ref_path: &str,
+                path: &str,
+            ) -> Option<String> {
+                (path == "loaded.mes").then(|| path.to_string())
+            }
+
+            fn load_module(&self, _realm: &QuickJsRealmAdapter, _absolute_path: &str) -> String {
+                "{ let fromLoaded = 3; }\nexport const loaded = typeof fromLoaded;".to_string()
+            }
+        }
+
+        let rt = QuickJsRuntimeBuilder::new()
+            .script_module_loader(LetToVar {})
+            .script_pre_processor(|script: &mut Script| -> Result<(), JsError> {
+                if script.get_code().contains("fail_pre_process") {
+                    return Err(JsError::new_str("refusing to process"));
+                }
+                let code = script.get_code().replace("let ", "var ");
+                script.set_code(code);
+                Ok(())
+            })
+            .build();
+
+        // a block scoped let would not be visible outside of the block
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_pre_process.js",
+                    "{ let fromEval = 1; } typeof fromEval;",
+                ),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_str(), "number");
+
+        rt.eval_module_sync(
+            None,
+            Script::new(
+                "test_pre_process.mes",
+                "import {loaded} from 'loaded.mes';\n{ let fromModule = 2; }\nglobalThis.preProcessed = [typeof fromModule, loaded].join();",
+            ),
+        )
+        .expect("module failed");
+        let res = rt
+            .eval_sync(None, Script::new("test_pre_process2.js", "preProcessed;"))
+            .expect("script failed");
+        assert_eq!(res.get_str(), "number,number");
+
+        let err = rt
+            .eval_sync(
+                None,
+                Script::new("test_pre_process_fail.js", "fail_pre_process;"),
+            )
+            .expect_err("pre processor should fail");
+        assert_eq!(
+            err.get_message(),
+            "could not pre-process test_pre_process_fail.js: refusing to process"
+        );
+    }
 }
@@ -1,8 +1,12 @@
 //! contains the QuickJsRuntimeFacade
 
 use crate::builder::QuickJsRuntimeBuilder;
+use crate::jsutils::helper_tasks::{HelperPool, HelperPoolMetrics};
+use crate::jsutils::promises::{
+    new_resolving_promise, ResolvingPromiseStats, ResolvingPromiseStatsSnapshot,
+};
 use crate::jsutils::{JsError, Script};
-use crate::quickjs_utils::{functions, objects};
+use crate::quickjs_utils::{functions, modules, objects, promises};
 use crate::quickjsrealmadapter::QuickJsRealmAdapter;
 use crate::quickjsruntimeadapter::{
     CompiledModuleLoaderAdapter, MemoryUsage, NativeModuleLoaderAdapter, QuickJsRuntimeAdapter,
@@ -11,23 +15,21 @@ use crate::quickjsruntimeadapter::{
 use crate::quickjsvalueadapter::QuickJsValueAdapter;
 use crate::reflection;
 use crate::values::JsValueFacade;
+use futures::future::Either;
 use hirofa_utils::eventloop::EventLoop;
-use hirofa_utils::task_manager::TaskManager;
 use libquickjs_sys as q;
+use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::sync::{Arc, Weak};
+use std::time::Duration;
 use tokio::task::JoinError;
 
-lazy_static! {
-    /// a static Multithreaded task manager used to run rust ops async and multithreaded ( in at least 2 threads)
-    static ref HELPER_TASKS: TaskManager = TaskManager::new(std::cmp::max(2, num_cpus::get()));
-}
-
 impl Drop for QuickJsRuntimeFacade {
     fn drop(&mut self) {
         log::trace!("> EsRuntime::drop");
+        self.cancel_promise_tokens();
         self.clear_contexts();
         log::trace!("< EsRuntime::drop");
     }
@@ -35,9 +37,37 @@ impl Drop for QuickJsRuntimeFacade {
 
 pub struct QuickjsRuntimeFacadeInner {
     event_loop: EventLoop,
+    helper_pool: Option<HelperPool>,
+    resolving_promise_stats: Option<Arc<ResolvingPromiseStats>>,
+    // the number of tasks which were added to the event loop
+    #[cfg(test)]
+    pub(crate) event_loop_task_ct: std::sync::atomic::AtomicUsize,
 }
 
 impl QuickjsRuntimeFacadeInner {
+    /// add a task to the helper thread pool of this runtime, or the shared "helper" thread pool if it has none
+    /// returns an Err if the queue of the pool is full and the HelperTaskQueuePolicy is Reject
+    pub fn add_helper_task<T>(&self, task: T) -> Result<(), JsError>
+    where
+        T: FnOnce() + Send + 'static,
+    {
+        match self.helper_pool.as_ref() {
+            Some(pool) => pool.add_task(task),
+            None => {
+                crate::jsutils::helper_tasks::add_helper_task(task);
+                Ok(())
+            }
+        }
+    }
+
+    /// get the number of queued and running tasks of the helper thread pool used by this runtime
+    pub fn helper_pool_metrics(&self) -> HelperPoolMetrics {
+        match self.helper_pool.as_ref() {
+            Some(pool) => pool.get_metrics(),
+            None => crate::jsutils::helper_tasks::get_helper_pool_metrics(),
+        }
+    }
+
     /// this is how you add a closure to the worker thread which has an instance of the QuickJsRuntime
     /// this will run and return synchronously
     /// # example
@@ -96,6 +126,8 @@ impl QuickjsRuntimeFacadeInner {
     where
         C: FnOnce() + Send + 'static,
     {
+        #[cfg(test)]
+        self.count_event_loop_task();
         self.event_loop.add_void(move || {
             task();
             EventLoop::add_local_void(|| {
@@ -110,6 +142,8 @@ impl QuickjsRuntimeFacadeInner {
     where
         C: FnOnce() -> R + Send + 'static,
     {
+        #[cfg(test)]
+        self.count_event_loop_task();
         self.event_loop.exe(move || {
             let res = task();
             EventLoop::add_local_void(|| {
@@ -125,6 +159,8 @@ impl QuickjsRuntimeFacadeInner {
     where
         C: FnOnce() -> R + Send + 'static,
     {
+        #[cfg(test)]
+        self.count_event_loop_task();
         self.event_loop.add(move || {
             let res = task();
             EventLoop::add_local_void(|| {
@@ -136,6 +172,12 @@ impl QuickjsRuntimeFacadeInner {
         })
     }
 
+    #[cfg(test)]
+    fn count_event_loop_task(&self) {
+        self.event_loop_task_ct
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    }
+
     /// used to add tasks from the worker threads which require run_pending_jobs_if_any to run after it
     #[allow(dead_code)]
     pub(crate) fn add_local_task_to_event_loop<C>(consumer: C)
@@ -168,9 +210,48 @@ pub struct QuickJsRuntimeFacade {
 
 impl QuickJsRuntimeFacade {
     pub(crate) fn new(mut builder: QuickJsRuntimeBuilder) -> Self {
+        if let Some(size) = builder.opt_helper_thread_pool_size {
+            if size == 0 {
+                panic!("helper thread pool size must be at least 1");
+            }
+            if let Err(e) = crate::jsutils::helper_tasks::set_helper_thread_pool_size(size) {
+                log::error!("could not set helper thread pool size to {}: {}", size, e);
+            }
+        }
+
+        let helper_pool = if builder.opt_helper_thread_count.is_some()
+            || builder.opt_helper_task_queue_max.is_some()
+        {
+            let thread_count = builder
+                .opt_helper_thread_count
+                .unwrap_or_else(|| std::cmp::max(2, num_cpus::get()));
+            match HelperPool::new(
+                thread_count,
+                builder.opt_helper_task_queue_max,
+                builder.helper_task_queue_policy,
+            ) {
+                Ok(pool) => Some(pool),
+                Err(e) => panic!("could not create helper pool: {}", e),
+            }
+        } else {
+            None
+        };
+
+        let resolving_promise_stats = if builder.resolving_promise_stats_enabled {
+            Some(Arc::new(ResolvingPromiseStats::new(
+                builder.resolving_promise_stats_listener.take(),
+            )))
+        } else {
+            None
+        };
+
         let ret = Self {
             inner: Arc::new(QuickjsRuntimeFacadeInner {
                 event_loop: EventLoop::new(),
+                helper_pool,
+                resolving_promise_stats: resolving_promise_stats.clone(),
+                #[cfg(test)]
+                event_loop_task_ct: std::sync::atomic::AtomicUsize::new(0),
             }),
         };
 
@@ -189,6 +270,7 @@ impl QuickJsRuntimeFacade {
         ret.exe_task_in_event_loop(move || {
             QuickJsRuntimeAdapter::do_with_mut(move |m_q_js_rt| {
                 m_q_js_rt.init_rti_ref(rti_weak);
+                m_q_js_rt.resolving_promise_stats = resolving_promise_stats;
             })
         });
 
@@ -198,7 +280,8 @@ impl QuickJsRuntimeFacade {
             feature = "settimeout",
             feature = "setinterval",
             feature = "console",
-            feature = "setimmediate"
+            feature = "setimmediate",
+            feature = "queuemicrotask"
         ))]
         {
             let res = crate::features::init(&ret);
@@ -231,6 +314,9 @@ impl QuickJsRuntimeFacade {
 
         ret.exe_task_in_event_loop(move || {
             QuickJsRuntimeAdapter::do_with_mut(|q_js_rt| {
+                for (name, factory) in builder.native_modules {
+                    modules::register_native_module(q_js_rt, name.as_str(), factory);
+                }
                 for native_module_loader in builder.native_module_loaders {
                     q_js_rt.add_native_module_loader(NativeModuleLoaderAdapter::new(
                         native_module_loader,
@@ -247,6 +333,9 @@ impl QuickJsRuntimeFacade {
                     ));
                 }
                 q_js_rt.script_pre_processors = builder.script_pre_processors;
+                q_js_rt.bytecode_cache = builder.opt_bytecode_cache;
+                q_js_rt.set_module_cache_enabled(builder.module_cache_enabled);
+                q_js_rt.set_module_root(builder.opt_module_root);
 
                 if let Some(limit) = builder.opt_memory_limit_bytes {
                     unsafe {
@@ -266,6 +355,13 @@ impl QuickJsRuntimeFacade {
                 if let Some(interrupt_handler) = builder.interrupt_handler {
                     q_js_rt.set_interrupt_handler(interrupt_handler);
                 }
+                if let Some(handler) = builder.promise_rejection_handler {
+                    q_js_rt.set_promise_rejection_handler(handler);
+                }
+                #[cfg(feature = "console")]
+                if let Some(handler) = builder.console_handler {
+                    crate::features::console::set_console_handler(q_js_rt, handler);
+                }
             })
         });
 
@@ -286,6 +382,44 @@ impl QuickJsRuntimeFacade {
         self.loop_async(|rt| rt.memory_usage()).await
     }
 
+    /// get memory usage for this runtime synchronously, the usage is computed in the event loop thread
+    /// # Example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// let rt = QuickJsRuntimeBuilder::new().build();
+    /// let mu = rt.memory_usage_sync();
+    /// assert!(mu.memory_used_size > 0);
+    /// ```
+    pub fn memory_usage_sync(&self) -> MemoryUsage {
+        self.loop_sync(|rt| rt.memory_usage())
+    }
+
+    /// remove a single module from the module cache (see [QuickJsRuntimeBuilder::module_cache]), returns true if the module was cached
+    /// please note that realms which already loaded the module keep using the loaded version
+    pub fn invalidate_cached_module(&self, absolute_path: &str) -> bool {
+        let absolute_path = absolute_path.to_string();
+        self.loop_sync(move |rt| rt.invalidate_cached_module(absolute_path.as_str()))
+    }
+
+    /// remove all modules which were loaded in a realm from the module cache (see [QuickJsRuntimeBuilder::module_cache])
+    /// and forget which imports could not be found in that realm, see [QuickJsRealmAdapter::invalidate_all_cached_modules]
+    pub fn clear_module_cache(&self, realm_id: Option<&str>) {
+        self.loop_realm_sync(realm_id, |_rt, realm| realm.invalidate_all_cached_modules())
+    }
+
+    /// cancel the tokens of all running producers so they may stop before the contexts are cleared
+    fn cancel_promise_tokens(&self) {
+        self.exe_task_in_event_loop(|| {
+            QuickJsRuntimeAdapter::do_with(|q_js_rt| {
+                for id in QuickJsRuntimeAdapter::get_context_ids() {
+                    q_js_rt
+                        .get_context(id.as_str())
+                        .cancel_promise_tokens("runtime was dropped");
+                }
+            })
+        });
+    }
+
     pub(crate) fn clear_contexts(&self) {
         log::trace!("EsRuntime::clear_contexts");
         self.exe_task_in_event_loop(|| {
@@ -366,10 +500,17 @@ impl QuickJsRuntimeFacade {
     }
 
     /// run the garbage collector and wait for it to be done
+    /// this runs a full gc regardless of the gc threshold and does not change the threshold
     pub fn gc_sync(&self) {
         self.exe_rt_task_in_event_loop(|q_js_rt| q_js_rt.gc())
     }
 
+    /// set the number of bytes which may be allocated before the garbage collector is run automatically
+    /// see also [QuickJsRuntimeBuilder::gc_threshold]
+    pub fn set_gc_threshold(&self, bytes: usize) {
+        self.exe_rt_task_in_event_loop(move |q_js_rt| q_js_rt.set_gc_threshold(bytes))
+    }
+
     /// this is how you add a closure to the worker thread which has an instance of the QuickJsRuntime
     /// this will run and return synchronously
     /// # example
@@ -474,16 +615,29 @@ impl QuickJsRuntimeFacade {
     where
         T: FnOnce() + Send + 'static,
     {
-        log::trace!("adding a helper task");
-        HELPER_TASKS.add_task(task);
+        crate::jsutils::helper_tasks::add_helper_task(task);
+    }
+
+    /// get the number of queued and running tasks of the helper thread pool used by this runtime
+    /// if the runtime was not built with its own pool (see [QuickJsRuntimeBuilder::helper_thread_count]) these are the metrics of the shared "helper" thread pool
+    pub fn helper_pool_metrics(&self) -> HelperPoolMetrics {
+        self.inner.helper_pool_metrics()
+    }
+
+    /// get a snapshot of the counts of promises which are resolved from rust (e.g. by new_resolving_promise)
+    /// returns None if the runtime was not built with [QuickJsRuntimeBuilder::resolving_promise_stats]
+    pub fn resolving_promise_stats(&self) -> Option<ResolvingPromiseStatsSnapshot> {
+        self.inner
+            .resolving_promise_stats
+            .as_ref()
+            .map(|stats| stats.snapshot())
     }
 
     /// add an async task the the "helper" thread pool
     pub fn add_helper_task_async<R: Send + 'static, T: Future<Output = R> + Send + 'static>(
         task: T,
     ) -> impl Future<Output = Result<R, JoinError>> {
-        log::trace!("adding an async helper task");
-        HELPER_TASKS.add_task_async(task)
+        crate::jsutils::helper_tasks::add_helper_task_async(task)
     }
 
     /// create a new context besides the always existing main_context
@@ -572,13 +726,15 @@ impl QuickJsRuntimeFacade {
 
     pub fn destroy_realm(&self, name: &str) -> Result<(), JsError> {
         let name = name.to_string();
+        if name.eq("__main__") {
+            return Err(JsError::new_str("the main realm can not be destroyed"));
+        }
         self.exe_task_in_event_loop(move || {
-            QuickJsRuntimeAdapter::do_with_mut(|rt| {
-                if rt.get_realm(name.as_str()).is_some() {
-                    rt.remove_realm(name.as_str());
-                }
-                Ok(())
-            })
+            let exists = QuickJsRuntimeAdapter::do_with(|rt| rt.get_realm(name.as_str()).is_some());
+            if exists {
+                QuickJsRuntimeAdapter::remove_context(name.as_str());
+            }
+            Ok(())
         })
     }
 
@@ -689,6 +845,39 @@ impl QuickJsRuntimeFacade {
         })
     }
 
+    /// Evaluate a script asynchronously and map the result in the event loop
+    /// unlike [QuickJsRuntimeFacade::eval] the mapper gets the raw QuickJsValueAdapter (which is not Send) and may convert it to any Send type
+    /// the returned Future is Send so it may be spawned on a multithreaded async runtime, awaiting it does not block the calling thread
+    /// # Example
+    /// ```rust
+    /// use futures::executor::block_on;
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::jsutils::Script;
+    /// use quickjs_runtime::quickjs_utils::primitives;
+    /// let rt = QuickJsRuntimeBuilder::new().build();
+    /// let fut = rt.eval_async(None, Script::new("my_script.js", "'a' + 'b'"), |realm, value_ref| {
+    ///     primitives::to_string_q(realm, &value_ref)
+    /// });
+    /// assert_eq!(block_on(fut).expect("script failed"), "ab");
+    /// ```
+    pub fn eval_async<
+        R: Send + 'static,
+        M: FnOnce(&QuickJsRealmAdapter, QuickJsValueAdapter) -> Result<R, JsError> + Send + 'static,
+    >(
+        &self,
+        realm_name: Option<&str>,
+        script: Script,
+        mapper: M,
+    ) -> Pin<Box<dyn Future<Output = Result<R, JsError>> + Send>> {
+        let realm_name = realm_name.map(|s| s.to_string());
+        Box::pin(self.add_task_to_event_loop(|| {
+            loop_realm_func(realm_name, move |_rt, realm| {
+                let res = realm.eval(script)?;
+                mapper(realm, res)
+            })
+        }))
+    }
+
     /// Evaluate a script and return the result synchronously
     /// # example
     /// ```rust
@@ -714,6 +903,30 @@ impl QuickJsRuntimeFacade {
         })
     }
 
+    /// Evaluate a script and return the result synchronously, the script is interrupted if it runs longer than timeout
+    /// an interrupted script results in an Err with name InterruptedError
+    /// # Example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::jsutils::Script;
+    /// use std::time::Duration;
+    /// let rt = QuickJsRuntimeBuilder::new().build();
+    /// let script = Script::new("my_file.js", "while (true) {}");
+    /// let err = rt.eval_with_timeout_sync(None, script, Duration::from_millis(10)).expect_err("script should be interrupted");
+    /// assert_eq!(err.get_name(), "InterruptedError");
+    /// ```
+    pub fn eval_with_timeout_sync(
+        &self,
+        realm_name: Option<&str>,
+        script: Script,
+        timeout: Duration,
+    ) -> Result<JsValueFacade, JsError> {
+        self.loop_realm_sync(realm_name, move |_rt, realm| {
+            let res = realm.eval_with_timeout(script, timeout)?;
+            realm.to_js_value_facade(&res)
+        })
+    }
+
     /// evaluate a module, you need this if you want to compile a script that contains static imports
     /// e.g.
     /// ```javascript
@@ -802,6 +1015,44 @@ impl QuickJsRuntimeFacade {
         })
     }
 
+    /// get the names of the modules which were loaded in a realm
+    /// see [QuickJsRealmAdapter::loaded_module_names], returns an empty Vec if the realm does not exist
+    pub fn loaded_modules(&self, realm_name: Option<&str>) -> Vec<String> {
+        let realm_name = realm_name.unwrap_or("__main__").to_string();
+        self.loop_sync(move |rt| {
+            rt.opt_context(realm_name.as_str())
+                .map(|realm| realm.loaded_module_names())
+                .unwrap_or_default()
+        })
+    }
+
+    /// evaluate a module and return its exports by name synchronously, a default export is returned as "default"
+    /// # example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::jsutils::Script;
+    /// let rt = QuickJsRuntimeBuilder::new().build();
+    /// let script = Script::new("exports.mes", "export const name = 'foobar'; export default 12;");
+    /// let exports = rt.eval_module_sync_exports(None, script).expect("module failed");
+    /// assert_eq!(exports.get("name").unwrap().get_str(), "foobar");
+    /// assert_eq!(exports.get("default").unwrap().get_i32(), 12);
+    /// ```
+    pub fn eval_module_sync_exports(
+        &self,
+        realm_name: Option<&str>,
+        script: Script,
+    ) -> Result<HashMap<String, JsValueFacade>, JsError> {
+        self.loop_realm_sync(realm_name, |_rt, realm| {
+            let ns = realm.eval_module_exports(script)?;
+            let mut exports = HashMap::new();
+            objects::traverse_properties_q_mut(realm, &ns, |name, value| {
+                exports.insert(name.to_string(), realm.to_js_value_facade(value)?);
+                Ok(())
+            })?;
+            Ok(exports)
+        })
+    }
+
     /// invoke a function in the engine and get the result synchronously
     /// # example
     /// ```rust
@@ -896,6 +1147,101 @@ impl QuickJsRuntimeFacade {
         })
     }
 
+    /// create a new promise in a realm which is resolved by a producer running in a helper thread
+    /// the result of the producer is mapped to a value by the mapper in the EventQueue thread
+    /// the returned JsValueFacade keeps the promise alive (via the realms object cache) and may be passed to invoke_function or set as a global
+    /// # example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::jsutils::Script;
+    /// use quickjs_runtime::values::{JsValueConvertable, JsValueFacade};
+    /// let rt = QuickJsRuntimeBuilder::new().build();
+    /// rt.eval_sync(None, Script::new("set_global.js", "globalThis.setGlobal = (name, val) => {globalThis[name] = val;};")).expect("script failed");
+    /// let prom = rt.create_resolving_promise(None, || Ok(21), |realm, res| realm.create_i32(res * 2)).expect("could not create promise");
+    /// rt.invoke_function_sync(None, &[], "setGlobal", vec!["answerProm".to_js_value_facade(), prom]).expect("could not set global");
+    /// let res = rt.eval_sync(None, Script::new("await_answer.js", "(async () => {return await answerProm;})();")).expect("script failed");
+    /// match res {
+    ///     JsValueFacade::JsPromise { cached_promise } => {
+    ///         let res = cached_promise.get_promise_result_sync().expect("promise timed out").expect("promise failed");
+    ///         assert_eq!(res.get_i32(), 42);
+    ///     }
+    ///     _ => panic!("not a promise"),
+    /// }
+    /// ```
+    pub fn create_resolving_promise<P, R, M>(
+        &self,
+        realm_name: Option<&str>,
+        producer: P,
+        mapper: M,
+    ) -> Result<JsValueFacade, JsError>
+    where
+        R: Send + 'static,
+        P: FnOnce() -> Result<R, JsError> + Send + 'static,
+        M: FnOnce(&QuickJsRealmAdapter, R) -> Result<QuickJsValueAdapter, JsError> + Send + 'static,
+    {
+        self.loop_realm_sync(realm_name, move |_rt, realm| {
+            let prom = new_resolving_promise(realm, producer, mapper)?;
+            realm.to_js_value_facade(&prom)
+        })
+    }
+
+    /// invoke a function in the engine and await the promise it returns (e.g. an async function)
+    /// if the function does not return a promise its result is returned as is
+    /// a rejection is returned as an Err with the message and stack of the rejection reason
+    /// # example
+    /// ```rust
+    /// use futures::executor::block_on;
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::jsutils::Script;
+    /// use quickjs_runtime::values::JsValueConvertable;
+    /// let rt = QuickJsRuntimeBuilder::new().build();
+    /// let script = Script::new("my_async_file.es", "this.com = {my: {methodA: async function(a, b){await null; return a*b;}}};");
+    /// rt.eval_sync(None, script).ok().expect("script failed");
+    /// let res = block_on(rt.invoke_function_async(None, &["com", "my"], "methodA", vec![7.to_js_value_facade(), 5.to_js_value_facade()])).expect("func failed");
+    /// assert_eq!(res.get_i32(), 35);
+    /// ```
+    pub fn invoke_function_async(
+        &self,
+        realm_name: Option<&str>,
+        namespace: &[&str],
+        method_name: &str,
+        args: Vec<JsValueFacade>,
+    ) -> impl Future<Output = Result<JsValueFacade, JsError>> {
+        let movable_namespace: Vec<String> = namespace.iter().map(|s| s.to_string()).collect();
+        let movable_method_name = method_name.to_string();
+
+        let res_fut = self.loop_realm(realm_name, move |_rt, realm| {
+            let args_adapters = args
+                .into_iter()
+                .map(|jsvf| realm.from_js_value_facade(jsvf))
+                .collect::<Result<Vec<QuickJsValueAdapter>, JsError>>()?;
+
+            let namespace = movable_namespace
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<&str>>();
+
+            let res = realm.invoke_function_by_name(
+                namespace.as_slice(),
+                movable_method_name.as_str(),
+                args_adapters.as_slice(),
+            )?;
+
+            if promises::is_promise_q(realm, &res) {
+                Ok::<_, JsError>(Either::Left(promises::promise_to_future_q(realm, &res)))
+            } else {
+                Ok(Either::Right(realm.to_js_value_facade(&res)))
+            }
+        });
+
+        async move {
+            match res_fut.await? {
+                Either::Left(prom_fut) => prom_fut.await,
+                Either::Right(res) => res,
+            }
+        }
+    }
+
     pub fn invoke_function_void(
         &self,
         realm_name: Option<&str>,
@@ -952,7 +1298,9 @@ lazy_static! {
 #[cfg(test)]
 pub mod tests {
 
+    use crate::builder::QuickJsRuntimeBuilder;
     use crate::facades::QuickJsRuntimeFacade;
+    use crate::jsutils::helper_tasks::HelperTaskQueuePolicy;
     use crate::jsutils::modules::{NativeModuleLoader, ScriptModuleLoader};
     use crate::jsutils::JsError;
     use crate::jsutils::Script;
@@ -964,6 +1312,7 @@ pub mod tests {
     use futures::executor::block_on;
     use log::debug;
     use std::panic;
+    use std::sync::Arc;
     use std::time::Duration;
 
     struct TestNativeModuleLoader {}
@@ -1296,6 +1645,212 @@ pub mod tests {
         let res = block_on(fut);
         assert_eq!(res, 123);
     }
+
+    #[test]
+    fn test_invoke_function_async() {
+        let rt = init_test_rt();
+        rt.eval_sync(
+            None,
+            Script::new(
+                "test_invoke_function_async.es",
+                "this.asyncFacadeNs = {slow: async function(p) {return (await p) + 1;}, fail: async function() {await null; throw Error('facade oops');}};",
+            ),
+        )
+        .expect("script failed");
+        // the promise is resolved from a helper thread so it can not settle by just running jobs
+        let prom = rt
+            .create_resolving_promise(
+                None,
+                || {
+                    std::thread::sleep(Duration::from_millis(10));
+                    Ok(41)
+                },
+                |realm, res| realm.create_i32(res),
+            )
+            .expect("could not create promise");
+        let res = block_on(rt.invoke_function_async(None, &["asyncFacadeNs"], "slow", vec![prom]))
+            .expect("invoke failed");
+        assert_eq!(res.get_i32(), 42);
+
+        let err = block_on(rt.invoke_function_async(None, &["asyncFacadeNs"], "fail", vec![]))
+            .expect_err("invoke did not fail");
+        assert_eq!(err.get_message(), "facade oops");
+        assert!(err.get_stack().contains("test_invoke_function_async.es"));
+    }
+
+    #[test]
+    fn test_helper_pool() {
+        let rt = QuickJsRuntimeBuilder::new()
+            .helper_thread_count(1)
+            .helper_task_queue_max(1)
+            .helper_task_queue_policy(HelperTaskQueuePolicy::Reject)
+            .build();
+        let finished = Arc::new(std::sync::Mutex::new(vec![]));
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let release_rx = Arc::new(std::sync::Mutex::new(release_rx));
+        let create = |nr: i32| {
+            let finished = finished.clone();
+            let started_tx = started_tx.clone();
+            let release_rx = release_rx.clone();
+            rt.create_resolving_promise(
+                None,
+                move || {
+                    started_tx.send(nr).expect("could not send");
+                    if nr == 1 {
+                        release_rx
+                            .lock()
+                            .unwrap()
+                            .recv()
+                            .expect("could not receive");
+                    }
+                    finished.lock().unwrap().push(nr);
+                    Ok(nr)
+                },
+                |realm, res| realm.create_i32(res),
+            )
+        };
+        let prom1 = create(1).expect("could not create promise");
+        assert_eq!(started_rx.recv().expect("producer did not start"), 1);
+        let prom2 = create(2).expect("could not create promise");
+        let metrics = rt.helper_pool_metrics();
+        assert_eq!(metrics.running, 1);
+        assert_eq!(metrics.queued, 1);
+        // the queue is full
+        assert!(create(3).is_err());
+        // the second producer waits for the only thread
+        assert!(started_rx.try_recv().is_err());
+        release_tx.send(()).expect("could not send");
+
+        let JsValueFacade::JsPromise { cached_promise } = prom2 else {
+            panic!("not a promise");
+        };
+        let res2 = cached_promise
+            .get_promise_result_sync()
+            .expect("promise timed out")
+            .expect("promise failed");
+        assert_eq!(res2.get_i32(), 2);
+        let JsValueFacade::JsPromise { cached_promise } = prom1 else {
+            panic!("not a promise");
+        };
+        let res1 = cached_promise
+            .get_promise_result_sync()
+            .expect("promise timed out")
+            .expect("promise failed");
+        assert_eq!(res1.get_i32(), 1);
+
+        // the producers ran one after the other
+        assert_eq!(*finished.lock().unwrap(), vec![1, 2]);
+        assert_eq!(started_rx.recv().expect("producer did not start"), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "helper thread count must be at least 1")]
+    fn test_helper_pool_zero_threads() {
+        let _rt = QuickJsRuntimeBuilder::new().helper_thread_count(0).build();
+    }
+
+    #[test]
+    #[should_panic(expected = "helper thread pool size must be at least 1")]
+    fn test_helper_thread_pool_size_zero() {
+        let _rt = QuickJsRuntimeBuilder::new()
+            .helper_thread_pool_size(0)
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "helper task queue max must be at least 1")]
+    fn test_helper_pool_zero_queue_max() {
+        let _rt = QuickJsRuntimeBuilder::new()
+            .helper_task_queue_max(0)
+            .build();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_eval_async() {
+        let rt = init_test_rt();
+
+        // the future is Send so it can be spawned on a multithreaded runtime
+        let fut = rt.eval_async(
+            None,
+            Script::new(
+                "test_eval_async.js",
+                "[1, 2, 3].map((v) => v * 2).join(',')",
+            ),
+            |realm, value_ref| primitives::to_string_q(realm, &value_ref),
+        );
+        let res = tokio::spawn(fut).await.expect("join failed");
+        assert_eq!(res.expect("script failed"), "2,4,6");
+
+        let res = rt
+            .eval_async(
+                None,
+                Script::new("test_eval_async2.js", "throw Error('oops');"),
+                |_realm, _value_ref| Ok(()),
+            )
+            .await;
+        assert!(res
+            .expect_err("script should fail")
+            .get_message()
+            .contains("oops"));
+
+        // errors from the mapper are returned as well
+        let res: Result<i32, JsError> = rt
+            .eval_async(
+                None,
+                Script::new("test_eval_async3.js", "'not a number'"),
+                |_realm, value_ref| primitives::to_i32(&value_ref),
+            )
+            .await;
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let rt = init_test_rt();
+        let before = rt.memory_usage_sync();
+        rt.eval_sync(
+            None,
+            Script::new(
+                "test_memory_usage.js",
+                "globalThis.memTest = []; for (let i = 0; i < 1000; i++) { globalThis.memTest.push({i, arr: [i, 'x' + i]}); }",
+            ),
+        )
+        .expect("script failed");
+        let after = rt.memory_usage_sync();
+        assert!(after.memory_used_size > before.memory_used_size);
+        assert!(after.obj_count >= before.obj_count + 2000);
+        assert!(after.array_count >= before.array_count + 1000);
+        assert!(after.prop_count > before.prop_count);
+        assert!(after.malloc_count > 0);
+    }
+
+    #[test]
+    fn test_gc_threshold() {
+        let code = "for (let i = 0; i < 20000; i++) { let a = {}; let b = {a}; a.b = b; }";
+        let obj_count_after = |rt: &QuickJsRuntimeFacade| {
+            rt.eval_sync(None, Script::new("test_gc_threshold.js", code))
+                .expect("script failed");
+            rt.memory_usage_sync().obj_count
+        };
+
+        // cyclic garbage is only freed by the gc, a huge threshold means the gc never runs automatically
+        let lazy_rt = QuickJsRuntimeBuilder::new()
+            .gc_threshold(u64::MAX >> 1)
+            .build();
+        let lazy_obj_count = obj_count_after(&lazy_rt);
+
+        let eager_rt = QuickJsRuntimeBuilder::new().build();
+        eager_rt.set_gc_threshold(64 * 1024);
+        let eager_obj_count = obj_count_after(&eager_rt);
+        assert!(
+            eager_obj_count + 20000 < lazy_obj_count,
+            "eager: {eager_obj_count} lazy: {lazy_obj_count}"
+        );
+
+        lazy_rt.gc_sync();
+        assert!(lazy_rt.memory_usage_sync().obj_count + 20000 < lazy_obj_count);
+    }
 }
 
 #[cfg(test)]
@@ -1366,6 +1921,63 @@ pub mod abstraction_tests {
         assert_eq!(res.get_str(), "1trueq123.3");
     }
 
+    #[tokio::test]
+    async fn test_date_facade() {
+        let rt = init_test_rt();
+
+        let date = rt
+            .eval(None, Script::new("date.js", "new Date(1234567890123);"))
+            .await
+            .expect("script failed");
+        assert!(date.is_date());
+        assert_eq!(date.get_date_millis(), 1234567890123f64);
+        assert_eq!(
+            date.to_serde_value().await.expect("to_serde failed"),
+            serde_json::Value::from("2009-02-13T23:31:30.123Z")
+        );
+
+        let _ = rt
+            .eval(
+                None,
+                Script::new(
+                    "date_check.js",
+                    "function checkDate(d) { return d instanceof Date ? d.getTime() : -1; }",
+                ),
+            )
+            .await
+            .expect("script failed");
+        let res = rt
+            .invoke_function(None, &[], "checkDate", vec![date])
+            .await
+            .expect("func failed");
+        assert_eq!(res.get_f64(), 1234567890123f64);
+
+        // SystemTime to Date
+        let time = std::time::UNIX_EPOCH + std::time::Duration::from_millis(1234567890123);
+        let res = rt
+            .invoke_function(
+                None,
+                &[],
+                "checkDate",
+                vec![JsValueFacade::new_date_from_system_time(time)],
+            )
+            .await
+            .expect("func failed");
+        assert_eq!(res.get_f64(), 1234567890123f64);
+
+        // Invalid Date
+        let invalid = rt
+            .eval(None, Script::new("invalid_date.js", "new Date('garbage');"))
+            .await
+            .expect("script failed");
+        assert!(invalid.get_date_millis().is_nan());
+        assert!(invalid.get_date_system_time().is_none());
+        assert_eq!(
+            invalid.to_serde_value().await.expect("to_serde failed"),
+            serde_json::Value::Null
+        );
+    }
+
     #[derive(Serialize, Deserialize)]
     #[serde(rename_all = "camelCase")]
     struct User {
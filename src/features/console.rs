@@ -4,11 +4,13 @@
 //! * console.log()
 //! * console.info()
 //! * console.error()
-//! * console.warning()
+//! * console.warn()
+//! * console.debug()
 //! * console.trace()
 //!
 //! The methods use rust's log crate to output messages. e.g. console.info() uses the log::info!() macro
 //! so the console messages should appear in the log you initialized from rust
+//! use [set_console_handler] or [crate::builder::QuickJsRuntimeBuilder::set_console_handler] to handle the messages in rust instead
 //!
 //! All methods accept a single message string and optional substitution values
 //!
@@ -19,10 +21,15 @@
 //! will output 'Oh dear some guy totaly failed 12 times because of a 2.4600 variance in the space time continuum'
 //!
 //! The string substitution you can use are
-//! * %o or %O Outputs a JavaScript object (serialized)
+//! * %o or %O Outputs a JavaScript object (inspected like the other non string arguments, see below)
 //! * %d or %i Outputs an integer. Number formatting is supported, for example  console.log("Foo %.2d", 1.1) will output the number as two significant figures with a leading 0: Foo 01
-//! * %s Outputs a string (will attempt to call .toString() on objects, use %o to output a serialized JSON string)
+//! * %s Outputs a string, values which are not strings are inspected like %o (e.g. `{ a: 1 }`)
 //! * %f Outputs a floating-point value. Formatting is supported, for example  console.log("Foo %.2f", 1.1) will output the number to 2 decimal places: Foo 1.10
+//! * %c is accepted but the css value is ignored
+//! * %% Outputs a single %
+//!
+//! Arguments which are not strings are output like a browser console would do, e.g. `{ a: 1, b: [ 'x', 2 ] }`
+//! objects nested more than 3 levels deep are output as [Object] and circular references as [Circular]
 //! # Example
 //! ```rust
 //! use quickjs_runtime::builder::QuickJsRuntimeBuilder;
@@ -44,16 +51,56 @@
 use crate::jsutils::{JsError, JsValueType};
 use crate::quickjs_utils;
 use crate::quickjs_utils::functions::call_to_string;
-use crate::quickjs_utils::json::stringify;
-use crate::quickjs_utils::{functions, json, parse_args, primitives};
+use crate::quickjs_utils::promises::PromiseState;
+use crate::quickjs_utils::{
+    arrays, dates, errors, functions, objects, parse_args, primitives, promises, symbols,
+};
 use crate::quickjsrealmadapter::QuickJsRealmAdapter;
 use crate::quickjsruntimeadapter::QuickJsRuntimeAdapter;
 use crate::quickjsvalueadapter::QuickJsValueAdapter;
 use crate::reflection::Proxy;
 use libquickjs_sys as q;
-use log::LevelFilter;
 use std::str::FromStr;
 
+/// the level of a console message, console.log() uses [ConsoleLevel::Log] which is logged at the info level by default
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConsoleLevel {
+    Log,
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl ConsoleLevel {
+    /// the level of the log crate which is used when no console handler is set
+    pub fn log_level(&self) -> log::Level {
+        match self {
+            ConsoleLevel::Log => log::Level::Info,
+            ConsoleLevel::Trace => log::Level::Trace,
+            ConsoleLevel::Debug => log::Level::Debug,
+            ConsoleLevel::Info => log::Level::Info,
+            ConsoleLevel::Warn => log::Level::Warn,
+            ConsoleLevel::Error => log::Level::Error,
+        }
+    }
+}
+
+/// a handler for console messages, see [set_console_handler]
+pub type ConsoleHandler = Box<dyn Fn(&QuickJsRealmAdapter, ConsoleLevel, Vec<QuickJsValueAdapter>)>;
+
+/// set a handler which is called for all console.log(), console.info() etc. calls instead of writing them to the log crate
+/// the handler receives the realm, the level and the arguments, use [format_args_q] to format the arguments like the default output does
+pub fn set_console_handler(q_js_rt: &mut QuickJsRuntimeAdapter, handler: ConsoleHandler) {
+    q_js_rt.console_handler = Some(handler);
+}
+
+/// format the arguments of a console call, substitutions like %s and %i in the first argument are filled with the following arguments
+pub fn format_args_q(realm: &QuickJsRealmAdapter, args: &[QuickJsValueAdapter]) -> String {
+    unsafe { format_args(realm.context, args) }
+}
+
 pub fn init(q_js_rt: &QuickJsRuntimeAdapter) -> Result<(), JsError> {
     q_js_rt.add_context_init_hook(|_q_js_rt, q_ctx| init_ctx(q_ctx))
 }
@@ -150,30 +197,195 @@ unsafe fn parse_field_value(
             return f_val;
         }
     } else if field.ends_with('o') || field.ends_with('O') {
-        let json_str_res = json::stringify(ctx, value, None);
-        let json = match json_str_res {
-            Ok(json_str) => {
-                if json_str.is_undefined() {
-                    // if undefined is passed tp json.stringify it returns undefined, else always a string
-                    "undefined".to_string()
-                } else {
-                    primitives::to_string(ctx, &json_str).unwrap_or_default()
-                }
-            }
-            Err(_e) => "".to_string(),
-        };
-        return json;
+        return inspect(ctx, value, 0, &mut vec![]);
     }
-    call_to_string(ctx, value).unwrap_or_default()
+    format_arg(ctx, value)
 }
 
-unsafe fn stringify_log_obj(ctx: *mut q::JSContext, arg: &QuickJsValueAdapter) -> String {
-    match stringify(ctx, arg, None) {
-        Ok(r) => match primitives::to_string(ctx, &r) {
-            Ok(s) => s,
-            Err(e) => format!("Error: {e}"),
+/// format a single argument, strings are output as is and other values are inspected
+unsafe fn format_arg(ctx: *mut q::JSContext, value: &QuickJsValueAdapter) -> String {
+    if value.is_string() {
+        primitives::to_string(ctx, value).unwrap_or_default()
+    } else {
+        inspect(ctx, value, 0, &mut vec![])
+    }
+}
+
+/// objects and arrays nested deeper than this are output as [Object] and [Array]
+const INSPECT_MAX_DEPTH: usize = 3;
+/// arrays and objects output at most this many entries
+const INSPECT_MAX_ENTRIES: usize = 100;
+
+/// output a value like a browser console would, e.g. { a: 1, b: [ 'x', 2 ] }
+/// seen holds the objects which are currently being output, objects which are already in there are output as [Circular]
+unsafe fn inspect(
+    ctx: *mut q::JSContext,
+    value: &QuickJsValueAdapter,
+    depth: usize,
+    seen: &mut Vec<QuickJsValueAdapter>,
+) -> String {
+    if symbols::is_symbol(value) {
+        return match symbols::get_description(ctx, value) {
+            Ok(Some(description)) => format!("Symbol({description})"),
+            _ => "Symbol()".to_string(),
+        };
+    }
+    if value.is_big_int() {
+        return format!("{}n", call_to_string(ctx, value).unwrap_or_default());
+    }
+    if value.is_string() {
+        return quote(
+            primitives::to_string(ctx, value)
+                .unwrap_or_default()
+                .as_str(),
+        );
+    }
+    if value.is_f64() {
+        let f = value.to_f64();
+        if f.is_nan() {
+            return "NaN".to_string();
+        } else if f.is_infinite() {
+            return if f > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+        }
+    }
+    if !value.is_object() {
+        return call_to_string(ctx, value).unwrap_or_default();
+    }
+    if seen.contains(value) {
+        return "[Circular]".to_string();
+    }
+    match value.get_js_type() {
+        JsValueType::Function => {
+            let name = objects::get_property(ctx, value, "name")
+                .ok()
+                .filter(|name| name.is_string())
+                .and_then(|name| primitives::to_string(ctx, &name).ok())
+                .unwrap_or_default();
+            if name.is_empty() {
+                "[Function (anonymous)]".to_string()
+            } else {
+                format!("[Function: {name}]")
+            }
+        }
+        JsValueType::Error => {
+            if depth == 0 {
+                call_to_string(ctx, value).unwrap_or_default()
+            } else {
+                let err = errors::error_to_js_error(ctx, value);
+                format!("[{}: {}]", err.get_name(), err.get_message())
+            }
+        }
+        JsValueType::Array => {
+            if depth >= INSPECT_MAX_DEPTH {
+                return "[Array]".to_string();
+            }
+            let len = arrays::get_length(ctx, value).unwrap_or(0) as usize;
+            seen.push(value.clone());
+            let mut items = vec![];
+            for index in 0..len.min(INSPECT_MAX_ENTRIES) {
+                items.push(match arrays::get_element(ctx, value, index as u32) {
+                    Ok(element) => inspect(ctx, &element, depth + 1, seen),
+                    Err(_) => "undefined".to_string(),
+                });
+            }
+            seen.pop();
+            if len > INSPECT_MAX_ENTRIES {
+                items.push(format!("... {} more items", len - INSPECT_MAX_ENTRIES));
+            }
+            if items.is_empty() {
+                "[]".to_string()
+            } else {
+                format!("[ {} ]", items.join(", "))
+            }
+        }
+        JsValueType::Promise => match promises::get_promise_state(ctx, value) {
+            Ok(PromiseState::Pending) => "Promise { <pending> }".to_string(),
+            Ok(PromiseState::Fulfilled(result)) => {
+                seen.push(value.clone());
+                let result = inspect(ctx, &result, depth + 1, seen);
+                seen.pop();
+                format!("Promise {{ {result} }}")
+            }
+            Ok(PromiseState::Rejected(reason)) => {
+                seen.push(value.clone());
+                let reason = inspect(ctx, &reason, depth + 1, seen);
+                seen.pop();
+                format!("Promise {{ <rejected> {reason} }}")
+            }
+            Err(_) => "Promise {}".to_string(),
         },
-        Err(e) => format!("Error: {e}"),
+        _ => {
+            if dates::is_date(ctx, value) {
+                return functions::invoke_member_function(ctx, value, "toISOString", &[])
+                    .and_then(|iso| primitives::to_string(ctx, &iso))
+                    .unwrap_or_else(|_| "Invalid Date".to_string());
+            }
+            let class_name = objects::get_property(ctx, value, "constructor")
+                .ok()
+                .filter(|constructor| constructor.is_function())
+                .and_then(|constructor| objects::get_property(ctx, &constructor, "name").ok())
+                .filter(|name| name.is_string())
+                .and_then(|name| primitives::to_string(ctx, &name).ok())
+                .filter(|name| !name.is_empty() && name != "Object");
+            if depth >= INSPECT_MAX_DEPTH {
+                return format!("[{}]", class_name.as_deref().unwrap_or("Object"));
+            }
+            let prefix = class_name
+                .map(|name| format!("{name} "))
+                .unwrap_or_default();
+            let names = objects::get_property_names(ctx, value).unwrap_or_default();
+            seen.push(value.clone());
+            let mut items = vec![];
+            for name in names.iter().take(INSPECT_MAX_ENTRIES) {
+                let item = match objects::get_property(ctx, value, name) {
+                    Ok(item) => inspect(ctx, &item, depth + 1, seen),
+                    Err(_) => "undefined".to_string(),
+                };
+                items.push(format!("{}: {item}", format_key(name)));
+            }
+            seen.pop();
+            if names.len() > INSPECT_MAX_ENTRIES {
+                items.push(format!(
+                    "... {} more items",
+                    names.len() - INSPECT_MAX_ENTRIES
+                ));
+            }
+            if items.is_empty() {
+                format!("{prefix}{{}}")
+            } else {
+                format!("{prefix}{{ {} }}", items.join(", "))
+            }
+        }
+    }
+}
+
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('\'');
+    for chr in s.chars() {
+        match chr {
+            '\'' => quoted.push_str("\\'"),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(chr),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+fn format_key(key: &str) -> String {
+    let is_identifier = key
+        .chars()
+        .next()
+        .is_some_and(|chr| chr.is_alphabetic() || chr == '_' || chr == '$')
+        && key
+            .chars()
+            .all(|chr| chr.is_alphanumeric() || chr == '_' || chr == '$');
+    if is_identifier {
+        key.to_string()
+    } else {
+        quote(key)
     }
 }
 
@@ -192,91 +404,101 @@ unsafe fn parse_line(ctx: *mut q::JSContext, args: Vec<QuickJsValueAdapter>) ->
         output.push_str("]: ");
     });
 
-    if args.is_empty() {
-        return output;
-    }
+    output.push_str(format_args(ctx, &args).as_str());
+    output
+}
 
-    let message = match &args[0].get_js_type() {
-        JsValueType::Object => stringify_log_obj(ctx, &args[0]),
-        JsValueType::Function => stringify_log_obj(ctx, &args[0]),
-        JsValueType::Array => stringify_log_obj(ctx, &args[0]),
-        _ => functions::call_to_string(ctx, &args[0]).unwrap_or_default(),
-    };
-
-    let mut field_code = String::new();
-    let mut in_field = false;
-
-    let mut x = 1;
-
-    let mut filled = 1;
-
-    if args[0].is_string() {
-        for chr in message.chars() {
-            if in_field {
-                field_code.push(chr);
-                if chr.eq(&'s') || chr.eq(&'d') || chr.eq(&'f') || chr.eq(&'o') || chr.eq(&'i') {
-                    // end field
-
-                    if x < args.len() {
-                        output.push_str(
-                            parse_field_value(ctx, field_code.as_str(), &args[x]).as_str(),
-                        );
-                        x += 1;
-                        filled += 1;
+unsafe fn format_args(ctx: *mut q::JSContext, args: &[QuickJsValueAdapter]) -> String {
+    let mut output = String::new();
+    let mut values = args.iter();
+
+    if let Some(message) = args.first().filter(|arg| arg.is_string()) {
+        values.next();
+        let message = primitives::to_string(ctx, message).unwrap_or_default();
+        let mut chars = message.chars().peekable();
+        while let Some(chr) = chars.next() {
+            if chr != '%' {
+                output.push(chr);
+                continue;
+            }
+            let mut field_code = String::from("%");
+            if chars.next_if_eq(&'.').is_some() {
+                field_code.push('.');
+                while let Some(digit) = chars.next_if(|chr| chr.is_ascii_digit()) {
+                    field_code.push(digit);
+                }
+            }
+            match chars.next_if(|chr| "sdifoOc%".contains(*chr)) {
+                Some('%') if field_code.len() == 1 => output.push('%'),
+                Some(conversion) if conversion != '%' => {
+                    field_code.push(conversion);
+                    match values.next() {
+                        // %c applies css to the message which is not supported, the value is dropped
+                        Some(_) if conversion == 'c' => {}
+                        Some(value) => output
+                            .push_str(parse_field_value(ctx, field_code.as_str(), value).as_str()),
+                        // output the field as is if there is no value for it
+                        None => output.push_str(field_code.as_str()),
                     }
-
-                    in_field = false;
-                    field_code = String::new();
                 }
-            } else if chr.eq(&'%') {
-                in_field = true;
-            } else {
-                output.push(chr);
+                Some(other) => {
+                    output.push_str(field_code.as_str());
+                    output.push(other);
+                }
+                None => output.push_str(field_code.as_str()),
             }
         }
-    } else {
-        output.push_str(message.as_str());
     }
 
-    for arg in args.iter().skip(filled) {
-        // add args which we're not filled in str
-        output.push(' ');
-        let tail_arg = match arg.get_js_type() {
-            JsValueType::Object => stringify_log_obj(ctx, arg),
-            JsValueType::Function => stringify_log_obj(ctx, arg),
-            JsValueType::Array => stringify_log_obj(ctx, arg),
-            _ => call_to_string(ctx, arg).unwrap_or_default(),
-        };
-        output.push_str(tail_arg.as_str());
+    for arg in values {
+        // add args which were not used as a substitution value
+        if !output.is_empty() {
+            output.push(' ');
+        }
+        output.push_str(format_arg(ctx, arg).as_str());
     }
 
     output
 }
 
-unsafe extern "C" fn console_log(
+unsafe fn console_output(
     ctx: *mut q::JSContext,
-    _this_val: q::JSValue,
+    level: ConsoleLevel,
     argc: ::std::os::raw::c_int,
     argv: *mut q::JSValue,
 ) -> q::JSValue {
-    if log::max_level() >= LevelFilter::Info {
+    let handled = QuickJsRuntimeAdapter::do_with(|q_js_rt| {
+        if let Some(handler) = q_js_rt.console_handler.as_ref() {
+            let args = parse_args(ctx, argc, argv);
+            handler(q_js_rt.get_quickjs_context(ctx), level, args);
+            true
+        } else {
+            false
+        }
+    });
+    if !handled && log::max_level() >= level.log_level() {
         let args = parse_args(ctx, argc, argv);
-        log::info!("{}", parse_line(ctx, args));
+        log::log!(level.log_level(), "{}", parse_line(ctx, args));
     }
     quickjs_utils::new_null()
 }
 
+unsafe extern "C" fn console_log(
+    ctx: *mut q::JSContext,
+    _this_val: q::JSValue,
+    argc: ::std::os::raw::c_int,
+    argv: *mut q::JSValue,
+) -> q::JSValue {
+    console_output(ctx, ConsoleLevel::Log, argc, argv)
+}
+
 unsafe extern "C" fn console_trace(
     ctx: *mut q::JSContext,
     _this_val: q::JSValue,
     argc: ::std::os::raw::c_int,
     argv: *mut q::JSValue,
 ) -> q::JSValue {
-    if log::max_level() >= LevelFilter::Trace {
-        let args = parse_args(ctx, argc, argv);
-        log::trace!("{}", parse_line(ctx, args));
-    }
-    quickjs_utils::new_null()
+    console_output(ctx, ConsoleLevel::Trace, argc, argv)
 }
 
 unsafe extern "C" fn console_debug(
@@ -285,11 +507,7 @@ unsafe extern "C" fn console_debug(
     argc: ::std::os::raw::c_int,
     argv: *mut q::JSValue,
 ) -> q::JSValue {
-    if log::max_level() >= LevelFilter::Debug {
-        let args = parse_args(ctx, argc, argv);
-        log::debug!("{}", parse_line(ctx, args));
-    }
-    quickjs_utils::new_null()
+    console_output(ctx, ConsoleLevel::Debug, argc, argv)
 }
 
 unsafe extern "C" fn console_info(
@@ -298,11 +516,7 @@ unsafe extern "C" fn console_info(
     argc: ::std::os::raw::c_int,
     argv: *mut q::JSValue,
 ) -> q::JSValue {
-    if log::max_level() >= LevelFilter::Info {
-        let args = parse_args(ctx, argc, argv);
-        log::info!("{}", parse_line(ctx, args));
-    }
-    quickjs_utils::new_null()
+    console_output(ctx, ConsoleLevel::Info, argc, argv)
 }
 
 unsafe extern "C" fn console_warn(
@@ -311,11 +525,7 @@ unsafe extern "C" fn console_warn(
     argc: ::std::os::raw::c_int,
     argv: *mut q::JSValue,
 ) -> q::JSValue {
-    if log::max_level() >= LevelFilter::Warn {
-        let args = parse_args(ctx, argc, argv);
-        log::warn!("{}", parse_line(ctx, args));
-    }
-    quickjs_utils::new_null()
+    console_output(ctx, ConsoleLevel::Warn, argc, argv)
 }
 
 unsafe extern "C" fn console_error(
@@ -324,11 +534,7 @@ unsafe extern "C" fn console_error(
     argc: ::std::os::raw::c_int,
     argv: *mut q::JSValue,
 ) -> q::JSValue {
-    if log::max_level() >= LevelFilter::Error {
-        let args = parse_args(ctx, argc, argv);
-        log::error!("{}", parse_line(ctx, args));
-    }
-    quickjs_utils::new_null()
+    console_output(ctx, ConsoleLevel::Error, argc, argv)
 }
 
 #[cfg(test)]
@@ -395,4 +601,113 @@ pub mod tests {
 
         thread::sleep(Duration::from_secs(1));
     }
+
+    #[test]
+    fn test_console_handler() {
+        use crate::features::console::{format_args_q, ConsoleLevel};
+        use std::sync::{Arc, Mutex};
+
+        let messages = Arc::new(Mutex::new(vec![]));
+        let messages2 = messages.clone();
+        let rt = QuickJsRuntimeBuilder::new()
+            .set_console_handler(move |realm, level, args| {
+                messages2
+                    .lock()
+                    .unwrap()
+                    .push((level, format_args_q(realm, &args)));
+            })
+            .build();
+        rt.eval_sync(
+            None,
+            Script::new(
+                "test_console_handler.es",
+                "console.log('hello %s', 'world');\
+            console.info('count: %i', 3);\
+            console.warn({a: 1}, 'tail');\
+            console.error('failed');\
+            console.debug();",
+            ),
+        )
+        .expect("test_console_handler.es failed");
+
+        let messages = messages.lock().unwrap();
+        assert_eq!(
+            *messages,
+            vec![
+                (ConsoleLevel::Log, "hello world".to_string()),
+                (ConsoleLevel::Info, "count: 3".to_string()),
+                (ConsoleLevel::Warn, "{ a: 1 } tail".to_string()),
+                (ConsoleLevel::Error, "failed".to_string()),
+                (ConsoleLevel::Debug, "".to_string()),
+            ]
+        );
+    }
+
+    fn format_console(code: &str) -> Vec<String> {
+        use crate::features::console::format_args_q;
+        use std::sync::{Arc, Mutex};
+
+        let lines = Arc::new(Mutex::new(vec![]));
+        let lines2 = lines.clone();
+        let rt = QuickJsRuntimeBuilder::new()
+            .set_console_handler(move |realm, _level, args| {
+                lines2.lock().unwrap().push(format_args_q(realm, &args));
+            })
+            .build();
+        rt.eval_sync(None, Script::new("format_console.es", code))
+            .expect("format_console.es failed");
+        let lines = lines.lock().unwrap().clone();
+        lines
+    }
+
+    #[test]
+    fn test_console_format() {
+        let lines = format_console(
+            "console.log('x=%d', 5);\
+            console.log('%s has %i items which cost %.2f', 'cart', 3, 4.5);\
+            console.log('%s. obj: %o', 'done', {a: 1});\
+            console.log('100% done, %% left: %d %s', 0);\
+            console.log('%cstyled', 'color: red', 'tail');\
+            console.log({a: 1, b: 'two', 'c-d': [1, true, null, undefined], e: {f: {g: {h: 1}}}});\
+            console.log([], {}, [[1, [2, [3, [4]]]]]);\
+            console.log(function named() {}, () => {}, Symbol('sym'), 12n, NaN, -Infinity);\
+            console.log(new Date(0), 'quote\\'s', ['quote\\'s']);\
+            class Point { constructor() { this.x = 1; } }\
+            console.log(new Point(), {err: new Error('nested')});",
+        );
+        assert_eq!(
+            lines,
+            vec![
+                "x=5",
+                "cart has 3 items which cost 4.50",
+                "done. obj: { a: 1 }",
+                "100% done, % left: 0 %s",
+                "styled tail",
+                "{ a: 1, b: 'two', 'c-d': [ 1, true, null, undefined ], e: { f: { g: [Object] } } }",
+                "[] {} [ [ 1, [ 2, [Array] ] ] ]",
+                "[Function: named] [Function (anonymous)] Symbol(sym) 12n NaN -Infinity",
+                "1970-01-01T00:00:00.000Z quote's [ 'quote\\'s' ]",
+                "Point { x: 1 } { err: [Error: nested] }",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_console_circular() {
+        let lines = format_console(
+            "let a = {name: 'a'}; a.self = a; a.list = [a, {parent: a}];\
+            let shared = {s: 1};\
+            console.log(a);\
+            console.log('%o', a.list);\
+            console.log([shared, shared]);",
+        );
+        assert_eq!(
+            lines,
+            vec![
+                "{ name: 'a', self: [Circular], list: [ [Circular], { parent: [Circular] } ] }",
+                "[ { name: 'a', self: [Circular], list: [Circular] }, { parent: { name: 'a', self: [Circular], list: [Circular] } } ]",
+                "[ { s: 1 }, { s: 1 } ]",
+            ]
+        );
+    }
 }
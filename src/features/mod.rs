@@ -1,9 +1,11 @@
-//! contains engine features like console, setTimeout, setInterval and setImmediate
+//! contains engine features like console, setTimeout, setInterval, setImmediate and queueMicrotask
 
 use crate::facades::QuickJsRuntimeFacade;
 use crate::jsutils::JsError;
 #[cfg(feature = "console")]
 pub mod console;
+#[cfg(feature = "queuemicrotask")]
+pub mod queuemicrotask;
 #[cfg(any(feature = "settimeout", feature = "setinterval"))]
 pub mod set_timeout;
 #[cfg(feature = "setimmediate")]
@@ -13,7 +15,8 @@ pub mod setimmediate;
     feature = "settimeout",
     feature = "setinterval",
     feature = "console",
-    feature = "setimmediate"
+    feature = "setimmediate",
+    feature = "queuemicrotask"
 ))]
 pub fn init(es_rt: &QuickJsRuntimeFacade) -> Result<(), JsError> {
     log::trace!("features::init");
@@ -23,6 +26,8 @@ pub fn init(es_rt: &QuickJsRuntimeFacade) -> Result<(), JsError> {
         console::init(q_js_rt)?;
         #[cfg(feature = "setimmediate")]
         setimmediate::init(q_js_rt)?;
+        #[cfg(feature = "queuemicrotask")]
+        queuemicrotask::init(q_js_rt)?;
 
         #[cfg(any(feature = "settimeout", feature = "setinterval"))]
         set_timeout::init(q_js_rt)?;
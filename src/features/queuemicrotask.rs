@@ -0,0 +1,124 @@
+use crate::jsutils::JsError;
+use crate::quickjs_utils;
+use crate::quickjs_utils::{functions, get_global_q, objects, parse_args};
+use crate::quickjsruntimeadapter::QuickJsRuntimeAdapter;
+use libquickjs_sys as q;
+
+/// provides the queueMicrotask method for the runtime
+/// the callback is added to the QuickJS job queue, so it runs after the current script or job and before the next event loop task (like setTimeout callbacks)
+/// # Example
+/// ```rust
+/// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+/// use quickjs_runtime::jsutils::Script;
+/// let rt = QuickJsRuntimeBuilder::new().build();
+/// rt.eval_sync(None, Script::new("test_microtask.es", "queueMicrotask(() => {console.log('microtask logging')});")).expect("script failed");
+/// ```
+pub fn init(q_js_rt: &QuickJsRuntimeAdapter) -> Result<(), JsError> {
+    log::trace!("queuemicrotask::init");
+
+    q_js_rt.add_context_init_hook(|_q_js_rt, q_ctx| {
+        let queue_microtask_func = functions::new_native_function_q(
+            q_ctx,
+            "queueMicrotask",
+            Some(queue_microtask),
+            1,
+            false,
+        )?;
+
+        let global = get_global_q(q_ctx);
+
+        objects::set_property2_q(q_ctx, &global, "queueMicrotask", &queue_microtask_func, 0)?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+unsafe extern "C" fn queue_microtask(
+    context: *mut q::JSContext,
+    _this_val: q::JSValue,
+    argc: ::std::os::raw::c_int,
+    argv: *mut q::JSValue,
+) -> q::JSValue {
+    log::trace!("> queue_microtask");
+
+    let args = parse_args(context, argc, argv);
+
+    QuickJsRuntimeAdapter::do_with(move |q_js_rt| {
+        let q_ctx = q_js_rt.get_quickjs_context(context);
+        if args.is_empty() {
+            return q_ctx.report_ex("queueMicrotask requires at least one argument");
+        }
+        if !functions::is_function(context, &args[0]) {
+            return q_ctx.report_ex("queueMicrotask requires a function as first arg");
+        }
+
+        // JS_EnqueueJob dups the args so the job holds its own ref to the callback
+        let mut job_args = [*args[0].borrow_value()];
+        if q::JS_EnqueueJob(context, Some(run_microtask), 1, job_args.as_mut_ptr()) != 0 {
+            return q_ctx.report_ex("queueMicrotask could not enqueue the callback");
+        }
+
+        quickjs_utils::new_null()
+    })
+}
+
+unsafe extern "C" fn run_microtask(
+    context: *mut q::JSContext,
+    _argc: ::std::os::raw::c_int,
+    argv: *mut q::JSValue,
+) -> q::JSValue {
+    let func = *argv;
+    q::JS_Call(
+        context,
+        func,
+        quickjs_utils::new_undefined(),
+        0,
+        std::ptr::null_mut(),
+    )
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::facades::tests::init_test_rt;
+    use crate::jsutils::Script;
+    use std::time::Duration;
+
+    #[test]
+    fn test_queue_microtask() {
+        let rt = init_test_rt();
+        rt.eval_sync(
+            None,
+            Script::new(
+                "test_queue_microtask.js",
+                "globalThis.microtaskLog = [];\n\
+                 setTimeout(() => microtaskLog.push('timeout'), 0);\n\
+                 Promise.resolve().then(() => {\n\
+                     microtaskLog.push('then');\n\
+                     queueMicrotask(() => microtaskLog.push('microtask in then'));\n\
+                 });\n\
+                 queueMicrotask(() => microtaskLog.push('microtask'));\n\
+                 microtaskLog.push('sync');",
+            ),
+        )
+        .expect("script failed");
+        std::thread::sleep(Duration::from_millis(50));
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_queue_microtask2.js", "JSON.stringify(microtaskLog);"),
+            )
+            .expect("script failed");
+        assert_eq!(
+            res.get_str(),
+            "[\"sync\",\"then\",\"microtask\",\"microtask in then\",\"timeout\"]"
+        );
+
+        let err = rt
+            .eval_sync(
+                None,
+                Script::new("test_queue_microtask3.js", "queueMicrotask(12);"),
+            )
+            .expect_err("queueMicrotask should fail");
+        assert!(err.get_message().contains("function"), "{}", err);
+    }
+}
@@ -2,11 +2,23 @@ use crate::jsutils::JsError;
 use crate::quickjs_utils;
 use crate::quickjs_utils::{functions, get_global, objects, parse_args, primitives};
 use crate::quickjsruntimeadapter::QuickJsRuntimeAdapter;
+#[cfg(feature = "setinterval")]
+use crate::quickjsvalueadapter::QuickJsValueAdapter;
+#[cfg(feature = "setinterval")]
+use hirofa_utils::auto_id_map::AutoIdMap;
 use hirofa_utils::eventloop::EventLoop;
 use libquickjs_sys as q;
+#[cfg(feature = "setinterval")]
+use std::cell::RefCell;
 use std::time::Duration;
+#[cfg(feature = "setinterval")]
+use std::time::Instant;
 
-/// provides the setImmediate methods for the runtime
+/// provides the setTimeout, clearTimeout, setInterval and clearInterval methods for the runtime
+/// callbacks run as tasks in the event loop, pending promise jobs are run after every callback
+/// the ticks of an interval are scheduled relative to when the interval was started so they do not drift, ticks which were missed because the event loop was busy are skipped
+/// intervals are stopped when their realm is destroyed or the runtime is dropped
+/// calling clearTimeout or clearInterval with an id which is not a number does nothing, like in a browser
 /// # Example
 /// ```rust
 /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
@@ -156,34 +168,96 @@ unsafe extern "C" fn set_interval(
             0
         };
 
-        let q_ctx_id = q_ctx.id.clone();
-
-        let id = EventLoop::add_interval(
-            move || {
-                QuickJsRuntimeAdapter::do_with(|q_js_rt| {
-                    if let Some(q_ctx) = q_js_rt.opt_context(q_ctx_id.as_str()) {
-                        let func = &args[0];
-
-                        match functions::call_function_q(q_ctx, func, &args[2..], None) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                log::error!("setInterval func failed: {}", e);
-                            }
-                        };
-                    } else {
-                        log::error!("setInterval func failed: no such context: {}", q_ctx_id);
-                    }
-                    q_js_rt.run_pending_jobs_if_any();
-                })
-            },
-            Duration::from_millis(delay_ms),
-            Duration::from_millis(delay_ms),
-        );
+        let interval = Duration::from_millis(delay_ms);
+        let id = INTERVALS.with(|rc| rc.borrow_mut().insert(None));
+        schedule_interval_tick(IntervalTick {
+            id,
+            q_ctx_id: q_ctx.id.clone(),
+            args,
+            next_run: Instant::now() + interval,
+            interval,
+        });
         log::trace!("set_interval: {}", id);
-        primitives::from_i32(id).clone_value_incr_rc()
+        primitives::from_i32(id as i32).clone_value_incr_rc()
     })
 }
 
+#[cfg(feature = "setinterval")]
+thread_local! {
+    // the id of the timeout which runs the next tick by interval id, None while a tick is running
+    static INTERVALS: RefCell<AutoIdMap<Option<i32>>> =
+        RefCell::new(AutoIdMap::new_with_max_size(i32::MAX as usize));
+}
+
+#[cfg(feature = "setinterval")]
+struct IntervalTick {
+    id: usize,
+    q_ctx_id: String,
+    args: Vec<QuickJsValueAdapter>,
+    next_run: Instant,
+    interval: Duration,
+}
+
+#[cfg(feature = "setinterval")]
+fn schedule_interval_tick(tick: IntervalTick) {
+    let id = tick.id;
+    let delay = tick.next_run.saturating_duration_since(Instant::now());
+    let timeout_id = EventLoop::add_timeout(move || run_interval_tick(tick), delay);
+    INTERVALS.with(|rc| {
+        if let Some(current) = rc.borrow_mut().get_mut(&id) {
+            *current = Some(timeout_id);
+        }
+    });
+}
+
+#[cfg(feature = "setinterval")]
+fn run_interval_tick(mut tick: IntervalTick) {
+    INTERVALS.with(|rc| {
+        if let Some(current) = rc.borrow_mut().get_mut(&tick.id) {
+            *current = None;
+        }
+    });
+    QuickJsRuntimeAdapter::do_with(|q_js_rt| {
+        if let Some(q_ctx) = q_js_rt.opt_context(tick.q_ctx_id.as_str()) {
+            let func = &tick.args[0];
+            if let Err(e) = functions::call_function_q(q_ctx, func, &tick.args[2..], None) {
+                log::error!("setInterval func failed: {}", e);
+            }
+        } else {
+            log::error!(
+                "setInterval func failed: no such context: {}, clearing interval",
+                tick.q_ctx_id
+            );
+            INTERVALS.with(|rc| rc.borrow_mut().remove_opt(&tick.id));
+        }
+        q_js_rt.run_pending_jobs_if_any();
+    });
+    // the interval may have been cleared by the callback
+    if INTERVALS.with(|rc| rc.borrow().contains_key(&tick.id)) {
+        tick.next_run = next_interval_run(tick.next_run, tick.interval, Instant::now());
+        schedule_interval_tick(tick);
+    }
+}
+
+#[cfg(all(test, feature = "setinterval"))]
+pub(crate) fn active_interval_count() -> usize {
+    INTERVALS.with(|rc| rc.borrow().len())
+}
+
+/// get the moment of the next tick of an interval which was due at last_run, ticks which should have run before now are skipped
+#[cfg(feature = "setinterval")]
+fn next_interval_run(last_run: Instant, interval: Duration, now: Instant) -> Instant {
+    let next_run = last_run + interval;
+    if next_run > now {
+        next_run
+    } else if interval.is_zero() {
+        now
+    } else {
+        let missed = (now - next_run).as_nanos() / interval.as_nanos() + 1;
+        next_run + Duration::from_nanos((interval.as_nanos() * missed) as u64)
+    }
+}
+
 #[cfg(feature = "setinterval")]
 unsafe extern "C" fn clear_interval(
     context: *mut q::JSContext,
@@ -194,17 +268,19 @@ unsafe extern "C" fn clear_interval(
     log::trace!("> clear_interval");
 
     let args = parse_args(context, argc, argv);
-    QuickJsRuntimeAdapter::do_with(|q_js_rt| {
-        let q_ctx = q_js_rt.get_quickjs_context(context);
-        if args.is_empty() {
-            return q_ctx.report_ex("clearInterval requires at least one argument");
-        }
-        if !&args[0].is_i32() {
-            return q_ctx.report_ex("clearInterval requires a number as first arg");
+    QuickJsRuntimeAdapter::do_with(|_q_js_rt| {
+        if args.is_empty() || !args[0].is_i32() {
+            // like in browsers invalid ids are ignored
+            return quickjs_utils::new_null();
         }
         let id = primitives::to_i32(&args[0]).ok().unwrap();
         log::trace!("clear_interval: {}", id);
-        EventLoop::clear_interval(id);
+        if id >= 0 {
+            let removed = INTERVALS.with(|rc| rc.borrow_mut().remove_opt(&(id as usize)));
+            if let Some(Some(timeout_id)) = removed {
+                EventLoop::clear_timeout(timeout_id);
+            }
+        }
         quickjs_utils::new_null()
     })
 }
@@ -220,13 +296,10 @@ unsafe extern "C" fn clear_timeout(
 
     let args = parse_args(context, argc, argv);
 
-    QuickJsRuntimeAdapter::do_with(move |q_js_rt| {
-        let q_ctx = q_js_rt.get_quickjs_context(context);
-        if args.is_empty() {
-            return q_ctx.report_ex("clearTimeout requires at least one argument");
-        }
-        if !&args[0].is_i32() {
-            return q_ctx.report_ex("clearTimeout requires a number as first arg");
+    QuickJsRuntimeAdapter::do_with(move |_q_js_rt| {
+        if args.is_empty() || !args[0].is_i32() {
+            // like in browsers invalid ids are ignored
+            return quickjs_utils::new_null();
         }
         let id = primitives::to_i32(&args[0]).ok().unwrap();
         log::trace!("clear_timeout: {}", id);
@@ -240,10 +313,13 @@ unsafe extern "C" fn clear_timeout(
 #[cfg(test)]
 pub mod tests {
     use crate::facades::tests::init_test_rt;
+    #[cfg(feature = "setinterval")]
+    use crate::features::set_timeout::{active_interval_count, next_interval_run};
     use crate::jsutils::Script;
     use crate::quickjs_utils::get_global_q;
     use crate::quickjs_utils::objects::get_property_q;
     use crate::quickjs_utils::primitives::to_i32;
+    use crate::quickjsruntimeadapter::QuickJsRuntimeAdapter;
     use crate::values::JsValueFacade;
     use std::time::Duration;
 
@@ -427,4 +503,118 @@ pub mod tests {
 
         rt.gc_sync();
     }
+
+    #[test]
+    fn test_clear_timeout() {
+        let rt = init_test_rt();
+        rt.eval_sync(
+            None,
+            Script::new(
+                "test_clear_timeout.js",
+                "globalThis.timeoutLog = [];\n\
+                 setTimeout(() => {\n\
+                     timeoutLog.push('ran');\n\
+                     Promise.resolve().then(() => timeoutLog.push('job'));\n\
+                 }, 50);\n\
+                 const cancelled = setTimeout(() => timeoutLog.push('cancelled'), 50);\n\
+                 clearTimeout(cancelled);\n\
+                 clearTimeout(undefined);\n\
+                 clearTimeout();",
+            ),
+        )
+        .expect("script failed");
+        std::thread::sleep(Duration::from_millis(200));
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_clear_timeout2.js", "JSON.stringify(timeoutLog);"),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_str(), "[\"ran\",\"job\"]");
+    }
+
+    #[test]
+    fn test_clear_interval_in_callback() {
+        let rt = init_test_rt();
+        rt.eval_sync(
+            None,
+            Script::new(
+                "test_clear_interval.js",
+                "globalThis.intervalTicks = 0;\n\
+                 const intervalId = setInterval(() => {\n\
+                     intervalTicks++;\n\
+                     if (intervalTicks === 3) { clearInterval(intervalId); }\n\
+                 }, 20);",
+            ),
+        )
+        .expect("script failed");
+        std::thread::sleep(Duration::from_millis(300));
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_clear_interval2.js", "intervalTicks;"),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_i32(), 3);
+        std::thread::sleep(Duration::from_millis(100));
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_clear_interval3.js", "intervalTicks;"),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_i32(), 3);
+    }
+
+    #[cfg(feature = "setinterval")]
+    #[test]
+    fn test_next_interval_run() {
+        let start = std::time::Instant::now();
+        let interval = Duration::from_millis(100);
+        // a slow callback does not delay the next tick
+        assert_eq!(
+            next_interval_run(start, interval, start + Duration::from_millis(30)),
+            start + interval
+        );
+        // missed ticks are skipped
+        assert_eq!(
+            next_interval_run(start, interval, start + Duration::from_millis(250)),
+            start + Duration::from_millis(300)
+        );
+        assert_eq!(
+            next_interval_run(start, interval, start + Duration::from_millis(200)),
+            start + Duration::from_millis(300)
+        );
+        let now = start + Duration::from_millis(5);
+        assert_eq!(next_interval_run(start, Duration::ZERO, now), now);
+    }
+
+    #[cfg(feature = "setinterval")]
+    #[test]
+    fn test_interval_cleared_with_realm() {
+        let rt = init_test_rt();
+        rt.eval_sync(
+            Some("test_interval_realm"),
+            Script::new(
+                "test_interval_realm.js",
+                "setInterval(() => {globalThis.realmTicks = (globalThis.realmTicks || 0) + 1;}, 10);",
+            ),
+        )
+        .expect("script failed");
+        assert_eq!(rt.exe_task_in_event_loop(active_interval_count), 1);
+        rt.destroy_realm("test_interval_realm")
+            .expect("could not destroy realm");
+        // the interval is cleared at its next tick
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while rt.exe_task_in_event_loop(active_interval_count) > 0 {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "interval was not cleared"
+            );
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(!rt.exe_task_in_event_loop(|| {
+            QuickJsRuntimeAdapter::do_with(|q_js_rt| q_js_rt.has_context("test_interval_realm"))
+        }));
+    }
 }
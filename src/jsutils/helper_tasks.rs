@@ -1,11 +1,60 @@
+use crate::jsutils::JsError;
 use futures::Future;
 use hirofa_utils::task_manager::TaskManager;
 use lazy_static::lazy_static;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 use tokio::task::JoinError;
 
+struct HelperPoolConfig {
+    started: bool,
+    size: Option<usize>,
+}
+
+static HELPER_POOL_CONFIG: Mutex<HelperPoolConfig> = Mutex::new(HelperPoolConfig {
+    started: false,
+    size: None,
+});
+
 lazy_static! {
     /// a static Multithreaded task manager used to run rust ops async and multithreaded ( in at least 2 threads)
-    static ref HELPER_TASKS: TaskManager = TaskManager::new(std::cmp::max(2, num_cpus::get()));
+    static ref HELPER_TASKS: TaskManager = {
+        let config = &mut *HELPER_POOL_CONFIG.lock().unwrap();
+        config.started = true;
+        TaskManager::new(config.size.unwrap_or_else(|| std::cmp::max(2, num_cpus::get())))
+    };
+}
+
+/// set the max number of threads the "helper" thread pool may use to run blocking tasks
+/// tasks which are added when all threads are busy are queued until a thread becomes available
+/// the pool is shared by all runtimes and is started when the first helper task is added, after that the size can no longer be changed
+/// please note that a blocking task which waits for the result of another helper task may deadlock when the pool is small
+pub fn set_helper_thread_pool_size(size: usize) -> Result<(), JsError> {
+    if size == 0 {
+        return Err(JsError::new_str(
+            "helper thread pool size must be at least 1",
+        ));
+    }
+    let config = &mut *HELPER_POOL_CONFIG.lock().unwrap();
+    if config.started {
+        Err(JsError::new_str("helper thread pool was already started"))
+    } else {
+        config.size = Some(size);
+        Ok(())
+    }
+}
+
+static HELPER_TASKS_QUEUED: AtomicUsize = AtomicUsize::new(0);
+static HELPER_TASKS_RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// decrements the running count when a task is done (or panicked)
+struct RunningGuard<'a>(&'a AtomicUsize);
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 /// add a task the the "helper" thread pool
@@ -14,7 +63,21 @@ where
     T: FnOnce() + Send + 'static,
 {
     log::trace!("adding a helper task");
-    HELPER_TASKS.add_task(task);
+    HELPER_TASKS_QUEUED.fetch_add(1, Ordering::SeqCst);
+    HELPER_TASKS.add_task(move || {
+        HELPER_TASKS_RUNNING.fetch_add(1, Ordering::SeqCst);
+        HELPER_TASKS_QUEUED.fetch_sub(1, Ordering::SeqCst);
+        let _guard = RunningGuard(&HELPER_TASKS_RUNNING);
+        task();
+    });
+}
+
+/// get the number of queued and running tasks of the shared "helper" thread pool
+pub fn get_helper_pool_metrics() -> HelperPoolMetrics {
+    HelperPoolMetrics {
+        queued: HELPER_TASKS_QUEUED.load(Ordering::SeqCst),
+        running: HELPER_TASKS_RUNNING.load(Ordering::SeqCst),
+    }
 }
 
 /// add an async task the the "helper" thread pool
@@ -24,3 +87,207 @@ pub fn add_helper_task_async<R: Send + 'static, T: Future<Output = R> + Send + '
     log::trace!("adding an async helper task");
     HELPER_TASKS.add_task_async(task)
 }
+
+/// the number of queued and running tasks of a helper pool
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HelperPoolMetrics {
+    /// tasks which were added but did not start yet
+    pub queued: usize,
+    /// tasks which are currently running
+    pub running: usize,
+}
+
+/// what to do when a task is added to a HelperPool of which the queue is full
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HelperTaskQueuePolicy {
+    /// block the adding thread until there is room in the queue
+    #[default]
+    Block,
+    /// fail with an Err
+    Reject,
+}
+
+type HelperTask = Box<dyn FnOnce() + Send>;
+
+struct HelperPoolState {
+    queue: VecDeque<HelperTask>,
+    threads: usize,
+    idle: usize,
+    running: usize,
+    shutdown: bool,
+}
+
+/// a thread pool with a fixed max number of threads and an optionally bounded queue
+/// threads are started when tasks are added, not when the pool is created
+/// this is used by a runtime instead of the shared "helper" thread pool when it was built with [crate::builder::QuickJsRuntimeBuilder::helper_thread_count] or [crate::builder::QuickJsRuntimeBuilder::helper_task_queue_max]
+pub struct HelperPool {
+    state: Arc<(Mutex<HelperPoolState>, Condvar)>,
+    thread_count: usize,
+    queue_max: Option<usize>,
+    policy: HelperTaskQueuePolicy,
+}
+
+impl HelperPool {
+    /// create a new HelperPool, queue_max is the max number of tasks which may wait for a thread
+    pub fn new(
+        thread_count: usize,
+        queue_max: Option<usize>,
+        policy: HelperTaskQueuePolicy,
+    ) -> Result<Self, JsError> {
+        if thread_count == 0 {
+            return Err(JsError::new_str("helper thread count must be at least 1"));
+        }
+        if queue_max == Some(0) {
+            return Err(JsError::new_str("helper task queue max must be at least 1"));
+        }
+        Ok(Self {
+            state: Arc::new((
+                Mutex::new(HelperPoolState {
+                    queue: VecDeque::new(),
+                    threads: 0,
+                    idle: 0,
+                    running: 0,
+                    shutdown: false,
+                }),
+                Condvar::new(),
+            )),
+            thread_count,
+            queue_max,
+            policy,
+        })
+    }
+
+    /// add a task to the pool
+    /// when the queue is full this either blocks until there is room or returns an Err, depending on the HelperTaskQueuePolicy
+    /// please note that blocking from a task running in the same pool may deadlock
+    pub fn add_task<T>(&self, task: T) -> Result<(), JsError>
+    where
+        T: FnOnce() + Send + 'static,
+    {
+        let (lock, cvar) = &*self.state;
+        let mut state = lock.lock().unwrap();
+        if let Some(queue_max) = self.queue_max {
+            while state.queue.len() >= queue_max {
+                match self.policy {
+                    HelperTaskQueuePolicy::Reject => {
+                        return Err(JsError::new_str("helper task queue is full"));
+                    }
+                    HelperTaskQueuePolicy::Block => {
+                        state = cvar.wait(state).unwrap();
+                    }
+                }
+            }
+        }
+        state.queue.push_back(Box::new(task));
+        if state.idle < state.queue.len() && state.threads < self.thread_count {
+            state.threads += 1;
+            let state_ref = self.state.clone();
+            if let Err(e) = std::thread::Builder::new()
+                .name("quickjs-helper".to_string())
+                .spawn(move || Self::run_worker(state_ref))
+            {
+                // the task was rejected so it must not be run by another thread later
+                state.queue.pop_back();
+                state.threads -= 1;
+                return Err(JsError::new_string(format!(
+                    "could not start helper thread: {e}"
+                )));
+            }
+        }
+        cvar.notify_all();
+        Ok(())
+    }
+
+    fn run_worker(state_ref: Arc<(Mutex<HelperPoolState>, Condvar)>) {
+        let (lock, cvar) = &*state_ref;
+        let mut state = lock.lock().unwrap();
+        loop {
+            if let Some(task) = state.queue.pop_front() {
+                state.running += 1;
+                // wake up threads blocked by a full queue
+                cvar.notify_all();
+                drop(state);
+                if std::panic::catch_unwind(std::panic::AssertUnwindSafe(task)).is_err() {
+                    log::error!("helper task panicked");
+                }
+                state = lock.lock().unwrap();
+                state.running -= 1;
+            } else if state.shutdown {
+                state.threads -= 1;
+                return;
+            } else {
+                state.idle += 1;
+                state = cvar.wait(state).unwrap();
+                state.idle -= 1;
+            }
+        }
+    }
+
+    /// get the number of queued and running tasks
+    pub fn get_metrics(&self) -> HelperPoolMetrics {
+        let state = self.state.0.lock().unwrap();
+        HelperPoolMetrics {
+            queued: state.queue.len(),
+            running: state.running,
+        }
+    }
+}
+
+impl Drop for HelperPool {
+    fn drop(&mut self) {
+        // threads finish the queued tasks and then exit
+        let (lock, cvar) = &*self.state;
+        lock.lock().unwrap().shutdown = true;
+        cvar.notify_all();
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::jsutils::helper_tasks::{
+        add_helper_task, set_helper_thread_pool_size, HelperPool, HelperTaskQueuePolicy,
+    };
+
+    #[test]
+    fn test_pool_size() {
+        assert!(set_helper_thread_pool_size(0).is_err());
+        let (tx, rx) = std::sync::mpsc::channel();
+        add_helper_task(move || {
+            tx.send(()).expect("could not send");
+        });
+        rx.recv().expect("helper task did not run");
+        // pool is running now
+        assert!(set_helper_thread_pool_size(4).is_err());
+    }
+
+    #[test]
+    fn test_helper_pool() {
+        assert!(HelperPool::new(0, None, HelperTaskQueuePolicy::Block).is_err());
+
+        let pool = HelperPool::new(1, Some(1), HelperTaskQueuePolicy::Reject).expect("pool failed");
+        let (start_tx, start_rx) = std::sync::mpsc::channel();
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+        pool.add_task(move || {
+            start_tx.send(()).expect("could not send");
+            let _ = done_rx.recv();
+        })
+        .expect("add failed");
+        start_rx.recv().expect("task did not start");
+        // running task does not count as queued
+        let (queued_tx, queued_rx) = std::sync::mpsc::channel();
+        pool.add_task(move || {
+            queued_tx.send(()).expect("could not send");
+        })
+        .expect("add failed");
+        let metrics = pool.get_metrics();
+        assert_eq!(metrics.running, 1);
+        assert_eq!(metrics.queued, 1);
+        // queue is full
+        assert!(pool.add_task(|| {}).is_err());
+
+        done_tx.send(()).expect("could not send");
+        queued_rx.recv().expect("queued task did not run");
+        // the queue is empty again
+        pool.add_task(|| {}).expect("add failed");
+    }
+}
@@ -10,10 +10,19 @@ pub mod jsproxies;
 pub mod modules;
 pub mod promises;
 
+/// a ScriptPreProcessor may alter the code of a script before it is compiled (e.g. to transpile it)
+/// pre processors are called for evaluated scripts and modules and for the source of modules loaded by a ScriptModuleLoader
+/// closures with the same signature as process are also ScriptPreProcessors
 pub trait ScriptPreProcessor {
     fn process(&self, script: &mut Script) -> Result<(), JsError>;
 }
 
+impl<F: Fn(&mut Script) -> Result<(), JsError>> ScriptPreProcessor for F {
+    fn process(&self, script: &mut Script) -> Result<(), JsError> {
+        self(script)
+    }
+}
+
 /// the JsValueType represents the type of value for a JSValue
 #[derive(PartialEq, Copy, Clone, Eq)]
 pub enum JsValueType {
@@ -107,6 +116,18 @@ impl From<Error> for JsError {
     }
 }
 
+impl From<String> for JsError {
+    fn from(message: String) -> Self {
+        JsError::new_string(message)
+    }
+}
+
+impl From<&str> for JsError {
+    fn from(message: &str) -> Self {
+        JsError::new_str(message)
+    }
+}
+
 pub struct Script {
     path: String,
     code: String,
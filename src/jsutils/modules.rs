@@ -1,15 +1,42 @@
+use crate::jsutils::JsError;
 use crate::quickjsrealmadapter::QuickJsRealmAdapter;
 use crate::quickjsvalueadapter::QuickJsValueAdapter;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+/// a loader which provides the source code of modules, this can be used to load modules from any source (memory, file system, database etc.)
 pub trait ScriptModuleLoader {
+    /// translate a (possibly relative) path to an absolute path, return None if this loader can not load the module
+    /// this is used for both static imports and dynamic import() so specifiers resolve the same way
     fn normalize_path(
         &self,
         realm: &QuickJsRealmAdapter,
         ref_path: &str,
         path: &str,
     ) -> Option<String>;
+    /// translate a (possibly relative) path to an absolute path, implement this if resolving may fail (e.g. a lookup in a database)
+    /// Ok(None) means this loader can not load the module, an Err is thrown as an exception in the importing script
+    /// the default implementation calls normalize_path
+    fn try_normalize_path(
+        &self,
+        realm: &QuickJsRealmAdapter,
+        ref_path: &str,
+        path: &str,
+    ) -> Result<Option<String>, JsError> {
+        Ok(self.normalize_path(realm, ref_path, path))
+    }
+    /// load the source code of a module
     fn load_module(&self, realm: &QuickJsRealmAdapter, absolute_path: &str) -> String;
+    /// load the source code of a module, implement this if loading may fail
+    /// an Err is thrown as an exception in the importing script
+    /// the default implementation calls load_module
+    fn try_load_module(
+        &self,
+        realm: &QuickJsRealmAdapter,
+        absolute_path: &str,
+    ) -> Result<String, JsError> {
+        Ok(self.load_module(realm, absolute_path))
+    }
 }
 
 pub trait CompiledModuleLoader {
@@ -22,12 +49,584 @@ pub trait CompiledModuleLoader {
     fn load_module(&self, realm: &QuickJsRealmAdapter, absolute_path: &str) -> Arc<Vec<u8>>;
 }
 
+/// a cache for the bytecode of modules loaded by a ScriptModuleLoader, this can be used to persist compiled modules (e.g. on disk) between runs
+/// the source_hash is the [crate::quickjs_utils::compile::source_hash] of the pre-processed source, entries for a different hash should be treated as stale
+/// bytecode which can not be read (e.g. because it was written by a different QuickJS version) is ignored and the module is compiled again
+/// the cache must only return bytecode written by this runtime, QuickJS does not verify bytecode so crafted entries can corrupt memory
+pub trait BytecodeCache {
+    /// get the bytecode of a module, return None if the module is not cached
+    fn get(&self, specifier: &str, source_hash: u64) -> Option<Vec<u8>>;
+    /// store the bytecode of a module after it was compiled
+    fn put(&self, specifier: &str, source_hash: u64, bytecode: Vec<u8>);
+}
+
+/// a loader which provides modules implemented in rust, e.g. `import {readFile} from 'myhost:fs'`
+/// the exports may be any value, including functions created with [crate::quickjs_utils::functions::new_function_q]
 pub trait NativeModuleLoader {
+    /// check if this loader provides a module
     fn has_module(&self, realm: &QuickJsRealmAdapter, module_name: &str) -> bool;
+    /// the names of the exports of a module, these are declared when the module is created
     fn get_module_export_names(&self, realm: &QuickJsRealmAdapter, module_name: &str) -> Vec<&str>;
+    /// the values of the exports of a module, these are set when the module is initialized
     fn get_module_exports(
         &self,
         realm: &QuickJsRealmAdapter,
         module_name: &str,
     ) -> Vec<(&str, QuickJsValueAdapter)>;
 }
+
+/// a ScriptModuleLoader which loads modules from the file system
+/// module names are resolved against a set of root directories, imports which resolve to a file outside of those roots are rejected
+/// # Example
+/// ```rust
+/// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+/// use quickjs_runtime::jsutils::modules::FileSystemModuleLoader;
+/// let rt = QuickJsRuntimeBuilder::new()
+///     .script_module_loader(FileSystemModuleLoader::new(vec!["./scripts"]))
+///     .build();
+/// ```
+pub struct FileSystemModuleLoader {
+    roots: Vec<PathBuf>,
+    extensions: Vec<String>,
+}
+
+impl FileSystemModuleLoader {
+    /// the default file extensions which are tried when a module name has no extension
+    const DEFAULT_EXTENSIONS: [&'static str; 2] = ["mjs", "js"];
+
+    /// create a new FileSystemModuleLoader which loads modules from the given root dirs
+    /// roots which do not exist are ignored
+    /// module names without an extension are resolved by trying the .mjs and .js extensions, and if the name is a dir its index.mjs or index.js file
+    /// .json files are loaded as JSON modules
+    pub fn new<P: Into<PathBuf>, I: IntoIterator<Item = P>>(roots: I) -> Self {
+        let roots = roots
+            .into_iter()
+            .filter_map(|root| {
+                let root: PathBuf = root.into();
+                match root.canonicalize() {
+                    Ok(canonical) => Some(canonical),
+                    Err(e) => {
+                        log::error!("module root {} could not be used: {}", root.display(), e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self {
+            roots,
+            extensions: Self::DEFAULT_EXTENSIONS
+                .iter()
+                .map(|ext| ext.to_string())
+                .collect(),
+        }
+    }
+
+    /// set the file extensions (without the leading dot) which are tried in order when a module name has no extension
+    /// # Example
+    /// ```rust
+    /// use quickjs_runtime::jsutils::modules::FileSystemModuleLoader;
+    /// let loader = FileSystemModuleLoader::new(vec!["./scripts"]).extensions(["es", "mjs", "js"]);
+    /// assert_eq!(loader.get_extensions(), ["es", "mjs", "js"]);
+    /// ```
+    pub fn extensions<S: Into<String>, I: IntoIterator<Item = S>>(mut self, extensions: I) -> Self {
+        self.extensions = extensions.into_iter().map(|ext| ext.into()).collect();
+        self
+    }
+
+    /// get the file extensions which are tried when a module name has no extension
+    pub fn get_extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    /// get the (canonicalized) root dirs of this loader
+    pub fn get_roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    fn is_in_roots(&self, path: &Path) -> bool {
+        self.roots.iter().any(|root| path.starts_with(root))
+    }
+
+    /// resolve a path to an existing file with one of the extensions (or to the index file of a dir) within one of the roots
+    fn resolve_file(&self, path: &Path) -> Option<PathBuf> {
+        let mut candidates = vec![];
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext == "json" || self.extensions.iter().any(|e| e == ext) => {
+                candidates.push(path.to_path_buf())
+            }
+            _ => {
+                for ext in &self.extensions {
+                    let mut candidate = path.as_os_str().to_os_string();
+                    candidate.push(".");
+                    candidate.push(ext);
+                    candidates.push(PathBuf::from(candidate));
+                }
+                for ext in &self.extensions {
+                    candidates.push(path.join(format!("index.{ext}")));
+                }
+            }
+        }
+        candidates.into_iter().find_map(|candidate| {
+            // canonicalize resolves symlinks and .. so we check the roots after that
+            let canonical = candidate.canonicalize().ok()?;
+            if canonical.is_file() && self.is_in_roots(&canonical) {
+                Some(canonical)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl ScriptModuleLoader for FileSystemModuleLoader {
+    fn normalize_path(
+        &self,
+        _realm: &QuickJsRealmAdapter,
+        ref_path: &str,
+        path: &str,
+    ) -> Option<String> {
+        let module_path = Path::new(path);
+        let resolved = if module_path.is_absolute() {
+            self.resolve_file(module_path)
+        } else if path.starts_with("./") || path.starts_with("../") {
+            // relative to the importing module if that was loaded from one of our roots, else relative to the roots
+            let ref_dir = Path::new(ref_path)
+                .parent()
+                .filter(|dir| dir.is_absolute() && self.is_in_roots(dir));
+            match ref_dir {
+                Some(dir) => self.resolve_file(&dir.join(module_path)),
+                None => self
+                    .roots
+                    .iter()
+                    .find_map(|root| self.resolve_file(&root.join(module_path))),
+            }
+        } else {
+            self.roots
+                .iter()
+                .find_map(|root| self.resolve_file(&root.join(module_path)))
+        };
+        resolved.and_then(|p| p.to_str().map(|s| s.to_string()))
+    }
+
+    /// returns an empty module if the file can not be read, the runtime uses try_load_module which throws the error in the importing script
+    fn load_module(&self, realm: &QuickJsRealmAdapter, absolute_path: &str) -> String {
+        match self.try_load_module(realm, absolute_path) {
+            Ok(code) => code,
+            Err(e) => {
+                log::error!("{}", e);
+                String::new()
+            }
+        }
+    }
+
+    fn try_load_module(
+        &self,
+        _realm: &QuickJsRealmAdapter,
+        absolute_path: &str,
+    ) -> Result<String, JsError> {
+        let bytes = std::fs::read(absolute_path).map_err(|e| {
+            JsError::new_string(format!("could not read module {absolute_path}: {e}"))
+        })?;
+        String::from_utf8(bytes).map_err(|e| {
+            JsError::new_string(format!(
+                "module {absolute_path} is not valid UTF-8: {}",
+                e.utf8_error()
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::builder::QuickJsRuntimeBuilder;
+    use crate::jsutils::modules::{FileSystemModuleLoader, ScriptModuleLoader};
+    use crate::jsutils::{JsError, Script};
+    use crate::quickjsrealmadapter::QuickJsRealmAdapter;
+    use crate::values::JsValueFacade;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    struct MapModuleLoader {
+        modules: HashMap<&'static str, Result<&'static str, &'static str>>,
+    }
+
+    impl ScriptModuleLoader for MapModuleLoader {
+        fn normalize_path(
+            &self,
+            _realm: &QuickJsRealmAdapter,
+            _ref_path: &str,
+            path: &str,
+        ) -> Option<String> {
+            if self.modules.contains_key(path) {
+                Some(path.to_string())
+            } else {
+                None
+            }
+        }
+
+        fn try_normalize_path(
+            &self,
+            realm: &QuickJsRealmAdapter,
+            ref_path: &str,
+            path: &str,
+        ) -> Result<Option<String>, JsError> {
+            if path == "mem:unresolvable" {
+                Err(JsError::new_str("lookup service is offline"))
+            } else {
+                Ok(self.normalize_path(realm, ref_path, path))
+            }
+        }
+
+        fn load_module(&self, realm: &QuickJsRealmAdapter, absolute_path: &str) -> String {
+            self.try_load_module(realm, absolute_path)
+                .expect("could not load module")
+        }
+
+        fn try_load_module(
+            &self,
+            _realm: &QuickJsRealmAdapter,
+            absolute_path: &str,
+        ) -> Result<String, JsError> {
+            match self.modules.get(absolute_path) {
+                Some(Ok(code)) => Ok(code.to_string()),
+                Some(Err(msg)) => Err(JsError::new_str(msg)),
+                None => Err(JsError::new_string(format!(
+                    "no such module {absolute_path}"
+                ))),
+            }
+        }
+    }
+
+    #[test]
+    fn test_map_loader() {
+        let mut modules = HashMap::new();
+        modules.insert("mem:a", Ok("export const a = 'A';"));
+        modules.insert("mem:broken", Err("database is offline"));
+        let rt = QuickJsRuntimeBuilder::new()
+            .script_module_loader(MapModuleLoader { modules })
+            .build();
+
+        rt.eval_module_sync(
+            None,
+            Script::new(
+                "test_map_loader.mjs",
+                "import {a} from 'mem:a';\nglobalThis.memA = a;",
+            ),
+        )
+        .expect("module failed");
+        let res = rt
+            .eval_sync(None, Script::new("test_map_loader2.js", "globalThis.memA;"))
+            .expect("script failed");
+        assert_eq!(res.get_str(), "A");
+
+        let err = rt
+            .eval_module_sync(
+                None,
+                Script::new(
+                    "test_map_loader3.mjs",
+                    "import {b} from 'mem:broken';\nglobalThis.memB = b;",
+                ),
+            )
+            .expect_err("module did not fail");
+        assert!(err.get_message().contains("database is offline"));
+
+        let err = rt
+            .eval_module_sync(
+                None,
+                Script::new(
+                    "test_map_loader4.mjs",
+                    "import {c} from 'mem:c';\nglobalThis.memC = c;",
+                ),
+            )
+            .expect_err("module did not fail");
+        assert!(err.get_message().contains("mem:c"));
+
+        let err = rt
+            .eval_module_sync(
+                None,
+                Script::new(
+                    "test_map_loader5.mjs",
+                    "import {d} from 'mem:unresolvable';\nglobalThis.memD = d;",
+                ),
+            )
+            .expect_err("module did not fail");
+        assert!(
+            err.get_message().contains("lookup service is offline"),
+            "{}",
+            err
+        );
+
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_map_loader6.js",
+                    "import('mem:unresolvable').then(() => 'imported').catch((e) => e.message);",
+                ),
+            )
+            .expect("script failed");
+        let JsValueFacade::JsPromise { cached_promise } = res else {
+            panic!("import() did not return a promise");
+        };
+        let res = cached_promise
+            .get_promise_result_sync_timeout(Some(Duration::from_secs(5)))
+            .expect("import() timed out")
+            .expect("import() was rejected");
+        assert_eq!(
+            res.get_str(),
+            "Module mem:unresolvable could not be resolved: lookup service is offline"
+        );
+    }
+
+    fn create_test_dirs(name: &str) -> PathBuf {
+        let base =
+            std::env::temp_dir().join(format!("quickjs_runtime_{}_{}", name, std::process::id()));
+        let root = base.join("root");
+        std::fs::create_dir_all(root.join("lib")).expect("could not create dirs");
+        std::fs::write(
+            root.join("foo.js"),
+            "import {y} from './lib/bar.mjs';\nexport const x = 'x' + y;",
+        )
+        .expect("could not write foo.js");
+        std::fs::write(
+            root.join("lib").join("bar.mjs"),
+            "import {z} from '../baz';\nexport const y = 'y' + z;",
+        )
+        .expect("could not write bar.mjs");
+        std::fs::write(root.join("baz.js"), "export const z = 'z';").expect("could not write");
+        std::fs::write(base.join("secret.js"), "export const s = 'secret';")
+            .expect("could not write secret.js");
+        base
+    }
+
+    #[test]
+    fn test_fs_loader() {
+        let base = create_test_dirs("fs_loader");
+        let rt = QuickJsRuntimeBuilder::new()
+            .file_system_module_loader(vec![base.join("root")])
+            .build();
+
+        rt.eval_module_sync(
+            None,
+            Script::new(
+                "test_fs_loader.mjs",
+                "import {x} from './foo.js';\nglobalThis.fsLoaded = x;",
+            ),
+        )
+        .expect("module failed");
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_fs_loader2.js", "globalThis.fsLoaded;"),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_str(), "xyz");
+
+        let res = rt.eval_module_sync(
+            None,
+            Script::new(
+                "test_fs_loader3.mjs",
+                "import {s} from '../secret.js';\nglobalThis.leaked = s;",
+            ),
+        );
+        assert!(res.is_err());
+        let res = rt.eval_module_sync(
+            None,
+            Script::new(
+                "test_fs_loader4.mjs",
+                format!(
+                    "import {{s}} from '{}';\nglobalThis.leaked = s;",
+                    base.join("secret.js").display()
+                )
+                .as_str(),
+            ),
+        );
+        assert!(res.is_err());
+
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn test_fs_loader_import_meta() {
+        let base = create_test_dirs("fs_loader_meta");
+        let meta_path = base.join("root").join("lib").join("meta.mjs");
+        std::fs::write(
+            &meta_path,
+            "export const url = import.meta.url;\nexport const main = import.meta.main;",
+        )
+        .expect("could not write meta.mjs");
+        let rt = QuickJsRuntimeBuilder::new()
+            .file_system_module_loader(vec![base.join("root")])
+            .build();
+
+        rt.eval_module_sync(
+            None,
+            Script::new(
+                "test_fs_loader_meta.mjs",
+                "import {url, main} from './lib/meta.mjs';\nglobalThis.metaRes = [url, main, import.meta.url, import.meta.main];",
+            ),
+        )
+        .expect("module failed");
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_fs_loader_meta.js", "globalThis.metaRes.join('|');"),
+            )
+            .expect("script failed");
+        let expected = format!(
+            "{}|false|test_fs_loader_meta.mjs|true",
+            meta_path.canonicalize().expect("no such file").display()
+        );
+        assert_eq!(res.get_str(), expected.as_str());
+
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn test_fs_loader_dynamic_import() {
+        let base = create_test_dirs("fs_loader_dyn");
+        std::fs::write(
+            base.join("root").join("lib").join("dyn.mjs"),
+            "import * as staticBaz from '../baz.js';\nexport const load = () => import('../baz.js').then((ns) => ns === staticBaz ? ns.z : 'other namespace');",
+        )
+        .expect("could not write dyn.mjs");
+        let rt = QuickJsRuntimeBuilder::new()
+            .file_system_module_loader(vec![base.join("root")])
+            .build();
+
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_fs_loader_dyn.js",
+                    "import('./foo.js').then((ns) => {globalThis.dynX = ns.x; return import('./lib/dyn.mjs');}).then((ns) => ns.load()).then((z) => {globalThis.dynZ = z;}).catch((e) => {globalThis.dynX = 'failed: ' + e;});",
+                ),
+            )
+            .expect("script failed");
+        assert!(res.is_js_promise());
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_fs_loader_dyn2.js",
+                    "globalThis.dynX + ':' + globalThis.dynZ;",
+                ),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_str(), "xyz:z");
+
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn test_fs_loader_extensions_and_index() {
+        let base =
+            std::env::temp_dir().join(format!("quickjs_runtime_fs_ext_{}", std::process::id()));
+        let root = base.join("root");
+        std::fs::create_dir_all(root.join("lib")).expect("could not create dirs");
+        std::fs::create_dir_all(root.join("widgets")).expect("could not create dirs");
+        std::fs::write(
+            root.join("main.mes"),
+            "import {helper} from './lib/helpers.js';\nimport {widget} from './widgets';\nimport {es} from './lib/util';\nimport cfg from './lib/config.json';\nglobalThis.fsExtResult = helper + widget + es + cfg.suffix;",
+        )
+        .expect("could not write");
+        std::fs::write(
+            root.join("lib").join("helpers.js"),
+            "import {shared} from '../shared.js';\nexport const helper = 'helper+' + shared;",
+        )
+        .expect("could not write");
+        std::fs::write(root.join("shared.js"), "export const shared = 'shared';")
+            .expect("could not write");
+        std::fs::write(
+            root.join("widgets").join("index.js"),
+            "export const widget = '+widget';",
+        )
+        .expect("could not write");
+        std::fs::write(root.join("lib").join("util.es"), "export const es = '+es';")
+            .expect("could not write");
+        std::fs::write(
+            root.join("lib").join("config.json"),
+            "{\"suffix\": \"+json\"}",
+        )
+        .expect("could not write");
+        std::fs::write(root.join("broken.js"), b"export const s = '\xff\xfe';")
+            .expect("could not write");
+
+        let rt = QuickJsRuntimeBuilder::new()
+            .script_module_loader(
+                FileSystemModuleLoader::new(vec![root.clone()]).extensions(["js", "es"]),
+            )
+            .build();
+
+        let main_path = root.join("main.mes").canonicalize().unwrap();
+        let main_src = std::fs::read_to_string(&main_path).unwrap();
+        rt.eval_module_sync(
+            None,
+            Script::new(main_path.to_str().unwrap(), main_src.as_str()),
+        )
+        .expect("module failed");
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_fs_ext.js", "globalThis.fsExtResult;"),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_str(), "helper+shared+widget+es+json");
+
+        let err = rt
+            .eval_module_sync(
+                None,
+                Script::new("test_fs_ext2.mjs", "import {s} from './broken.js';"),
+            )
+            .expect_err("module did not fail");
+        assert!(err.get_message().contains("not valid UTF-8"), "{}", err);
+
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn test_fs_loader_missing_file() {
+        let base =
+            std::env::temp_dir().join(format!("quickjs_runtime_fs_missing_{}", std::process::id()));
+        std::fs::create_dir_all(&base).expect("could not create dirs");
+        let missing = base.join("missing.js");
+        let missing = missing.to_str().unwrap().to_string();
+
+        let rt = QuickJsRuntimeBuilder::new().build();
+        let loader = FileSystemModuleLoader::new(vec![base.clone()]);
+        let missing2 = missing.clone();
+        let (try_res, load_res) = rt.exe_rt_task_in_event_loop(move |q_js_rt| {
+            let realm = q_js_rt.get_main_realm();
+            let try_res = loader.try_load_module(realm, missing2.as_str());
+            let load_res = loader.load_module(realm, missing2.as_str());
+            (try_res, load_res)
+        });
+        let err = try_res.expect_err("missing module was loaded");
+        assert!(
+            err.get_message().contains("could not read module"),
+            "{}",
+            err
+        );
+        assert_eq!(load_res, "");
+
+        // importing the missing file throws in the importing script
+        let rt = QuickJsRuntimeBuilder::new()
+            .script_module_loader(FileSystemModuleLoader::new(vec![base.clone()]))
+            .build();
+        let err = rt
+            .eval_module_sync(
+                None,
+                Script::new(
+                    "test_fs_missing.mjs",
+                    format!("import {{m}} from '{missing}';").as_str(),
+                ),
+            )
+            .expect_err("module did not fail");
+        assert_eq!(err.get_name(), "ReferenceError");
+        assert!(err.get_message().contains("was not found"), "{}", err);
+
+        let _ = std::fs::remove_dir_all(base);
+    }
+}
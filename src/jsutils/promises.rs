@@ -1,13 +1,22 @@
-use crate::jsutils::helper_tasks::{add_helper_task, add_helper_task_async};
+use crate::facades::QuickjsRuntimeFacadeInner;
+use crate::jsutils::helper_tasks::add_helper_task_async;
 use crate::jsutils::JsError;
 use crate::quickjs_utils::promises::QuickJsPromiseAdapter;
 use crate::quickjsrealmadapter::QuickJsRealmAdapter;
+use crate::quickjsruntimeadapter::QuickJsRuntimeAdapter;
 use crate::quickjsvalueadapter::QuickJsValueAdapter;
+use futures::future::BoxFuture;
 use futures::Future;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
 
 #[allow(clippy::type_complexity)]
 /// create a new promise with a producer and a mapper
 /// the producer will run in a helper thread(in the tokio thread pool) and thus get a result asynchronously
+/// if the producer or mapper fails the promise is rejected with an Error which has the name, message and stack of the JsError
+/// (String errors may be converted with JsError::from or `?`)
 /// the resulting value will then be mapped to a JSValueRef by the mapper in the EventQueue thread
 /// the promise which was returned is then resolved with the value which is returned by the mapper
 pub fn new_resolving_promise<P, R, M>(
@@ -25,203 +34,1518 @@ where
     let return_ref = promise_ref.js_promise_get_value(realm);
 
     // add to map and keep id
-    let id = realm.cache_promise(promise_ref);
+    let id = cache_resolving_promise(realm, promise_ref);
 
     let rti_ref = realm.get_runtime_facade_inner();
 
     let realm_id = realm.get_realm_id().to_string();
     // go async
-    add_helper_task(move || {
+    add_producer_task(realm, id, move || {
         // in helper thread, produce result
         let produced_result = producer();
-        if let Some(rti) = rti_ref.upgrade() {
-            rti.add_rt_task_to_event_loop_void(move |rt| {
-                if let Some(realm) = rt.get_realm(realm_id.as_str()) {
-                    // in q_js_rt worker thread, resolve promise
-                    // retrieve promise
-                    let prom_ref_opt: Option<QuickJsPromiseAdapter> =
-                        realm.consume_cached_promise(id);
-                    if let Some(prom_ref) = prom_ref_opt {
-                        //let prom_ref = realm.js_promise_cache_consume(id);
-                        match produced_result {
-                            Ok(ok_res) => {
-                                // map result to JSValueRef
-                                let raw_res = mapper(realm, ok_res);
-
-                                // resolve or reject promise
-                                match raw_res {
-                                    Ok(val_ref) => {
-                                        if let Err(e) = prom_ref.js_promise_resolve(realm, &val_ref)
-                                        {
-                                            log::error!(
-                                                "[{}] could not resolve promise5: {}",
-                                                realm.get_realm_id(),
-                                                e
-                                            );
-                                        }
-                                    }
-                                    Err(err) => {
-                                        let err_ref = realm
-                                            .create_error(
-                                                err.get_name(),
-                                                err.get_message(),
-                                                err.get_stack(),
-                                            )
-                                            .expect("could not create error");
-                                        if let Err(e) = prom_ref.js_promise_reject(realm, &err_ref)
-                                        {
-                                            log::error!(
-                                                "[{}] could not reject promise4: {}",
-                                                realm.get_realm_id(),
-                                                e
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                            Err(err) => {
-                                // todo use error:new_error(err)
-                                let err_ref = realm
-                                    .create_error(
-                                        err.get_name(),
-                                        err.get_message(),
-                                        err.get_stack(),
-                                    )
-                                    .expect("could not create error");
-                                if let Err(e) = prom_ref.js_promise_reject(realm, &err_ref) {
-                                    log::error!(
-                                        "[{}] could not reject promise3: {}",
-                                        realm.get_realm_id(),
-                                        e
-                                    );
-                                }
-                            }
-                        }
-                    } else {
-                        log::error!(
-                            "async promise running for dropped realm: {} promise_id:{}",
-                            realm_id,
-                            id
-                        );
-                    }
-                } else {
-                    log::error!("async promise running for dropped realm: {}", realm_id);
-                }
-            });
-        } else {
-            log::error!("async promise running for dropped runtime");
-        }
-    });
+        resolve_cached_promise(rti_ref, realm_id, id, produced_result, mapper);
+    })?;
 
     Ok(return_ref)
 }
 
+pub(crate) struct PromiseTokenState {
+    cancelled: AtomicBool,
+    reason: Mutex<Option<String>>,
+}
+
+/// a token which is passed to the producer of new_resolving_promise_with_token
+/// it is cancelled when the runtime or the realm of the promise is dropped, a long running producer may poll it to stop early
+#[derive(Clone)]
+pub struct PromiseToken {
+    state: Arc<PromiseTokenState>,
+}
+
+impl PromiseToken {
+    fn new() -> Self {
+        Self::from_state(Arc::new(PromiseTokenState {
+            cancelled: AtomicBool::new(false),
+            reason: Mutex::new(None),
+        }))
+    }
+
+    pub(crate) fn from_state(state: Arc<PromiseTokenState>) -> Self {
+        Self { state }
+    }
+
+    pub(crate) fn downgrade(&self) -> Weak<PromiseTokenState> {
+        Arc::downgrade(&self.state)
+    }
+
+    /// cancel the token, only the first reason is kept
+    pub(crate) fn cancel(&self, reason: &str) {
+        let cur_reason = &mut *self.state.reason.lock().unwrap();
+        if cur_reason.is_none() {
+            log::trace!("cancelling promise token: {}", reason);
+            *cur_reason = Some(reason.to_string());
+            self.state.cancelled.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// check if the result of the producer is no longer needed
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// get the reason why the token was cancelled (e.g. "runtime was dropped"), None if it was not cancelled
+    pub fn cancelled_reason(&self) -> Option<String> {
+        self.state.reason.lock().unwrap().clone()
+    }
+}
+
+/// create a new promise with a producer and a mapper, the producer gets a PromiseToken which is cancelled when the runtime or the realm is dropped
+/// a long running producer may check the token to stop producing a result which nobody will receive
+/// # Example
+/// ```rust
+/// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+/// use quickjs_runtime::jsutils::promises::new_resolving_promise_with_token;
+/// let rt = QuickJsRuntimeBuilder::new().build();
+/// rt.loop_realm_sync(None, |_rt, realm| {
+///     let _prom = new_resolving_promise_with_token(realm, |token| {
+///         let mut sum = 0;
+///         for x in 0..1000 {
+///             if token.is_cancelled() {
+///                 return Err(quickjs_runtime::jsutils::JsError::new_str("cancelled"));
+///             }
+///             sum += x;
+///         }
+///         Ok(sum)
+///     }, |realm, res| realm.create_i32(res)).expect("could not create promise");
+/// });
+/// ```
+pub fn new_resolving_promise_with_token<P, R, M>(
+    realm: &QuickJsRealmAdapter,
+    producer: P,
+    mapper: M,
+) -> Result<QuickJsValueAdapter, JsError>
+where
+    R: Send + 'static,
+    P: FnOnce(&PromiseToken) -> Result<R, JsError> + Send + 'static,
+    M: FnOnce(&QuickJsRealmAdapter, R) -> Result<QuickJsValueAdapter, JsError> + Send + 'static,
+{
+    let token = PromiseToken::new();
+    realm.register_promise_token(&token);
+    new_resolving_promise(realm, move || producer(&token), mapper)
+}
+
 #[allow(clippy::type_complexity)]
 /// create a new promise with an async producer and a mapper
-/// the producer will be awaited asynchronously and
+/// the producer will be awaited asynchronously (in the tokio thread pool, without blocking a helper thread) and
+/// the resulting value will then be mapped to a JSValueRef by the mapper in the EventQueue thread
+/// the promise which was returned is then resolved with the value which is returned by the mapper
+pub fn new_resolving_promise_async<P, R, M>(
+    realm: &QuickJsRealmAdapter,
+    producer: P,
+    mapper: M,
+) -> Result<QuickJsValueAdapter, JsError>
+where
+    R: Send + 'static,
+    P: Future<Output = Result<R, JsError>> + Send + 'static,
+    M: FnOnce(&QuickJsRealmAdapter, R) -> Result<QuickJsValueAdapter, JsError> + Send + 'static,
+{
+    new_resolving_promise_with_spawner(realm, producer, mapper, |fut| {
+        let _ignore_result = add_helper_task_async(fut);
+    })
+}
+
+#[allow(clippy::type_complexity)]
+/// create a new promise with an async producer and a mapper, the producer is passed to the spawner which should run it on an executor of choice
 /// the resulting value will then be mapped to a JSValueRef by the mapper in the EventQueue thread
 /// the promise which was returned is then resolved with the value which is returned by the mapper
-pub(crate) fn new_resolving_promise_async<P, R, M>(
+/// if the spawner (or its executor) drops the future before it completed, the promise is rejected
+/// # Example
+/// ```rust
+/// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+/// use quickjs_runtime::jsutils::promises::new_resolving_promise_with_spawner;
+/// let rt = QuickJsRuntimeBuilder::new().build();
+/// rt.loop_realm_sync(None, |_rt, realm| {
+///     new_resolving_promise_with_spawner(
+///         realm,
+///         async { Ok(12) },
+///         |realm, res| realm.create_i32(res),
+///         |fut| {
+///             std::thread::spawn(move || futures::executor::block_on(fut));
+///         },
+///     )
+///     .expect("could not create promise");
+/// });
+/// ```
+pub fn new_resolving_promise_with_spawner<P, R, M, S>(
     realm: &QuickJsRealmAdapter,
     producer: P,
     mapper: M,
+    spawner: S,
 ) -> Result<QuickJsValueAdapter, JsError>
 where
     R: Send + 'static,
     P: Future<Output = Result<R, JsError>> + Send + 'static,
     M: FnOnce(&QuickJsRealmAdapter, R) -> Result<QuickJsValueAdapter, JsError> + Send + 'static,
+    S: FnOnce(BoxFuture<'static, ()>),
 {
     // create promise
     let promise_ref = realm.create_promise()?;
     let return_ref = promise_ref.js_promise_get_value(realm);
 
     // add to map and keep id
-    let id = realm.cache_promise(promise_ref);
+    let id = cache_resolving_promise(realm, promise_ref);
 
     let rti_ref = realm.get_runtime_facade_inner();
 
     let realm_id = realm.get_realm_id().to_string();
+    let mut guard = DroppedProducerGuard {
+        rti_ref: Some(rti_ref),
+        realm_id: realm_id.clone(),
+        id,
+    };
     // go async
-    let _ignore_result = add_helper_task_async(async move {
-        // in helper thread, produce result
+    spawner(Box::pin(async move {
+        // in executor, produce result
         let produced_result = producer.await;
-        if let Some(rti) = rti_ref.upgrade() {
+        if let Some(rti_ref) = guard.rti_ref.take() {
+            resolve_cached_promise(rti_ref, realm_id, id, produced_result, mapper);
+        }
+    }));
+    Ok(return_ref)
+}
+
+/// rejects a cached promise if the future which should resolve it is dropped before it completed
+struct DroppedProducerGuard {
+    rti_ref: Option<Weak<QuickjsRuntimeFacadeInner>>,
+    realm_id: String,
+    id: usize,
+}
+
+impl Drop for DroppedProducerGuard {
+    fn drop(&mut self) {
+        let Some(rti) = self.rti_ref.take().and_then(|rti_ref| rti_ref.upgrade()) else {
+            return;
+        };
+        let realm_id = std::mem::take(&mut self.realm_id);
+        let id = self.id;
+        rti.add_rt_task_to_event_loop_void(move |rt| {
+            if let Some(realm) = rt.get_realm(realm_id.as_str()) {
+                if let Some(prom_ref) = realm.consume_cached_promise(id) {
+                    record_promise_event(realm, id, ResolvingPromiseEvent::Rejected);
+                    reject_with_error(
+                        realm,
+                        &prom_ref,
+                        JsError::new_str("the producer of this promise was dropped"),
+                    );
+                }
+            }
+        });
+    }
+}
+
+/// a handle which may be used to cancel a promise created by new_resolving_promise_cancellable
+pub struct ResolvingPromiseHandle {
+    rti_ref: Weak<QuickjsRuntimeFacadeInner>,
+    realm_id: String,
+    id: usize,
+    cancelled: Arc<AtomicBool>,
+    // set by whoever settles the promise first (the producer or cancel)
+    settled: Arc<AtomicBool>,
+}
+
+impl ResolvingPromiseHandle {
+    /// cancel the promise, the promise is rejected with a CancelledError and the result of the producer (if any) is discarded
+    /// this may be called from any thread
+    pub fn cancel(&self) {
+        self.reject_early(JsError::new(
+            "CancelledError".to_string(),
+            "cancelled".to_string(),
+            "".to_string(),
+        ));
+    }
+    /// check if cancel was called
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+    /// reject the promise if the producer did not deliver a result yet
+    fn reject_early(&self, err: JsError) {
+        if self.settled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        self.cancelled.store(true, Ordering::SeqCst);
+        if let Some(rti) = self.rti_ref.upgrade() {
+            let realm_id = self.realm_id.clone();
+            let id = self.id;
             rti.add_rt_task_to_event_loop_void(move |rt| {
                 if let Some(realm) = rt.get_realm(realm_id.as_str()) {
-                    // in q_js_rt worker thread, resolve promise
-                    // retrieve promise
-                    let prom_ref_opt: Option<QuickJsPromiseAdapter> =
-                        realm.consume_cached_promise(id);
-                    if let Some(prom_ref) = prom_ref_opt {
-                        //let prom_ref = realm.js_promise_cache_consume(id);
-                        match produced_result {
-                            Ok(ok_res) => {
-                                // map result to JSValueRef
-                                let raw_res = mapper(realm, ok_res);
-
-                                // resolve or reject promise
-                                match raw_res {
-                                    Ok(val_ref) => {
-                                        if let Err(e) = prom_ref.js_promise_resolve(realm, &val_ref)
-                                        {
-                                            log::error!(
-                                                "[{}] could not resolve promise: {}",
-                                                realm.get_realm_id(),
-                                                e
-                                            );
-                                        }
-                                    }
-                                    Err(err) => {
-                                        let err_ref = realm
-                                            .create_error(
-                                                err.get_name(),
-                                                err.get_message(),
-                                                err.get_stack(),
-                                            )
-                                            .expect("could not create err");
-                                        if let Err(e) = prom_ref.js_promise_reject(realm, &err_ref)
-                                        {
-                                            log::error!(
-                                                "[{}] could not reject promise: {}",
-                                                realm.get_realm_id(),
-                                                e
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                            Err(err) => {
-                                // todo use error:new_error(err)
-                                let err_ref = realm
-                                    .create_error(
-                                        err.get_name(),
-                                        err.get_message(),
-                                        err.get_stack(),
-                                    )
-                                    .expect("could not create str");
-                                if let Err(e) = prom_ref.js_promise_reject(realm, &err_ref) {
-                                    log::error!(
-                                        "[{}] could not reject promise2: {}",
-                                        realm.get_realm_id(),
-                                        e
-                                    );
-                                }
+                    if let Some(prom_ref) = realm.consume_cached_promise(id) {
+                        record_promise_event(realm, id, ResolvingPromiseEvent::Rejected);
+                        reject_with_error(realm, &prom_ref, err);
+                    }
+                }
+            });
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+/// create a new promise with a producer and a mapper which may be cancelled by using the returned ResolvingPromiseHandle
+/// the producer gets a flag which it may poll to see if the promise was cancelled, if so the mapper is not called
+pub fn new_resolving_promise_cancellable<P, R, M>(
+    realm: &QuickJsRealmAdapter,
+    producer: P,
+    mapper: M,
+) -> Result<(QuickJsValueAdapter, ResolvingPromiseHandle), JsError>
+where
+    R: Send + 'static,
+    P: FnOnce(Arc<AtomicBool>) -> Result<R, JsError> + Send + 'static,
+    M: FnOnce(&QuickJsRealmAdapter, R) -> Result<QuickJsValueAdapter, JsError> + Send + 'static,
+{
+    // create promise
+    let promise_ref = realm.create_promise()?;
+    let return_ref = promise_ref.js_promise_get_value(realm);
+
+    // add to map and keep id
+    let id = cache_resolving_promise(realm, promise_ref);
+
+    let rti_ref = realm.get_runtime_facade_inner();
+    let realm_id = realm.get_realm_id().to_string();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let settled = Arc::new(AtomicBool::new(false));
+
+    let handle = ResolvingPromiseHandle {
+        rti_ref: rti_ref.clone(),
+        realm_id: realm_id.clone(),
+        id,
+        cancelled: cancelled.clone(),
+        settled: settled.clone(),
+    };
+
+    // go async
+    add_producer_task(realm, id, move || {
+        // in helper thread, produce result
+        let produced_result = producer(cancelled);
+        if settled.swap(true, Ordering::SeqCst) {
+            log::trace!("resolving promise {} was cancelled", id);
+            return;
+        }
+        resolve_cached_promise(rti_ref, realm_id, id, produced_result, mapper);
+    })?;
+
+    Ok((return_ref, handle))
+}
+
+#[allow(clippy::type_complexity)]
+/// create a new promise with a producer and a mapper
+/// if the producer does not deliver a result within the timeout the promise is rejected with a TimeoutError and the result of the producer is discarded
+pub fn new_resolving_promise_with_timeout<P, R, M>(
+    realm: &QuickJsRealmAdapter,
+    producer: P,
+    mapper: M,
+    timeout: Duration,
+) -> Result<QuickJsValueAdapter, JsError>
+where
+    R: Send + 'static,
+    P: FnOnce() -> Result<R, JsError> + Send + 'static,
+    M: FnOnce(&QuickJsRealmAdapter, R) -> Result<QuickJsValueAdapter, JsError> + Send + 'static,
+{
+    // the timer stops as soon as the producer is done so it does not outlive the promise
+    let (done_tx, done_rx) = flume::bounded::<()>(1);
+    let (return_ref, handle) = new_resolving_promise_cancellable(
+        realm,
+        move |_cancelled| {
+            let res = producer();
+            let _ = done_tx.send(());
+            res
+        },
+        mapper,
+    )?;
+    let _ignore_result = add_helper_task_async(async move {
+        tokio::select! {
+            _ = tokio::time::sleep(timeout) => {
+                handle.reject_early(JsError::new(
+                    "TimeoutError".to_string(),
+                    format!("promise timed out after {timeout:?}"),
+                    "".to_string(),
+                ));
+            }
+            _ = done_rx.recv_async() => {}
+        }
+    });
+    Ok(return_ref)
+}
+
+/// used by the producer of new_resolving_promise_with_progress to report progress
+pub struct ProgressSender<PR> {
+    rti_ref: Weak<QuickjsRuntimeFacadeInner>,
+    realm_id: String,
+    id: usize,
+    callback_name: String,
+    #[allow(clippy::type_complexity)]
+    progress_mapper:
+        Arc<dyn Fn(&QuickJsRealmAdapter, PR) -> Result<QuickJsValueAdapter, JsError> + Send + Sync>,
+}
+
+impl<PR: Send + 'static> ProgressSender<PR> {
+    /// send a progress value, it is mapped in the EventQueue thread and passed to the progress callback of the promise (if set)
+    pub fn send(&self, progress: PR) {
+        if let Some(rti) = self.rti_ref.upgrade() {
+            let realm_id = self.realm_id.clone();
+            let id = self.id;
+            let callback_name = self.callback_name.clone();
+            let progress_mapper = self.progress_mapper.clone();
+            rti.add_rt_task_to_event_loop_void(move |rt| {
+                if let Some(realm) = rt.get_realm(realm_id.as_str()) {
+                    // if the promise was already resolved the progress is discarded
+                    if let Some(prom_ref) = realm.get_cached_promise(id) {
+                        let res = (|| {
+                            let prom_obj = prom_ref.js_promise_get_value(realm);
+                            let callback =
+                                realm.get_object_property(&prom_obj, callback_name.as_str())?;
+                            if callback.is_function() {
+                                let val = progress_mapper(realm, progress)?;
+                                realm.invoke_function(Some(&prom_obj), &callback, &[&val])?;
                             }
+                            Ok::<(), JsError>(())
+                        })();
+                        if let Err(e) = res {
+                            log::error!(
+                                "[{}] could not report progress for promise: {}",
+                                realm_id,
+                                e
+                            );
                         }
-                    } else {
-                        log::error!(
-                            "async promise running on dropped realm: {} promise_id:{}",
-                            realm_id,
-                            id
-                        );
                     }
-                } else {
-                    log::error!("async promise running on dropped realm: {}", realm_id);
                 }
             });
-        } else {
-            log::error!("async promise running on dropped runtime");
         }
-    });
+    }
+}
+
+#[allow(clippy::type_complexity)]
+/// create a new promise with a producer and a mapper, the producer gets a ProgressSender which it may use to report progress
+/// progress values are mapped by the progress_mapper in the EventQueue thread and passed to the function which script set as
+/// property named progress_callback_name on the returned promise
+/// # Example
+/// ```javascript
+/// let prom = startTranscode();
+/// prom.onProgress = (pct) => {console.log("progress: %s", pct);};
+/// let result = await prom;
+/// ```
+pub fn new_resolving_promise_with_progress<P, R, PR, M, PM>(
+    realm: &QuickJsRealmAdapter,
+    producer: P,
+    mapper: M,
+    progress_mapper: PM,
+    progress_callback_name: &str,
+) -> Result<QuickJsValueAdapter, JsError>
+where
+    R: Send + 'static,
+    PR: Send + 'static,
+    P: FnOnce(ProgressSender<PR>) -> Result<R, JsError> + Send + 'static,
+    M: FnOnce(&QuickJsRealmAdapter, R) -> Result<QuickJsValueAdapter, JsError> + Send + 'static,
+    PM: Fn(&QuickJsRealmAdapter, PR) -> Result<QuickJsValueAdapter, JsError>
+        + Send
+        + Sync
+        + 'static,
+{
+    // create promise
+    let promise_ref = realm.create_promise()?;
+    let return_ref = promise_ref.js_promise_get_value(realm);
+
+    // add to map and keep id
+    let id = cache_resolving_promise(realm, promise_ref);
+
+    let rti_ref = realm.get_runtime_facade_inner();
+    let realm_id = realm.get_realm_id().to_string();
+
+    let progress_sender = ProgressSender {
+        rti_ref: rti_ref.clone(),
+        realm_id: realm_id.clone(),
+        id,
+        callback_name: progress_callback_name.to_string(),
+        progress_mapper: Arc::new(progress_mapper),
+    };
+
+    // go async
+    add_producer_task(realm, id, move || {
+        // in helper thread, produce result
+        let produced_result = producer(progress_sender);
+        resolve_cached_promise(rti_ref, realm_id, id, produced_result, mapper);
+    })?;
+
     Ok(return_ref)
 }
+
+/// add the producer task of a cached promise to the helper thread pool of the runtime
+/// if the task could not be added (e.g. because the queue is full) the promise is removed from the cache
+fn add_producer_task<T>(realm: &QuickJsRealmAdapter, id: usize, task: T) -> Result<(), JsError>
+where
+    T: FnOnce() + Send + 'static,
+{
+    let res = match realm.get_runtime_facade_inner().upgrade() {
+        Some(rti) => rti.add_helper_task(task),
+        None => Err(JsError::new_str("runtime was dropped")),
+    };
+    if res.is_err() {
+        let _ = realm.consume_cached_promise(id);
+        record_promise_event(realm, id, ResolvingPromiseEvent::Rejected);
+    }
+    res
+}
+
+/// add a task to the event loop which consumes a cached promise and resolves or rejects it with the produced result
+fn resolve_cached_promise<R, M>(
+    rti_ref: Weak<QuickjsRuntimeFacadeInner>,
+    realm_id: String,
+    id: usize,
+    produced_result: Result<R, JsError>,
+    mapper: M,
+) where
+    R: Send + 'static,
+    M: FnOnce(&QuickJsRealmAdapter, R) -> Result<QuickJsValueAdapter, JsError> + Send + 'static,
+{
+    if let Some(rti) = rti_ref.upgrade() {
+        rti.add_rt_task_to_event_loop_void(move |rt| {
+            if let Some(realm) = rt.get_realm(realm_id.as_str()) {
+                settle_cached_promise(realm, id, produced_result, mapper);
+            } else {
+                log::error!("async promise running for dropped realm: {}", realm_id);
+            }
+        });
+    } else {
+        log::error!("async promise running for dropped runtime");
+    }
+}
+
+/// settle a promise from the realms promise cache, this should be called from the EventQueue thread
+fn settle_cached_promise<R, M>(
+    realm: &QuickJsRealmAdapter,
+    id: usize,
+    produced_result: Result<R, JsError>,
+    mapper: M,
+) where
+    M: FnOnce(&QuickJsRealmAdapter, R) -> Result<QuickJsValueAdapter, JsError>,
+{
+    // retrieve promise
+    let prom_ref_opt: Option<QuickJsPromiseAdapter> = realm.consume_cached_promise(id);
+    if let Some(prom_ref) = prom_ref_opt {
+        // map result to JSValueRef
+        match produced_result.and_then(|ok_res| mapper(realm, ok_res)) {
+            Ok(val_ref) => {
+                record_promise_event(realm, id, ResolvingPromiseEvent::Resolved);
+                // if the mapper returned a promise (or other thenable) the resolve function adopts its state
+                // so the returned promise only settles when the inner promise does
+                if let Err(e) = prom_ref.js_promise_resolve(realm, &val_ref) {
+                    log::error!(
+                        "[{}] could not resolve promise: {}",
+                        realm.get_realm_id(),
+                        e
+                    );
+                }
+            }
+            Err(err) => {
+                record_promise_event(realm, id, ResolvingPromiseEvent::Rejected);
+                reject_with_error(realm, &prom_ref, err);
+            }
+        }
+    } else {
+        log::error!(
+            "async promise running for dropped realm: {} promise_id:{}",
+            realm.get_realm_id(),
+            id
+        );
+    }
+}
+
+/// what happened to a promise which is resolved from rust, see ResolvingPromiseStatsListener
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResolvingPromiseEvent {
+    Created,
+    Resolved,
+    Rejected,
+    /// the realm of the promise was dropped before the promise was settled
+    ContextDropped,
+}
+
+/// a listener which is notified when promises which are resolved from rust (e.g. by new_resolving_promise) are created and settled
+/// see [crate::builder::QuickJsRuntimeBuilder::resolving_promise_stats_listener]
+pub trait ResolvingPromiseStatsListener: Send + Sync {
+    /// called from the EventQueue thread
+    fn on_event(&self, promise_id: &PromiseId, event: ResolvingPromiseEvent, at: Instant);
+}
+
+/// a snapshot of the counts of promises which are resolved from rust
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResolvingPromiseStatsSnapshot {
+    /// promises which were created but not settled yet
+    pub outstanding: usize,
+    pub total_created: u64,
+    pub total_resolved: u64,
+    pub total_rejected: u64,
+    pub total_context_dropped: u64,
+}
+
+#[derive(Default)]
+struct ResolvingPromiseCounts {
+    // (realm_id, id) of the outstanding promises, settling an unknown promise is not counted
+    outstanding: HashSet<(String, usize)>,
+    snapshot: ResolvingPromiseStatsSnapshot,
+}
+
+/// collects the ResolvingPromiseStatsSnapshot of a runtime, this only exists if the runtime was built with stats
+pub(crate) struct ResolvingPromiseStats {
+    counts: Mutex<ResolvingPromiseCounts>,
+    listener: Option<Box<dyn ResolvingPromiseStatsListener>>,
+}
+
+impl ResolvingPromiseStats {
+    pub(crate) fn new(listener: Option<Box<dyn ResolvingPromiseStatsListener>>) -> Self {
+        Self {
+            counts: Mutex::new(Default::default()),
+            listener,
+        }
+    }
+
+    fn record(&self, realm_id: &str, id: usize, event: ResolvingPromiseEvent) {
+        {
+            let counts = &mut *self.counts.lock().unwrap();
+            let key = (realm_id.to_string(), id);
+            if let ResolvingPromiseEvent::Created = event {
+                counts.outstanding.insert(key);
+            } else if !counts.outstanding.remove(&key) {
+                return;
+            }
+            let snapshot = &mut counts.snapshot;
+            match event {
+                ResolvingPromiseEvent::Created => snapshot.total_created += 1,
+                ResolvingPromiseEvent::Resolved => snapshot.total_resolved += 1,
+                ResolvingPromiseEvent::Rejected => snapshot.total_rejected += 1,
+                ResolvingPromiseEvent::ContextDropped => snapshot.total_context_dropped += 1,
+            }
+        }
+        if let Some(listener) = self.listener.as_ref() {
+            let promise_id = PromiseId {
+                realm_id: realm_id.to_string(),
+                id,
+            };
+            listener.on_event(&promise_id, event, Instant::now());
+        }
+    }
+
+    /// record a ContextDropped event for all outstanding promises of a realm
+    pub(crate) fn record_realm_dropped(&self, realm_id: &str) {
+        let ids: Vec<usize> = {
+            let counts = &*self.counts.lock().unwrap();
+            counts
+                .outstanding
+                .iter()
+                .filter(|(r_id, _)| r_id == realm_id)
+                .map(|(_, id)| *id)
+                .collect()
+        };
+        for id in ids {
+            self.record(realm_id, id, ResolvingPromiseEvent::ContextDropped);
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> ResolvingPromiseStatsSnapshot {
+        let counts = &*self.counts.lock().unwrap();
+        let mut snapshot = counts.snapshot;
+        snapshot.outstanding = counts.outstanding.len();
+        snapshot
+    }
+}
+
+/// record an event for a cached promise if the runtime collects stats, this should be called from the EventQueue thread
+fn record_promise_event(realm: &QuickJsRealmAdapter, id: usize, event: ResolvingPromiseEvent) {
+    QuickJsRuntimeAdapter::do_with(|q_js_rt| {
+        if let Some(stats) = q_js_rt.resolving_promise_stats.as_ref() {
+            stats.record(realm.get_realm_id(), id, event);
+        }
+    })
+}
+
+/// add a promise to the promise cache of the realm and record its creation
+fn cache_resolving_promise(
+    realm: &QuickJsRealmAdapter,
+    promise_ref: QuickJsPromiseAdapter,
+) -> usize {
+    let id = realm.cache_promise(promise_ref);
+    record_promise_event(realm, id, ResolvingPromiseEvent::Created);
+    id
+}
+
+/// the id of a promise which is waiting to be resolved from rust, see new_cached_promise
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PromiseId {
+    realm_id: String,
+    id: usize,
+}
+
+impl PromiseId {
+    pub fn get_realm_id(&self) -> &str {
+        self.realm_id.as_str()
+    }
+}
+
+/// create a new promise which is kept in the realm until it is resolved by calling resolve_batch with the returned PromiseId
+pub fn new_cached_promise(
+    realm: &QuickJsRealmAdapter,
+) -> Result<(QuickJsValueAdapter, PromiseId), JsError> {
+    let promise_ref = realm.create_promise()?;
+    let return_ref = promise_ref.js_promise_get_value(realm);
+    let id = cache_resolving_promise(realm, promise_ref);
+    Ok((
+        return_ref,
+        PromiseId {
+            realm_id: realm.get_realm_id().to_string(),
+            id,
+        },
+    ))
+}
+
+/// resolve or reject a batch of promises created by new_cached_promise in a single EventQueue task
+/// Ok results are mapped to a JSValueRef by the mapper, Err results reject the promise
+/// # Example
+/// ```rust
+/// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+/// use quickjs_runtime::jsutils::promises::{new_cached_promise, resolve_batch};
+/// let rt = QuickJsRuntimeBuilder::new().build();
+/// let (rti_ref, ids) = rt.loop_realm_sync(None, |_rt, realm| {
+///     let ids: Vec<_> = (0..10).map(|_| new_cached_promise(realm).expect("could not create promise").1).collect();
+///     (realm.get_runtime_facade_inner(), ids)
+/// });
+/// let results = ids.into_iter().enumerate().map(|(i, id)| (id, Ok(i as i32))).collect();
+/// resolve_batch(rti_ref, results, |realm, res| realm.create_i32(res));
+/// ```
+pub fn resolve_batch<R, M>(
+    rti_ref: Weak<QuickjsRuntimeFacadeInner>,
+    results: Vec<(PromiseId, Result<R, JsError>)>,
+    mapper: M,
+) where
+    R: Send + 'static,
+    M: Fn(&QuickJsRealmAdapter, R) -> Result<QuickJsValueAdapter, JsError> + Send + 'static,
+{
+    if let Some(rti) = rti_ref.upgrade() {
+        rti.add_rt_task_to_event_loop_void(move |rt| {
+            for (promise_id, produced_result) in results {
+                if let Some(realm) = rt.get_realm(promise_id.realm_id.as_str()) {
+                    settle_cached_promise(realm, promise_id.id, produced_result, &mapper);
+                } else {
+                    log::error!(
+                        "batch resolving promise for dropped realm: {}",
+                        promise_id.realm_id
+                    );
+                }
+            }
+        });
+    } else {
+        log::error!("batch resolving promises for dropped runtime");
+    }
+}
+
+/// a handle which may be used to settle a promise created by new_external_promise from any thread
+#[derive(Clone)]
+pub struct ExternalPromiseHandle {
+    rti_ref: Weak<QuickjsRuntimeFacadeInner>,
+    promise_id: PromiseId,
+    settled: Arc<AtomicBool>,
+}
+
+impl ExternalPromiseHandle {
+    /// get the PromiseId of the promise, this may be used to resolve it with resolve_batch
+    /// please note that the promise can then no longer be settled with this handle
+    pub fn get_promise_id(&self) -> &PromiseId {
+        &self.promise_id
+    }
+    /// resolve the promise with the value created by the mapper, the mapper runs in the EventQueue thread
+    /// returns an Err if the promise was already settled
+    pub fn resolve_with<M>(&self, mapper: M) -> Result<(), JsError>
+    where
+        M: FnOnce(&QuickJsRealmAdapter) -> Result<QuickJsValueAdapter, JsError> + Send + 'static,
+    {
+        self.settle(Ok(()), move |realm, _| mapper(realm))
+    }
+    /// reject the promise with an Error with the given name and message
+    /// returns an Err if the promise was already settled
+    pub fn reject_with(&self, name: &str, message: &str) -> Result<(), JsError> {
+        self.settle(
+            Err(JsError::new(
+                name.to_string(),
+                message.to_string(),
+                "".to_string(),
+            )),
+            |_realm, _: ()| unreachable!("mapper called for rejected promise"),
+        )
+    }
+    /// check if resolve_with or reject_with was called
+    pub fn is_settled(&self) -> bool {
+        self.settled.load(Ordering::SeqCst)
+    }
+    fn settle<M>(&self, result: Result<(), JsError>, mapper: M) -> Result<(), JsError>
+    where
+        M: FnOnce(&QuickJsRealmAdapter, ()) -> Result<QuickJsValueAdapter, JsError>
+            + Send
+            + 'static,
+    {
+        if self.settled.swap(true, Ordering::SeqCst) {
+            return Err(JsError::new_str("promise was already settled"));
+        }
+        if self.rti_ref.upgrade().is_none() {
+            return Err(JsError::new_str("runtime was dropped"));
+        }
+        resolve_cached_promise(
+            self.rti_ref.clone(),
+            self.promise_id.realm_id.clone(),
+            self.promise_id.id,
+            result,
+            mapper,
+        );
+        Ok(())
+    }
+}
+
+/// create a new promise which can be resolved or rejected later from any thread by using the returned handle
+/// this is useful for callback based apis where the result arrives in an unrelated callback
+/// # Example
+/// ```rust
+/// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+/// use quickjs_runtime::jsutils::promises::new_external_promise;
+/// let rt = QuickJsRuntimeBuilder::new().build();
+/// let handle = rt.loop_realm_sync(None, |_rt, realm| {
+///     let (_prom, handle) = new_external_promise(realm).expect("could not create promise");
+///     handle
+/// });
+/// std::thread::spawn(move || {
+///     handle.resolve_with(|realm| realm.create_i32(12)).expect("could not resolve");
+/// });
+/// ```
+pub fn new_external_promise(
+    realm: &QuickJsRealmAdapter,
+) -> Result<(QuickJsValueAdapter, ExternalPromiseHandle), JsError> {
+    let (prom, promise_id) = new_cached_promise(realm)?;
+    Ok((
+        prom,
+        ExternalPromiseHandle {
+            rti_ref: realm.get_runtime_facade_inner(),
+            promise_id,
+            settled: Arc::new(AtomicBool::new(false)),
+        },
+    ))
+}
+
+/// reject a promise with an Error created from a JsError
+fn reject_with_error(realm: &QuickJsRealmAdapter, prom_ref: &QuickJsPromiseAdapter, err: JsError) {
+    let err_ref = realm
+        .create_error(err.get_name(), err.get_message(), err.get_stack())
+        .expect("could not create error");
+    if let Err(e) = prom_ref.js_promise_reject(realm, &err_ref) {
+        log::error!("[{}] could not reject promise: {}", realm.get_realm_id(), e);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::builder::QuickJsRuntimeBuilder;
+    use crate::facades::tests::init_test_rt;
+    use crate::facades::QuickjsRuntimeFacadeInner;
+    use crate::jsutils::promises::{
+        new_cached_promise, new_external_promise, new_resolving_promise,
+        new_resolving_promise_async, new_resolving_promise_cancellable,
+        new_resolving_promise_with_progress, new_resolving_promise_with_spawner,
+        new_resolving_promise_with_timeout, new_resolving_promise_with_token, resolve_batch,
+        PromiseId,
+    };
+    use crate::jsutils::{JsError, Script};
+    use crate::quickjs_utils;
+    use crate::values::JsValueFacade;
+    use std::sync::atomic::Ordering;
+    use std::sync::Weak;
+    use std::time::Duration;
+
+    #[test]
+    fn test_resolving_promise_async() {
+        let rt = init_test_rt();
+        let prom_jsvf = rt.loop_realm_sync(None, |_rt, realm| {
+            let prom = new_resolving_promise_async(
+                realm,
+                async {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    Ok(37)
+                },
+                |realm, res| realm.create_i32(res),
+            )
+            .expect("could not create promise");
+            realm.to_js_value_facade(&prom).expect("conversion failed")
+        });
+        let JsValueFacade::JsPromise { cached_promise } = prom_jsvf else {
+            panic!("not a promise");
+        };
+        let res = cached_promise
+            .get_promise_result_sync()
+            .expect("promise timed out")
+            .expect("promise was rejected");
+        assert_eq!(res.get_i32(), 37);
+    }
+
+    #[test]
+    fn test_resolving_promise_async_err() {
+        let rt = init_test_rt();
+        rt.loop_realm_sync(None, |_rt, realm| {
+            let prom = new_resolving_promise_async(
+                realm,
+                async { Err::<i32, JsError>(JsError::new_str("poof")) },
+                |realm, res| realm.create_i32(res),
+            )
+            .expect("could not create promise");
+            let global = realm.get_global().expect("no global");
+            realm
+                .set_object_property(&global, "testProm", &prom)
+                .expect("could not set prop");
+            realm
+                .eval(Script::new(
+                    "test_resolving_promise_async_err.js",
+                    "testProm.catch((err) => {globalThis.testErr = err.message;});",
+                ))
+                .expect("script failed");
+        });
+        std::thread::sleep(Duration::from_millis(100));
+        let res = rt
+            .eval_sync(None, Script::new("test_err.js", "testErr;"))
+            .expect("script failed");
+        assert_eq!(res.get_str(), "poof");
+    }
+
+    #[test]
+    fn test_resolving_promise_cancel() {
+        let rt = init_test_rt();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (observed_tx, observed_rx) = std::sync::mpsc::channel();
+        let (handle, result_jsvf) = rt.loop_realm_sync(None, move |_rt, realm| {
+            let (prom, handle) = new_resolving_promise_cancellable(
+                realm,
+                move |cancelled| {
+                    let _ = rx.recv();
+                    // assert on the test thread, a panic in the producer would only reject the promise
+                    let _ = observed_tx.send(cancelled.load(std::sync::atomic::Ordering::SeqCst));
+                    Ok(12)
+                },
+                |realm, res| realm.create_i32(res),
+            )
+            .expect("could not create promise");
+            let global = realm.get_global().expect("no global");
+            realm
+                .set_object_property(&global, "testCancelProm", &prom)
+                .expect("could not set prop");
+            let result = realm
+                .eval(Script::new(
+                    "test_resolving_promise_cancel.js",
+                    "testCancelProm.then((res) => 'resolved:' + res).catch((err) => err.name + ': ' + err.message);",
+                ))
+                .expect("script failed");
+            (
+                handle,
+                realm.to_js_value_facade(&result).expect("conversion failed"),
+            )
+        });
+        handle.cancel();
+        assert!(handle.is_cancelled());
+        tx.send(()).expect("could not send");
+        let observed = observed_rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("producer did not run");
+        assert!(observed, "producer did not see the cancellation");
+        let JsValueFacade::JsPromise { cached_promise } = result_jsvf else {
+            panic!("not a promise");
+        };
+        let res = cached_promise
+            .get_promise_result_sync_timeout(Some(Duration::from_secs(5)))
+            .expect("promise timed out")
+            .expect("promise was rejected");
+        assert_eq!(res.get_str(), "CancelledError: cancelled");
+        let ct = rt.loop_realm_sync(None, |_rt, realm| realm.get_cached_promise_count());
+        assert_eq!(ct, 0);
+    }
+
+    #[test]
+    fn test_resolving_promise_dropped_realm() {
+        let rt = init_test_rt();
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        rt.create_context("test_dropped_realm")
+            .expect("could not create realm");
+        let ct = rt.loop_realm_sync(Some("test_dropped_realm"), move |_rt, realm| {
+            let prom = new_resolving_promise(
+                realm,
+                move || {
+                    let _ = rx.recv();
+                    Ok(12)
+                },
+                |realm, res| realm.create_i32(res),
+            )
+            .expect("could not create promise");
+            let global = realm.get_global().expect("no global");
+            realm
+                .set_object_property(&global, "testDroppedRealmProm", &prom)
+                .expect("could not set prop");
+            realm.get_cached_promise_count()
+        });
+        assert_eq!(ct, 1);
+        rt.drop_context("test_dropped_realm");
+        assert!(!rt
+            .has_realm("test_dropped_realm")
+            .expect("could not check realm"));
+        // let the producer finish after the realm is gone
+        tx.send(()).expect("could not send");
+        std::thread::sleep(Duration::from_millis(100));
+        let res = rt
+            .eval_sync(None, Script::new("test_dropped_realm.js", "1 + 1;"))
+            .expect("script failed");
+        assert_eq!(res.get_i32(), 2);
+    }
+
+    #[test]
+    fn test_resolving_promises_per_realm() {
+        let rt = init_test_rt();
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let rx = std::sync::Arc::new(std::sync::Mutex::new(rx));
+        for realm_name in ["test_per_realm_a", "test_per_realm_b"] {
+            rt.create_context(realm_name)
+                .expect("could not create realm");
+            let rx = rx.clone();
+            rt.loop_realm_sync(Some(realm_name), move |_rt, realm| {
+                let prom = new_resolving_promise(
+                    realm,
+                    move || {
+                        let _ = rx.lock().unwrap().recv();
+                        Ok(12)
+                    },
+                    |realm, res| realm.create_i32(res),
+                )
+                .expect("could not create promise");
+                let global = realm.get_global().expect("no global");
+                realm
+                    .set_object_property(&global, "testPerRealmProm", &prom)
+                    .expect("could not set prop");
+            });
+        }
+        let count = |realm_name: &'static str| {
+            rt.loop_realm_sync(Some(realm_name), |_rt, realm| {
+                realm.get_cached_promise_count()
+            })
+        };
+        assert_eq!(count("test_per_realm_a"), 1);
+        assert_eq!(count("test_per_realm_b"), 1);
+
+        // dropping a realm purges its own cached promises only
+        rt.drop_context("test_per_realm_b");
+        assert_eq!(count("test_per_realm_a"), 1);
+
+        tx.send(()).expect("could not send");
+        tx.send(()).expect("could not send");
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(count("test_per_realm_a"), 0);
+        let res = rt
+            .eval_sync(
+                Some("test_per_realm_a"),
+                Script::new("test_per_realm.js", "testPerRealmProm;"),
+            )
+            .expect("script failed");
+        let JsValueFacade::JsPromise { cached_promise } = res else {
+            panic!("not a promise");
+        };
+        let res = cached_promise
+            .get_promise_result_sync()
+            .expect("promise timed out")
+            .expect("promise failed");
+        assert_eq!(res.get_i32(), 12);
+        rt.drop_context("test_per_realm_a");
+    }
+
+    fn start_token_producer(
+        realm: &crate::quickjsrealmadapter::QuickJsRealmAdapter,
+        tx: std::sync::mpsc::Sender<Option<String>>,
+    ) {
+        new_resolving_promise_with_token(
+            realm,
+            move |token| {
+                while !token.is_cancelled() {
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+                let _ = tx.send(token.cancelled_reason());
+                Err(JsError::new_str("cancelled"))
+            },
+            |realm, res: i32| realm.create_i32(res),
+        )
+        .expect("could not create promise");
+    }
+
+    #[test]
+    fn test_promise_token_runtime_dropped() {
+        let rt = QuickJsRuntimeBuilder::new().build();
+        let (tx, rx) = std::sync::mpsc::channel();
+        rt.loop_realm_sync(None, move |_rt, realm| start_token_producer(realm, tx));
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+        drop(rt);
+        let reason = rx
+            .recv_timeout(Duration::from_secs(2))
+            .expect("producer did not observe cancellation");
+        assert_eq!(reason.as_deref(), Some("runtime was dropped"));
+    }
+
+    #[test]
+    fn test_promise_token_realm_dropped() {
+        let rt = init_test_rt();
+        let (tx, rx) = std::sync::mpsc::channel();
+        rt.create_context("test_promise_token_realm")
+            .expect("could not create realm");
+        rt.loop_realm_sync(Some("test_promise_token_realm"), move |_rt, realm| {
+            start_token_producer(realm, tx)
+        });
+        rt.drop_context("test_promise_token_realm");
+        let reason = rx
+            .recv_timeout(Duration::from_secs(2))
+            .expect("producer did not observe cancellation");
+        assert_eq!(reason.as_deref(), Some("realm was dropped"));
+    }
+
+    #[test]
+    fn test_resolving_promise_stats() {
+        use crate::jsutils::promises::{
+            PromiseId, ResolvingPromiseEvent, ResolvingPromiseStatsListener,
+        };
+        use std::sync::{Arc, Mutex};
+
+        struct EventLog(Arc<Mutex<Vec<ResolvingPromiseEvent>>>);
+        impl ResolvingPromiseStatsListener for EventLog {
+            fn on_event(
+                &self,
+                _promise_id: &PromiseId,
+                event: ResolvingPromiseEvent,
+                _at: std::time::Instant,
+            ) {
+                self.0.lock().unwrap().push(event);
+            }
+        }
+
+        assert!(init_test_rt().resolving_promise_stats().is_none());
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let rt = QuickJsRuntimeBuilder::new()
+            .resolving_promise_stats_listener(EventLog(events.clone()))
+            .build();
+        rt.loop_realm_sync(None, |_rt, realm| {
+            for x in 0..10 {
+                new_resolving_promise(
+                    realm,
+                    move || {
+                        std::thread::sleep(Duration::from_millis(10));
+                        if x < 7 {
+                            Ok(x)
+                        } else {
+                            Err(JsError::new_str("stats rejection"))
+                        }
+                    },
+                    |realm, res| realm.create_i32(res),
+                )
+                .expect("could not create promise");
+            }
+        });
+        let snapshot = rt.resolving_promise_stats().expect("no stats");
+        assert_eq!(snapshot.total_created, 10);
+        assert_eq!(
+            snapshot.outstanding as u64 + snapshot.total_resolved + snapshot.total_rejected,
+            10
+        );
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while rt.resolving_promise_stats().unwrap().outstanding > 0 {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "promises did not settle"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let snapshot = rt.resolving_promise_stats().expect("no stats");
+        assert_eq!(snapshot.total_created, 10);
+        assert_eq!(snapshot.total_resolved, 7);
+        assert_eq!(snapshot.total_rejected, 3);
+        assert_eq!(snapshot.total_context_dropped, 0);
+
+        // promises of a dropped realm are no longer outstanding
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        rt.create_context("test_stats_realm")
+            .expect("could not create realm");
+        rt.loop_realm_sync(Some("test_stats_realm"), move |_rt, realm| {
+            new_resolving_promise(
+                realm,
+                move || {
+                    let _ = rx.recv();
+                    Ok(1)
+                },
+                |realm, res| realm.create_i32(res),
+            )
+            .expect("could not create promise");
+        });
+        assert_eq!(rt.resolving_promise_stats().unwrap().outstanding, 1);
+        rt.drop_context("test_stats_realm");
+        let _ = tx.send(());
+        std::thread::sleep(Duration::from_millis(50));
+        let snapshot = rt.resolving_promise_stats().expect("no stats");
+        assert_eq!(snapshot.outstanding, 0);
+        assert_eq!(snapshot.total_created, 11);
+        assert_eq!(snapshot.total_context_dropped, 1);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 22);
+        assert_eq!(
+            events
+                .iter()
+                .filter(|e| **e == ResolvingPromiseEvent::Rejected)
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_resolve_batch() {
+        // no gc interval, so only this test adds tasks to the event loop
+        let rt = QuickJsRuntimeBuilder::new().build();
+        let create_promises = |res_name: &'static str| {
+            rt.loop_realm_sync(None, move |_rt, realm| {
+                let mut proms = vec![];
+                let mut ids = vec![];
+                for _ in 0..1000 {
+                    let (prom, id) = new_cached_promise(realm).expect("could not create promise");
+                    proms.push(prom);
+                    ids.push(id);
+                }
+                let all = quickjs_utils::promises::all_q(realm, proms).expect("all failed");
+                let then_func = realm
+                    .create_function(
+                        "then",
+                        move |realm, _this, args| {
+                            let global = realm.get_global()?;
+                            realm.set_object_property(&global, res_name, &args[0])?;
+                            realm.create_null()
+                        },
+                        1,
+                    )
+                    .expect("could not create func");
+                realm
+                    .add_promise_reactions(&all, Some(then_func), None, None)
+                    .expect("could not add reactions");
+                (realm.get_runtime_facade_inner(), ids)
+            })
+        };
+        let to_results = |ids: Vec<PromiseId>| -> Vec<(PromiseId, Result<i32, JsError>)> {
+            ids.into_iter()
+                .enumerate()
+                .map(|(i, id)| (id, Ok(i as i32)))
+                .collect()
+        };
+        let task_ct = |rti_ref: &Weak<QuickjsRuntimeFacadeInner>| {
+            rti_ref
+                .upgrade()
+                .expect("runtime was dropped")
+                .event_loop_task_ct
+                .load(Ordering::SeqCst)
+        };
+
+        let (rti_ref, ids) = create_promises("testBatchRes");
+        let task_ct_before = task_ct(&rti_ref);
+        resolve_batch(rti_ref.clone(), to_results(ids), |realm, res| {
+            realm.create_i32(res)
+        });
+        assert_eq!(task_ct(&rti_ref) - task_ct_before, 1);
+
+        let (rti_ref, ids) = create_promises("testSingleRes");
+        let task_ct_before = task_ct(&rti_ref);
+        for result in to_results(ids) {
+            resolve_batch(rti_ref.clone(), vec![result], |realm, res| {
+                realm.create_i32(res)
+            });
+        }
+        assert_eq!(task_ct(&rti_ref) - task_ct_before, 1000);
+
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_resolve_batch.js",
+                    "[testBatchRes, testSingleRes].map((res) => res.length + ':' + res.reduce((a, b) => a + b, 0)).join(', ');",
+                ),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_str(), "1000:499500, 1000:499500");
+        let ct = rt.loop_realm_sync(None, |_rt, realm| realm.get_cached_promise_count());
+        assert_eq!(ct, 0);
+    }
+
+    #[test]
+    fn test_external_promise() {
+        let rt = init_test_rt();
+        let (handle, handle2) = rt.loop_realm_sync(None, |_rt, realm| {
+            let (prom, handle) = new_external_promise(realm).expect("could not create promise");
+            let (prom2, handle2) = new_external_promise(realm).expect("could not create promise");
+            let global = realm.get_global().expect("no global");
+            realm
+                .set_object_property(&global, "testExtProm", &prom)
+                .expect("could not set prop");
+            realm
+                .set_object_property(&global, "testExtProm2", &prom2)
+                .expect("could not set prop");
+            realm
+                .eval(Script::new(
+                    "test_external_promise.js",
+                    "testExtProm.then((res) => {globalThis.testExtRes = 'resolved:' + res;});\ntestExtProm2.catch((err) => {globalThis.testExtRes2 = err.name + ': ' + err.message;});",
+                ))
+                .expect("script failed");
+            (handle, handle2)
+        });
+
+        let handle_clone = handle.clone();
+        std::thread::spawn(move || {
+            handle_clone
+                .resolve_with(|realm| realm.create_i32(37))
+                .expect("could not resolve");
+        })
+        .join()
+        .expect("thread failed");
+        assert!(handle.is_settled());
+        assert!(handle.resolve_with(|realm| realm.create_i32(38)).is_err());
+        assert!(handle.reject_with("Error", "too late").is_err());
+
+        handle2
+            .reject_with("HostError", "callback failed")
+            .expect("could not reject");
+        assert!(handle2.reject_with("HostError", "again").is_err());
+
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_external_promise2.js",
+                    "testExtRes + ', ' + testExtRes2;",
+                ),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_str(), "resolved:37, HostError: callback failed");
+        let ct = rt.loop_realm_sync(None, |_rt, realm| realm.get_cached_promise_count());
+        assert_eq!(ct, 0);
+    }
+
+    #[test]
+    fn test_resolving_promise_with_spawner() {
+        let rt = init_test_rt();
+        let prom_jsvf = rt.loop_realm_sync(None, |_rt, realm| {
+            let prom = new_resolving_promise_with_spawner(
+                realm,
+                async { Ok("spawned".to_string()) },
+                |realm, res| realm.create_string(res.as_str()),
+                |fut| {
+                    std::thread::spawn(move || futures::executor::block_on(fut));
+                },
+            )
+            .expect("could not create promise");
+            realm.to_js_value_facade(&prom).expect("conversion failed")
+        });
+        let JsValueFacade::JsPromise { cached_promise } = prom_jsvf else {
+            panic!("not a promise");
+        };
+        let res = cached_promise
+            .get_promise_result_sync()
+            .expect("promise timed out")
+            .expect("promise was rejected");
+        assert_eq!(res.get_str(), "spawned");
+    }
+
+    #[test]
+    fn test_resolving_promise_with_spawner_dropped() {
+        let rt = init_test_rt();
+        let prom_jsvf = rt.loop_realm_sync(None, |_rt, realm| {
+            let prom = new_resolving_promise_with_spawner(
+                realm,
+                async { Ok(1) },
+                |realm, res| realm.create_i32(res),
+                drop,
+            )
+            .expect("could not create promise");
+            realm.to_js_value_facade(&prom).expect("conversion failed")
+        });
+        let JsValueFacade::JsPromise { cached_promise } = prom_jsvf else {
+            panic!("not a promise");
+        };
+        let err = cached_promise
+            .get_promise_result_sync()
+            .expect("promise timed out")
+            .expect_err("promise was resolved");
+        assert!(err
+            .stringify()
+            .contains("the producer of this promise was dropped"));
+        let ct = rt.loop_realm_sync(None, |_rt, realm| realm.get_cached_promise_count());
+        assert_eq!(ct, 0);
+    }
+
+    fn eval_timeout_prom(delay: Duration) -> String {
+        let rt = init_test_rt();
+        rt.loop_realm_sync(None, move |_rt, realm| {
+            let prom = new_resolving_promise_with_timeout(
+                realm,
+                move || {
+                    std::thread::sleep(delay);
+                    Ok(5)
+                },
+                |realm, res| realm.create_i32(res),
+                Duration::from_millis(50),
+            )
+            .expect("could not create promise");
+            let global = realm.get_global().expect("no global");
+            realm
+                .set_object_property(&global, "testTimeoutProm", &prom)
+                .expect("could not set prop");
+            realm
+                .eval(Script::new(
+                    "test_resolving_promise_timeout.js",
+                    "testTimeoutProm.then((res) => {globalThis.testTimeoutRes = 'resolved:' + res;}).catch((err) => {globalThis.testTimeoutRes = err.name + ': ' + err.message;});",
+                ))
+                .expect("script failed");
+        });
+        std::thread::sleep(Duration::from_millis(200));
+        let res = rt
+            .eval_sync(None, Script::new("test_timeout.js", "testTimeoutRes;"))
+            .expect("script failed");
+        let ct = rt.loop_realm_sync(None, |_rt, realm| realm.get_cached_promise_count());
+        assert_eq!(ct, 0);
+        res.get_str().to_string()
+    }
+
+    #[test]
+    fn test_resolving_promise_timeout() {
+        assert_eq!(
+            eval_timeout_prom(Duration::from_millis(150)),
+            "TimeoutError: promise timed out after 50ms"
+        );
+        assert_eq!(eval_timeout_prom(Duration::from_millis(1)), "resolved:5");
+    }
+
+    #[test]
+    fn test_resolving_promise_with_progress() {
+        let rt = init_test_rt();
+        rt.set_function(&["com", "hirofa"], "startJob", |realm, _args| {
+            let prom = new_resolving_promise_with_progress(
+                realm,
+                |progress| {
+                    // wait for script to set the callback
+                    std::thread::sleep(Duration::from_millis(20));
+                    for x in 1..4 {
+                        progress.send(x * 25);
+                    }
+                    Ok("done")
+                },
+                |realm, res| realm.create_string(res),
+                |realm, pct| realm.create_i32(pct),
+                "onProgress",
+            )?;
+            realm.to_js_value_facade(&prom)
+        })
+        .expect("could not set function");
+        rt.eval_sync(
+            None,
+            Script::new(
+                "test_resolving_promise_with_progress.js",
+                "globalThis.progressRes = []; let p = com.hirofa.startJob(); p.onProgress = (pct) => {progressRes.push(pct);}; p.then((res) => {progressRes.push(res);}); 1;",
+            ),
+        )
+        .expect("script failed");
+        std::thread::sleep(Duration::from_millis(200));
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_progress.js", "progressRes.join(',');"),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_str(), "25,50,75,done");
+    }
+
+    #[test]
+    fn test_resolving_promise_js_error() {
+        let rt = init_test_rt();
+        rt.loop_realm_sync(None, |_rt, realm| {
+            let prom_named = new_resolving_promise(
+                realm,
+                || {
+                    Err::<i32, JsError>(JsError::new(
+                        "NotFoundError".to_string(),
+                        "no such file".to_string(),
+                        "at producer".to_string(),
+                    ))
+                },
+                |realm, res| realm.create_i32(res),
+            )
+            .expect("could not create promise");
+            let prom_str = new_resolving_promise(
+                realm,
+                || {
+                    let res: Result<i32, String> = Err("plain failure".to_string());
+                    Ok(res?)
+                },
+                |realm, res| realm.create_i32(res),
+            )
+            .expect("could not create promise");
+            let global = realm.get_global().expect("no global");
+            realm
+                .set_object_property(&global, "testNamedProm", &prom_named)
+                .expect("could not set prop");
+            realm
+                .set_object_property(&global, "testStrProm", &prom_str)
+                .expect("could not set prop");
+            realm
+                .eval(Script::new(
+                    "test_resolving_promise_js_error.js",
+                    "globalThis.testErrRes = []; testNamedProm.catch((err) => {testErrRes.push(err.name + ':' + err.message);}); testStrProm.catch((err) => {testErrRes.push(err.name + ':' + err.message);});",
+                ))
+                .expect("script failed");
+        });
+        std::thread::sleep(Duration::from_millis(100));
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_err.js", "testErrRes.sort().join(',');"),
+            )
+            .expect("script failed");
+        assert_eq!(
+            res.get_str(),
+            "Error:plain failure,NotFoundError:no such file"
+        );
+    }
+
+    #[test]
+    fn test_resolving_promise_nested() {
+        let rt = init_test_rt();
+        let prom_jsvf = rt.loop_realm_sync(None, |_rt, realm| {
+            let prom = new_resolving_promise(
+                realm,
+                || Ok(6),
+                |realm, res| {
+                    new_resolving_promise(
+                        realm,
+                        move || {
+                            std::thread::sleep(Duration::from_millis(20));
+                            Ok(res * 7)
+                        },
+                        |realm, res| realm.create_i32(res),
+                    )
+                },
+            )
+            .expect("could not create promise");
+            realm.to_js_value_facade(&prom).expect("conversion failed")
+        });
+        let JsValueFacade::JsPromise { cached_promise } = prom_jsvf else {
+            panic!("not a promise");
+        };
+        let res = cached_promise
+            .get_promise_result_sync()
+            .expect("promise timed out")
+            .expect("promise was rejected");
+        assert_eq!(res.get_i32(), 42);
+    }
+}
@@ -81,7 +81,8 @@ pub mod facades;
     feature = "settimeout",
     feature = "setinterval",
     feature = "console",
-    feature = "setimmediate"
+    feature = "setimmediate",
+    feature = "queuemicrotask"
 ))]
 pub mod features;
 pub mod jsutils;
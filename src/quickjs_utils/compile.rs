@@ -156,7 +156,9 @@ pub unsafe fn from_bytecode(
     context: *mut q::JSContext,
     bytecode: &[u8],
 ) -> Result<QuickJsValueAdapter, JsError> {
-    assert!(!bytecode.is_empty());
+    if bytecode.is_empty() {
+        return Err(JsError::new_str("can not read empty bytecode"));
+    }
     {
         let len = bytecode.len();
 
@@ -179,6 +181,59 @@ pub unsafe fn from_bytecode(
     }
 }
 
+const SEALED_MAGIC: &[u8; 4] = b"QJRB";
+const SEALED_FORMAT_VERSION: u8 = 1;
+const SEALED_HEADER_LEN: usize = 4 + 1 + 8;
+
+/// hash a source with FNV-1a, unlike std's DefaultHasher the hash is stable between builds so it may be used in keys of a persistent cache
+pub fn source_hash(source: &str) -> u64 {
+    fnv1a(source.as_bytes())
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// prefix bytecode with a header which holds a checksum so corrupt bytecode can be detected by [unseal_bytecode]
+/// use this for bytecode which is stored outside of the runtime (e.g. on disk)
+pub fn seal_bytecode(bytecode: &[u8]) -> Vec<u8> {
+    let mut sealed = Vec::with_capacity(SEALED_HEADER_LEN + bytecode.len());
+    sealed.extend_from_slice(SEALED_MAGIC);
+    sealed.push(SEALED_FORMAT_VERSION);
+    sealed.extend_from_slice(&fnv1a(bytecode).to_le_bytes());
+    sealed.extend_from_slice(bytecode);
+    sealed
+}
+
+/// validate the header of bytecode created by [seal_bytecode] and return the bytecode
+/// returns an Err if the header is missing or the bytecode does not match the checksum
+/// please note that QuickJS itself returns an Err when reading bytecode of a different QuickJS version
+/// the checksum only detects accidental corruption, it is not a signature; never read bytecode from an untrusted source as
+/// QuickJS does not verify bytecode and crafted bytecode can corrupt memory
+pub fn unseal_bytecode(sealed: &[u8]) -> Result<&[u8], JsError> {
+    if sealed.len() <= SEALED_HEADER_LEN || !sealed.starts_with(SEALED_MAGIC) {
+        return Err(JsError::new_str("invalid bytecode, header is missing"));
+    }
+    if sealed[4] != SEALED_FORMAT_VERSION {
+        return Err(JsError::new_string(format!(
+            "invalid bytecode, unsupported format version {}",
+            sealed[4]
+        )));
+    }
+    let mut checksum = [0u8; 8];
+    checksum.copy_from_slice(&sealed[5..SEALED_HEADER_LEN]);
+    let bytecode = &sealed[SEALED_HEADER_LEN..];
+    if u64::from_le_bytes(checksum) != fnv1a(bytecode) {
+        return Err(JsError::new_str("invalid bytecode, checksum mismatch"));
+    }
+    Ok(bytecode)
+}
+
 #[cfg(test)]
 pub mod tests {
     use crate::builder::QuickJsRuntimeBuilder;
@@ -186,7 +241,7 @@ pub mod tests {
     use crate::jsutils::modules::CompiledModuleLoader;
     use crate::jsutils::Script;
     use crate::quickjs_utils::compile::{
-        compile, from_bytecode, run_compiled_function, to_bytecode,
+        compile, from_bytecode, run_compiled_function, seal_bytecode, to_bytecode, unseal_bytecode,
     };
     use crate::quickjs_utils::modules::compile_module;
     use crate::quickjs_utils::primitives;
@@ -395,4 +450,155 @@ pub mod tests {
             panic!("did not get a prom");
         }
     }
+
+    #[test]
+    fn test_sealed_bytecode() {
+        let rt = QuickJsRuntimeBuilder::new().build();
+        let (script_bytes, module_bytes) = rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let realm = q_js_rt.get_main_realm();
+            let script_bytes = realm
+                .compile_script(Script::new("sealed.js", "let sealed_a = 6; sealed_a * 7;"))
+                .expect("compile script failed");
+            let module_bytes = realm
+                .compile_module(Script::new(
+                    "/sealed/module.mes",
+                    "globalThis.sealedUrl = import.meta.url;",
+                ))
+                .expect("compile module failed");
+            (script_bytes, module_bytes)
+        });
+
+        // evaluate in a different runtime
+        let rt2 = QuickJsRuntimeBuilder::new().build();
+        rt2.exe_rt_task_in_event_loop(move |q_js_rt| {
+            let realm = q_js_rt.get_main_realm();
+            let res = realm
+                .eval_compiled_script(&script_bytes)
+                .expect("eval script failed");
+            assert_eq!(res.to_i32(), 42);
+
+            realm
+                .eval_compiled_module(&module_bytes)
+                .expect("eval module failed");
+            let url = realm
+                .eval(Script::new("sealed_url.js", "sealedUrl;"))
+                .expect("script failed");
+            assert_eq!(url.to_string().unwrap(), "/sealed/module.mes");
+            assert!(realm.is_evaluated_module("/sealed/module.mes"));
+
+            // wrong kind of bytecode
+            assert!(realm.eval_compiled_module(&script_bytes).is_err());
+            assert!(realm.eval_compiled_script(&module_bytes).is_err());
+
+            // corrupt, truncated and unsealed bytecode
+            let mut corrupt = script_bytes.clone();
+            let last = corrupt.len() - 1;
+            corrupt[last] ^= 0xff;
+            assert!(realm.eval_compiled_script(&corrupt).is_err());
+            assert!(realm.eval_compiled_script(&script_bytes[..10]).is_err());
+            assert!(realm.eval_compiled_script(&[]).is_err());
+            let unsealed = unseal_bytecode(&script_bytes).unwrap().to_vec();
+            assert!(realm.eval_compiled_script(&unsealed).is_err());
+            let mut other_version = script_bytes.clone();
+            other_version[4] = 2;
+            let err = realm.eval_compiled_script(&other_version).unwrap_err();
+            assert!(err.get_message().contains("format version 2"));
+
+            // unsealed bytecode of a different QuickJS version is rejected by QuickJS itself
+            let mut other_qjs_version = unsealed;
+            other_qjs_version[0] ^= 0xff;
+            let resealed = seal_bytecode(&other_qjs_version);
+            assert!(realm.eval_compiled_script(&resealed).is_err());
+        });
+    }
+
+    #[test]
+    fn test_bytecode_cache() {
+        use crate::jsutils::modules::{BytecodeCache, ScriptModuleLoader};
+        use std::collections::HashMap;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Mutex;
+
+        type CacheEntries = HashMap<(String, u64), Vec<u8>>;
+
+        #[derive(Clone, Default)]
+        struct MemoryCache {
+            entries: Arc<Mutex<CacheEntries>>,
+            hits: Arc<AtomicUsize>,
+            puts: Arc<AtomicUsize>,
+        }
+        impl BytecodeCache for MemoryCache {
+            fn get(&self, specifier: &str, source_hash: u64) -> Option<Vec<u8>> {
+                let entries = self.entries.lock().unwrap();
+                let res = entries.get(&(specifier.to_string(), source_hash)).cloned();
+                if res.is_some() {
+                    self.hits.fetch_add(1, Ordering::SeqCst);
+                }
+                res
+            }
+
+            fn put(&self, specifier: &str, source_hash: u64, bytecode: Vec<u8>) {
+                self.puts.fetch_add(1, Ordering::SeqCst);
+                self.entries
+                    .lock()
+                    .unwrap()
+                    .insert((specifier.to_string(), source_hash), bytecode);
+            }
+        }
+
+        struct AnswerLoader {}
+        impl ScriptModuleLoader for AnswerLoader {
+            fn normalize_path(
+                &self,
+                _realm: &QuickJsRealmAdapter,
+                _ref_path: &str,
+                path: &str,
+            ) -> Option<String> {
+                (path == "answer.mes").then(|| path.to_string())
+            }
+
+            fn load_module(&self, _realm: &QuickJsRealmAdapter, _absolute_path: &str) -> String {
+                "export const answer = 42;".to_string()
+            }
+        }
+
+        let run = |cache: &MemoryCache| {
+            let rt = QuickJsRuntimeBuilder::new()
+                .script_module_loader(AnswerLoader {})
+                .bytecode_cache(cache.clone())
+                .build();
+            rt.eval_module_sync(
+                None,
+                Script::new(
+                    "cache_main.mes",
+                    "import {answer} from 'answer.mes'; globalThis.answer = answer;",
+                ),
+            )
+            .expect("module failed");
+            rt.eval_sync(None, Script::new("cache_answer.js", "answer;"))
+                .expect("script failed")
+                .get_i32()
+        };
+
+        let cache = MemoryCache::default();
+        assert_eq!(run(&cache), 42);
+        assert_eq!(cache.hits.load(Ordering::SeqCst), 0);
+        assert_eq!(cache.puts.load(Ordering::SeqCst), 1);
+
+        assert_eq!(run(&cache), 42);
+        assert_eq!(cache.hits.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.puts.load(Ordering::SeqCst), 1);
+
+        // corrupt entries are ignored and replaced
+        for bytecode in cache.entries.lock().unwrap().values_mut() {
+            let last = bytecode.len() - 1;
+            bytecode[last] ^= 0xff;
+        }
+        assert_eq!(run(&cache), 42);
+        assert_eq!(cache.hits.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.puts.load(Ordering::SeqCst), 2);
+        assert_eq!(run(&cache), 42);
+        assert_eq!(cache.hits.load(Ordering::SeqCst), 3);
+        assert_eq!(cache.puts.load(Ordering::SeqCst), 2);
+    }
 }
@@ -0,0 +1,209 @@
+//! DataView utils, these methods can be used to read and write numbers in an ArrayBuffer with an explicit endianness
+//! see [MDN](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/DataView) for more on DataViews
+
+use crate::jsutils::JsError;
+use crate::quickjs_utils::objects::{construct_object, is_instance_of_by_name};
+use crate::quickjs_utils::{functions, get_constructor, primitives};
+use crate::quickjsrealmadapter::QuickJsRealmAdapter;
+use crate::quickjsvalueadapter::QuickJsValueAdapter;
+
+/// create a new DataView over an ArrayBuffer
+pub fn new_data_view_q(
+    q_ctx: &QuickJsRealmAdapter,
+    array_buffer: &QuickJsValueAdapter,
+) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe {
+        let constructor = get_constructor(q_ctx.context, "DataView")?;
+        construct_object(q_ctx.context, &constructor, &[array_buffer])
+    }
+}
+
+/// check if a ref is a DataView
+pub fn is_data_view_q(q_ctx: &QuickJsRealmAdapter, obj: &QuickJsValueAdapter) -> bool {
+    obj.is_object()
+        && unsafe { is_instance_of_by_name(q_ctx.context, obj, "DataView").unwrap_or(false) }
+}
+
+fn invoke_getter(
+    q_ctx: &QuickJsRealmAdapter,
+    data_view: &QuickJsValueAdapter,
+    function_name: &str,
+    byte_offset: usize,
+    little_endian: bool,
+) -> Result<f64, JsError> {
+    // out of range offsets result in a RangeError
+    let res = unsafe {
+        functions::invoke_member_function(
+            q_ctx.context,
+            data_view,
+            function_name,
+            &[
+                primitives::from_f64(byte_offset as f64),
+                primitives::from_bool(little_endian),
+            ],
+        )?
+    };
+    if res.is_i32() {
+        Ok(res.to_i32() as f64)
+    } else {
+        primitives::to_f64(&res)
+    }
+}
+
+fn invoke_setter(
+    q_ctx: &QuickJsRealmAdapter,
+    data_view: &QuickJsValueAdapter,
+    function_name: &str,
+    byte_offset: usize,
+    value: QuickJsValueAdapter,
+    little_endian: bool,
+) -> Result<(), JsError> {
+    unsafe {
+        functions::invoke_member_function(
+            q_ctx.context,
+            data_view,
+            function_name,
+            &[
+                primitives::from_f64(byte_offset as f64),
+                value,
+                primitives::from_bool(little_endian),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// read a signed 32 bit integer at byte_offset
+pub fn get_int32_q(
+    q_ctx: &QuickJsRealmAdapter,
+    data_view: &QuickJsValueAdapter,
+    byte_offset: usize,
+    little_endian: bool,
+) -> Result<i32, JsError> {
+    invoke_getter(q_ctx, data_view, "getInt32", byte_offset, little_endian).map(|v| v as i32)
+}
+
+/// write a signed 32 bit integer at byte_offset
+pub fn set_int32_q(
+    q_ctx: &QuickJsRealmAdapter,
+    data_view: &QuickJsValueAdapter,
+    byte_offset: usize,
+    value: i32,
+    little_endian: bool,
+) -> Result<(), JsError> {
+    invoke_setter(
+        q_ctx,
+        data_view,
+        "setInt32",
+        byte_offset,
+        primitives::from_i32(value),
+        little_endian,
+    )
+}
+
+/// read an unsigned 32 bit integer at byte_offset
+pub fn get_uint32_q(
+    q_ctx: &QuickJsRealmAdapter,
+    data_view: &QuickJsValueAdapter,
+    byte_offset: usize,
+    little_endian: bool,
+) -> Result<u32, JsError> {
+    invoke_getter(q_ctx, data_view, "getUint32", byte_offset, little_endian).map(|v| v as u32)
+}
+
+/// write an unsigned 32 bit integer at byte_offset
+pub fn set_uint32_q(
+    q_ctx: &QuickJsRealmAdapter,
+    data_view: &QuickJsValueAdapter,
+    byte_offset: usize,
+    value: u32,
+    little_endian: bool,
+) -> Result<(), JsError> {
+    invoke_setter(
+        q_ctx,
+        data_view,
+        "setUint32",
+        byte_offset,
+        primitives::from_f64(value as f64),
+        little_endian,
+    )
+}
+
+/// read a 64 bit float at byte_offset
+pub fn get_float64_q(
+    q_ctx: &QuickJsRealmAdapter,
+    data_view: &QuickJsValueAdapter,
+    byte_offset: usize,
+    little_endian: bool,
+) -> Result<f64, JsError> {
+    invoke_getter(q_ctx, data_view, "getFloat64", byte_offset, little_endian)
+}
+
+/// write a 64 bit float at byte_offset
+pub fn set_float64_q(
+    q_ctx: &QuickJsRealmAdapter,
+    data_view: &QuickJsValueAdapter,
+    byte_offset: usize,
+    value: f64,
+    little_endian: bool,
+) -> Result<(), JsError> {
+    invoke_setter(
+        q_ctx,
+        data_view,
+        "setFloat64",
+        byte_offset,
+        primitives::from_f64(value),
+        little_endian,
+    )
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::facades::tests::init_test_rt;
+    use crate::quickjs_utils::dataview::{
+        get_float64_q, get_int32_q, get_uint32_q, is_data_view_q, new_data_view_q, set_float64_q,
+        set_int32_q, set_uint32_q,
+    };
+    use crate::quickjs_utils::typedarrays::{get_array_buffer_bytes_q, new_array_buffer_copy_q};
+
+    #[test]
+    fn test_data_view() {
+        let rt = init_test_rt();
+        rt.loop_realm_sync(None, |_rt, realm| {
+            let ab =
+                new_array_buffer_copy_q(realm, &[0x01, 0x02, 0x03, 0x04, 0xff, 0xff, 0xff, 0xfe])
+                    .expect("could not create buffer");
+            let view = new_data_view_q(realm, &ab).expect("could not create view");
+            assert!(is_data_view_q(realm, &view));
+            assert!(!is_data_view_q(realm, &ab));
+
+            assert_eq!(get_int32_q(realm, &view, 0, false).unwrap(), 0x01020304);
+            assert_eq!(get_int32_q(realm, &view, 0, true).unwrap(), 0x04030201);
+            assert_eq!(get_int32_q(realm, &view, 4, false).unwrap(), -2);
+            assert_eq!(get_uint32_q(realm, &view, 4, false).unwrap(), 0xfffffffe);
+
+            set_int32_q(realm, &view, 0, -1, true).expect("set failed");
+            set_uint32_q(realm, &view, 4, 0x0a0b0c0d, true).expect("set failed");
+            assert_eq!(
+                get_array_buffer_bytes_q(realm, &ab).unwrap(),
+                vec![0xff, 0xff, 0xff, 0xff, 0x0d, 0x0c, 0x0b, 0x0a]
+            );
+
+            set_float64_q(realm, &view, 0, 1.5, false).expect("set failed");
+            assert_eq!(
+                get_array_buffer_bytes_q(realm, &ab).unwrap(),
+                1.5f64.to_be_bytes().to_vec()
+            );
+            assert_eq!(get_float64_q(realm, &view, 0, false).unwrap(), 1.5);
+            assert_eq!(
+                get_float64_q(realm, &view, 0, true).unwrap(),
+                f64::from_le_bytes(1.5f64.to_be_bytes())
+            );
+
+            let err = get_int32_q(realm, &view, 6, false).expect_err("read out of bounds");
+            assert_eq!(err.get_name(), "RangeError");
+            let err = set_float64_q(realm, &view, 1, 1.0, false).expect_err("write out of bounds");
+            assert_eq!(err.get_name(), "RangeError");
+        });
+    }
+}
@@ -1,11 +1,13 @@
 //! Utils for working with Date objects
 
-use crate::jsutils::JsError;
+use crate::jsutils::{JsError, Script};
 use crate::quickjs_utils;
-use crate::quickjs_utils::{functions, objects, primitives};
+use crate::quickjs_utils::{errors, functions, json, objects, primitives};
 use crate::quickjsrealmadapter::QuickJsRealmAdapter;
 use crate::quickjsvalueadapter::QuickJsValueAdapter;
 use libquickjs_sys as q;
+use std::cmp::Ordering;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// create a new instance of a Date object
 pub fn new_date_q(context: &QuickJsRealmAdapter) -> Result<QuickJsValueAdapter, JsError> {
@@ -21,6 +23,192 @@ pub unsafe fn new_date(context: *mut q::JSContext) -> Result<QuickJsValueAdapter
     Ok(date_ref)
 }
 
+/// get the current time in milliseconds since the epoch, like Date.now()
+pub fn now_q(context: &QuickJsRealmAdapter) -> Result<f64, JsError> {
+    unsafe { now(context.context) }
+}
+
+/// get the current time in milliseconds since the epoch, like Date.now()
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn now(context: *mut q::JSContext) -> Result<f64, JsError> {
+    let constructor = quickjs_utils::get_constructor(context, "Date")?;
+    let now_ref = functions::invoke_member_function(context, &constructor, "now", &[])?;
+    let mut now: f64 = 0.0;
+    if q::JS_ToFloat64(context, &mut now, *now_ref.borrow_value()) != 0 {
+        return Err(errors::get_exception(context)
+            .unwrap_or_else(|| JsError::new_str("could not convert timestamp to a Number")));
+    }
+    Ok(now)
+}
+
+/// replaces the global Date constructor with a subclass which gets the current time from a clock function
+/// performance.now() (if present) is replaced with a function which returns the clock time since the shim was installed
+const CLOCK_SHIM: &str = r#"
+(function(clock) {
+    const OrigDate = Date;
+    function ClockDate(...args) {
+        if (!new.target) {
+            return new OrigDate(clock()).toString();
+        }
+        if (args.length === 0) {
+            args = [clock()];
+        }
+        return Reflect.construct(OrigDate, args, new.target);
+    }
+    Object.setPrototypeOf(ClockDate, OrigDate);
+    Object.defineProperty(ClockDate, 'prototype', {value: OrigDate.prototype});
+    Object.defineProperty(ClockDate, 'length', {value: OrigDate.length});
+    Object.defineProperty(ClockDate, 'name', {value: OrigDate.name});
+    Object.defineProperty(OrigDate.prototype, 'constructor', {value: ClockDate, writable: true, configurable: true});
+    Object.defineProperty(ClockDate, 'now', {value: function now() { return clock(); }, writable: true, configurable: true});
+    Object.defineProperty(globalThis, 'Date', {value: ClockDate, writable: true, configurable: true});
+    if (typeof performance !== 'undefined' && typeof performance.now === 'function') {
+        const origin = clock();
+        performance.now = function now() { return clock() - origin; };
+    }
+})
+"#;
+
+/// make Date.now(), new Date() and performance.now() (if present) observe the time (in milliseconds since the epoch) returned by a clock function
+/// this is mainly useful for deterministic tests, see also [crate::builder::QuickJsRuntimeBuilder::set_clock]
+pub fn set_clock_q<C: Fn() -> f64 + 'static>(
+    context: &QuickJsRealmAdapter,
+    clock: C,
+) -> Result<(), JsError> {
+    let clock_ref = functions::new_function_q(
+        context,
+        "clock",
+        move |_q_ctx, _this, _args| Ok(primitives::from_f64(clock())),
+        0,
+    )?;
+    let shim_ref = context.eval(Script::new("set_clock.js", CLOCK_SHIM))?;
+    functions::call_function_q(context, &shim_ref, &[clock_ref], None)?;
+    Ok(())
+}
+
+/// create a new instance of a Date object for a timestamp (milliseconds since the epoch)
+pub fn new_date_from_millis_q(
+    context: &QuickJsRealmAdapter,
+    millis: f64,
+) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { new_date_from_millis(context.context, millis) }
+}
+
+/// create a new instance of a Date object for a timestamp (milliseconds since the epoch)
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn new_date_from_millis(
+    context: *mut q::JSContext,
+    millis: f64,
+) -> Result<QuickJsValueAdapter, JsError> {
+    let constructor = quickjs_utils::get_constructor(context, "Date")?;
+    functions::call_constructor(context, &constructor, &[primitives::from_f64(millis)])
+}
+
+/// the max number of milliseconds from the epoch a Date can represent (in both directions)
+const MAX_DATE_MILLIS: f64 = 8.64e15;
+
+/// create a new instance of a Date object for a timestamp (milliseconds since the epoch)
+/// unlike [new_date_from_millis_q] this returns an Err instead of an Invalid Date when the timestamp is out of range
+pub fn new_date_from_timestamp_q(
+    context: &QuickJsRealmAdapter,
+    millis: f64,
+) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { new_date_from_timestamp(context.context, millis) }
+}
+
+/// create a new instance of a Date object for a timestamp (milliseconds since the epoch)
+/// unlike [new_date_from_millis] this returns an Err instead of an Invalid Date when the timestamp is out of range
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn new_date_from_timestamp(
+    context: *mut q::JSContext,
+    millis: f64,
+) -> Result<QuickJsValueAdapter, JsError> {
+    if millis.is_nan() || millis.abs() > MAX_DATE_MILLIS {
+        return Err(JsError::new_string(format!(
+            "timestamp {millis} is out of the range of a Date"
+        )));
+    }
+    new_date_from_millis(context, millis)
+}
+
+/// create a new instance of a Date object from its components in local time, like new Date(year, month, ...) in JavaScript
+/// please note that months are zero-based (0 = January), values outside their normal range roll over (e.g. month 12 is January of the next year)
+#[allow(clippy::too_many_arguments)]
+pub fn new_date_from_components_q(
+    context: &QuickJsRealmAdapter,
+    year: i32,
+    month: i32,
+    day: i32,
+    hours: i32,
+    minutes: i32,
+    seconds: i32,
+    millis: i32,
+) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe {
+        let constructor = quickjs_utils::get_constructor(context.context, "Date")?;
+        let date_ref = functions::call_constructor(
+            context.context,
+            &constructor,
+            &[
+                primitives::from_i32(year),
+                primitives::from_i32(month),
+                primitives::from_i32(day),
+                primitives::from_i32(hours),
+                primitives::from_i32(minutes),
+                primitives::from_i32(seconds),
+                primitives::from_i32(millis),
+            ],
+        )?;
+        if !is_valid_date(context.context, &date_ref) {
+            return Err(JsError::new_str(
+                "date components are out of the range of a Date",
+            ));
+        }
+        Ok(date_ref)
+    }
+}
+
+/// create a new instance of a Date object for a SystemTime
+/// please note that JS dates have millisecond precision so any sub-millisecond part of the SystemTime is truncated
+pub fn new_date_from_system_time_q(
+    context: &QuickJsRealmAdapter,
+    time: SystemTime,
+) -> Result<QuickJsValueAdapter, JsError> {
+    new_date_from_timestamp_q(context, system_time_to_millis(time))
+}
+
+/// get the timestamp (milliseconds since the epoch) of a SystemTime, anything below millisecond precision is truncated
+pub fn system_time_to_millis(time: SystemTime) -> f64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_millis() as f64,
+        // before the epoch, round towards negative infinity so truncation is consistent
+        Err(e) => -(e.duration().as_micros() as f64 / 1000f64).ceil(),
+    }
+}
+
+/// convert a Date object to a SystemTime
+/// returns an Err if the Date is invalid
+pub fn to_system_time_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<SystemTime, JsError> {
+    let millis = get_time_q(context, date_ref)?;
+    let duration = Duration::from_millis(millis.abs() as u64);
+    let time = if millis < 0f64 {
+        UNIX_EPOCH.checked_sub(duration)
+    } else {
+        UNIX_EPOCH.checked_add(duration)
+    };
+    time.ok_or_else(|| {
+        JsError::new_string(format!(
+            "timestamp {millis} is out of the range of a SystemTime"
+        ))
+    })
+}
+
 /// check if a JSValueRef is an instance of Date
 pub fn is_date_q(context: &QuickJsRealmAdapter, obj_ref: &QuickJsValueAdapter) -> bool {
     unsafe { is_date(context.context, obj_ref) }
@@ -38,7 +226,7 @@ pub fn set_time_q(
     context: &QuickJsRealmAdapter,
     date_ref: &QuickJsValueAdapter,
     timestamp: f64,
-) -> Result<(), JsError> {
+) -> Result<f64, JsError> {
     unsafe { set_time(context.context, date_ref, timestamp) }
 }
 
@@ -49,16 +237,16 @@ pub unsafe fn set_time(
     context: *mut q::JSContext,
     date_ref: &QuickJsValueAdapter,
     timestamp: f64,
-) -> Result<(), JsError> {
-    functions::invoke_member_function(
+) -> Result<f64, JsError> {
+    invoke_setter(
         context,
         date_ref,
         "setTime",
         &[primitives::from_f64(timestamp)],
-    )?;
-    Ok(())
+    )
 }
 /// get the timestamp from a Date object
+/// returns an Err for an Invalid Date
 pub fn get_time_q(
     context: &QuickJsRealmAdapter,
     date_ref: &QuickJsValueAdapter,
@@ -66,6 +254,7 @@ pub fn get_time_q(
     unsafe { get_time(context.context, date_ref) }
 }
 /// get the timestamp from a Date object
+/// returns an Err for an Invalid Date
 /// # Safety
 /// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
 pub unsafe fn get_time(
@@ -73,19 +262,823 @@ pub unsafe fn get_time(
     date_ref: &QuickJsValueAdapter,
 ) -> Result<f64, JsError> {
     let time_ref = functions::invoke_member_function(context, date_ref, "getTime", &[])?;
-    if time_ref.is_f64() {
-        primitives::to_f64(&time_ref)
+    // convert any numeric representation (int or float tagged) losslessly
+    let mut time: f64 = 0.0;
+    if q::JS_ToFloat64(context, &mut time, *time_ref.borrow_value()) != 0 {
+        return Err(errors::get_exception(context)
+            .unwrap_or_else(|| JsError::new_str("could not convert timestamp to a Number")));
+    }
+    if time.is_nan() {
+        Err(JsError::new_str("Date is invalid"))
+    } else {
+        Ok(time)
+    }
+}
+
+/// check if a JSValueRef is a Date which represents an actual point in time (and not an Invalid Date like new Date('garbage'))
+pub fn is_valid_date_q(context: &QuickJsRealmAdapter, date_ref: &QuickJsValueAdapter) -> bool {
+    unsafe { is_valid_date(context.context, date_ref) }
+}
+
+/// check if a JSValueRef is a Date which represents an actual point in time (and not an Invalid Date like new Date('garbage'))
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn is_valid_date(context: *mut q::JSContext, date_ref: &QuickJsValueAdapter) -> bool {
+    is_date(context, date_ref) && get_time(context, date_ref).is_ok()
+}
+
+/// get the timestamp of an argument which must be a valid Date
+fn get_time_of_date_arg(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    arg_name: &str,
+) -> Result<f64, JsError> {
+    if !is_date_q(context, date_ref) {
+        return Err(JsError::new_string(format!("{arg_name} is not a Date")));
+    }
+    get_time_q(context, date_ref)
+        .map_err(|_| JsError::new_string(format!("{arg_name} is an Invalid Date")))
+}
+
+/// compare two Date objects by their timestamp
+/// returns an Err if either of them is not a (valid) Date
+pub fn compare_q(
+    context: &QuickJsRealmAdapter,
+    a: &QuickJsValueAdapter,
+    b: &QuickJsValueAdapter,
+) -> Result<Ordering, JsError> {
+    let a_time = get_time_of_date_arg(context, a, "a")?;
+    let b_time = get_time_of_date_arg(context, b, "b")?;
+    // both are valid so neither is NaN
+    Ok(a_time.total_cmp(&b_time))
+}
+
+/// create a new Date object which is delta milliseconds later (or earlier for a negative delta) than date_ref
+/// returns an Err if date_ref is not a (valid) Date or if the result is out of the range of a Date
+pub fn add_millis_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    delta: f64,
+) -> Result<QuickJsValueAdapter, JsError> {
+    let time = get_time_of_date_arg(context, date_ref, "date_ref")?;
+    new_date_from_timestamp_q(context, time + delta)
+}
+
+/// get the number of milliseconds between two Date objects (a - b)
+/// returns an Err if either of them is not a (valid) Date
+pub fn diff_millis_q(
+    context: &QuickJsRealmAdapter,
+    a: &QuickJsValueAdapter,
+    b: &QuickJsValueAdapter,
+) -> Result<f64, JsError> {
+    let a_time = get_time_of_date_arg(context, a, "a")?;
+    let b_time = get_time_of_date_arg(context, b, "b")?;
+    Ok(a_time - b_time)
+}
+
+/// invoke a getter member function (e.g. getUTCFullYear) of a Date object which returns an integer
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+unsafe fn invoke_int_getter(
+    context: *mut q::JSContext,
+    date_ref: &QuickJsValueAdapter,
+    function_name: &str,
+) -> Result<i32, JsError> {
+    let val_ref = functions::invoke_member_function(context, date_ref, function_name, &[])?;
+    if val_ref.is_i32() {
+        primitives::to_i32(&val_ref)
+    } else {
+        // invalid dates return NaN
+        Err(JsError::new_string(format!(
+            "{function_name} did not return an integer, is the Date valid?"
+        )))
+    }
+}
+
+/// invoke a setter member function (e.g. setTime) of a Date object and return the timestamp it reports
+/// errors (e.g. when the Date is wrapped in a Proxy) are reported with the name of the setter which failed
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+unsafe fn invoke_setter(
+    context: *mut q::JSContext,
+    date_ref: &QuickJsValueAdapter,
+    function_name: &str,
+    arguments: &[QuickJsValueAdapter],
+) -> Result<f64, JsError> {
+    let res_ref = functions::invoke_member_function(context, date_ref, function_name, arguments)
+        .map_err(|e| {
+            JsError::new_string(format!("could not set Date with {function_name}: {e}"))
+        })?;
+    let mut timestamp: f64 = 0.0;
+    if q::JS_ToFloat64(context, &mut timestamp, *res_ref.borrow_value()) != 0 {
+        return Err(errors::get_exception(context).unwrap_or_else(|| {
+            JsError::new_string(format!("{function_name} did not return a timestamp"))
+        }));
+    }
+    Ok(timestamp)
+}
+
+/// invoke a setter member function (e.g. setUTCFullYear) of a Date object with a single integer argument
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+unsafe fn invoke_int_setter(
+    context: *mut q::JSContext,
+    date_ref: &QuickJsValueAdapter,
+    function_name: &str,
+    value: i32,
+) -> Result<(), JsError> {
+    invoke_setter(
+        context,
+        date_ref,
+        function_name,
+        &[primitives::from_i32(value)],
+    )?;
+    Ok(())
+}
+
+/// the components of a Date, used by [set_components_q] and [set_utc_components_q] to update several components at once
+/// components which are None are left unchanged
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DateComponents {
+    pub year: Option<i32>,
+    /// the month (0 for January)
+    pub month: Option<i32>,
+    /// the day of the month (1-31)
+    pub date: Option<i32>,
+    pub hours: Option<i32>,
+    pub minutes: Option<i32>,
+    pub seconds: Option<i32>,
+    pub milliseconds: Option<i32>,
+}
+
+/// update several components of a Date object in local time and return the resulting timestamp
+/// the components are set in a single setFullYear and setHours call so e.g. setting the month and date of Jan 31 to Feb 28 does not overflow
+pub fn set_components_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    components: &DateComponents,
+) -> Result<f64, JsError> {
+    unsafe { set_components(context.context, date_ref, components, false) }
+}
+
+/// update several components of a Date object in UTC and return the resulting timestamp
+pub fn set_utc_components_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    components: &DateComponents,
+) -> Result<f64, JsError> {
+    unsafe { set_components(context.context, date_ref, components, true) }
+}
+
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+unsafe fn set_components(
+    context: *mut q::JSContext,
+    date_ref: &QuickJsValueAdapter,
+    components: &DateComponents,
+    utc: bool,
+) -> Result<f64, JsError> {
+    let prefix = if utc { "UTC" } else { "" };
+    // fill the components which are not set with the current values
+    let component = |value: Option<i32>, name: &str| -> Result<QuickJsValueAdapter, JsError> {
+        let value = match value {
+            Some(v) => v,
+            None => invoke_int_getter(context, date_ref, format!("get{prefix}{name}").as_str())?,
+        };
+        Ok(primitives::from_i32(value))
+    };
+    let date_fields = [
+        (components.year, "FullYear"),
+        (components.month, "Month"),
+        (components.date, "Date"),
+    ];
+    let time_fields = [
+        (components.hours, "Hours"),
+        (components.minutes, "Minutes"),
+        (components.seconds, "Seconds"),
+        (components.milliseconds, "Milliseconds"),
+    ];
+    let mut timestamp = None;
+    for (fields, setter) in [(&date_fields[..], "FullYear"), (&time_fields[..], "Hours")] {
+        if fields.iter().all(|(value, _)| value.is_none()) {
+            continue;
+        }
+        let setter = format!("set{prefix}{setter}");
+        let args = fields
+            .iter()
+            .map(|(value, name)| component(*value, name))
+            .collect::<Result<Vec<_>, JsError>>()
+            .map_err(|e| JsError::new_string(format!("could not set Date with {setter}: {e}")))?;
+        timestamp = Some(invoke_setter(context, date_ref, setter.as_str(), &args)?);
+    }
+    match timestamp {
+        Some(timestamp) => Ok(timestamp),
+        // nothing to set
+        None => get_time(context, date_ref),
+    }
+}
+
+/// get the year of a Date object in local time
+pub fn get_full_year_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getFullYear") }
+}
+
+/// set the year of a Date object in local time (Date.prototype.setFullYear)
+pub fn set_full_year_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    value: i32,
+) -> Result<(), JsError> {
+    unsafe { invoke_int_setter(context.context, date_ref, "setFullYear", value) }
+}
+
+/// set the year of a Date object in UTC (Date.prototype.setUTCFullYear)
+pub fn set_utc_full_year_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    value: i32,
+) -> Result<(), JsError> {
+    unsafe { invoke_int_setter(context.context, date_ref, "setUTCFullYear", value) }
+}
+
+/// get the zero-based month (0 = January) of a Date object in local time
+pub fn get_month_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getMonth") }
+}
+
+/// set the zero-based month of a Date object in local time, out of range values roll over into the year
+pub fn set_month_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    value: i32,
+) -> Result<(), JsError> {
+    unsafe { invoke_int_setter(context.context, date_ref, "setMonth", value) }
+}
+
+/// set the zero-based month of a Date object in UTC, out of range values roll over into the year
+pub fn set_utc_month_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    value: i32,
+) -> Result<(), JsError> {
+    unsafe { invoke_int_setter(context.context, date_ref, "setUTCMonth", value) }
+}
+
+/// get the day of the month (1-31) of a Date object in local time
+pub fn get_date_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getDate") }
+}
+
+/// set the day of the month of a Date object in local time, out of range values roll over into the month
+pub fn set_date_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    value: i32,
+) -> Result<(), JsError> {
+    unsafe { invoke_int_setter(context.context, date_ref, "setDate", value) }
+}
+
+/// set the day of the month of a Date object in UTC, out of range values roll over into the month
+pub fn set_utc_date_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    value: i32,
+) -> Result<(), JsError> {
+    unsafe { invoke_int_setter(context.context, date_ref, "setUTCDate", value) }
+}
+
+/// get the day of the week (0 = Sunday) of a Date object in local time
+pub fn get_day_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getDay") }
+}
+
+/// get the hours (0-23) of a Date object in local time
+pub fn get_hours_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getHours") }
+}
+
+/// set the hours of a Date object in local time, out of range values roll over into the day
+pub fn set_hours_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    value: i32,
+) -> Result<(), JsError> {
+    unsafe { invoke_int_setter(context.context, date_ref, "setHours", value) }
+}
+
+/// set the hours of a Date object in UTC, out of range values roll over into the day
+pub fn set_utc_hours_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    value: i32,
+) -> Result<(), JsError> {
+    unsafe { invoke_int_setter(context.context, date_ref, "setUTCHours", value) }
+}
+
+/// get the minutes (0-59) of a Date object in local time
+pub fn get_minutes_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getMinutes") }
+}
+
+/// set the minutes of a Date object in local time, out of range values roll over into the hour
+pub fn set_minutes_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    value: i32,
+) -> Result<(), JsError> {
+    unsafe { invoke_int_setter(context.context, date_ref, "setMinutes", value) }
+}
+
+/// set the minutes of a Date object in UTC, out of range values roll over into the hour
+pub fn set_utc_minutes_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    value: i32,
+) -> Result<(), JsError> {
+    unsafe { invoke_int_setter(context.context, date_ref, "setUTCMinutes", value) }
+}
+
+/// get the seconds (0-59) of a Date object in local time
+pub fn get_seconds_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getSeconds") }
+}
+
+/// set the seconds of a Date object in local time, out of range values roll over into the minute
+pub fn set_seconds_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    value: i32,
+) -> Result<(), JsError> {
+    unsafe { invoke_int_setter(context.context, date_ref, "setSeconds", value) }
+}
+
+/// set the seconds of a Date object in UTC, out of range values roll over into the minute
+pub fn set_utc_seconds_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    value: i32,
+) -> Result<(), JsError> {
+    unsafe { invoke_int_setter(context.context, date_ref, "setUTCSeconds", value) }
+}
+
+/// get the milliseconds (0-999) of a Date object in local time
+pub fn get_milliseconds_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getMilliseconds") }
+}
+
+/// set the milliseconds of a Date object in local time, out of range values roll over into the second
+pub fn set_milliseconds_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    value: i32,
+) -> Result<(), JsError> {
+    unsafe { invoke_int_setter(context.context, date_ref, "setMilliseconds", value) }
+}
+
+/// set the milliseconds of a Date object in UTC, out of range values roll over into the second
+pub fn set_utc_milliseconds_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    value: i32,
+) -> Result<(), JsError> {
+    unsafe { invoke_int_setter(context.context, date_ref, "setUTCMilliseconds", value) }
+}
+
+/// get the year of a Date object in UTC
+pub fn get_utc_full_year_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getUTCFullYear") }
+}
+
+/// get the zero-based month (0 = January) of a Date object in UTC
+pub fn get_utc_month_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getUTCMonth") }
+}
+
+/// get the day of the month (1-31) of a Date object in UTC
+pub fn get_utc_date_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getUTCDate") }
+}
+
+/// get the day of the week (0 = Sunday) of a Date object in UTC
+pub fn get_utc_day_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getUTCDay") }
+}
+
+/// a day of the week, the discriminants match the values returned by getDay in JavaScript
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Weekday {
+    Sunday = 0,
+    Monday = 1,
+    Tuesday = 2,
+    Wednesday = 3,
+    Thursday = 4,
+    Friday = 5,
+    Saturday = 6,
+}
+
+impl TryFrom<i32> for Weekday {
+    type Error = JsError;
+
+    fn try_from(day: i32) -> Result<Self, Self::Error> {
+        match day {
+            0 => Ok(Weekday::Sunday),
+            1 => Ok(Weekday::Monday),
+            2 => Ok(Weekday::Tuesday),
+            3 => Ok(Weekday::Wednesday),
+            4 => Ok(Weekday::Thursday),
+            5 => Ok(Weekday::Friday),
+            6 => Ok(Weekday::Saturday),
+            _ => Err(JsError::new_string(format!(
+                "{day} is not a valid day of the week (0-6)"
+            ))),
+        }
+    }
+}
+
+impl From<Weekday> for i32 {
+    fn from(day: Weekday) -> Self {
+        day as i32
+    }
+}
+
+/// a month, the discriminants match the (zero-based) values returned by getMonth in JavaScript
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Month {
+    January = 0,
+    February = 1,
+    March = 2,
+    April = 3,
+    May = 4,
+    June = 5,
+    July = 6,
+    August = 7,
+    September = 8,
+    October = 9,
+    November = 10,
+    December = 11,
+}
+
+impl TryFrom<i32> for Month {
+    type Error = JsError;
+
+    fn try_from(month: i32) -> Result<Self, Self::Error> {
+        match month {
+            0 => Ok(Month::January),
+            1 => Ok(Month::February),
+            2 => Ok(Month::March),
+            3 => Ok(Month::April),
+            4 => Ok(Month::May),
+            5 => Ok(Month::June),
+            6 => Ok(Month::July),
+            7 => Ok(Month::August),
+            8 => Ok(Month::September),
+            9 => Ok(Month::October),
+            10 => Ok(Month::November),
+            11 => Ok(Month::December),
+            _ => Err(JsError::new_string(format!(
+                "{month} is not a valid month (0-11)"
+            ))),
+        }
+    }
+}
+
+impl From<Month> for i32 {
+    fn from(month: Month) -> Self {
+        month as i32
+    }
+}
+
+/// get the day of the week of a Date object in local time
+/// returns an Err if getDay does not return a valid day (e.g. when it was replaced by script)
+pub fn get_weekday_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<Weekday, JsError> {
+    Weekday::try_from(get_day_q(context, date_ref)?)
+}
+
+/// get the day of the week of a Date object in UTC
+/// returns an Err if getUTCDay does not return a valid day (e.g. when it was replaced by script)
+pub fn get_utc_weekday_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<Weekday, JsError> {
+    Weekday::try_from(get_utc_day_q(context, date_ref)?)
+}
+
+/// get the month of a Date object in local time
+/// returns an Err if getMonth does not return a valid month (e.g. when it was replaced by script)
+pub fn get_month_enum_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<Month, JsError> {
+    Month::try_from(get_month_q(context, date_ref)?)
+}
+
+/// get the month of a Date object in UTC
+/// returns an Err if getUTCMonth does not return a valid month (e.g. when it was replaced by script)
+pub fn get_utc_month_enum_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<Month, JsError> {
+    Month::try_from(get_utc_month_q(context, date_ref)?)
+}
+
+/// get the hours (0-23) of a Date object in UTC
+pub fn get_utc_hours_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getUTCHours") }
+}
+
+/// get the minutes (0-59) of a Date object in UTC
+pub fn get_utc_minutes_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getUTCMinutes") }
+}
+
+/// get the seconds (0-59) of a Date object in UTC
+pub fn get_utc_seconds_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getUTCSeconds") }
+}
+
+/// get the milliseconds (0-999) of a Date object in UTC
+pub fn get_utc_milliseconds_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getUTCMilliseconds") }
+}
+
+/// get the ISO-8601 representation of a Date object (e.g. 2011-10-05T14:48:00.000Z)
+/// returns an Err (the RangeError thrown by toISOString) for an Invalid Date
+pub fn to_iso_string_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<String, JsError> {
+    unsafe { to_iso_string(context.context, date_ref) }
+}
+
+/// get the ISO-8601 representation of a Date object (e.g. 2011-10-05T14:48:00.000Z)
+/// returns an Err (the RangeError thrown by toISOString) for an Invalid Date
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn to_iso_string(
+    context: *mut q::JSContext,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<String, JsError> {
+    let iso_ref = functions::invoke_member_function(context, date_ref, "toISOString", &[])?;
+    primitives::to_string(context, &iso_ref)
+}
+
+/// format a timestamp like Date.prototype.toISOString does without needing a realm, None for an Invalid Date
+pub fn millis_to_iso_string(millis: f64) -> Option<String> {
+    if !millis.is_finite() || millis.abs() > 8.64e15 {
+        return None;
+    }
+    let millis = millis as i64;
+    let days = millis.div_euclid(86_400_000);
+    let ms_of_day = millis.rem_euclid(86_400_000);
+    // days to civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let year_str = if (0..=9999).contains(&year) {
+        format!("{year:04}")
+    } else if year < 0 {
+        format!("-{:06}", -year)
+    } else {
+        format!("+{year:06}")
+    };
+    Some(format!(
+        "{year_str}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        ms_of_day / 3_600_000,
+        (ms_of_day / 60_000) % 60,
+        (ms_of_day / 1000) % 60,
+        ms_of_day % 1000
+    ))
+}
+
+/// invoke one of the toLocale...String member functions of a Date object
+fn invoke_locale_function(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    function_name: &str,
+    locale: Option<&str>,
+    options_json: Option<&str>,
+) -> Result<String, JsError> {
+    let with_args = locale.is_some() || options_json.is_some();
+    if with_args {
+        let intl_ref =
+            objects::get_property_q(context, &quickjs_utils::get_global_q(context), "Intl")?;
+        if intl_ref.is_null_or_undefined() {
+            return Err(JsError::new_string(format!(
+                "{function_name} with a locale or options is not supported, this QuickJS build has no Intl support"
+            )));
+        }
+    }
+    let mut args = vec![];
+    if with_args {
+        args.push(match locale {
+            Some(locale) => primitives::from_string_q(context, locale)?,
+            None => quickjs_utils::new_undefined_ref(),
+        });
+    }
+    if let Some(options_json) = options_json {
+        args.push(json::parse_q(context, options_json)?);
+    }
+    let res_ref = unsafe {
+        functions::invoke_member_function(context.context, date_ref, function_name, &args)?
+    };
+    res_ref.to_string()
+}
+
+/// format a Date object with toLocaleString
+/// locale (e.g. "en-US") and options_json (e.g. {"timeZone": "UTC"}) require Intl support, if the QuickJS build has no Intl support passing them results in an Err
+pub fn to_locale_string_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    locale: Option<&str>,
+    options_json: Option<&str>,
+) -> Result<String, JsError> {
+    invoke_locale_function(context, date_ref, "toLocaleString", locale, options_json)
+}
+
+/// format the date part of a Date object with toLocaleDateString
+/// locale and options_json require Intl support, see [to_locale_string_q]
+pub fn to_locale_date_string_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    locale: Option<&str>,
+    options_json: Option<&str>,
+) -> Result<String, JsError> {
+    invoke_locale_function(
+        context,
+        date_ref,
+        "toLocaleDateString",
+        locale,
+        options_json,
+    )
+}
+
+/// format the time part of a Date object with toLocaleTimeString
+/// locale and options_json require Intl support, see [to_locale_string_q]
+pub fn to_locale_time_string_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+    locale: Option<&str>,
+    options_json: Option<&str>,
+) -> Result<String, JsError> {
+    invoke_locale_function(
+        context,
+        date_ref,
+        "toLocaleTimeString",
+        locale,
+        options_json,
+    )
+}
+
+/// get the difference in minutes between UTC and the local timezone for a Date object, like getTimezoneOffset in JavaScript
+pub fn get_timezone_offset_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<i32, JsError> {
+    unsafe { invoke_int_getter(context.context, date_ref, "getTimezoneOffset") }
+}
+
+/// create a new Date object by parsing an ISO-8601 string with Date.parse
+/// returns an Err if the string could not be parsed
+pub fn parse_iso_q(context: &QuickJsRealmAdapter, s: &str) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { parse_iso(context.context, s) }
+}
+
+/// create a new Date object by parsing an ISO-8601 string with Date.parse
+/// returns an Err if the string could not be parsed
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn parse_iso(
+    context: *mut q::JSContext,
+    s: &str,
+) -> Result<QuickJsValueAdapter, JsError> {
+    let constructor = quickjs_utils::get_constructor(context, "Date")?;
+    let millis_ref = functions::invoke_member_function(
+        context,
+        &constructor,
+        "parse",
+        &[primitives::from_string(context, s)?],
+    )?;
+    let millis = if millis_ref.is_f64() {
+        primitives::to_f64(&millis_ref)?
     } else {
-        primitives::to_i32(&time_ref).map(|i| i as f64)
+        primitives::to_i32(&millis_ref)? as f64
+    };
+    if millis.is_nan() {
+        return Err(JsError::new_string(format!("could not parse date: {s}")));
     }
+    new_date_from_millis(context, millis)
+}
+
+/// create a new instance of a Date object for a chrono DateTime
+/// please note that JS dates have millisecond precision so any sub-millisecond part of the DateTime is truncated
+#[cfg(feature = "chrono")]
+pub fn new_date_from_chrono_q(
+    context: &QuickJsRealmAdapter,
+    dt: chrono::DateTime<chrono::Utc>,
+) -> Result<QuickJsValueAdapter, JsError> {
+    new_date_from_timestamp_q(context, dt.timestamp_millis() as f64)
+}
+
+/// convert a Date object to a chrono DateTime
+/// returns an Err if the Date is invalid or outside the range of a chrono DateTime
+#[cfg(feature = "chrono")]
+pub fn to_chrono_q(
+    context: &QuickJsRealmAdapter,
+    date_ref: &QuickJsValueAdapter,
+) -> Result<chrono::DateTime<chrono::Utc>, JsError> {
+    let millis = get_time_q(context, date_ref)?;
+    chrono::DateTime::from_timestamp_millis(millis as i64).ok_or_else(|| {
+        JsError::new_string(format!(
+            "timestamp {millis} is out of the range of a chrono DateTime"
+        ))
+    })
 }
 
 #[cfg(test)]
 pub mod tests {
 
     use crate::facades::tests::init_test_rt;
+    use crate::jsutils::Script;
     use crate::quickjs_utils::dates;
-    use crate::quickjs_utils::dates::{get_time_q, is_date_q, set_time_q};
+    use crate::quickjs_utils::dates::{
+        add_millis_q, compare_q, diff_millis_q, get_timezone_offset_q, millis_to_iso_string,
+        to_locale_date_string_q, to_locale_string_q, to_locale_time_string_q,
+    };
+    use crate::quickjs_utils::dates::{
+        get_date_q, get_day_q, get_full_year_q, get_hours_q, get_milliseconds_q, get_minutes_q,
+        get_month_q, get_seconds_q, get_time_q, get_utc_date_q, get_utc_day_q, get_utc_full_year_q,
+        get_utc_hours_q, get_utc_milliseconds_q, get_utc_minutes_q, get_utc_month_q,
+        get_utc_seconds_q, is_date_q, is_valid_date_q, new_date_from_components_q,
+        new_date_from_millis_q, new_date_from_system_time_q, new_date_from_timestamp_q,
+        parse_iso_q, set_date_q, set_full_year_q, set_hours_q, set_milliseconds_q, set_minutes_q,
+        set_month_q, set_seconds_q, set_time_q, set_utc_date_q, set_utc_full_year_q,
+        set_utc_hours_q, set_utc_milliseconds_q, set_utc_minutes_q, set_utc_month_q,
+        set_utc_seconds_q, to_iso_string_q, to_system_time_q,
+    };
+    use crate::quickjs_utils::dates::{
+        get_month_enum_q, get_utc_month_enum_q, get_utc_weekday_q, get_weekday_q, Month, Weekday,
+    };
+    use crate::quickjs_utils::dates::{set_components_q, set_utc_components_q, DateComponents};
+    use crate::quickjs_utils::primitives;
+    use std::cmp::Ordering;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
     #[test]
     fn test_date() {
@@ -118,4 +1111,481 @@ pub mod tests {
             }
         });
     }
+
+    #[test]
+    fn test_get_time_round_trip() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let date_ref = dates::new_date_q(q_ctx).expect("new_date failed");
+            for timestamp in [
+                0f64,
+                -1f64,
+                2_147_483_647f64,
+                2_147_483_648_000f64,
+                -2_208_988_800_000f64,
+                8.64e15,
+                -8.64e15,
+            ] {
+                set_time_q(q_ctx, &date_ref, timestamp).expect("could not set time");
+                assert_eq!(get_time_q(q_ctx, &date_ref).unwrap(), timestamp);
+            }
+        });
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let a_ref = new_date_from_millis_q(q_ctx, 1_000f64).expect("new_date failed");
+            let a2_ref = new_date_from_millis_q(q_ctx, 1_000f64).expect("new_date failed");
+            let b_ref = add_millis_q(q_ctx, &a_ref, 1_500f64).expect("add failed");
+            assert_eq!(get_time_q(q_ctx, &b_ref).unwrap(), 2_500f64);
+            // a is unchanged
+            assert_eq!(get_time_q(q_ctx, &a_ref).unwrap(), 1_000f64);
+
+            assert_eq!(compare_q(q_ctx, &a_ref, &a2_ref).unwrap(), Ordering::Equal);
+            assert_eq!(compare_q(q_ctx, &a_ref, &b_ref).unwrap(), Ordering::Less);
+            assert_eq!(compare_q(q_ctx, &b_ref, &a_ref).unwrap(), Ordering::Greater);
+            assert_eq!(diff_millis_q(q_ctx, &a_ref, &a2_ref).unwrap(), 0f64);
+            assert_eq!(diff_millis_q(q_ctx, &a_ref, &b_ref).unwrap(), -1_500f64);
+            assert_eq!(diff_millis_q(q_ctx, &b_ref, &a_ref).unwrap(), 1_500f64);
+
+            let not_a_date = primitives::from_f64(1_000f64);
+            let err = compare_q(q_ctx, &a_ref, &not_a_date).expect_err("compared a non-Date");
+            assert_eq!(err.get_message(), "b is not a Date");
+            assert!(diff_millis_q(q_ctx, &a_ref, &not_a_date).is_err());
+            assert!(add_millis_q(q_ctx, &not_a_date, 1f64).is_err());
+
+            let invalid_ref = new_date_from_millis_q(q_ctx, f64::NAN).expect("new_date failed");
+            let err = compare_q(q_ctx, &invalid_ref, &a_ref).expect_err("compared an Invalid Date");
+            assert_eq!(err.get_message(), "a is an Invalid Date");
+            assert!(add_millis_q(q_ctx, &a_ref, 8.64e15).is_err());
+        });
+    }
+
+    #[test]
+    fn test_locale() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let date_ref =
+                new_date_from_millis_q(q_ctx, 1_317_826_087_123f64).expect("new_date failed");
+            let expected = q_ctx
+                .eval(Script::new(
+                    "test_locale.js",
+                    "let ld = new Date(1317826087123); [ld.toLocaleString(), ld.toLocaleDateString(), ld.toLocaleTimeString(), ld.getTimezoneOffset(), typeof Intl].join('|');",
+                ))
+                .expect("script failed")
+                .to_string()
+                .expect("not a string");
+            let actual = format!(
+                "{}|{}|{}|{}|",
+                to_locale_string_q(q_ctx, &date_ref, None, None).unwrap(),
+                to_locale_date_string_q(q_ctx, &date_ref, None, None).unwrap(),
+                to_locale_time_string_q(q_ctx, &date_ref, None, None).unwrap(),
+                get_timezone_offset_q(q_ctx, &date_ref).unwrap(),
+            );
+            assert!(expected.starts_with(actual.as_str()));
+
+            let res = to_locale_string_q(
+                q_ctx,
+                &date_ref,
+                Some("en-US"),
+                Some(r#"{"timeZone": "UTC"}"#),
+            );
+            if expected.ends_with("|undefined") {
+                // no Intl support
+                let err = res.expect_err("locale without Intl succeeded");
+                assert!(err.get_message().contains("no Intl support"));
+            } else {
+                assert!(res.is_ok());
+            }
+        });
+    }
+
+    #[test]
+    fn test_set_components() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            // 2011-01-31T14:48:07.123Z
+            let date_ref = dates::new_date_from_millis_q(q_ctx, 1_296_485_287_123f64).unwrap();
+            assert_eq!(
+                set_time_q(q_ctx, &date_ref, 1_296_485_287_123.9f64).unwrap(),
+                1_296_485_287_123f64
+            );
+
+            // setting the month first would overflow to March
+            let ts = set_utc_components_q(
+                q_ctx,
+                &date_ref,
+                &DateComponents {
+                    month: Some(1),
+                    date: Some(28),
+                    minutes: Some(0),
+                    ..Default::default()
+                },
+            )
+            .expect("set failed");
+            assert_eq!(dates::get_time_q(q_ctx, &date_ref).unwrap(), ts);
+            assert_eq!(
+                to_iso_string_q(q_ctx, &date_ref).unwrap(),
+                "2011-02-28T14:00:07.123Z"
+            );
+
+            let ts = set_components_q(
+                q_ctx,
+                &date_ref,
+                &DateComponents {
+                    year: Some(2020),
+                    hours: Some(3),
+                    milliseconds: Some(0),
+                    ..Default::default()
+                },
+            )
+            .expect("set failed");
+            assert_eq!(dates::get_time_q(q_ctx, &date_ref).unwrap(), ts);
+            assert_eq!(get_full_year_q(q_ctx, &date_ref).unwrap(), 2020);
+            assert_eq!(get_hours_q(q_ctx, &date_ref).unwrap(), 3);
+            assert_eq!(get_milliseconds_q(q_ctx, &date_ref).unwrap(), 0);
+            assert_eq!(
+                set_components_q(q_ctx, &date_ref, &DateComponents::default()).unwrap(),
+                ts
+            );
+
+            // a frozen Proxy for a Date can not be set
+            let proxy_ref = q_ctx
+                .eval(Script::new(
+                    "test_set_components.js",
+                    "Object.freeze(new Proxy(new Date(0), {}));",
+                ))
+                .expect("script failed");
+            let err = set_time_q(q_ctx, &proxy_ref, 1000f64).expect_err("set should fail");
+            assert!(err.to_string().contains("setTime"), "{}", err);
+            let err = set_components_q(
+                q_ctx,
+                &proxy_ref,
+                &DateComponents {
+                    year: Some(2020),
+                    ..Default::default()
+                },
+            )
+            .expect_err("set should fail");
+            assert!(err.to_string().contains("setFullYear"), "{}", err);
+        });
+    }
+
+    #[test]
+    fn test_weekday_month() {
+        for (i, day) in [(0, Weekday::Sunday), (6, Weekday::Saturday)] {
+            assert_eq!(Weekday::try_from(i).unwrap(), day);
+            assert_eq!(i32::from(day), i);
+        }
+        for i in [-1, 7, i32::MIN, i32::MAX] {
+            assert!(Weekday::try_from(i).is_err());
+        }
+        for (i, month) in [(0, Month::January), (11, Month::December)] {
+            assert_eq!(Month::try_from(i).unwrap(), month);
+            assert_eq!(i32::from(month), i);
+        }
+        for i in [-1, 12, i32::MIN, i32::MAX] {
+            assert!(Month::try_from(i).is_err());
+        }
+
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            // 2011-01-02T12:00:00Z was a Sunday, 2011-12-31T12:00:00Z a Saturday
+            let date_ref = new_date_from_millis_q(q_ctx, 1_293_969_600_000f64).unwrap();
+            assert_eq!(
+                get_utc_weekday_q(q_ctx, &date_ref).unwrap(),
+                Weekday::Sunday
+            );
+            assert_eq!(
+                get_utc_month_enum_q(q_ctx, &date_ref).unwrap(),
+                Month::January
+            );
+            let date_ref = new_date_from_millis_q(q_ctx, 1_325_332_800_000f64).unwrap();
+            assert_eq!(
+                get_utc_weekday_q(q_ctx, &date_ref).unwrap(),
+                Weekday::Saturday
+            );
+            assert_eq!(
+                get_utc_month_enum_q(q_ctx, &date_ref).unwrap(),
+                Month::December
+            );
+            assert_eq!(
+                i32::from(get_weekday_q(q_ctx, &date_ref).unwrap()),
+                get_day_q(q_ctx, &date_ref).unwrap()
+            );
+            assert_eq!(
+                i32::from(get_month_enum_q(q_ctx, &date_ref).unwrap()),
+                get_month_q(q_ctx, &date_ref).unwrap()
+            );
+
+            let patched_ref = q_ctx
+                .eval(Script::new(
+                    "test_weekday_month.js",
+                    "let d = new Date(0); d.getDay = () => 7; d.getMonth = () => -1; d;",
+                ))
+                .expect("script failed");
+            assert!(get_weekday_q(q_ctx, &patched_ref).is_err());
+            assert!(get_month_enum_q(q_ctx, &patched_ref).is_err());
+        });
+    }
+
+    #[test]
+    fn test_valid_date() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let date_ref = new_date_from_millis_q(q_ctx, 0f64).expect("new_date failed");
+            assert!(is_valid_date_q(q_ctx, &date_ref));
+            assert_eq!(get_time_q(q_ctx, &date_ref).unwrap(), 0f64);
+
+            let invalid_ref = q_ctx
+                .eval(Script::new("test_valid_date.js", "new Date('garbage');"))
+                .expect("script failed");
+            assert!(is_date_q(q_ctx, &invalid_ref));
+            assert!(!is_valid_date_q(q_ctx, &invalid_ref));
+            assert!(get_time_q(q_ctx, &invalid_ref).is_err());
+
+            let obj_ref = q_ctx
+                .eval(Script::new("test_valid_date2.js", "({getTime: 1});"))
+                .expect("script failed");
+            assert!(!is_valid_date_q(q_ctx, &obj_ref));
+            assert!(get_time_q(q_ctx, &obj_ref).is_err());
+        });
+    }
+
+    #[test]
+    fn test_date_from_millis() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            for millis in [0f64, 1_700_000_000_123f64, -86_400_000f64] {
+                let date_ref = new_date_from_millis_q(q_ctx, millis).expect("new_date failed");
+                assert!(is_date_q(q_ctx, &date_ref));
+                assert_eq!(
+                    get_time_q(q_ctx, &date_ref).expect("get time failed"),
+                    millis
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_iso() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let date_ref =
+                new_date_from_millis_q(q_ctx, 1_317_826_080_123f64).expect("new_date failed");
+            let iso = to_iso_string_q(q_ctx, &date_ref).expect("to_iso_string failed");
+            assert_eq!(iso, "2011-10-05T14:48:00.123Z");
+
+            let parsed = parse_iso_q(q_ctx, iso.as_str()).expect("parse failed");
+            assert!(is_date_q(q_ctx, &parsed));
+            assert_eq!(
+                get_time_q(q_ctx, &parsed).expect("get time failed"),
+                1_317_826_080_123f64
+            );
+
+            assert!(parse_iso_q(q_ctx, "not a date").is_err());
+            assert!(parse_iso_q(q_ctx, "").is_err());
+
+            // Invalid Date can not be formatted and can not be parsed back
+            let invalid_ref = new_date_from_millis_q(q_ctx, f64::NAN).expect("new_date failed");
+            let err = to_iso_string_q(q_ctx, &invalid_ref).expect_err("invalid date formatted");
+            assert_eq!(err.get_name(), "RangeError");
+            assert!(parse_iso_q(q_ctx, "Invalid Date").is_err());
+        });
+    }
+
+    #[test]
+    fn test_millis_to_iso_string() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            for millis in [
+                0f64,
+                1_317_826_080_123f64,
+                -1f64,
+                -62_198_755_200_000f64,
+                253_402_300_800_000f64,
+                8.64e15,
+                -8.64e15,
+            ] {
+                let date_ref = new_date_from_millis_q(q_ctx, millis).expect("new_date failed");
+                let iso = to_iso_string_q(q_ctx, &date_ref).expect("to_iso_string failed");
+                assert_eq!(millis_to_iso_string(millis), Some(iso));
+            }
+            assert_eq!(millis_to_iso_string(f64::NAN), None);
+            assert_eq!(millis_to_iso_string(8.64e15 + 1f64), None);
+        });
+    }
+
+    #[test]
+    fn test_utc_getters() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            // 2011-10-05T14:48:07.123Z, a wednesday
+            let date_ref =
+                new_date_from_millis_q(q_ctx, 1_317_826_087_123f64).expect("new_date failed");
+            assert_eq!(get_utc_full_year_q(q_ctx, &date_ref).unwrap(), 2011);
+            assert_eq!(get_utc_month_q(q_ctx, &date_ref).unwrap(), 9);
+            assert_eq!(get_utc_date_q(q_ctx, &date_ref).unwrap(), 5);
+            assert_eq!(get_utc_day_q(q_ctx, &date_ref).unwrap(), 3);
+            assert_eq!(get_utc_hours_q(q_ctx, &date_ref).unwrap(), 14);
+            assert_eq!(get_utc_minutes_q(q_ctx, &date_ref).unwrap(), 48);
+            assert_eq!(get_utc_seconds_q(q_ctx, &date_ref).unwrap(), 7);
+            assert_eq!(get_utc_milliseconds_q(q_ctx, &date_ref).unwrap(), 123);
+
+            let invalid_ref = new_date_from_millis_q(q_ctx, f64::NAN).expect("new_date failed");
+            assert!(get_utc_full_year_q(q_ctx, &invalid_ref).is_err());
+        });
+    }
+
+    #[test]
+    fn test_getters_setters() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            // 2011-10-05T14:48:07.123Z
+            let date_ref =
+                new_date_from_millis_q(q_ctx, 1_317_826_087_123f64).expect("new_date failed");
+            // local values depend on the timezone so compare them to what JS says
+            let local = q_ctx
+                .eval(Script::new(
+                    "test_getters_setters.js",
+                    "let d = new Date(1317826087123); [d.getFullYear(), d.getMonth(), d.getDate(), d.getDay(), d.getHours(), d.getMinutes(), d.getSeconds(), d.getMilliseconds()].join(',');",
+                ))
+                .expect("script failed")
+                .to_string()
+                .expect("not a string");
+            let local_rust = [
+                get_full_year_q(q_ctx, &date_ref).unwrap(),
+                get_month_q(q_ctx, &date_ref).unwrap(),
+                get_date_q(q_ctx, &date_ref).unwrap(),
+                get_day_q(q_ctx, &date_ref).unwrap(),
+                get_hours_q(q_ctx, &date_ref).unwrap(),
+                get_minutes_q(q_ctx, &date_ref).unwrap(),
+                get_seconds_q(q_ctx, &date_ref).unwrap(),
+                get_milliseconds_q(q_ctx, &date_ref).unwrap(),
+            ]
+            .map(|i| i.to_string())
+            .join(",");
+            assert_eq!(local, local_rust);
+
+            set_utc_full_year_q(q_ctx, &date_ref, 2020).unwrap();
+            set_utc_month_q(q_ctx, &date_ref, 1).unwrap();
+            set_utc_date_q(q_ctx, &date_ref, 29).unwrap();
+            set_utc_hours_q(q_ctx, &date_ref, 1).unwrap();
+            set_utc_minutes_q(q_ctx, &date_ref, 2).unwrap();
+            set_utc_seconds_q(q_ctx, &date_ref, 3).unwrap();
+            set_utc_milliseconds_q(q_ctx, &date_ref, 4).unwrap();
+            assert_eq!(
+                to_iso_string_q(q_ctx, &date_ref).unwrap(),
+                "2020-02-29T01:02:03.004Z"
+            );
+
+            set_full_year_q(q_ctx, &date_ref, 2019).unwrap();
+            set_month_q(q_ctx, &date_ref, 11).unwrap();
+            set_date_q(q_ctx, &date_ref, 31).unwrap();
+            set_hours_q(q_ctx, &date_ref, 23).unwrap();
+            set_minutes_q(q_ctx, &date_ref, 59).unwrap();
+            set_seconds_q(q_ctx, &date_ref, 58).unwrap();
+            set_milliseconds_q(q_ctx, &date_ref, 999).unwrap();
+            assert_eq!(get_full_year_q(q_ctx, &date_ref).unwrap(), 2019);
+            assert_eq!(get_month_q(q_ctx, &date_ref).unwrap(), 11);
+            assert_eq!(get_date_q(q_ctx, &date_ref).unwrap(), 31);
+            assert_eq!(get_hours_q(q_ctx, &date_ref).unwrap(), 23);
+            assert_eq!(get_minutes_q(q_ctx, &date_ref).unwrap(), 59);
+            assert_eq!(get_seconds_q(q_ctx, &date_ref).unwrap(), 58);
+            assert_eq!(get_milliseconds_q(q_ctx, &date_ref).unwrap(), 999);
+        });
+    }
+
+    #[test]
+    fn test_date_from_timestamp_and_components() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let date_ref =
+                new_date_from_timestamp_q(q_ctx, 1_317_826_087_123f64).expect("new_date failed");
+            assert_eq!(
+                to_iso_string_q(q_ctx, &date_ref).unwrap(),
+                "2011-10-05T14:48:07.123Z"
+            );
+            assert!(new_date_from_timestamp_q(q_ctx, 8.64e15).is_ok());
+            assert!(new_date_from_timestamp_q(q_ctx, 8.64e15 + 1f64).is_err());
+            assert!(new_date_from_timestamp_q(q_ctx, f64::NAN).is_err());
+
+            let date_ref = new_date_from_components_q(q_ctx, 2020, 1, 29, 1, 2, 3, 4)
+                .expect("new_date failed");
+            assert_eq!(get_full_year_q(q_ctx, &date_ref).unwrap(), 2020);
+            assert_eq!(get_month_q(q_ctx, &date_ref).unwrap(), 1);
+            assert_eq!(get_date_q(q_ctx, &date_ref).unwrap(), 29);
+            assert_eq!(get_hours_q(q_ctx, &date_ref).unwrap(), 1);
+            assert_eq!(get_minutes_q(q_ctx, &date_ref).unwrap(), 2);
+            assert_eq!(get_seconds_q(q_ctx, &date_ref).unwrap(), 3);
+            assert_eq!(get_milliseconds_q(q_ctx, &date_ref).unwrap(), 4);
+            assert!(new_date_from_components_q(q_ctx, 300_000, 0, 1, 0, 0, 0, 0).is_err());
+        });
+    }
+
+    #[test]
+    fn test_system_time() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let now = SystemTime::now();
+            let date_ref = new_date_from_system_time_q(q_ctx, now).expect("new_date failed");
+            let back = to_system_time_q(q_ctx, &date_ref).expect("to_system_time failed");
+            let diff = now
+                .duration_since(back)
+                .expect("round trip was later than now");
+            assert!(diff < Duration::from_millis(1));
+
+            let before_epoch = UNIX_EPOCH - Duration::from_millis(1_500);
+            let date_ref =
+                new_date_from_system_time_q(q_ctx, before_epoch).expect("new_date failed");
+            assert_eq!(get_time_q(q_ctx, &date_ref).unwrap(), -1500f64);
+            assert_eq!(to_system_time_q(q_ctx, &date_ref).unwrap(), before_epoch);
+
+            let invalid_ref = new_date_from_millis_q(q_ctx, f64::NAN).expect("new_date failed");
+            assert!(to_system_time_q(q_ctx, &invalid_ref).is_err());
+        });
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        use crate::quickjs_utils::dates::{new_date_from_chrono_q, to_chrono_q};
+        use chrono::TimeZone;
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let dt = chrono::Utc
+                .timestamp_opt(1_317_826_080, 123_456_789)
+                .single()
+                .expect("invalid timestamp");
+            let date_ref = new_date_from_chrono_q(q_ctx, dt).expect("new_date failed");
+            assert_eq!(get_time_q(q_ctx, &date_ref).unwrap(), 1_317_826_080_123f64);
+
+            let back = to_chrono_q(q_ctx, &date_ref).expect("to_chrono failed");
+            assert_eq!(back.timestamp_millis(), 1_317_826_080_123);
+            // sub-millisecond precision is lost
+            assert_eq!(back.timestamp_subsec_nanos(), 123_000_000);
+
+            let invalid_ref = new_date_from_millis_q(q_ctx, f64::NAN).expect("new_date failed");
+            assert!(to_chrono_q(q_ctx, &invalid_ref).is_err());
+            // valid in JS but outside of the range of chrono
+            let far_ref = new_date_from_millis_q(q_ctx, 8.64e15).expect("new_date failed");
+            assert!(to_chrono_q(q_ctx, &far_ref).is_err());
+        });
+    }
 }
@@ -3,7 +3,7 @@
 use crate::jsutils::JsError;
 use crate::jsutils::Script;
 use crate::quickjs_utils::errors::error_to_js_error;
-use crate::quickjs_utils::{atoms, errors, objects, parse_args, primitives};
+use crate::quickjs_utils::{atoms, errors, objects, parse_args, primitives, promises};
 use crate::quickjsrealmadapter::QuickJsRealmAdapter;
 use crate::quickjsruntimeadapter::{make_cstring, QuickJsRuntimeAdapter};
 use crate::quickjsvalueadapter::QuickJsValueAdapter;
@@ -218,6 +218,26 @@ pub unsafe fn invoke_member_function(
     }
 }
 
+/// invoke an (async) function by name and wait for the returned promise to settle by running pending jobs
+/// a rejection is returned as an Err with the message and stack of the rejection reason
+/// this only works for promises which settle by running jobs, use QuickJsRuntimeFacade::invoke_function_async if the promise depends on something outside of the EventQueue (like a resolving promise)
+pub fn invoke_async_member_function_q(
+    q_ctx: &QuickJsRealmAdapter,
+    namespace: &[&str],
+    function_name: &str,
+    arguments: &[QuickJsValueAdapter],
+) -> Result<QuickJsValueAdapter, JsError> {
+    let namespace_ref = objects::get_namespace_q(q_ctx, namespace, false)?;
+    let res = invoke_member_function_q(q_ctx, &namespace_ref, function_name, arguments)?;
+    if !promises::is_promise_q(q_ctx, &res) {
+        return Ok(res);
+    }
+    match promises::await_promise_blocking_q(q_ctx, &res, usize::MAX)? {
+        Ok(val) => Ok(val),
+        Err(reason) => Err(promises::rejection_to_js_error(q_ctx, &reason)),
+    }
+}
+
 /// call an objects to_String method or convert a value to string
 pub fn call_to_string_q(
     q_ctx: &QuickJsRealmAdapter,
@@ -674,7 +694,8 @@ where
 pub mod tests {
     use crate::facades::tests::init_test_rt;
     use crate::quickjs_utils::functions::{
-        call_function_q, call_to_string_q, invoke_member_function_q, new_function_q,
+        call_function_q, call_to_string_q, invoke_async_member_function_q,
+        invoke_member_function_q, new_function_q,
     };
     use crate::quickjs_utils::{functions, objects, primitives};
 
@@ -933,6 +954,39 @@ pub mod tests {
         assert!(err.contains("[testMe]"));
         assert!(err.contains("test_927.es"));
     }
+
+    #[test]
+    pub fn test_invoke_async() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            q_ctx
+                .eval(Script::new(
+                    "test_invoke_async.es",
+                    "this.asyncNs = {ok: async function(a) {await null; return a * 2;}, fail: async function() {await null; throw new TypeError('async oops');}, sync: function() {return 3;}};",
+                ))
+                .expect("script failed");
+
+            let res = invoke_async_member_function_q(
+                q_ctx,
+                &["asyncNs"],
+                "ok",
+                &[primitives::from_i32(21)],
+            )
+            .expect("invoke failed");
+            assert_eq!(res.to_i32(), 42);
+
+            let res = invoke_async_member_function_q(q_ctx, &["asyncNs"], "sync", &[])
+                .expect("invoke failed");
+            assert_eq!(res.to_i32(), 3);
+
+            let err = invoke_async_member_function_q(q_ctx, &["asyncNs"], "fail", &[])
+                .expect_err("invoke did not fail");
+            assert_eq!(err.get_name(), "TypeError");
+            assert_eq!(err.get_message(), "async oops");
+            assert!(err.get_stack().contains("test_invoke_async.es"));
+        });
+    }
 }
 
 unsafe extern "C" fn callback_finalizer(_rt: *mut q::JSRuntime, val: q::JSValue) {
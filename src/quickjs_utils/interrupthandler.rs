@@ -18,8 +18,13 @@ pub(crate) fn init(q_js_rt: &QuickJsRuntimeAdapter) {
 
 unsafe extern "C" fn interrupt_handler(_rt: *mut q::JSRuntime, _opaque: *mut c_void) -> c_int {
     QuickJsRuntimeAdapter::do_with(|q_js_rt| {
-        let handler = q_js_rt.interrupt_handler.as_ref().unwrap();
-        i32::from(handler(q_js_rt))
+        if q_js_rt.is_eval_deadline_exceeded() {
+            return 1;
+        }
+        match q_js_rt.interrupt_handler.as_ref() {
+            Some(handler) => i32::from(handler(q_js_rt)),
+            None => 0,
+        }
     })
 }
 
@@ -31,7 +36,9 @@ pub mod tests {
 
     use std::cell::RefCell;
     use std::panic;
+    use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_interrupt_handler() {
@@ -101,4 +108,95 @@ pub mod tests {
         let lck = called.lock().unwrap();
         assert!(*lck.borrow());
     }
+
+    #[test]
+    fn test_eval_with_timeout() {
+        let rt = crate::facades::tests::init_test_rt();
+        let start = Instant::now();
+        let err = rt
+            .eval_with_timeout_sync(
+                None,
+                Script::new("test_eval_with_timeout.js", "while(true){}"),
+                Duration::from_millis(100),
+            )
+            .expect_err("script should be interrupted");
+        assert_eq!(err.get_name(), "InterruptedError");
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // try catch can not prevent the interrupt
+        let err = rt
+            .eval_with_timeout_sync(
+                None,
+                Script::new(
+                    "test_eval_with_timeout2.js",
+                    "while(true){ try { while(true){} } catch(e) {} }",
+                ),
+                Duration::from_millis(50),
+            )
+            .expect_err("script should be interrupted");
+        assert_eq!(err.get_name(), "InterruptedError");
+
+        // the runtime is still usable and scripts which finish in time are not affected
+        let res = rt
+            .eval_with_timeout_sync(
+                None,
+                Script::new(
+                    "test_eval_with_timeout3.js",
+                    "let x = 0; for (let i = 0; i < 1000; i++) { x += i; } x;",
+                ),
+                Duration::from_secs(5),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_i32(), 499500);
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_eval_with_timeout4.js",
+                    "let y = 0; for (let i = 0; i < 100000; i++) { y += 1; } y;",
+                ),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_i32(), 100000);
+    }
+
+    #[test]
+    fn test_interrupt_from_other_thread() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let cancelled2 = cancelled.clone();
+        let rt = QuickJsRuntimeBuilder::new()
+            .set_interrupt_handler(move |_q_js_rt| cancelled2.load(Ordering::SeqCst))
+            .build();
+
+        let cancelled3 = cancelled.clone();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            cancelled3.store(true, Ordering::SeqCst);
+        });
+
+        let start = Instant::now();
+        let err = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_interrupt_from_other_thread.js",
+                    "while(true){ try { while(true){} } catch(e) {} }",
+                ),
+            )
+            .expect_err("script should be interrupted");
+        assert!(err.get_message().contains("interrupted"), "{}", err);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        canceller.join().unwrap();
+
+        // scripts run again once the flag is reset
+        cancelled.store(false, Ordering::SeqCst);
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_interrupt_from_other_thread2.js", "1 + 2;"),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_i32(), 3);
+    }
 }
@@ -2,6 +2,7 @@
 
 use crate::jsutils::JsError;
 use crate::quickjs_utils;
+use crate::quickjs_utils::primitives;
 use crate::quickjsrealmadapter::QuickJsRealmAdapter;
 use crate::quickjsvalueadapter::QuickJsValueAdapter;
 use libquickjs_sys as q;
@@ -41,7 +42,8 @@ pub unsafe fn parse(
     context: *mut q::JSContext,
     input: &str,
 ) -> Result<QuickJsValueAdapter, JsError> {
-    let s = CString::new(input).ok().unwrap();
+    let s =
+        CString::new(input).map_err(|_| JsError::new_str("json may not contain nul characters"))?;
     let f_n = CString::new("JSON.parse").ok().unwrap();
 
     let len = input.len();
@@ -122,11 +124,39 @@ pub unsafe fn stringify(
     }
 }
 
+/// Stringify a value to a String, space is the number of spaces used to indent nested objects
+/// returns None for values which can not be serialized to JSON (e.g. undefined or a function)
+pub fn stringify_to_string_q(
+    q_ctx: &QuickJsRealmAdapter,
+    input: &QuickJsValueAdapter,
+    space: Option<u8>,
+) -> Result<Option<String>, JsError> {
+    unsafe { stringify_to_string(q_ctx.context, input, space) }
+}
+
+/// Stringify a value to a String, space is the number of spaces used to indent nested objects
+/// returns None for values which can not be serialized to JSON (e.g. undefined or a function)
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn stringify_to_string(
+    context: *mut q::JSContext,
+    input: &QuickJsValueAdapter,
+    space: Option<u8>,
+) -> Result<Option<String>, JsError> {
+    let opt_space = space.map(|s| primitives::from_i32(s as i32));
+    let res = stringify(context, input, opt_space)?;
+    if res.is_undefined() {
+        Ok(None)
+    } else {
+        primitives::to_string(context, &res).map(Some)
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use crate::facades::tests::init_test_rt;
     use crate::jsutils::Script;
-    use crate::quickjs_utils::json::parse_q;
+    use crate::quickjs_utils::json::{parse_q, stringify_to_string_q};
     use crate::quickjs_utils::{get_global_q, json, objects, primitives};
     use crate::values::JsValueFacade;
     use std::collections::HashMap;
@@ -167,6 +197,44 @@ pub mod tests {
         });
     }
 
+    #[test]
+    fn test_stringify_to_string() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let obj =
+                parse_q(q_ctx, "{\"a\": [1, 2], \"b\": {\"c\": \"d\"}}").expect("parse failed");
+            let json = stringify_to_string_q(q_ctx, &obj, None)
+                .expect("stringify failed")
+                .expect("no json");
+            assert_eq!(json, "{\"a\":[1,2],\"b\":{\"c\":\"d\"}}");
+            let pretty = stringify_to_string_q(q_ctx, &obj, Some(2))
+                .expect("stringify failed")
+                .expect("no json");
+            assert_eq!(
+                pretty,
+                "{\n  \"a\": [\n    1,\n    2\n  ],\n  \"b\": {\n    \"c\": \"d\"\n  }\n}"
+            );
+            // round trip
+            let obj2 = parse_q(q_ctx, pretty.as_str()).expect("parse failed");
+            assert_eq!(
+                stringify_to_string_q(q_ctx, &obj2, None).unwrap().unwrap(),
+                json
+            );
+
+            let undef = crate::quickjs_utils::new_undefined_ref();
+            assert!(stringify_to_string_q(q_ctx, &undef, None)
+                .expect("stringify failed")
+                .is_none());
+
+            let err = parse_q(q_ctx, "{\"a\": 1,\n \"b\" }").expect_err("parse did not fail");
+            assert_eq!(err.get_name(), "SyntaxError");
+            // the position of the error is in the stack
+            assert!(err.get_stack().contains("JSON.parse:2"));
+            assert!(parse_q(q_ctx, "\"a\u{0}\"").is_err());
+        });
+    }
+
     #[tokio::test]
     async fn test_json_arg() {
         let rt = init_test_rt();
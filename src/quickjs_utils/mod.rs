@@ -6,6 +6,7 @@ pub mod arrays;
 pub mod atoms;
 pub mod bigints;
 pub mod compile;
+pub mod dataview;
 pub mod dates;
 pub mod errors;
 pub mod functions;
@@ -19,7 +20,11 @@ pub mod primitives;
 pub mod promises;
 pub mod properties;
 pub mod runtime;
+pub mod serde_js;
+#[cfg(feature = "serde_values")]
+pub mod serde_values;
 pub mod sets;
+pub mod symbols;
 pub mod typedarrays;
 
 use crate::jsutils::JsError;
@@ -62,6 +67,16 @@ pub fn new_null_ref() -> QuickJsValueAdapter {
     QuickJsValueAdapter::new_no_context(new_null(), "null_ref")
 }
 
+/// check if a value is null, please note that undefined is not null
+pub fn is_null(value_ref: &QuickJsValueAdapter) -> bool {
+    value_ref.is_null()
+}
+
+/// check if a value is undefined, please note that null is not undefined
+pub fn is_undefined(value_ref: &QuickJsValueAdapter) -> bool {
+    value_ref.is_undefined()
+}
+
 /// get the current filename
 pub fn get_script_or_module_name_q(ctx: &QuickJsRealmAdapter) -> Result<String, JsError> {
     unsafe { get_script_or_module_name(ctx.context) }
@@ -163,9 +178,16 @@ pub unsafe fn parse_args(
 #[cfg(test)]
 pub mod tests {
     use crate::facades::tests::init_test_rt;
-    use crate::jsutils::Script;
-    use crate::quickjs_utils::{get_global_q, get_script_or_module_name_q};
+    use crate::jsutils::{JsError, Script};
+    use crate::quickjs_utils::arrays;
+    use crate::quickjs_utils::functions::new_function_q;
+    use crate::quickjs_utils::objects::set_property_q;
+    use crate::quickjs_utils::{
+        get_global_q, get_script_or_module_name_q, is_null, is_undefined, new_null_ref,
+        new_undefined_ref,
+    };
     use crate::values::JsValueConvertable;
+    use crate::values::JsValueFacade;
 
     #[test]
     fn test_global() {
@@ -207,4 +229,57 @@ pub mod tests {
             .expect("script failed");
         assert_eq!(name_esvf.get_str(), "https://githubstuff.org/tes.js");
     }
+
+    #[test]
+    fn test_null_undefined() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            assert!(is_null(&new_null_ref()));
+            assert!(!is_undefined(&new_null_ref()));
+            assert!(is_undefined(&new_undefined_ref()));
+            assert!(!is_null(&new_undefined_ref()));
+
+            let func_ref = new_function_q(
+                q_ctx,
+                "checkArgs",
+                |_q_ctx, _this, args| {
+                    // return the undefined arg so it can be checked after the round trip
+                    if args.len() == 2 && is_undefined(&args[0]) && is_null(&args[1]) {
+                        Ok(args[0].clone())
+                    } else {
+                        Err(JsError::new_str("unexpected args"))
+                    }
+                },
+                2,
+            )
+            .unwrap();
+            let global = get_global_q(q_ctx);
+            set_property_q(q_ctx, &global, "checkArgs", &func_ref).unwrap();
+            let res = q_ctx
+                .eval(Script::new(
+                    "test_null_undefined.js",
+                    "let o = {a: 1}; delete o.a; [checkArgs(undefined, null), o.a];",
+                ))
+                .expect("script failed");
+            for index in 0..2 {
+                let element_ref = arrays::get_element_q(q_ctx, &res, index).unwrap();
+                let element = q_ctx.to_js_value_facade(&element_ref).unwrap();
+                assert!(matches!(element, JsValueFacade::Undefined));
+            }
+
+            let undefined_ref = q_ctx
+                .from_js_value_facade(JsValueFacade::Undefined)
+                .unwrap();
+            assert!(is_undefined(&undefined_ref));
+
+            // deleted properties are undefined, not null
+            let obj_ref = q_ctx
+                .eval(Script::new("test_null_undefined2.js", "({a: 1});"))
+                .expect("script failed");
+            q_ctx.delete_object_property(&obj_ref, "a").unwrap();
+            let a_ref = q_ctx.get_object_property(&obj_ref, "a").unwrap();
+            assert!(is_undefined(&a_ref));
+        });
+    }
 }
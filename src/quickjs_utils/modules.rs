@@ -3,8 +3,9 @@
 use crate::jsutils::{JsError, Script};
 use crate::quickjs_utils::atoms;
 use crate::quickjs_utils::atoms::JSAtomRef;
+use crate::quickjs_utils::{functions, objects, primitives};
 use crate::quickjsrealmadapter::QuickJsRealmAdapter;
-use crate::quickjsruntimeadapter::QuickJsRuntimeAdapter;
+use crate::quickjsruntimeadapter::{make_cstring, NamedNativeModuleAdapter, QuickJsRuntimeAdapter};
 use crate::quickjsvalueadapter::QuickJsValueAdapter;
 use core::ptr;
 
@@ -64,6 +65,53 @@ pub fn get_module_def(value: &QuickJsValueAdapter) -> *mut q::JSModuleDef {
     unsafe { value.borrow_value().u.ptr as *mut q::JSModuleDef }
 }
 
+/// set import.meta.url, import.meta.main and import.meta.resolve() of a module, this should be called after compiling and before evaluating the module
+/// # Safety
+/// Please ensure the context passed is still valid
+pub unsafe fn set_module_meta(
+    ctx: *mut q::JSContext,
+    module: *mut q::JSModuleDef,
+    url: &str,
+    is_main: bool,
+) -> Result<(), JsError> {
+    let meta_raw = q::JS_GetImportMeta(ctx, module);
+    let meta = QuickJsValueAdapter::new(ctx, meta_raw, false, true, "modules::set_module_meta");
+    if meta.is_exception() {
+        return Err(QuickJsRealmAdapter::get_exception(ctx)
+            .unwrap_or_else(|| JsError::new_str("could not get import.meta")));
+    }
+    objects::set_property(ctx, &meta, "url", &primitives::from_string(ctx, url)?)?;
+    objects::set_property(ctx, &meta, "main", &primitives::from_bool(is_main))?;
+    let base_path = url.to_string();
+    let resolve_func = functions::new_function(
+        ctx,
+        "resolve",
+        move |ctx, _this, args| {
+            let Some(specifier) = args.first().filter(|arg| arg.is_string()) else {
+                return Err(JsError::new_str(
+                    "import.meta.resolve expects a module specifier string",
+                ));
+            };
+            let specifier = primitives::to_string(ctx, specifier)?;
+            let resolved = QuickJsRuntimeAdapter::do_with(|q_js_rt| {
+                let q_ctx = q_js_rt.get_quickjs_context(ctx);
+                resolve_module_name(q_js_rt, q_ctx, base_path.as_str(), specifier.as_str())
+            })
+            .map_err(|e| {
+                JsError::new(
+                    "ReferenceError".to_string(),
+                    e.get_message().to_string(),
+                    String::new(),
+                )
+            })?;
+            primitives::from_string(ctx, resolved.as_str())
+        },
+        1,
+    )?;
+    objects::set_property(ctx, &meta, "resolve", &resolve_func)?;
+    Ok(())
+}
+
 #[allow(dead_code)]
 pub fn set_module_loader(q_js_rt: &QuickJsRuntimeAdapter) {
     log::trace!("setting up module loader");
@@ -76,6 +124,48 @@ pub fn set_module_loader(q_js_rt: &QuickJsRuntimeAdapter) {
     unsafe { q::JS_SetModuleLoaderFunc(q_js_rt.runtime, module_normalize, module_loader, opaque) }
 }
 
+/// a factory which creates the exports of a native module, see register_native_module
+pub type NativeModuleFactory =
+    Box<dyn Fn(&QuickJsRealmAdapter) -> Result<Vec<(String, QuickJsValueAdapter)>, JsError>>;
+
+/// register a native module which can be imported by name (e.g. `import {readFile} from 'host:fs'`)
+/// the factory is called to create the exports each time the module is imported in a new realm
+/// registered native modules are resolved before any other module loader is consulted
+pub fn register_native_module(
+    q_js_rt: &mut QuickJsRuntimeAdapter,
+    name: &str,
+    factory: NativeModuleFactory,
+) {
+    q_js_rt
+        .named_native_modules
+        .push(NamedNativeModuleAdapter::new(name, factory));
+}
+
+/// check if a module should be loaded as a JSON module (its normalized path ends with .json)
+pub fn is_json_module(absolute_path: &str) -> bool {
+    absolute_path.ends_with(".json")
+}
+
+/// create the script for a JSON module, the module has the parsed JSON as its default export
+/// (e.g. `import cfg from './config.json'`)
+/// returns a SyntaxError with the line and column of the problem if the source is not valid JSON
+pub fn json_module_script(absolute_path: &str, source: &str) -> Result<Script, JsError> {
+    if let Err(e) = serde_json::from_str::<serde::de::IgnoredAny>(source) {
+        return Err(JsError::new(
+            "SyntaxError".to_string(),
+            format!("could not parse JSON module {absolute_path}: {e}"),
+            String::new(),
+        ));
+    }
+    // the source is passed to JSON.parse as a string literal so keys like __proto__ are handled like JSON
+    let literal = serde_json::to_string(source)
+        .map_err(|e| JsError::new_string(format!("could not quote JSON module source: {e}")))?;
+    Ok(Script::new(
+        absolute_path,
+        format!("export default JSON.parse({literal});").as_str(),
+    ))
+}
+
 /// detect if a script is module (contains import or export statements)
 pub fn detect_module(source: &str) -> bool {
     let cstr = CString::new(source).expect("could not create CString due to null term in source");
@@ -147,6 +237,125 @@ pub unsafe fn get_module_name(
     atoms::to_string(ctx, &atom_ref)
 }
 
+/// normalize a module specifier against the path of the importing module
+/// * relative specifiers (starting with ./ or ../) are joined with the directory of base_path
+/// * absolute specifiers (starting with /) are used as is
+/// * bare specifiers (like 'lodash' or 'greco://mod') are returned unchanged so the module loaders can resolve them
+///
+/// . and .. segments are collapsed, an Err is returned if the path escapes above the start of the path or outside of root (if set)
+///
+/// when base_path is a url like https://host/lib/a.js only the path of the url is joined, the scheme and host are kept
+/// (root is not checked for urls), relative specifiers of a base without a host (like node:fs) are returned unchanged
+/// # Example
+/// ```rust
+/// use quickjs_runtime::quickjs_utils::modules::normalize_module_path;
+/// assert_eq!(normalize_module_path("dir/a.js", "./util.js", None).unwrap(), "dir/util.js");
+/// assert_eq!(normalize_module_path("/app/lib/a.js", "../util.js", None).unwrap(), "/app/util.js");
+/// assert_eq!(normalize_module_path("/app/a.js", "lodash", None).unwrap(), "lodash");
+/// assert!(normalize_module_path("/app/a.js", "../../etc/passwd", Some("/app")).is_err());
+/// assert_eq!(normalize_module_path("https://host/lib/a.js", "../b.js", None).unwrap(), "https://host/b.js");
+/// ```
+pub fn normalize_module_path(
+    base_path: &str,
+    module_name: &str,
+    root: Option<&str>,
+) -> Result<String, JsError> {
+    let relative = module_name.starts_with("./") || module_name.starts_with("../");
+    if !relative && !module_name.starts_with('/') {
+        return Ok(module_name.to_string());
+    }
+
+    if let Some(scheme_len) = url_scheme_len(base_path) {
+        let Some(authority_and_path) = base_path[scheme_len + 1..].strip_prefix("//") else {
+            // a base without an authority (like node:fs) can not be joined
+            return Ok(module_name.to_string());
+        };
+        let authority_len = authority_and_path
+            .find('/')
+            .unwrap_or(authority_and_path.len());
+        let (prefix, path) = base_path.split_at(scheme_len + 3 + authority_len);
+        let path = if path.is_empty() { "/" } else { path };
+        let joined = join_module_path(base_path, path, module_name, relative)?;
+        return Ok(format!("{prefix}{joined}"));
+    }
+
+    let normalized = join_module_path(base_path, base_path, module_name, relative)?;
+
+    if let Some(root) = root {
+        let root = root.trim_end_matches('/');
+        let in_root = normalized
+            .strip_prefix(root)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+        if !in_root {
+            return Err(JsError::new_string(format!(
+                "Module {module_name} resolves to {normalized} which is outside of the module root {root}"
+            )));
+        }
+    }
+    Ok(normalized)
+}
+
+/// get the length of the scheme of a url like https://host/a.js, returns None for plain paths
+/// single letter schemes are not accepted so windows paths like C:/app/a.js are handled as paths
+fn url_scheme_len(path: &str) -> Option<usize> {
+    let len = path.find(':')?;
+    let scheme = &path[..len];
+    let valid = len > 1
+        && scheme.starts_with(|chr: char| chr.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|chr| chr.is_ascii_alphanumeric() || chr == '+' || chr == '-' || chr == '.');
+    valid.then_some(len)
+}
+
+/// join module_name with the directory of path and collapse . and .. segments
+fn join_module_path(
+    base_path: &str,
+    path: &str,
+    module_name: &str,
+    relative: bool,
+) -> Result<String, JsError> {
+    let mut parts: Vec<&str> = vec![];
+    if relative {
+        parts.extend(path.split('/'));
+        // the file name of the importing module
+        parts.pop();
+    }
+    parts.extend(module_name.split('/'));
+
+    let absolute = parts.first() == Some(&"");
+    let mut segments: Vec<&str> = vec![];
+    for part in parts {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if segments.pop().is_none() {
+                    return Err(JsError::new_string(format!(
+                        "Module {module_name} escapes above the root of {base_path}"
+                    )));
+                }
+            }
+            _ => segments.push(part),
+        }
+    }
+    let joined = segments.join("/");
+    if absolute {
+        Ok(format!("/{joined}"))
+    } else {
+        Ok(joined)
+    }
+}
+
+/// throw a ReferenceError in a context
+/// # Safety
+/// Please ensure the context passed is still valid
+unsafe fn throw_reference_error(ctx: *mut q::JSContext, msg: &str) {
+    let fmt_c = CString::new("%s").expect("could not create CString");
+    let msg_c =
+        CString::new(msg.replace('\0', "")).expect("could not create CString for error message");
+    q::JS_ThrowReferenceError(ctx, fmt_c.as_ptr(), msg_c.as_ptr());
+}
+
 unsafe extern "C" fn js_module_normalize(
     ctx: *mut q::JSContext,
     module_base_name: *const ::std::os::raw::c_char,
@@ -156,13 +365,15 @@ unsafe extern "C" fn js_module_normalize(
     log::trace!("js_module_normalize called.");
 
     let base_c = CStr::from_ptr(module_base_name);
-    let base_str = base_c
-        .to_str()
-        .expect("could not convert module_base_name to str");
+    let Ok(base_str) = base_c.to_str() else {
+        QuickJsRealmAdapter::report_ex_ctx(ctx, "module base name is not valid UTF-8");
+        return ptr::null_mut();
+    };
     let name_c = CStr::from_ptr(module_name);
-    let name_str = name_c
-        .to_str()
-        .expect("could not convert module_name to str");
+    let Ok(name_str) = name_c.to_str() else {
+        QuickJsRealmAdapter::report_ex_ctx(ctx, "module name is not valid UTF-8");
+        return ptr::null_mut();
+    };
 
     log::trace!(
         "js_module_normalize called. base: {}. name: {}",
@@ -172,23 +383,68 @@ unsafe extern "C" fn js_module_normalize(
 
     QuickJsRuntimeAdapter::do_with(|q_js_rt| {
         let q_ctx = q_js_rt.get_quickjs_context(ctx);
-
-        if let Some(res) = q_js_rt.with_all_module_loaders(|loader| {
-            if let Some(normalized_path) = loader.normalize_path(q_ctx, base_str, name_str) {
-                let c_absolute_path = CString::new(normalized_path.as_str()).expect("fail");
-                Some(c_absolute_path.into_raw())
-            } else {
-                None
+        let normalized =
+            resolve_module_name(q_js_rt, q_ctx, base_str, name_str).and_then(|normalized| {
+                q_ctx.record_module_dependency(base_str, normalized.as_str());
+                make_cstring(normalized.as_str())
+            });
+        match normalized {
+            Ok(c_absolute_path) => c_absolute_path.into_raw(),
+            Err(e) => {
+                throw_reference_error(ctx, e.get_message());
+                ptr::null_mut()
             }
-        }) {
-            res
-        } else {
-            q_ctx.report_ex(format!("Module {name_str} was not found").as_str());
-            ptr::null_mut()
         }
     })
 }
 
+/// resolve the name of a module imported from the module at base_path, like an import statement would
+/// relative names are joined with the path of the importing module (see [normalize_module_path]) and then normalized by the first module loader which knows the module
+/// this is also used by import.meta.resolve(), returns an Err if the module was not found
+pub fn resolve_module_name(
+    q_js_rt: &QuickJsRuntimeAdapter,
+    q_ctx: &QuickJsRealmAdapter,
+    base_path: &str,
+    module_name: &str,
+) -> Result<String, JsError> {
+    let joined_name = normalize_module_path(base_path, module_name, q_js_rt.get_module_root())?;
+
+    // modules evaluated in this realm may be imported by their path without a loader
+    for evaluated_name in [joined_name.as_str(), module_name] {
+        if q_ctx.is_evaluated_module(evaluated_name) {
+            return Ok(evaluated_name.to_string());
+        }
+    }
+
+    let not_found = || JsError::new_string(format!("Module {module_name} was not found"));
+
+    if q_js_rt.module_cache_enabled
+        && q_ctx.is_module_not_found_cached(base_path, joined_name.as_str())
+    {
+        return Err(not_found());
+    }
+
+    q_js_rt
+        .with_all_module_loaders(|loader| {
+            // an Err of a loader stops the lookup, it is not cached as not found
+            loader
+                .try_normalize_path(q_ctx, base_path, joined_name.as_str())
+                .map_err(|e| {
+                    JsError::new_string(format!(
+                        "Module {module_name} could not be resolved: {}",
+                        e.get_message()
+                    ))
+                })
+                .transpose()
+        })
+        .unwrap_or_else(|| {
+            if q_js_rt.module_cache_enabled {
+                q_ctx.cache_module_not_found(base_path, joined_name.as_str());
+            }
+            Err(not_found())
+        })
+}
+
 unsafe extern "C" fn js_module_loader(
     ctx: *mut q::JSContext,
     module_name_raw: *const ::std::os::raw::c_char,
@@ -197,7 +453,10 @@ unsafe extern "C" fn js_module_loader(
     log::trace!("js_module_loader called.");
 
     let module_name_c = CStr::from_ptr(module_name_raw);
-    let module_name = module_name_c.to_str().expect("could not get module name");
+    let Ok(module_name) = module_name_c.to_str() else {
+        QuickJsRealmAdapter::report_ex_ctx(ctx, "module name is not valid UTF-8");
+        return ptr::null_mut();
+    };
 
     log::trace!("js_module_loader called: {}", module_name);
 
@@ -207,7 +466,10 @@ unsafe extern "C" fn js_module_loader(
                 if module_loader.has_module(q_ctx, module_name) {
                     let mod_val_res = module_loader.load_module(q_ctx, module_name);
                     return match mod_val_res {
-                        Ok(mod_val) => Some(mod_val),
+                        Ok(mod_val) => {
+                            q_ctx.record_loaded_module(module_name);
+                            Some(mod_val)
+                        }
                         Err(e) => {
                             let err =
                                 format!("Module load failed for {module_name} because of: {e}");
@@ -221,6 +483,7 @@ unsafe extern "C" fn js_module_loader(
             }) {
                 res
             } else {
+                throw_reference_error(ctx, format!("Module {module_name} was not found").as_str());
                 std::ptr::null_mut()
             }
         })
@@ -229,9 +492,12 @@ unsafe extern "C" fn js_module_loader(
 
 #[cfg(test)]
 pub mod tests {
+    use crate::builder::QuickJsRuntimeBuilder;
     use crate::facades::tests::init_test_rt;
     use crate::jsutils::Script;
-    use crate::quickjs_utils::modules::detect_module;
+    use crate::quickjs_utils::functions::call_function_q;
+    use crate::quickjs_utils::modules::{detect_module, normalize_module_path};
+    use crate::quickjs_utils::objects::get_property_q;
     use crate::values::JsValueFacade;
     use std::time::Duration;
 
@@ -272,6 +538,132 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_missing_module() {
+        let rt = init_test_rt();
+        let err = rt
+            .eval_module_sync(
+                None,
+                Script::new(
+                    "test_missing_module.mes",
+                    "import {a} from 'notfound.mes';\nconsole.log(a);",
+                ),
+            )
+            .expect_err("import did not fail");
+        assert_eq!(err.get_name(), "ReferenceError");
+        assert!(err
+            .get_message()
+            .contains("Module notfound.mes was not found"));
+
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_missing_module.js",
+                    "import('notfound.mes').then(() => 'imported').catch((e) => e.name + ': ' + e.message);",
+                ),
+            )
+            .expect("script failed");
+        let JsValueFacade::JsPromise { cached_promise } = res else {
+            panic!("import() did not return a promise");
+        };
+        let res = cached_promise
+            .get_promise_result_sync_timeout(Some(Duration::from_secs(5)))
+            .expect("import() timed out")
+            .expect("import() was rejected");
+        assert_eq!(
+            res.get_str(),
+            "ReferenceError: Module notfound.mes was not found"
+        );
+    }
+
+    #[test]
+    fn test_named_native_module() {
+        let rt = QuickJsRuntimeBuilder::new()
+            .native_module("host:fs", |realm| {
+                let read_file = realm.create_function(
+                    "readFile",
+                    |realm, _this, args| {
+                        let path = args[0].to_string()?;
+                        realm.create_string(format!("contents of {path}").as_str())
+                    },
+                    1,
+                )?;
+                Ok(vec![
+                    ("readFile".to_string(), read_file),
+                    ("sep".to_string(), realm.create_string("/")?),
+                ])
+            })
+            .build();
+
+        rt.eval_module_sync(
+            None,
+            Script::new(
+                "test_named_native_module.mjs",
+                "import {readFile, sep} from 'host:fs';\nglobalThis.nativeRes = readFile(sep + 'foo.txt');",
+            ),
+        )
+        .expect("module failed");
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_named_native_module.js", "globalThis.nativeRes;"),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_str(), "contents of /foo.txt");
+
+        let err = rt
+            .eval_module_sync(
+                None,
+                Script::new(
+                    "test_named_native_module2.mjs",
+                    "import {writeFile} from 'host:fs';\nwriteFile('a');",
+                ),
+            )
+            .expect_err("import of missing export did not fail");
+        assert!(err.get_message().contains("writeFile"));
+    }
+
+    #[test]
+    fn test_named_native_module_failed_link() {
+        let rt = QuickJsRuntimeBuilder::new()
+            .native_module("host:sep", |realm| {
+                Ok(vec![("sep".to_string(), realm.create_string("/")?)])
+            })
+            .build();
+        let pending_count = || {
+            rt.exe_rt_task_in_event_loop(|q_js_rt| {
+                q_js_rt.named_native_modules[0].pending_exports_count()
+            })
+        };
+
+        rt.create_context("failed_link_realm")
+            .expect("create failed");
+        // the native module is loaded but never initialized because the second import fails
+        rt.eval_module_sync(
+            Some("failed_link_realm"),
+            Script::new(
+                "test_failed_link.mes",
+                "import {sep} from 'host:sep';\nimport 'missing.mes';\nglobalThis.sep = sep;",
+            ),
+        )
+        .expect_err("module did not fail");
+        assert_eq!(pending_count(), 1);
+
+        rt.drop_context("failed_link_realm");
+        assert_eq!(pending_count(), 0);
+
+        rt.eval_module_sync(
+            None,
+            Script::new(
+                "test_linked.mes",
+                "import {sep} from 'host:sep';\nglobalThis.sep = sep;",
+            ),
+        )
+        .expect("module failed");
+        assert_eq!(pending_count(), 0);
+    }
+
     #[test]
     fn test_detect() {
         assert!(detect_module("import {} from 'foo.es';"));
@@ -280,6 +672,83 @@ pub mod tests {
         assert!(!detect_module("let a = 1;"));
     }
 
+    #[test]
+    fn test_eval_module_sync_exports() {
+        // no module loaders, the evaluated module is imported by its path
+        let rt = QuickJsRuntimeBuilder::new().build();
+        let exports = rt
+            .eval_module_sync_exports(
+                None,
+                Script::new(
+                    "test_exports.mes",
+                    "export const name = 'foobar';\nexport const nums = [1, 2];\nexport default 42;",
+                ),
+            )
+            .expect("module failed");
+        let mut names: Vec<&String> = exports.keys().collect();
+        names.sort();
+        assert_eq!(names, ["default", "name", "nums"]);
+        assert_eq!(exports.get("name").unwrap().get_str(), "foobar");
+        assert_eq!(exports.get("default").unwrap().get_i32(), 42);
+
+        rt.eval_module_sync(
+            None,
+            Script::new(
+                "test_exports_importer.mes",
+                "import answer, {name} from 'test_exports.mes';\nglobalThis.importedExports = name + answer;",
+            ),
+        )
+        .expect("import failed");
+        let res = rt
+            .eval_sync(None, Script::new("test_exports.js", "importedExports;"))
+            .expect("script failed");
+        assert_eq!(res.get_str(), "foobar42");
+
+        let err = rt
+            .eval_module_sync_exports(
+                None,
+                Script::new("test_exports_err.mes", "throw Error('module failed');"),
+            )
+            .expect_err("module should fail");
+        assert!(err.get_message().contains("module failed"), "{}", err);
+    }
+
+    #[test]
+    fn test_dynamic_import_await() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            q_ctx
+                .eval_module(Script::new("test1.mes", "export const name = 'foobar';"))
+                .expect("module failed");
+            q_ctx
+                .eval(Script::new(
+                    "test_dynamic_import_await.js",
+                    "(async function() {\n\
+                     const m = await import('test1.mes');\n\
+                     globalThis.dynNameOk = m.name === 'foobar';\n\
+                     try {\n\
+                         await import('notfound.mes');\n\
+                     } catch(e) {\n\
+                         globalThis.dynNotFound = '' + e;\n\
+                     }\n\
+                     })();",
+                ))
+                .expect("script failed");
+            q_js_rt.run_pending_jobs_if_any();
+            let res = q_ctx
+                .eval(Script::new(
+                    "test_dynamic_import_await2.js",
+                    "JSON.stringify([globalThis.dynNameOk, globalThis.dynNotFound]);",
+                ))
+                .expect("script failed");
+            assert_eq!(
+                res.to_string().unwrap(),
+                "[true,\"ReferenceError: Module notfound.mes was not found\"]"
+            );
+        });
+    }
+
     #[test]
     fn test_module_sandbox() {
         log::info!("> test_module_sandbox");
@@ -287,15 +756,22 @@ pub mod tests {
         let rt = init_test_rt();
         rt.exe_rt_task_in_event_loop(|q_js_rt| {
             let q_ctx = q_js_rt.get_main_realm();
-            let res = q_ctx.eval_module(Script::new(
-                "test1.mes",
-                "export const name = 'foobar';\nconsole.log('evalling module');",
-            ));
-
-            if res.is_err() {
-                panic!("parse module failed: {}", res.err().unwrap())
-            }
-            res.ok().expect("parse module failed");
+            let ns = q_ctx
+                .eval_module_exports(Script::new(
+                    "test1.mes",
+                    "export const name = 'foobar';\nexport default function greet() { return 'hi ' + name; };",
+                ))
+                .expect("parse module failed");
+            let name_ref = get_property_q(q_ctx, &ns, "name").expect("no name");
+            assert_eq!(name_ref.to_string().unwrap(), "foobar");
+            let default_ref = get_property_q(q_ctx, &ns, "default").expect("no default");
+            let greeting = call_function_q(q_ctx, &default_ref, &[], None).expect("call failed");
+            assert_eq!(greeting.to_string().unwrap(), "hi foobar");
+            // getting the exports is not an import of the module by itself
+            assert!(!q_ctx
+                .module_dependency_graph()
+                .iter()
+                .any(|(from, to)| from == "test1.mes" && to == "test1.mes"));
         });
 
         rt.exe_rt_task_in_event_loop(|q_js_rt| {
@@ -360,4 +836,508 @@ pub mod tests {
 
         log::info!("< test_module_sandbox");
     }
+
+    #[test]
+    fn test_import_meta_resolve() {
+        use crate::jsutils::modules::ScriptModuleLoader;
+        use crate::quickjsrealmadapter::QuickJsRealmAdapter;
+        use std::collections::HashMap;
+
+        struct ExactLoader {
+            modules: HashMap<&'static str, &'static str>,
+        }
+        impl ScriptModuleLoader for ExactLoader {
+            fn normalize_path(
+                &self,
+                _realm: &QuickJsRealmAdapter,
+                _ref_path: &str,
+                path: &str,
+            ) -> Option<String> {
+                // relative paths are already joined with the path of the importing module
+                self.modules.contains_key(path).then(|| path.to_string())
+            }
+
+            fn load_module(&self, _realm: &QuickJsRealmAdapter, absolute_path: &str) -> String {
+                self.modules[absolute_path].to_string()
+            }
+        }
+
+        let rt = QuickJsRuntimeBuilder::new()
+            .script_module_loader(ExactLoader {
+                modules: HashMap::from([
+                    ("/app/lib/url.mes", "export const url = import.meta.url;"),
+                    (
+                        "/app/lib/resolver.mes",
+                        "export const resolved = import.meta.resolve('./x.js');\n\
+                         export const parent = import.meta.resolve('../main.mes');\n\
+                         let missing;\n\
+                         try { import.meta.resolve('./missing.js'); } catch (e) { missing = e.name + ': ' + e.message; }\n\
+                         export {missing};",
+                    ),
+                    ("/app/lib/x.js", "export const x = 1;"),
+                ]),
+            })
+            .build();
+
+        rt.eval_module_sync(
+            None,
+            Script::new(
+                "/app/main.mes",
+                "import {url} from './lib/url.mes';\n\
+                 globalThis.metaResults = {url, mainUrl: import.meta.url, mainResolved: import.meta.resolve('./lib/x.js')};\n\
+                 globalThis.metaDone = import('./lib/resolver.mes').then((ns) => {\n\
+                     Object.assign(globalThis.metaResults, {resolved: ns.resolved, parent: ns.parent, missing: ns.missing});\n\
+                 }).catch((e) => {globalThis.metaResults.error = '' + e;});",
+            ),
+        )
+        .expect("module failed");
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new(
+                    "test_import_meta_resolve.js",
+                    "metaDone.then(() => JSON.stringify(metaResults));",
+                ),
+            )
+            .expect("script failed");
+        let JsValueFacade::JsPromise { cached_promise } = res else {
+            panic!("metaDone was not a promise");
+        };
+        let res = cached_promise
+            .get_promise_result_sync_timeout(Some(Duration::from_secs(5)))
+            .expect("import() timed out")
+            .expect("import() was rejected");
+        let results: serde_json::Value = serde_json::from_str(res.get_str()).unwrap();
+        assert_eq!(results["url"], "/app/lib/url.mes");
+        assert_eq!(results["mainUrl"], "/app/main.mes");
+        assert_eq!(results["mainResolved"], "/app/lib/x.js");
+        assert_eq!(results["resolved"], "/app/lib/x.js", "{results}");
+        assert_eq!(results["parent"], "/app/main.mes");
+        assert_eq!(
+            results["missing"],
+            "ReferenceError: Module ./missing.js was not found"
+        );
+    }
+
+    #[test]
+    fn test_json_modules() {
+        use crate::jsutils::modules::ScriptModuleLoader;
+        use crate::quickjsrealmadapter::QuickJsRealmAdapter;
+
+        struct JsonLoader {}
+        impl ScriptModuleLoader for JsonLoader {
+            fn normalize_path(
+                &self,
+                _realm: &QuickJsRealmAdapter,
+                _ref_path: &str,
+                path: &str,
+            ) -> Option<String> {
+                path.ends_with(".json").then(|| path.to_string())
+            }
+
+            fn load_module(&self, _realm: &QuickJsRealmAdapter, absolute_path: &str) -> String {
+                match absolute_path {
+                    "/data/data.json" => "{\"a\":[1,2,3],\"__proto__\":{\"b\":1}}".to_string(),
+                    _ => "{\n  \"a\": [1, 2,\n  x]\n}".to_string(),
+                }
+            }
+        }
+
+        let rt = QuickJsRuntimeBuilder::new()
+            .script_module_loader(JsonLoader {})
+            .build();
+        rt.eval_module_sync(
+            None,
+            Script::new(
+                "/data/main.mes",
+                "import data from './data.json';\n\
+                 globalThis.jsonResult = JSON.stringify([data.a, Object.keys(data), Object.getPrototypeOf(data) === Object.prototype]);",
+            ),
+        )
+        .expect("module failed");
+        let res = rt
+            .eval_sync(None, Script::new("test_json_modules.js", "jsonResult;"))
+            .expect("script failed");
+        assert_eq!(res.get_str(), "[[1,2,3],[\"a\",\"__proto__\"],true]");
+
+        let err = rt
+            .eval_module_sync(
+                None,
+                Script::new("/data/main2.mes", "import bad from './bad.json';"),
+            )
+            .expect_err("malformed json should fail");
+        assert!(
+            err.get_message().contains("/data/bad.json")
+                && err.get_message().contains("line 3 column 3"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_loaded_modules() {
+        use crate::jsutils::modules::ScriptModuleLoader;
+        use crate::quickjsrealmadapter::QuickJsRealmAdapter;
+        use std::collections::HashMap;
+
+        struct TreeLoader {
+            modules: HashMap<&'static str, &'static str>,
+        }
+        impl ScriptModuleLoader for TreeLoader {
+            fn normalize_path(
+                &self,
+                _realm: &QuickJsRealmAdapter,
+                _ref_path: &str,
+                path: &str,
+            ) -> Option<String> {
+                self.modules.contains_key(path).then(|| path.to_string())
+            }
+
+            fn load_module(&self, _realm: &QuickJsRealmAdapter, absolute_path: &str) -> String {
+                self.modules[absolute_path].to_string()
+            }
+        }
+
+        let rt = QuickJsRuntimeBuilder::new()
+            .script_module_loader(TreeLoader {
+                modules: HashMap::from([
+                    (
+                        "/app/lib/a.mes",
+                        "import {b} from './b.mes'; export const a = 'a' + b;",
+                    ),
+                    ("/app/lib/b.mes", "export const b = 'b';"),
+                ]),
+            })
+            .build();
+
+        rt.eval_module_sync(
+            Some("tree_realm"),
+            Script::new(
+                "/app/main.mes",
+                "import {a} from './lib/a.mes';\nimport {b} from './lib/b.mes';\nglobalThis.tree = a + b;",
+            ),
+        )
+        .expect("module failed");
+
+        assert_eq!(
+            rt.loaded_modules(Some("tree_realm")),
+            // a module is recorded when it finished loading, so after the modules it imports
+            ["/app/lib/b.mes", "/app/lib/a.mes", "/app/main.mes"]
+        );
+        assert!(rt.loaded_modules(None).is_empty());
+        assert!(rt.loaded_modules(Some("no_such_realm")).is_empty());
+
+        let mut edges = rt.loop_realm_sync(Some("tree_realm"), |_rt, realm| {
+            realm.module_dependency_graph()
+        });
+        edges.sort();
+        let edge = |from: &str, to: &str| (from.to_string(), to.to_string());
+        assert_eq!(
+            edges,
+            [
+                edge("/app/lib/a.mes", "/app/lib/b.mes"),
+                edge("/app/main.mes", "/app/lib/a.mes"),
+                edge("/app/main.mes", "/app/lib/b.mes"),
+            ]
+        );
+
+        // a new realm with the same id starts without loaded modules
+        rt.drop_context("tree_realm");
+        rt.create_context("tree_realm")
+            .expect("could not create realm");
+        assert!(rt.loaded_modules(Some("tree_realm")).is_empty());
+    }
+
+    #[test]
+    fn test_relative_map_loader() {
+        use crate::jsutils::modules::ScriptModuleLoader;
+        use crate::jsutils::JsError;
+        use crate::quickjsrealmadapter::QuickJsRealmAdapter;
+        use std::collections::HashMap;
+
+        struct MapLoader {
+            modules: HashMap<&'static str, &'static str>,
+        }
+        impl ScriptModuleLoader for MapLoader {
+            fn normalize_path(
+                &self,
+                _realm: &QuickJsRealmAdapter,
+                ref_path: &str,
+                path: &str,
+            ) -> Option<String> {
+                let mut segments: Vec<&str> = if path.starts_with("./") || path.starts_with("../") {
+                    let mut base: Vec<&str> = ref_path.split('/').collect();
+                    base.pop();
+                    base
+                } else {
+                    vec![]
+                };
+                for segment in path.split('/') {
+                    match segment {
+                        "." => {}
+                        ".." => {
+                            segments.pop();
+                        }
+                        _ => segments.push(segment),
+                    }
+                }
+                let absolute = segments.join("/");
+                self.modules
+                    .contains_key(absolute.as_str())
+                    .then_some(absolute)
+            }
+
+            fn load_module(&self, _realm: &QuickJsRealmAdapter, absolute_path: &str) -> String {
+                self.modules[absolute_path].to_string()
+            }
+
+            fn try_load_module(
+                &self,
+                _realm: &QuickJsRealmAdapter,
+                absolute_path: &str,
+            ) -> Result<String, JsError> {
+                match self.modules[absolute_path] {
+                    "" => Err(JsError::new_str("empty module")),
+                    source => Ok(source.to_string()),
+                }
+            }
+        }
+
+        let modules = HashMap::from([
+            (
+                "/app/lib/a.mjs",
+                "import {b} from './b.mjs'; export const a = 'a' + b;",
+            ),
+            (
+                "/app/lib/b.mjs",
+                "import {c} from '../util/c.mjs'; export const b = 'b' + c;",
+            ),
+            ("/app/util/c.mjs", "export const c = 'c';"),
+            ("/app/util/empty.mjs", ""),
+        ]);
+        let rt = QuickJsRuntimeBuilder::new()
+            .script_module_loader(MapLoader { modules })
+            .build();
+        rt.eval_module_sync(
+            None,
+            Script::new(
+                "/app/main.mjs",
+                "import {a} from './lib/a.mjs'; globalThis.result = a;",
+            ),
+        )
+        .expect("module failed");
+        let res = rt
+            .eval_sync(None, Script::new("test_relative_map_loader.js", "result"))
+            .expect("script failed");
+        assert_eq!(res.get_str(), "abc");
+
+        let err = rt
+            .eval_module_sync(
+                None,
+                Script::new("/app/main2.mjs", "import {x} from './util/empty.mjs';"),
+            )
+            .expect_err("load should fail");
+        assert!(err.get_message().contains("/app/util/empty.mjs"), "{}", err);
+        let err = rt
+            .eval_module_sync(
+                None,
+                Script::new("/app/main3.mjs", "import {x} from './util/missing.mjs';"),
+            )
+            .expect_err("load should fail");
+        assert!(err.get_message().contains("./util/missing.mjs"), "{}", err);
+    }
+
+    #[test]
+    fn test_normalize_module_path() {
+        let cases = [
+            ("dir/a.js", "./util.js", "dir/util.js"),
+            ("dir/a.js", "./sub/../util.js", "dir/util.js"),
+            ("/app/lib/deep/a.js", "../../util.js", "/app/util.js"),
+            ("/app/lib/a.js", ".././lib//b.js", "/app/lib/b.js"),
+            ("/app/lib/a.js", "/other/b.js", "/other/b.js"),
+            ("/app/lib/a.js", "/other/../b.js", "/b.js"),
+            ("/app/lib/a.js", "lodash", "lodash"),
+            (
+                "/app/lib/a.js",
+                "greco://testmodule1",
+                "greco://testmodule1",
+            ),
+            ("a.js", "./b.js", "b.js"),
+            // urls keep their scheme and host
+            ("https://host/lib/a.js", "./b.js", "https://host/lib/b.js"),
+            ("https://host/lib/a.js", "../b.js", "https://host/b.js"),
+            ("https://host/lib/a.js", "/b.js", "https://host/b.js"),
+            ("https://host:8080/a.js", "./b.js", "https://host:8080/b.js"),
+            ("https://host", "./b.js", "https://host/b.js"),
+            ("file:///app/lib/a.js", "./b.js", "file:///app/lib/b.js"),
+            ("file:///app/lib/a.js", "../../b.js", "file:///b.js"),
+            ("greco://testmodule1", "./b.js", "greco://testmodule1/b.js"),
+            // bases without a host can not be joined
+            ("node:fs", "./b.js", "./b.js"),
+            ("C:/app/a.js", "./b.js", "C:/app/b.js"),
+        ];
+        for (base, name, expected) in cases {
+            assert_eq!(
+                normalize_module_path(base, name, None).unwrap(),
+                expected,
+                "{base} {name}"
+            );
+        }
+        assert!(normalize_module_path("a.js", "../b.js", None).is_err());
+        assert!(normalize_module_path("/a.js", "../b.js", None).is_err());
+        assert!(normalize_module_path("/a.js", "/../b.js", None).is_err());
+        assert!(normalize_module_path("https://host/a.js", "../b.js", None).is_err());
+        assert!(normalize_module_path("file:///a.js", "../b.js", None).is_err());
+
+        assert_eq!(
+            normalize_module_path("/app/a.js", "./b.js", Some("/app/")).unwrap(),
+            "/app/b.js"
+        );
+        assert!(normalize_module_path("/app/a.js", "../b.js", Some("/app")).is_err());
+        assert!(normalize_module_path("/app/a.js", "/application/b.js", Some("/app")).is_err());
+        // bare specifiers are left to the loaders
+        assert!(normalize_module_path("/app/a.js", "lodash", Some("/app")).is_ok());
+        assert_eq!(
+            normalize_module_path("https://host/a.js", "./b.js", Some("/app")).unwrap(),
+            "https://host/b.js"
+        );
+    }
+
+    #[test]
+    fn test_relative_imports() {
+        use crate::jsutils::modules::ScriptModuleLoader;
+        use crate::quickjsrealmadapter::QuickJsRealmAdapter;
+        use std::collections::HashMap;
+
+        // a loader which does no resolving of its own
+        struct ExactMapLoader {
+            modules: HashMap<&'static str, &'static str>,
+        }
+        impl ScriptModuleLoader for ExactMapLoader {
+            fn normalize_path(
+                &self,
+                _realm: &QuickJsRealmAdapter,
+                _ref_path: &str,
+                path: &str,
+            ) -> Option<String> {
+                self.modules.contains_key(path).then(|| path.to_string())
+            }
+
+            fn load_module(&self, _realm: &QuickJsRealmAdapter, absolute_path: &str) -> String {
+                self.modules[absolute_path].to_string()
+            }
+        }
+
+        let modules = HashMap::from([
+            (
+                "/app/lib/deep/a.mjs",
+                "import {b} from '../../util/b.mjs'; import {c} from './c.mjs'; export const a = 'a' + b + c;",
+            ),
+            (
+                "/app/util/b.mjs",
+                "import {d} from '/app/util/d.mjs'; export const b = 'b' + d;",
+            ),
+            ("/app/lib/deep/c.mjs", "import {e} from 'bare'; export const c = 'c' + e;"),
+            ("/app/util/d.mjs", "export const d = 'd';"),
+            ("bare", "export const e = 'e';"),
+            ("/etc/secret.mjs", "export const secret = 's';"),
+        ]);
+        let rt = QuickJsRuntimeBuilder::new()
+            .script_module_loader(ExactMapLoader { modules })
+            .module_root("/app")
+            .build();
+
+        rt.eval_module_sync(
+            None,
+            Script::new(
+                "/app/main.mjs",
+                "import {a} from './lib/deep/a.mjs'; globalThis.result = a;",
+            ),
+        )
+        .expect("module failed");
+        let res = rt
+            .eval_sync(None, Script::new("test_relative_imports.js", "result"))
+            .expect("script failed");
+        assert_eq!(res.get_str(), "abdce");
+
+        for import in ["../etc/secret.mjs", "/etc/secret.mjs"] {
+            let err = rt
+                .eval_module_sync(
+                    None,
+                    Script::new(
+                        "/app/escape.mjs",
+                        format!("import {{secret}} from '{import}';").as_str(),
+                    ),
+                )
+                .expect_err("import should fail");
+            assert!(
+                err.get_message().contains("outside of the module root"),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_native_module_loader_function() {
+        use crate::jsutils::modules::NativeModuleLoader;
+        use crate::quickjs_utils::{functions, primitives};
+        use crate::quickjsrealmadapter::QuickJsRealmAdapter;
+        use crate::quickjsvalueadapter::QuickJsValueAdapter;
+
+        struct FsModuleLoader {}
+        impl NativeModuleLoader for FsModuleLoader {
+            fn has_module(&self, _q_ctx: &QuickJsRealmAdapter, module_name: &str) -> bool {
+                module_name == "myhost:fs"
+            }
+
+            fn get_module_export_names(
+                &self,
+                _q_ctx: &QuickJsRealmAdapter,
+                _module_name: &str,
+            ) -> Vec<&str> {
+                vec!["separator", "readFile"]
+            }
+
+            fn get_module_exports(
+                &self,
+                q_ctx: &QuickJsRealmAdapter,
+                _module_name: &str,
+            ) -> Vec<(&str, QuickJsValueAdapter)> {
+                let read_file = functions::new_function_q(
+                    q_ctx,
+                    "readFile",
+                    |q_ctx, _this, args| {
+                        let path = primitives::to_string_q(q_ctx, &args[0])?;
+                        primitives::from_string_q(q_ctx, format!("contents of {path}").as_str())
+                    },
+                    1,
+                )
+                .expect("could not create function");
+                vec![
+                    ("separator", primitives::from_string_q(q_ctx, "/").unwrap()),
+                    ("readFile", read_file),
+                ]
+            }
+        }
+
+        let rt = QuickJsRuntimeBuilder::new()
+            .native_module_loader(FsModuleLoader {})
+            .build();
+        rt.eval_module_sync(
+            None,
+            Script::new(
+                "test_native_module_loader_function.mes",
+                "import {separator, readFile} from 'myhost:fs';\nglobalThis.result = readFile(separator + 'tmp' + separator + 'a.txt');",
+            ),
+        )
+        .expect("module failed");
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_native_module_loader_function.js", "result"),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_str(), "contents of /tmp/a.txt");
+    }
 }
@@ -1,8 +1,9 @@
 //! Utils for working with objects
 
 use crate::jsutils::JsError;
+use crate::quickjs_utils::atoms::JSAtomRef;
 use crate::quickjs_utils::properties::JSPropertyEnumRef;
-use crate::quickjs_utils::{atoms, functions, get_constructor, get_global};
+use crate::quickjs_utils::{atoms, functions, get_constructor, get_global, primitives, symbols};
 use crate::quickjsrealmadapter::QuickJsRealmAdapter;
 use crate::quickjsruntimeadapter::{make_cstring, QuickJsRuntimeAdapter};
 use crate::quickjsvalueadapter::QuickJsValueAdapter;
@@ -226,6 +227,97 @@ pub unsafe fn set_property2(
     Ok(())
 }
 
+/// set a property with a Symbol (e.g. Symbol.iterator) as key, see [crate::quickjs_utils::symbols]
+pub fn set_property_by_symbol_q(
+    q_ctx: &QuickJsRealmAdapter,
+    obj_ref: &QuickJsValueAdapter,
+    symbol_ref: &QuickJsValueAdapter,
+    prop_ref: &QuickJsValueAdapter,
+) -> Result<(), JsError> {
+    unsafe { set_property_by_symbol(q_ctx.context, obj_ref, symbol_ref, prop_ref) }
+}
+
+/// set a property with a Symbol (e.g. Symbol.iterator) as key, see [crate::quickjs_utils::symbols]
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn set_property_by_symbol(
+    context: *mut q::JSContext,
+    obj_ref: &QuickJsValueAdapter,
+    symbol_ref: &QuickJsValueAdapter,
+    prop_ref: &QuickJsValueAdapter,
+) -> Result<(), JsError> {
+    let prop_atom = symbol_to_atom(context, symbol_ref)?;
+    let ret = q::JS_DefinePropertyValue(
+        context,
+        *obj_ref.borrow_value(),
+        prop_atom.get_atom(),
+        prop_ref.clone_value_incr_rc(),
+        q::JS_PROP_C_W_E as i32,
+    );
+    if ret < 0 {
+        return Err(QuickJsRealmAdapter::get_exception(context)
+            .unwrap_or_else(|| JsError::new_str("Could not add property to object")));
+    }
+    Ok(())
+}
+
+/// get a property with a Symbol (e.g. Symbol.iterator) as key, see [crate::quickjs_utils::symbols]
+pub fn get_property_by_symbol_q(
+    q_ctx: &QuickJsRealmAdapter,
+    obj_ref: &QuickJsValueAdapter,
+    symbol_ref: &QuickJsValueAdapter,
+) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { get_property_by_symbol(q_ctx.context, obj_ref, symbol_ref) }
+}
+
+/// get a property with a Symbol (e.g. Symbol.iterator) as key, see [crate::quickjs_utils::symbols]
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn get_property_by_symbol(
+    context: *mut q::JSContext,
+    obj_ref: &QuickJsValueAdapter,
+    symbol_ref: &QuickJsValueAdapter,
+) -> Result<QuickJsValueAdapter, JsError> {
+    if obj_ref.is_null() || obj_ref.is_undefined() {
+        return Err(JsError::new_str(
+            "could not get prop from null or undefined",
+        ));
+    }
+    let prop_atom = symbol_to_atom(context, symbol_ref)?;
+    let prop_val = q::JS_GetPropertyInternal(
+        context,
+        *obj_ref.borrow_value(),
+        prop_atom.get_atom(),
+        *obj_ref.borrow_value(),
+        0,
+    );
+    let prop_ref = QuickJsValueAdapter::new(
+        context,
+        prop_val,
+        false,
+        true,
+        "object::get_property_by_symbol result",
+    );
+    if prop_ref.is_exception() {
+        return Err(QuickJsRealmAdapter::get_exception(context)
+            .unwrap_or_else(|| JsError::new_str("Could not get property")));
+    }
+    Ok(prop_ref)
+}
+
+unsafe fn symbol_to_atom(
+    context: *mut q::JSContext,
+    symbol_ref: &QuickJsValueAdapter,
+) -> Result<JSAtomRef, JsError> {
+    if !symbols::is_symbol(symbol_ref) {
+        return Err(JsError::new_str("property key is not a Symbol"));
+    }
+    Ok(JSAtomRef::new(
+        context,
+        q::JS_ValueToAtom(context, *symbol_ref.borrow_value()),
+    ))
+}
+
 /// define a getter/setter property
 /// # Example
 /// ```dontrun
@@ -272,7 +364,6 @@ pub fn define_getter_setter_q(
     }
 }
 
-#[allow(dead_code)]
 /// define a getter/setter property
 /// # Safety
 /// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
@@ -283,40 +374,92 @@ pub unsafe fn define_getter_setter(
     getter_func_ref: &QuickJsValueAdapter,
     setter_func_ref: &QuickJsValueAdapter,
 ) -> Result<(), JsError> {
-    /*
-     pub fn JS_DefinePropertyGetSet(
-        ctx: *mut JSContext,
-        this_obj: JSValue,
-        prop: JSAtom,
-        getter: JSValue,
-        setter: JSValue,
-        flags: ::std::os::raw::c_int,
-    ) -> ::std::os::raw::c_int;
-     */
-
-    log::trace!("objects::define_getter_setter 1");
-
-    debug_assert!(functions::is_function(context, getter_func_ref));
-    log::trace!("objects::define_getter_setter 2");
-    debug_assert!(functions::is_function(context, setter_func_ref));
-    log::trace!("objects::define_getter_setter 3");
+    define_accessor(
+        context,
+        obj_ref,
+        prop_name,
+        Some(getter_func_ref),
+        Some(setter_func_ref),
+    )
+}
+
+/// define an accessor property with an optional getter and an optional setter
+/// without a getter reading the property returns undefined, without a setter assigning the property is ignored (or throws a TypeError in strict mode)
+/// # Example
+/// ```rust
+/// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+/// use quickjs_runtime::quickjs_utils::objects::{create_object_q, define_accessor_q, set_property_q};
+/// use quickjs_runtime::quickjs_utils::functions::new_function_q;
+/// use quickjs_runtime::quickjs_utils::primitives::from_i32;
+/// use quickjs_runtime::quickjs_utils::get_global_q;
+/// use quickjs_runtime::jsutils::Script;
+/// let rt = QuickJsRuntimeBuilder::new().build();
+/// rt.exe_rt_task_in_event_loop(|q_js_rt| {
+///     let q_ctx = q_js_rt.get_main_realm();
+///     let obj = create_object_q(q_ctx).expect("create obj failed");
+///     let getter_func = new_function_q(q_ctx, "getter", |_q_ctx, _this_ref, _args| Ok(from_i32(13)), 0).expect("new_function_q failed");
+///     // a getter-only property
+///     define_accessor_q(q_ctx, &obj, "lazyProp", Some(&getter_func), None).expect("define_accessor_q failed");
+///     let global = get_global_q(q_ctx);
+///     set_property_q(q_ctx, &global, "lazyObj", &obj).expect("set prop on global failed");
+///     let res = q_ctx.eval(Script::new("define_accessor_q.js", "lazyObj.lazyProp;")).expect("script failed");
+///     assert_eq!(res.to_i32(), 13);
+/// });
+/// ```
+pub fn define_accessor_q(
+    q_ctx: &QuickJsRealmAdapter,
+    obj_ref: &QuickJsValueAdapter,
+    prop_name: &str,
+    getter_func_ref: Option<&QuickJsValueAdapter>,
+    setter_func_ref: Option<&QuickJsValueAdapter>,
+) -> Result<(), JsError> {
+    unsafe {
+        define_accessor(
+            q_ctx.context,
+            obj_ref,
+            prop_name,
+            getter_func_ref,
+            setter_func_ref,
+        )
+    }
+}
+
+/// define an accessor property with an optional getter and an optional setter
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn define_accessor(
+    context: *mut q::JSContext,
+    obj_ref: &QuickJsValueAdapter,
+    prop_name: &str,
+    getter_func_ref: Option<&QuickJsValueAdapter>,
+    setter_func_ref: Option<&QuickJsValueAdapter>,
+) -> Result<(), JsError> {
+    for func_ref in [getter_func_ref, setter_func_ref].into_iter().flatten() {
+        if !functions::is_function(context, func_ref) {
+            return Err(JsError::new_str("getter and setter must be functions"));
+        }
+    }
 
     let prop_atom = atoms::from_string(context, prop_name)?;
 
-    log::trace!("objects::define_getter_setter 4");
+    let raw_or_undefined = |func_ref: Option<&QuickJsValueAdapter>| match func_ref {
+        Some(func_ref) => func_ref.clone_value_incr_rc(),
+        None => crate::quickjs_utils::new_undefined(),
+    };
 
+    // JS_DefinePropertyGetSet frees the getter and setter
     let res = q::JS_DefinePropertyGetSet(
         context,
         *obj_ref.borrow_value(),
         prop_atom.get_atom(),
-        getter_func_ref.clone_value_incr_rc(),
-        setter_func_ref.clone_value_incr_rc(),
-        q::JS_PROP_C_W_E as i32,
+        raw_or_undefined(getter_func_ref),
+        raw_or_undefined(setter_func_ref),
+        (q::JS_PROP_CONFIGURABLE | q::JS_PROP_ENUMERABLE) as i32,
     );
 
-    log::trace!("objects::define_getter_setter 5 {}", res);
+    log::trace!("objects::define_accessor {}", res);
 
-    if res != 0 {
+    if res < 0 {
         if let Some(err) = QuickJsRealmAdapter::get_exception(context) {
             Err(err)
         } else {
@@ -402,7 +545,7 @@ pub unsafe fn get_own_property_names(
     Ok(enum_ref)
 }
 
-/// get the names of all properties of an object
+/// get the names of all own enumerable properties of an object, symbol keys are skipped
 pub fn get_property_names_q(
     q_ctx: &QuickJsRealmAdapter,
     obj_ref: &QuickJsValueAdapter,
@@ -410,7 +553,7 @@ pub fn get_property_names_q(
     unsafe { get_property_names(q_ctx.context, obj_ref) }
 }
 
-/// get the names of all properties of an object
+/// get the names of all own enumerable properties of an object, symbol keys are skipped
 /// # Safety
 /// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
 pub unsafe fn get_property_names(
@@ -422,6 +565,9 @@ pub unsafe fn get_property_names(
     let mut names = vec![];
 
     for index in 0..enum_ref.len() {
+        if enum_ref.is_symbol(index) {
+            continue;
+        }
         let name = enum_ref.get_name(index)?;
         names.push(name);
     }
@@ -429,6 +575,8 @@ pub unsafe fn get_property_names(
     Ok(names)
 }
 
+/// call a visitor for every own enumerable property of an object with its name and value, symbol keys are skipped
+/// the traversal stops at the first Err returned by the visitor
 pub fn traverse_properties_q<V, R>(
     q_ctx: &QuickJsRealmAdapter,
     obj_ref: &QuickJsValueAdapter,
@@ -440,6 +588,7 @@ where
     unsafe { traverse_properties(q_ctx.context, obj_ref, visitor) }
 }
 
+/// like [traverse_properties_q] but with a FnMut visitor
 pub fn traverse_properties_q_mut<V, R>(
     q_ctx: &QuickJsRealmAdapter,
     obj_ref: &QuickJsValueAdapter,
@@ -466,6 +615,9 @@ where
     let mut result = vec![];
 
     for index in 0..enum_ref.len() {
+        if enum_ref.is_symbol(index) {
+            continue;
+        }
         let atom = enum_ref.get_atom_raw(index) as q::JSAtom;
         let prop_name = atoms::to_str(context, &atom)?;
 
@@ -508,6 +660,9 @@ where
     let enum_ref = get_own_property_names(context, obj_ref)?;
 
     for index in 0..enum_ref.len() {
+        if enum_ref.is_symbol(index) {
+            continue;
+        }
         let atom = enum_ref.get_atom_raw(index) as q::JSAtom;
         let prop_name = atoms::to_str(context, &atom)?;
 
@@ -535,6 +690,202 @@ where
     Ok(())
 }
 
+/// check if an object has a property (either own or inherited), like `propName in obj`
+pub fn has_property_q(
+    q_ctx: &QuickJsRealmAdapter,
+    obj_ref: &QuickJsValueAdapter,
+    prop_name: &str,
+) -> Result<bool, JsError> {
+    unsafe { has_property(q_ctx.context, obj_ref, prop_name) }
+}
+
+/// check if an object has a property (either own or inherited), like `propName in obj`
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn has_property(
+    context: *mut q::JSContext,
+    obj_ref: &QuickJsValueAdapter,
+    prop_name: &str,
+) -> Result<bool, JsError> {
+    let prop_atom = atoms::from_string(context, prop_name)?;
+    let res = q::JS_HasProperty(context, *obj_ref.borrow_value(), prop_atom.get_atom());
+    if res < 0 {
+        Err(QuickJsRealmAdapter::get_exception(context)
+            .unwrap_or_else(|| JsError::new_str("Could not check property")))
+    } else {
+        Ok(res != 0)
+    }
+}
+
+/// delete an own property from an object, like `delete obj[propName]`
+/// returns true if the property was deleted, false if the object did not have the own property or it is not configurable
+pub fn delete_property_q(
+    q_ctx: &QuickJsRealmAdapter,
+    obj_ref: &QuickJsValueAdapter,
+    prop_name: &str,
+) -> Result<bool, JsError> {
+    unsafe { delete_property(q_ctx.context, obj_ref, prop_name) }
+}
+
+/// delete an own property from an object, like `delete obj[propName]`
+/// returns true if the property was deleted, false if the object did not have the own property or it is not configurable
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn delete_property(
+    context: *mut q::JSContext,
+    obj_ref: &QuickJsValueAdapter,
+    prop_name: &str,
+) -> Result<bool, JsError> {
+    let prop_atom = atoms::from_string(context, prop_name)?;
+    if obj_ref.is_object() {
+        // unlike the delete operator we report false when there was nothing to delete
+        let own = q::JS_GetOwnProperty(
+            context,
+            std::ptr::null_mut(),
+            *obj_ref.borrow_value(),
+            prop_atom.get_atom(),
+        );
+        if own < 0 {
+            return Err(QuickJsRealmAdapter::get_exception(context)
+                .unwrap_or_else(|| JsError::new_str("Could not check property")));
+        }
+        if own == 0 {
+            return Ok(false);
+        }
+    }
+    let res = q::JS_DeleteProperty(context, *obj_ref.borrow_value(), prop_atom.get_atom(), 0);
+    if res < 0 {
+        Err(QuickJsRealmAdapter::get_exception(context)
+            .unwrap_or_else(|| JsError::new_str("Could not delete property")))
+    } else {
+        Ok(res != 0)
+    }
+}
+
+/// a property descriptor like the ones used by Object.getOwnPropertyDescriptor and Object.defineProperty
+/// fields which are None are absent from the descriptor, for define_property_q absent flags default to false
+#[derive(Clone, Default)]
+pub struct PropertyDescriptor {
+    pub value: Option<QuickJsValueAdapter>,
+    pub writable: Option<bool>,
+    pub enumerable: Option<bool>,
+    pub configurable: Option<bool>,
+    pub get: Option<QuickJsValueAdapter>,
+    pub set: Option<QuickJsValueAdapter>,
+}
+
+/// get the descriptor of an own property of an object, like Object.getOwnPropertyDescriptor
+/// returns None if the object has no own property with that name
+pub fn get_own_property_descriptor_q(
+    q_ctx: &QuickJsRealmAdapter,
+    obj_ref: &QuickJsValueAdapter,
+    prop_name: &str,
+) -> Result<Option<PropertyDescriptor>, JsError> {
+    unsafe { get_own_property_descriptor(q_ctx.context, obj_ref, prop_name) }
+}
+
+/// get the descriptor of an own property of an object, like Object.getOwnPropertyDescriptor
+/// returns None if the object has no own property with that name
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn get_own_property_descriptor(
+    context: *mut q::JSContext,
+    obj_ref: &QuickJsValueAdapter,
+    prop_name: &str,
+) -> Result<Option<PropertyDescriptor>, JsError> {
+    let constructor = get_constructor(context, "Object")?;
+    let desc_ref = functions::invoke_member_function(
+        context,
+        &constructor,
+        "getOwnPropertyDescriptor",
+        &[
+            obj_ref.clone(),
+            primitives::from_string(context, prop_name)?,
+        ],
+    )?;
+    if desc_ref.is_undefined() {
+        return Ok(None);
+    }
+    let get_field = |name: &str| -> Result<Option<QuickJsValueAdapter>, JsError> {
+        if has_property(context, &desc_ref, name)? {
+            Ok(Some(get_property(context, &desc_ref, name)?))
+        } else {
+            Ok(None)
+        }
+    };
+    let get_flag = |name: &str| -> Result<Option<bool>, JsError> {
+        get_field(name)?
+            .map(|flag_ref| primitives::to_bool(&flag_ref))
+            .transpose()
+    };
+    // getters and setters which are absent from an accessor descriptor are undefined
+    let get_accessor = |name: &str| -> Result<Option<QuickJsValueAdapter>, JsError> {
+        Ok(get_field(name)?.filter(|func_ref| !func_ref.is_undefined()))
+    };
+    Ok(Some(PropertyDescriptor {
+        value: get_field("value")?,
+        writable: get_flag("writable")?,
+        enumerable: get_flag("enumerable")?,
+        configurable: get_flag("configurable")?,
+        get: get_accessor("get")?,
+        set: get_accessor("set")?,
+    }))
+}
+
+/// define a property with a descriptor, like Object.defineProperty
+pub fn define_property_q(
+    q_ctx: &QuickJsRealmAdapter,
+    obj_ref: &QuickJsValueAdapter,
+    prop_name: &str,
+    descriptor: &PropertyDescriptor,
+) -> Result<(), JsError> {
+    unsafe { define_property(q_ctx.context, obj_ref, prop_name, descriptor) }
+}
+
+/// define a property with a descriptor, like Object.defineProperty
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn define_property(
+    context: *mut q::JSContext,
+    obj_ref: &QuickJsValueAdapter,
+    prop_name: &str,
+    descriptor: &PropertyDescriptor,
+) -> Result<(), JsError> {
+    let desc_ref = create_object(context)?;
+    let fields = [
+        ("value", descriptor.value.clone()),
+        ("writable", descriptor.writable.map(primitives::from_bool)),
+        (
+            "enumerable",
+            descriptor.enumerable.map(primitives::from_bool),
+        ),
+        (
+            "configurable",
+            descriptor.configurable.map(primitives::from_bool),
+        ),
+        ("get", descriptor.get.clone()),
+        ("set", descriptor.set.clone()),
+    ];
+    for (name, field) in fields {
+        if let Some(field_ref) = field {
+            set_property(context, &desc_ref, name, &field_ref)?;
+        }
+    }
+    let constructor = get_constructor(context, "Object")?;
+    functions::invoke_member_function(
+        context,
+        &constructor,
+        "defineProperty",
+        &[
+            obj_ref.clone(),
+            primitives::from_string(context, prop_name)?,
+            desc_ref,
+        ],
+    )?;
+    Ok(())
+}
+
+/// get the prototype of an object, like Object.getPrototypeOf
 pub fn get_prototype_of_q(
     q_ctx: &QuickJsRealmAdapter,
     obj_ref: &QuickJsValueAdapter,
@@ -565,6 +916,39 @@ pub unsafe fn get_prototype_of(
     }
 }
 
+/// set the prototype of an object, like Object.setPrototypeOf
+/// proto_ref may be an object or null
+pub fn set_prototype_of_q(
+    q_ctx: &QuickJsRealmAdapter,
+    obj_ref: &QuickJsValueAdapter,
+    proto_ref: &QuickJsValueAdapter,
+) -> Result<(), JsError> {
+    unsafe { set_prototype_of(q_ctx.context, obj_ref, proto_ref) }
+}
+
+/// set the prototype of an object, like Object.setPrototypeOf
+/// proto_ref may be an object or null
+/// # Safety
+/// please ensure the JSContext is valid and remains valid while using this function
+pub unsafe fn set_prototype_of(
+    ctx: *mut q::JSContext,
+    obj_ref: &QuickJsValueAdapter,
+    proto_ref: &QuickJsValueAdapter,
+) -> Result<(), JsError> {
+    let res = q::JS_SetPrototype(ctx, *obj_ref.borrow_value(), *proto_ref.borrow_value());
+    if res < 0 {
+        if let Some(ex) = QuickJsRealmAdapter::get_exception(ctx) {
+            Err(ex)
+        } else {
+            Err(JsError::new_str(
+                "set_prototype_of_q failed but could not get ex",
+            ))
+        }
+    } else {
+        Ok(())
+    }
+}
+
 pub fn is_instance_of_q(
     q_ctx: &QuickJsRealmAdapter,
     obj_ref: &QuickJsValueAdapter,
@@ -631,15 +1015,111 @@ pub unsafe fn is_instance_of_by_name(
     }
 }
 
+/// invoke a static method of the Object constructor (e.g. Object.freeze) with an object as single argument
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+unsafe fn invoke_object_static(
+    context: *mut q::JSContext,
+    function_name: &str,
+    obj_ref: &QuickJsValueAdapter,
+) -> Result<QuickJsValueAdapter, JsError> {
+    let constructor = get_constructor(context, "Object")?;
+    functions::invoke_member_function(
+        context,
+        &constructor,
+        function_name,
+        std::slice::from_ref(obj_ref),
+    )
+}
+
+/// freeze an object with Object.freeze, returns the same object so calls can be chained
+pub fn freeze_q(
+    q_ctx: &QuickJsRealmAdapter,
+    obj_ref: &QuickJsValueAdapter,
+) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { freeze(q_ctx.context, obj_ref) }
+}
+
+/// freeze an object with Object.freeze, returns the same object so calls can be chained
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn freeze(
+    context: *mut q::JSContext,
+    obj_ref: &QuickJsValueAdapter,
+) -> Result<QuickJsValueAdapter, JsError> {
+    invoke_object_static(context, "freeze", obj_ref)
+}
+
+/// seal an object with Object.seal, returns the same object so calls can be chained
+pub fn seal_q(
+    q_ctx: &QuickJsRealmAdapter,
+    obj_ref: &QuickJsValueAdapter,
+) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { seal(q_ctx.context, obj_ref) }
+}
+
+/// seal an object with Object.seal, returns the same object so calls can be chained
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn seal(
+    context: *mut q::JSContext,
+    obj_ref: &QuickJsValueAdapter,
+) -> Result<QuickJsValueAdapter, JsError> {
+    invoke_object_static(context, "seal", obj_ref)
+}
+
+/// check if an object is frozen with Object.isFrozen
+pub fn is_frozen_q(
+    q_ctx: &QuickJsRealmAdapter,
+    obj_ref: &QuickJsValueAdapter,
+) -> Result<bool, JsError> {
+    unsafe { is_frozen(q_ctx.context, obj_ref) }
+}
+
+/// check if an object is frozen with Object.isFrozen
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn is_frozen(
+    context: *mut q::JSContext,
+    obj_ref: &QuickJsValueAdapter,
+) -> Result<bool, JsError> {
+    primitives::to_bool(&invoke_object_static(context, "isFrozen", obj_ref)?)
+}
+
+/// check if an object is sealed with Object.isSealed
+pub fn is_sealed_q(
+    q_ctx: &QuickJsRealmAdapter,
+    obj_ref: &QuickJsValueAdapter,
+) -> Result<bool, JsError> {
+    unsafe { is_sealed(q_ctx.context, obj_ref) }
+}
+
+/// check if an object is sealed with Object.isSealed
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn is_sealed(
+    context: *mut q::JSContext,
+    obj_ref: &QuickJsValueAdapter,
+) -> Result<bool, JsError> {
+    primitives::to_bool(&invoke_object_static(context, "isSealed", obj_ref)?)
+}
+
 #[cfg(test)]
 pub mod tests {
     use crate::facades::tests::init_test_rt;
+    use crate::jsutils::JsError;
     use crate::jsutils::Script;
+    use crate::quickjs_utils::functions::new_function_q;
     use crate::quickjs_utils::objects::{
-        create_object_q, get_property_names_q, get_property_q, set_property_q,
+        create_object_q, define_accessor_q, define_getter_setter_q, define_property_q,
+        delete_property_q, freeze_q, get_own_property_descriptor_q, get_property_names_q,
+        get_property_q, get_prototype_of_q, has_property_q, is_frozen_q, is_sealed_q, seal_q,
+        set_property_q, set_prototype_of_q, traverse_properties_q, PropertyDescriptor,
     };
     use crate::quickjs_utils::primitives::{from_i32, to_i32};
     use crate::quickjs_utils::{get_global_q, primitives};
+    use std::cell::Cell;
+    use std::rc::Rc;
 
     #[test]
     fn test_get_refs() {
@@ -783,4 +1263,307 @@ pub mod tests {
 
         log::info!("< test_set_prop");
     }
+
+    #[test]
+    fn test_traverse_properties() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let obj_ref = q_ctx
+                .eval(Script::new(
+                    "test_traverse_properties.js",
+                    "let o = {a: 1, b: 2, c: 3, [Symbol('d')]: 4}; Object.defineProperty(o, 'e', {value: 5, enumerable: false}); o;",
+                ))
+                .expect("script failed");
+            let names = get_property_names_q(q_ctx, &obj_ref).expect("get names failed");
+            assert_eq!(names, vec!["a", "b", "c"]);
+
+            let entries = traverse_properties_q(q_ctx, &obj_ref, |key, value| {
+                Ok(format!("{key}={}", to_i32(value)?))
+            })
+            .expect("traverse failed");
+            assert_eq!(entries, vec!["a=1", "b=2", "c=3"]);
+
+            // an Err from the visitor stops the traversal
+            let res = traverse_properties_q(q_ctx, &obj_ref, |key, _value| {
+                if key == "b" {
+                    Err(JsError::new_str("stop"))
+                } else {
+                    Ok(())
+                }
+            });
+            assert_eq!(res.expect_err("did not stop").get_message(), "stop");
+        });
+    }
+
+    #[test]
+    fn test_define_accessor() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let obj_ref = create_object_q(q_ctx).expect("create failed");
+
+            // a computed getter, evaluated on every read
+            let counter = Rc::new(Cell::new(0));
+            let counter2 = counter.clone();
+            let getter_ref = new_function_q(
+                q_ctx,
+                "getter",
+                move |_q_ctx, _this, _args| {
+                    counter2.set(counter2.get() + 1);
+                    Ok(from_i32(counter2.get() * 10))
+                },
+                0,
+            )
+            .expect("new_function failed");
+            define_accessor_q(q_ctx, &obj_ref, "computed", Some(&getter_ref), None)
+                .expect("define failed");
+
+            let stored = Rc::new(Cell::new(0));
+            let stored2 = stored.clone();
+            let setter_ref = new_function_q(
+                q_ctx,
+                "setter",
+                move |_q_ctx, _this, args| {
+                    stored2.set(args[0].to_i32());
+                    Ok(crate::quickjs_utils::new_undefined_ref())
+                },
+                1,
+            )
+            .expect("new_function failed");
+            define_accessor_q(q_ctx, &obj_ref, "writeOnly", None, Some(&setter_ref))
+                .expect("define failed");
+            define_getter_setter_q(q_ctx, &obj_ref, "both", &getter_ref, &setter_ref)
+                .expect("define failed");
+
+            let global = get_global_q(q_ctx);
+            set_property_q(q_ctx, &global, "accessorObj", &obj_ref).expect("set failed");
+            assert_eq!(counter.get(), 0);
+
+            let res = q_ctx
+                .eval(Script::new(
+                    "test_define_accessor.js",
+                    "accessorObj.computed + accessorObj.computed;",
+                ))
+                .expect("script failed");
+            assert_eq!(res.to_i32(), 30);
+            assert_eq!(counter.get(), 2);
+
+            let res = q_ctx
+                .eval(Script::new(
+                    "test_define_accessor2.js",
+                    "accessorObj.writeOnly = 7; accessorObj.computed = 5; typeof accessorObj.writeOnly;",
+                ))
+                .expect("script failed");
+            assert_eq!(res.to_string().unwrap(), "undefined");
+            assert_eq!(stored.get(), 7);
+
+            // strict mode throws when there is no setter
+            let res = q_ctx.eval(Script::new(
+                "test_define_accessor3.js",
+                "'use strict'; accessorObj.computed = 5;",
+            ));
+            assert!(res.is_err());
+
+            q_ctx
+                .eval(Script::new(
+                    "test_define_accessor4.js",
+                    "accessorObj.both = 9;",
+                ))
+                .expect("script failed");
+            assert_eq!(stored.get(), 9);
+
+            let not_a_function = from_i32(1);
+            assert!(
+                define_accessor_q(q_ctx, &obj_ref, "invalid", Some(&not_a_function), None).is_err()
+            );
+        });
+    }
+
+    #[test]
+    fn test_freeze_seal() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let global = get_global_q(q_ctx);
+
+            let config_ref = create_object_q(q_ctx).expect("create failed");
+            set_property_q(q_ctx, &config_ref, "a", &from_i32(1)).expect("set failed");
+            assert!(!is_frozen_q(q_ctx, &config_ref).unwrap());
+            let frozen_ref = freeze_q(q_ctx, &config_ref).expect("freeze failed");
+            assert!(frozen_ref.is_object());
+            assert!(is_frozen_q(q_ctx, &config_ref).unwrap());
+            assert!(is_sealed_q(q_ctx, &config_ref).unwrap());
+            set_property_q(q_ctx, &global, "frozenConfig", &frozen_ref).expect("set failed");
+
+            let res = q_ctx.eval(Script::new(
+                "test_freeze.js",
+                "'use strict'; frozenConfig.a = 2;",
+            ));
+            assert!(res.is_err());
+            let res = q_ctx
+                .eval(Script::new("test_freeze2.js", "frozenConfig.a;"))
+                .expect("script failed");
+            assert_eq!(res.to_i32(), 1);
+
+            let sealed_ref = create_object_q(q_ctx).expect("create failed");
+            set_property_q(q_ctx, &sealed_ref, "a", &from_i32(1)).expect("set failed");
+            let sealed_ref = seal_q(q_ctx, &sealed_ref).expect("seal failed");
+            assert!(is_sealed_q(q_ctx, &sealed_ref).unwrap());
+            assert!(!is_frozen_q(q_ctx, &sealed_ref).unwrap());
+            set_property_q(q_ctx, &global, "sealedConfig", &sealed_ref).expect("set failed");
+            // existing props may change but new props may not be added
+            let res = q_ctx
+                .eval(Script::new(
+                    "test_seal.js",
+                    "'use strict'; sealedConfig.a = 2; sealedConfig.a;",
+                ))
+                .expect("script failed");
+            assert_eq!(res.to_i32(), 2);
+            let res = q_ctx.eval(Script::new(
+                "test_seal2.js",
+                "'use strict'; sealedConfig.b = 2;",
+            ));
+            assert!(res.is_err());
+        });
+    }
+
+    #[test]
+    fn test_prototype() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let proto_ref = create_object_q(q_ctx).expect("create failed");
+            set_property_q(q_ctx, &proto_ref, "inherited", &from_i32(42)).expect("set failed");
+            let obj_ref = create_object_q(q_ctx).expect("create failed");
+            set_prototype_of_q(q_ctx, &obj_ref, &proto_ref).expect("set proto failed");
+
+            let res = get_prototype_of_q(q_ctx, &obj_ref).expect("get proto failed");
+            assert!(res.is_object());
+            set_property_q(q_ctx, &res, "viaGet", &from_i32(1)).expect("set failed");
+
+            let global = get_global_q(q_ctx);
+            set_property_q(q_ctx, &global, "protoChild", &obj_ref).expect("set failed");
+            let res = q_ctx
+                .eval(Script::new(
+                    "test_prototype.js",
+                    "protoChild.inherited + protoChild.viaGet + (protoChild.hasOwnProperty('inherited') ? 100 : 0);",
+                ))
+                .expect("script failed");
+            assert_eq!(res.to_i32(), 43);
+
+            // null prototype
+            set_prototype_of_q(q_ctx, &obj_ref, &crate::quickjs_utils::new_null_ref())
+                .expect("set null proto failed");
+            assert!(get_prototype_of_q(q_ctx, &obj_ref).unwrap().is_null());
+            let res = q_ctx
+                .eval(Script::new(
+                    "test_prototype2.js",
+                    "typeof protoChild.inherited;",
+                ))
+                .expect("script failed");
+            assert_eq!(res.to_string().unwrap(), "undefined");
+
+            // cycles are rejected
+            set_prototype_of_q(q_ctx, &proto_ref, &obj_ref).expect("set proto failed");
+            assert!(set_prototype_of_q(q_ctx, &obj_ref, &proto_ref).is_err());
+            // only objects or null
+            assert!(set_prototype_of_q(q_ctx, &obj_ref, &from_i32(1)).is_err());
+        });
+    }
+
+    #[test]
+    fn test_has_delete_property() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let obj_ref = create_object_q(q_ctx).expect("create failed");
+            assert!(!has_property_q(q_ctx, &obj_ref, "a").unwrap());
+            set_property_q(q_ctx, &obj_ref, "a", &from_i32(1)).expect("set failed");
+            assert!(has_property_q(q_ctx, &obj_ref, "a").unwrap());
+            // inherited
+            assert!(has_property_q(q_ctx, &obj_ref, "toString").unwrap());
+
+            assert!(delete_property_q(q_ctx, &obj_ref, "a").unwrap());
+            assert!(!has_property_q(q_ctx, &obj_ref, "a").unwrap());
+            // deleting a missing or inherited property is not an error but deletes nothing
+            assert!(!delete_property_q(q_ctx, &obj_ref, "a").unwrap());
+            assert!(!delete_property_q(q_ctx, &obj_ref, "toString").unwrap());
+            assert!(has_property_q(q_ctx, &obj_ref, "toString").unwrap());
+
+            let frozen_ref = q_ctx
+                .eval(Script::new(
+                    "test_has_delete_property.js",
+                    "Object.freeze({b: 1});",
+                ))
+                .expect("script failed");
+            assert!(!delete_property_q(q_ctx, &frozen_ref, "b").unwrap());
+            assert!(has_property_q(q_ctx, &frozen_ref, "b").unwrap());
+
+            // primitives have no properties
+            assert!(!has_property_q(q_ctx, &from_i32(1), "a").unwrap());
+        });
+    }
+
+    #[test]
+    fn test_property_descriptor() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let src_ref = q_ctx
+                .eval(Script::new(
+                    "test_property_descriptor.js",
+                    "let src = {}; Object.defineProperty(src, 'ro', {value: 12, writable: false, enumerable: true, configurable: false}); Object.defineProperty(src, 'acc', {get: function() { return this.ro * 2; }, enumerable: false, configurable: true}); src;",
+                ))
+                .expect("script failed");
+
+            assert!(get_own_property_descriptor_q(q_ctx, &src_ref, "missing")
+                .unwrap()
+                .is_none());
+
+            let ro_desc = get_own_property_descriptor_q(q_ctx, &src_ref, "ro")
+                .unwrap()
+                .expect("no descriptor");
+            assert_eq!(ro_desc.value.as_ref().unwrap().to_i32(), 12);
+            assert_eq!(ro_desc.writable, Some(false));
+            assert_eq!(ro_desc.enumerable, Some(true));
+            assert_eq!(ro_desc.configurable, Some(false));
+            assert!(ro_desc.get.is_none());
+            assert!(ro_desc.set.is_none());
+
+            let acc_desc = get_own_property_descriptor_q(q_ctx, &src_ref, "acc")
+                .unwrap()
+                .expect("no descriptor");
+            assert!(acc_desc.value.is_none());
+            assert!(acc_desc.writable.is_none());
+            assert_eq!(acc_desc.enumerable, Some(false));
+            assert_eq!(acc_desc.configurable, Some(true));
+            assert!(acc_desc.get.is_some());
+            assert!(acc_desc.set.is_none());
+
+            // re-apply to a different object
+            let dst_ref = create_object_q(q_ctx).expect("create failed");
+            define_property_q(q_ctx, &dst_ref, "ro", &ro_desc).expect("define failed");
+            define_property_q(q_ctx, &dst_ref, "acc", &acc_desc).expect("define failed");
+            let global = get_global_q(q_ctx);
+            set_property_q(q_ctx, &global, "dst", &dst_ref).expect("set failed");
+            let res = q_ctx
+                .eval(Script::new(
+                    "test_property_descriptor2.js",
+                    "dst.ro = 5; JSON.stringify([dst.ro, dst.acc, Object.keys(dst), Object.getOwnPropertyDescriptor(dst, 'ro'), typeof Object.getOwnPropertyDescriptor(dst, 'acc').get]);",
+                ))
+                .expect("script failed");
+            assert_eq!(
+                res.to_string().unwrap(),
+                r#"[12,24,["ro"],{"value":12,"writable":false,"enumerable":true,"configurable":false},"function"]"#
+            );
+
+            // redefining a non configurable property throws
+            let desc = PropertyDescriptor {
+                value: Some(from_i32(1)),
+                ..Default::default()
+            };
+            assert!(define_property_q(q_ctx, &dst_ref, "ro", &desc).is_err());
+        });
+    }
 }
@@ -1,10 +1,13 @@
 use crate::jsutils::JsError;
+use crate::quickjs_utils::{bigints, errors};
 use crate::quickjsrealmadapter::QuickJsRealmAdapter;
 use crate::quickjsvalueadapter::QuickJsValueAdapter;
 use core::ptr;
 use libquickjs_sys as q;
 use std::os::raw::c_char;
 
+/// convert a boolean value to a bool, returns an Err if the value is not a boolean
+/// use [to_truthy_q] to get the JavaScript truthiness of any value instead
 pub fn to_bool(value_ref: &QuickJsValueAdapter) -> Result<bool, JsError> {
     if value_ref.is_bool() {
         let r = value_ref.borrow_value();
@@ -16,11 +19,27 @@ pub fn to_bool(value_ref: &QuickJsValueAdapter) -> Result<bool, JsError> {
     }
 }
 
+/// create a new boolean value
 pub fn from_bool(b: bool) -> QuickJsValueAdapter {
     let raw = unsafe { q::JS_NewBool(ptr::null_mut(), b) };
     QuickJsValueAdapter::new_no_context(raw, "primitives::from_bool")
 }
 
+/// check if a value of any type is truthy like `if (value)` in JavaScript
+/// e.g. 0, "", null, undefined and NaN are falsy while "0", [] and {} are truthy
+/// use [to_bool] to convert only actual boolean values
+pub fn to_truthy_q(q_ctx: &QuickJsRealmAdapter, value_ref: &QuickJsValueAdapter) -> bool {
+    unsafe { to_truthy(q_ctx.context, value_ref) }
+}
+
+/// check if a value of any type is truthy like `if (value)` in JavaScript
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn to_truthy(context: *mut q::JSContext, value_ref: &QuickJsValueAdapter) -> bool {
+    // JS_ToBool only returns -1 (an exception) when it is called with an exception value
+    q::JS_ToBool(context, *value_ref.borrow_value()) > 0
+}
+
 pub fn to_f64(value_ref: &QuickJsValueAdapter) -> Result<f64, JsError> {
     if value_ref.is_f64() {
         let r = value_ref.borrow_value();
@@ -51,12 +70,16 @@ pub fn from_i32(i: i32) -> QuickJsValueAdapter {
     QuickJsValueAdapter::new_no_context(raw, "primitives::from_i32")
 }
 
+/// convert a string value to a String, invalid UTF-8 (like lone surrogates) is replaced with U+FFFD
+/// embedded NUL characters are preserved
 pub fn to_string_q(
     q_ctx: &QuickJsRealmAdapter,
     value_ref: &QuickJsValueAdapter,
 ) -> Result<String, JsError> {
     unsafe { to_string(q_ctx.context, value_ref) }
 }
+/// convert a string value to a String, invalid UTF-8 (like lone surrogates) is replaced with U+FFFD
+/// embedded NUL characters are preserved
 /// # Safety
 /// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
 pub unsafe fn to_string(
@@ -65,30 +88,58 @@ pub unsafe fn to_string(
 ) -> Result<String, JsError> {
     //log::trace!("primitives::to_string on {}", value_ref.borrow_value().tag);
 
+    with_string_bytes(context, value_ref, |bytes| {
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    })
+}
+
+/// convert a string value to a String, returns an Err if the string is not valid UTF-8 (e.g. when it contains lone surrogates)
+pub fn to_string_strict_q(
+    q_ctx: &QuickJsRealmAdapter,
+    value_ref: &QuickJsValueAdapter,
+) -> Result<String, JsError> {
+    unsafe { to_string_strict(q_ctx.context, value_ref) }
+}
+
+/// convert a string value to a String, returns an Err if the string is not valid UTF-8 (e.g. when it contains lone surrogates)
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn to_string_strict(
+    context: *mut q::JSContext,
+    value_ref: &QuickJsValueAdapter,
+) -> Result<String, JsError> {
+    with_string_bytes(context, value_ref, |bytes| {
+        String::from_utf8(bytes.to_vec())
+            .map_err(|e| JsError::new_string(format!("string is not valid UTF-8: {e}")))
+    })
+}
+
+/// call a consumer with the (possibly not valid UTF-8) bytes of a string value, the bytes are only valid during the call
+unsafe fn with_string_bytes<R, C: FnOnce(&[u8]) -> Result<R, JsError>>(
+    context: *mut q::JSContext,
+    value_ref: &QuickJsValueAdapter,
+    consumer: C,
+) -> Result<R, JsError> {
     assert!(value_ref.is_string());
 
     let mut len = 0;
 
     let ptr: *const c_char = q::JS_ToCStringLen2(context, &mut len, *value_ref.borrow_value(), 0);
 
-    if len == 0 {
-        return Ok("".to_string());
-    }
-
     if ptr.is_null() {
         return Err(JsError::new_str(
             "Could not convert string: got a null pointer",
         ));
     }
 
-    let cstr = std::ffi::CStr::from_ptr(ptr);
-
-    let s = cstr.to_string_lossy().into_owned();
+    // use len instead of the NUL terminator so embedded NUL characters are preserved
+    let bytes = std::slice::from_raw_parts(ptr as *const u8, len);
+    let res = consumer(bytes);
 
     // Free the c string.
     q::JS_FreeCString(context, ptr);
 
-    Ok(s)
+    res
 }
 
 /// # Safety
@@ -144,11 +195,77 @@ pub unsafe fn from_string(
     Ok(ret)
 }
 
+/// check if a value is a BigInt
+pub fn is_big_int(value_ref: &QuickJsValueAdapter) -> bool {
+    value_ref.is_big_int()
+}
+
+/// create a new BigInt from an i64
+pub fn from_i64_q(q_ctx: &QuickJsRealmAdapter, i: i64) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { from_i64(q_ctx.context, i) }
+}
+
+/// create a new BigInt from an i64
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn from_i64(context: *mut q::JSContext, i: i64) -> Result<QuickJsValueAdapter, JsError> {
+    bigints::new_bigint_i64(context, i)
+}
+
+/// create a new BigInt from an u64
+pub fn from_u64_q(q_ctx: &QuickJsRealmAdapter, u: u64) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { from_u64(q_ctx.context, u) }
+}
+
+/// create a new BigInt from an u64
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn from_u64(context: *mut q::JSContext, u: u64) -> Result<QuickJsValueAdapter, JsError> {
+    bigints::new_bigint_u64(context, u)
+}
+
+/// convert a BigInt to an i64, returns an Err if the value is not a BigInt or if it does not fit in an i64
+pub fn to_i64_q(
+    q_ctx: &QuickJsRealmAdapter,
+    value_ref: &QuickJsValueAdapter,
+) -> Result<i64, JsError> {
+    unsafe { to_i64(q_ctx.context, value_ref) }
+}
+
+/// convert a BigInt to an i64, returns an Err if the value is not a BigInt or if it does not fit in an i64
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn to_i64(
+    context: *mut q::JSContext,
+    value_ref: &QuickJsValueAdapter,
+) -> Result<i64, JsError> {
+    if !value_ref.is_big_int() {
+        return Err(JsError::new_str("value is not a BigInt"));
+    }
+    let mut i: i64 = 0;
+    if q::JS_ToBigInt64(context, &mut i, *value_ref.borrow_value()) != 0 {
+        return Err(errors::get_exception(context)
+            .unwrap_or_else(|| JsError::new_str("could not convert BigInt to i64")));
+    }
+    // JS_ToBigInt64 wraps around (like BigInt.asIntN(64, v)) so check if the value was truncated
+    let s = bigints::to_string(context, value_ref)?;
+    if s != i.to_string() {
+        return Err(JsError::new_string(format!(
+            "BigInt {s} does not fit in an i64"
+        )));
+    }
+    Ok(i)
+}
+
 #[cfg(test)]
 pub mod tests {
 
     use crate::facades::tests::init_test_rt;
     use crate::jsutils::Script;
+    use crate::quickjs_utils::primitives::{
+        from_bool, from_f64, from_i32, from_i64_q, from_string_q, from_u64_q, is_big_int, to_bool,
+        to_i64_q, to_string_q, to_string_strict_q, to_truthy_q,
+    };
 
     #[tokio::test]
     async fn test_emoji() {
@@ -227,4 +344,118 @@ pub mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_big_int() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            for i in [i64::MAX, i64::MAX - 1, i64::MIN, i64::MIN + 1, 0, -1] {
+                let bi_ref = from_i64_q(q_ctx, i).expect("from failed");
+                assert!(is_big_int(&bi_ref));
+                assert_eq!(to_i64_q(q_ctx, &bi_ref).expect("to failed"), i);
+            }
+
+            let bi_ref = from_u64_q(q_ctx, i64::MAX as u64).expect("from failed");
+            assert_eq!(to_i64_q(q_ctx, &bi_ref).expect("to failed"), i64::MAX);
+            // overflows
+            let bi_ref = from_u64_q(q_ctx, i64::MAX as u64 + 1).expect("from failed");
+            assert!(to_i64_q(q_ctx, &bi_ref).is_err());
+            let bi_ref = from_u64_q(q_ctx, u64::MAX).expect("from failed");
+            assert!(to_i64_q(q_ctx, &bi_ref).is_err());
+
+            let bi_ref = q_ctx
+                .eval(Script::new("test_big_int.js", "-9223372036854775807n - 1n"))
+                .expect("script failed");
+            assert!(is_big_int(&bi_ref));
+            assert_eq!(to_i64_q(q_ctx, &bi_ref).expect("to failed"), i64::MIN);
+            let bi_ref = q_ctx
+                .eval(Script::new("test_big_int2.js", "-9223372036854775809n"))
+                .expect("script failed");
+            assert!(to_i64_q(q_ctx, &bi_ref).is_err());
+
+            assert!(!is_big_int(&from_i32(12)));
+            assert!(to_i64_q(q_ctx, &from_i32(12)).is_err());
+        });
+    }
+
+    #[test]
+    fn test_strings() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            for input in ["", "hi", "héllo wörld", "日本語", "pre\0post", "\0", "👍🏽 𝄞"]
+            {
+                let str_ref = from_string_q(q_ctx, input).expect("from failed");
+                assert_eq!(to_string_q(q_ctx, &str_ref).expect("to failed"), input);
+                assert_eq!(
+                    to_string_strict_q(q_ctx, &str_ref).expect("to failed"),
+                    input
+                );
+            }
+
+            // surrogate pairs are converted to a single char, lengths are in UTF-16 code units
+            let res = q_ctx
+                .eval(Script::new(
+                    "test_strings.js",
+                    "let s = '\\uD834\\uDD1E' + String.fromCodePoint(0x1F44D); [s, s.length];",
+                ))
+                .expect("script failed");
+            let s_ref = crate::quickjs_utils::arrays::get_element_q(q_ctx, &res, 0).unwrap();
+            let len_ref = crate::quickjs_utils::arrays::get_element_q(q_ctx, &res, 1).unwrap();
+            assert_eq!(to_string_strict_q(q_ctx, &s_ref).expect("to failed"), "𝄞👍");
+            assert_eq!(len_ref.to_i32(), 4);
+
+            let str_ref = from_string_q(q_ctx, "a\0b").expect("from failed");
+            let len = crate::quickjs_utils::objects::get_property_q(q_ctx, &str_ref, "length")
+                .expect("get failed");
+            assert_eq!(len.to_i32(), 3);
+
+            // a lone surrogate is not valid UTF-8
+            let lone_ref = q_ctx
+                .eval(Script::new("test_strings2.js", "'a\\uD834b'"))
+                .expect("script failed");
+            assert_eq!(
+                to_string_q(q_ctx, &lone_ref)
+                    .expect("to failed")
+                    .chars()
+                    .next(),
+                Some('a')
+            );
+            assert!(to_string_q(q_ctx, &lone_ref)
+                .expect("to failed")
+                .contains('\u{FFFD}'));
+            assert!(to_string_strict_q(q_ctx, &lone_ref).is_err());
+        });
+    }
+
+    #[test]
+    fn test_bools() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            assert!(to_bool(&from_bool(true)).unwrap());
+            assert!(!to_bool(&from_bool(false)).unwrap());
+            assert!(to_bool(&from_i32(1)).is_err());
+            assert!(to_bool(&crate::quickjs_utils::new_null_ref()).is_err());
+
+            assert!(to_truthy_q(q_ctx, &from_bool(true)));
+            assert!(!to_truthy_q(q_ctx, &from_bool(false)));
+            assert!(!to_truthy_q(q_ctx, &from_i32(0)));
+            assert!(to_truthy_q(q_ctx, &from_i32(-1)));
+            assert!(!to_truthy_q(q_ctx, &from_f64(f64::NAN)));
+            assert!(!to_truthy_q(q_ctx, &from_string_q(q_ctx, "").unwrap()));
+            assert!(to_truthy_q(q_ctx, &from_string_q(q_ctx, "0").unwrap()));
+            assert!(!to_truthy_q(q_ctx, &crate::quickjs_utils::new_null_ref()));
+            assert!(!to_truthy_q(
+                q_ctx,
+                &crate::quickjs_utils::new_undefined_ref()
+            ));
+            assert!(!to_truthy_q(q_ctx, &from_i64_q(q_ctx, 0).unwrap()));
+            let arr_ref = q_ctx
+                .eval(Script::new("test_bools.js", "[]"))
+                .expect("script failed");
+            assert!(to_truthy_q(q_ctx, &arr_ref));
+        });
+    }
 }
@@ -1,35 +1,172 @@
 use crate::jsutils::JsError;
 use crate::quickjs_utils;
 use crate::quickjs_utils::errors::get_stack;
-use crate::quickjs_utils::functions;
-use crate::quickjs_utils::objects::is_instance_of_by_name;
+use crate::quickjs_utils::{arrays, errors, functions, objects};
 use crate::quickjsrealmadapter::QuickJsRealmAdapter;
 use crate::quickjsruntimeadapter::QuickJsRuntimeAdapter;
 use crate::quickjsvalueadapter::QuickJsValueAdapter;
+use crate::values::JsValueFacade;
 use libquickjs_sys as q;
+use std::future::Future;
 
 pub fn is_promise_q(context: &QuickJsRealmAdapter, obj_ref: &QuickJsValueAdapter) -> bool {
     unsafe { is_promise(context.context, obj_ref) }
 }
 
 #[allow(dead_code)]
+/// check if a value is a Promise, this also detects Promises which were created in another realm
 /// # Safety
 /// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
 pub unsafe fn is_promise(context: *mut q::JSContext, obj_ref: &QuickJsValueAdapter) -> bool {
-    is_instance_of_by_name(context, obj_ref, "Promise").expect("could not check instance_of")
+    // JS_PromiseState returns -1 (as unsigned) for values which are not a Promise
+    obj_ref.is_object()
+        && q::JS_PromiseState(context, *obj_ref.borrow_value())
+            <= q::JSPromiseStateEnum_JS_PROMISE_REJECTED
 }
 
+/// check if a value is a Promise or a thenable (an object with a callable then property)
+pub fn is_thenable_q(q_ctx: &QuickJsRealmAdapter, obj_ref: &QuickJsValueAdapter) -> bool {
+    unsafe { is_thenable(q_ctx.context, obj_ref) }
+}
+
+/// check if a value is a Promise or a thenable (an object with a callable then property)
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn is_thenable(context: *mut q::JSContext, obj_ref: &QuickJsValueAdapter) -> bool {
+    if is_promise(context, obj_ref) {
+        return true;
+    }
+    if !obj_ref.is_object() {
+        return false;
+    }
+    match objects::get_property(context, obj_ref, "then") {
+        Ok(then_ref) => functions::is_function(context, &then_ref),
+        Err(_) => false,
+    }
+}
+
+/// get a native Promise for a value, Promises are returned as is, thenables and other values are wrapped with Promise.resolve()
+pub fn resolve_to_native_promise_q(
+    q_ctx: &QuickJsRealmAdapter,
+    value: &QuickJsValueAdapter,
+) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { resolve_to_native_promise(q_ctx.context, value) }
+}
+
+/// get a native Promise for a value, Promises are returned as is, thenables and other values are wrapped with Promise.resolve()
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn resolve_to_native_promise(
+    context: *mut q::JSContext,
+    value: &QuickJsValueAdapter,
+) -> Result<QuickJsValueAdapter, JsError> {
+    if is_promise(context, value) {
+        return Ok(value.clone());
+    }
+    let promise_constructor = quickjs_utils::get_constructor(context, "Promise")?;
+    functions::invoke_member_function(
+        context,
+        &promise_constructor,
+        "resolve",
+        std::slice::from_ref(value),
+    )
+}
+
+/// the state of a Promise, a settled Promise holds the value it was resolved or rejected with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromiseState {
+    Pending,
+    Fulfilled(QuickJsValueAdapter),
+    Rejected(QuickJsValueAdapter),
+}
+
+/// get the state of a Promise without adding reactions to it
+pub fn get_promise_state_q(
+    q_ctx: &QuickJsRealmAdapter,
+    promise: &QuickJsValueAdapter,
+) -> Result<PromiseState, JsError> {
+    unsafe { get_promise_state(q_ctx.context, promise) }
+}
+
+/// get the state of a Promise without adding reactions to it
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn get_promise_state(
+    context: *mut q::JSContext,
+    promise: &QuickJsValueAdapter,
+) -> Result<PromiseState, JsError> {
+    if !is_promise(context, promise) {
+        return Err(JsError::new_str("not a Promise"));
+    }
+    let state = q::JS_PromiseState(context, *promise.borrow_value());
+    let get_result = || {
+        let val = q::JS_PromiseResult(context, *promise.borrow_value());
+        QuickJsValueAdapter::new(context, val, false, true, "promises::get_promise_state")
+    };
+    #[allow(non_upper_case_globals)]
+    match state {
+        q::JSPromiseStateEnum_JS_PROMISE_PENDING => Ok(PromiseState::Pending),
+        q::JSPromiseStateEnum_JS_PROMISE_FULFILLED => Ok(PromiseState::Fulfilled(get_result())),
+        q::JSPromiseStateEnum_JS_PROMISE_REJECTED => Ok(PromiseState::Rejected(get_result())),
+        _ => Err(JsError::new_str("not a Promise")),
+    }
+}
+
+/// a Promise created from rust along with its resolve and reject functions
+/// the adapter may be cached (see QuickJsRealmAdapter::cache_promise) and resolved in a later event loop task
 pub struct QuickJsPromiseAdapter {
     promise_obj_ref: QuickJsValueAdapter,
     reject_function_obj_ref: QuickJsValueAdapter,
     resolve_function_obj_ref: QuickJsValueAdapter,
+    reject_on_drop: bool,
 }
-#[allow(dead_code)]
+
 impl QuickJsPromiseAdapter {
+    /// when set the Promise is rejected with an AbandonedError when this QuickJsPromiseAdapter is dropped while the Promise is still pending
+    /// clones of this adapter do not inherit the flag
+    /// please note that the adapter must be dropped before the context it was created in
+    pub fn set_reject_on_drop(&mut self, reject_on_drop: bool) {
+        self.reject_on_drop = reject_on_drop;
+    }
+
+    /// reject the Promise with an AbandonedError if it is still pending
+    pub fn abandon_q(&self, q_ctx: &QuickJsRealmAdapter) -> Result<(), JsError> {
+        unsafe { self.abandon(q_ctx.context) }
+    }
+
+    /// reject the Promise with an AbandonedError if it is still pending
+    /// # Safety
+    /// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+    pub unsafe fn abandon(&self, context: *mut q::JSContext) -> Result<(), JsError> {
+        if let PromiseState::Pending = self.get_state_ctx(context)? {
+            log::trace!("PromiseRef.abandon()");
+            let err = errors::new_error(
+                context,
+                "AbandonedError",
+                "promise was abandoned before it was settled",
+                "",
+            )?;
+            self.reject(context, err)?;
+        }
+        Ok(())
+    }
+
+    /// get the Promise object which may be returned to script
     pub fn get_promise_obj_ref(&self) -> QuickJsValueAdapter {
         self.promise_obj_ref.clone()
     }
 
+    /// get the resolve function of the Promise, this may be passed to script
+    pub fn get_resolve_function_ref(&self) -> QuickJsValueAdapter {
+        self.resolve_function_obj_ref.clone()
+    }
+
+    /// get the reject function of the Promise, this may be passed to script
+    pub fn get_reject_function_ref(&self) -> QuickJsValueAdapter {
+        self.reject_function_obj_ref.clone()
+    }
+
+    /// resolve the Promise
     pub fn resolve_q(
         &self,
         q_ctx: &QuickJsRealmAdapter,
@@ -53,6 +190,22 @@ impl QuickJsPromiseAdapter {
         )?;
         Ok(())
     }
+    /// resolve the Promise with another Promise (or thenable), the Promise will adopt the state of the inner Promise when it settles
+    /// returns an Err if inner is not a Promise or thenable
+    pub fn resolve_with_promise_q(
+        &self,
+        q_ctx: &QuickJsRealmAdapter,
+        inner: &QuickJsValueAdapter,
+    ) -> Result<(), JsError> {
+        if !is_thenable_q(q_ctx, inner) {
+            return Err(JsError::new_str(
+                "value to adopt is not a Promise or thenable",
+            ));
+        }
+        // the resolve function of a promise capability adopts the state of thenables
+        self.resolve_q(q_ctx, inner.clone())
+    }
+    /// reject the Promise
     pub fn reject_q(
         &self,
         q_ctx: &QuickJsRealmAdapter,
@@ -78,12 +231,52 @@ impl QuickJsPromiseAdapter {
     }
 }
 
+impl QuickJsPromiseAdapter {
+    /// get the current state of the Promise
+    pub fn get_state(&self, q_ctx: &QuickJsRealmAdapter) -> Result<PromiseState, JsError> {
+        unsafe { self.get_state_ctx(q_ctx.context) }
+    }
+    /// get the current state of the Promise
+    /// # Safety
+    /// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+    pub unsafe fn get_state_ctx(
+        &self,
+        context: *mut q::JSContext,
+    ) -> Result<PromiseState, JsError> {
+        get_promise_state(context, &self.promise_obj_ref)
+    }
+    /// get the value the Promise was resolved or rejected with, None if the Promise is still pending
+    pub fn get_result(&self, q_ctx: &QuickJsRealmAdapter) -> Option<QuickJsValueAdapter> {
+        unsafe { self.get_result_ctx(q_ctx.context) }
+    }
+    /// get the value the Promise was resolved or rejected with, None if the Promise is still pending
+    /// # Safety
+    /// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+    pub unsafe fn get_result_ctx(&self, context: *mut q::JSContext) -> Option<QuickJsValueAdapter> {
+        match self.get_state_ctx(context) {
+            Ok(PromiseState::Fulfilled(val)) | Ok(PromiseState::Rejected(val)) => Some(val),
+            _ => None,
+        }
+    }
+}
+
 impl Clone for QuickJsPromiseAdapter {
     fn clone(&self) -> Self {
         Self {
             promise_obj_ref: self.promise_obj_ref.clone(),
             reject_function_obj_ref: self.reject_function_obj_ref.clone(),
             resolve_function_obj_ref: self.resolve_function_obj_ref.clone(),
+            reject_on_drop: false,
+        }
+    }
+}
+
+impl Drop for QuickJsPromiseAdapter {
+    fn drop(&mut self) {
+        if self.reject_on_drop {
+            if let Err(e) = unsafe { self.abandon(self.promise_obj_ref.context) } {
+                log::error!("could not reject abandoned promise: {}", e);
+            }
         }
     }
 }
@@ -161,9 +354,226 @@ pub unsafe fn new_promise(context: *mut q::JSContext) -> Result<QuickJsPromiseAd
         promise_obj_ref,
         reject_function_obj_ref,
         resolve_function_obj_ref,
+        reject_on_drop: false,
+    })
+}
+
+/// create a Promise which resolves when all of the passed promises have resolved (see Promise.all)
+/// an empty Vec results in an already resolved Promise
+pub fn all_q(
+    q_ctx: &QuickJsRealmAdapter,
+    promises: Vec<QuickJsValueAdapter>,
+) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { all(q_ctx.context, promises) }
+}
+
+/// create a Promise which resolves when all of the passed promises have resolved (see Promise.all)
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn all(
+    context: *mut q::JSContext,
+    promises: Vec<QuickJsValueAdapter>,
+) -> Result<QuickJsValueAdapter, JsError> {
+    invoke_promise_static(context, "all", promises)
+}
+
+/// create a Promise which settles as soon as one of the passed promises settles (see Promise.race)
+pub fn race_q(
+    q_ctx: &QuickJsRealmAdapter,
+    promises: Vec<QuickJsValueAdapter>,
+) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { race(q_ctx.context, promises) }
+}
+
+/// create a Promise which settles as soon as one of the passed promises settles (see Promise.race)
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn race(
+    context: *mut q::JSContext,
+    promises: Vec<QuickJsValueAdapter>,
+) -> Result<QuickJsValueAdapter, JsError> {
+    invoke_promise_static(context, "race", promises)
+}
+
+/// create a Promise which resolves when all of the passed promises have settled (see Promise.allSettled)
+/// the Promise resolves to an Array of {status: 'fulfilled', value} or {status: 'rejected', reason} objects
+pub fn all_settled_q(
+    q_ctx: &QuickJsRealmAdapter,
+    promises: Vec<QuickJsValueAdapter>,
+) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { all_settled(q_ctx.context, promises) }
+}
+
+/// create a Promise which resolves when all of the passed promises have settled (see Promise.allSettled)
+/// # Safety
+/// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
+pub unsafe fn all_settled(
+    context: *mut q::JSContext,
+    promises: Vec<QuickJsValueAdapter>,
+) -> Result<QuickJsValueAdapter, JsError> {
+    invoke_promise_static(context, "allSettled", promises)
+}
+
+/// invoke a static method of the Promise constructor with an Array of promises as argument
+/// values which are not a Promise are wrapped with Promise.resolve()
+unsafe fn invoke_promise_static(
+    context: *mut q::JSContext,
+    function_name: &str,
+    promises: Vec<QuickJsValueAdapter>,
+) -> Result<QuickJsValueAdapter, JsError> {
+    log::trace!("promises::invoke_promise_static {}", function_name);
+    let promise_constructor = quickjs_utils::get_constructor(context, "Promise")?;
+    let arr = arrays::create_array(context)?;
+    for (index, promise) in promises.iter().enumerate() {
+        if is_promise(context, promise) {
+            arrays::set_element(context, &arr, index as u32, promise)?;
+        } else {
+            // wrap plain values in a resolved promise
+            let resolved = functions::invoke_member_function(
+                context,
+                &promise_constructor,
+                "resolve",
+                std::slice::from_ref(promise),
+            )?;
+            arrays::set_element(context, &arr, index as u32, &resolved)?;
+        }
+    }
+    functions::invoke_member_function(context, &promise_constructor, function_name, &[arr])
+}
+
+/// convert a Promise to a Future which may be awaited outside of the worker thread
+/// the reactions are added to the Promise immediately so this needs to be called from the worker thread
+/// if the Promise is rejected the Future resolves to an Err, if the realm is dropped before the Promise settles the Future also resolves to an Err
+/// # Example
+/// ```rust
+/// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+/// use quickjs_runtime::jsutils::Script;
+/// use quickjs_runtime::quickjs_utils::promises::promise_to_future_q;
+/// use futures::executor::block_on;
+/// let rt = QuickJsRuntimeBuilder::new().build();
+/// let fut = rt.loop_realm_sync(None, |_rt, realm| {
+///     let prom = realm.eval(Script::new("prom.js", "Promise.resolve(12);")).expect("script failed");
+///     promise_to_future_q(realm, &prom)
+/// });
+/// let res = block_on(fut).expect("promise failed");
+/// assert_eq!(res.get_i32(), 12);
+/// ```
+pub fn promise_to_future_q(
+    q_ctx: &QuickJsRealmAdapter,
+    promise_ref: &QuickJsValueAdapter,
+) -> impl Future<Output = Result<JsValueFacade, JsError>> + Send + 'static {
+    let (tx, rx) = flume::bounded(1);
+    let tx_catch = tx.clone();
+
+    let reg_res = (|| {
+        let then_func = functions::new_function_q(
+            q_ctx,
+            "then",
+            move |q_ctx, _this, args| {
+                let res = q_ctx.to_js_value_facade(&args[0]);
+                let _ = tx.send(res);
+                Ok(quickjs_utils::new_undefined_ref())
+            },
+            1,
+        )?;
+        let catch_func = functions::new_function_q(
+            q_ctx,
+            "catch",
+            move |q_ctx, _this, args| {
+                let _ = tx_catch.send(Err(rejection_to_js_error(q_ctx, &args[0])));
+                Ok(quickjs_utils::new_undefined_ref())
+            },
+            1,
+        )?;
+        add_promise_reactions_q(q_ctx, promise_ref, Some(then_func), Some(catch_func), None)
+    })();
+
+    async move {
+        reg_res?;
+        rx.into_recv_async()
+            .await
+            .map_err(|_| JsError::new_str("realm was dropped before the promise settled"))?
+    }
+}
+
+/// wait for a promise to settle by running pending jobs in the current thread
+/// this can only be used for promises which settle by running jobs (e.g. async functions awaiting other settled promises)
+/// at most max_jobs jobs are run, an error is returned if the promise is still pending after that or when it can not settle because there are no more jobs to run
+/// returns Ok(Ok(value)) if the promise was fulfilled and Ok(Err(reason)) if it was rejected
+/// # Example
+/// ```rust
+/// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+/// use quickjs_runtime::jsutils::Script;
+/// use quickjs_runtime::quickjs_utils::promises::await_promise_blocking_q;
+/// let rt = QuickJsRuntimeBuilder::new().build();
+/// rt.loop_realm_sync(None, |_rt, realm| {
+///     let prom = realm.eval(Script::new("await_blocking.js", "(async function(){await null; return 12;})();")).expect("script failed");
+///     let res = await_promise_blocking_q(realm, &prom, 100).expect("promise did not settle");
+///     assert_eq!(res.expect("promise was rejected").to_i32(), 12);
+/// });
+/// ```
+pub fn await_promise_blocking_q(
+    q_ctx: &QuickJsRealmAdapter,
+    promise_ref: &QuickJsValueAdapter,
+    max_jobs: usize,
+) -> Result<Result<QuickJsValueAdapter, QuickJsValueAdapter>, JsError> {
+    QuickJsRuntimeAdapter::do_with(|q_js_rt| {
+        let mut jobs_run = 0;
+        loop {
+            match get_promise_state_q(q_ctx, promise_ref)? {
+                PromiseState::Fulfilled(val) => return Ok(Ok(val)),
+                PromiseState::Rejected(reason) => return Ok(Err(reason)),
+                PromiseState::Pending => {}
+            }
+            if !q_js_rt.has_pending_jobs() {
+                return Err(JsError::new_str(
+                    "promise is pending and there are no jobs left to settle it",
+                ));
+            }
+            if jobs_run >= max_jobs {
+                return Err(JsError::new_string(format!(
+                    "promise was still pending after running {max_jobs} jobs"
+                )));
+            }
+            if let Err(e) = q_js_rt.run_pending_job() {
+                log::error!("run_pending_job failed: {}", e);
+            }
+            jobs_run += 1;
+        }
     })
 }
 
+/// convert a rejection reason to a JsError, Errors keep their name, message and stack
+pub(crate) fn rejection_to_js_error(
+    q_ctx: &QuickJsRealmAdapter,
+    rejection: &QuickJsValueAdapter,
+) -> JsError {
+    unsafe {
+        if errors::is_error(q_ctx.context, rejection) {
+            errors::error_to_js_error(q_ctx.context, rejection)
+        } else {
+            match functions::call_to_string(q_ctx.context, rejection) {
+                Ok(s) => JsError::new_string(s),
+                Err(e) => e,
+            }
+        }
+    }
+}
+
+/// a handler for promise rejections, see set_promise_rejection_handler
+pub type PromiseRejectionHandler =
+    Box<dyn Fn(&QuickJsRealmAdapter, QuickJsValueAdapter, QuickJsValueAdapter, bool)>;
+
+/// set a handler which is called when a promise is rejected without a rejection handler, or when a rejection handler is added to an already rejected promise
+/// the handler receives the realm, the promise, the rejection reason and a bool indicating if the rejection was handled
+/// when no handler is set unhandled rejections are logged as errors
+pub fn set_promise_rejection_handler(
+    q_js_rt: &mut QuickJsRuntimeAdapter,
+    handler: PromiseRejectionHandler,
+) {
+    q_js_rt.promise_rejection_handler = Some(handler);
+}
+
 pub(crate) fn init_promise_rejection_tracker(q_js_rt: &QuickJsRuntimeAdapter) {
     let tracker: q::JSHostPromiseRejectionTracker = Some(promise_rejection_tracker);
 
@@ -190,6 +600,136 @@ pub fn add_promise_reactions_q(
     }
 }
 
+/// add reactions to a promise which call rust closures directly
+/// on_finally is called after on_resolve or on_reject
+/// the functions which wrap the closures are only referenced by the promise, so the closures are released after the promise settles (and the gc has run)
+/// # Example
+/// ```rust
+/// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+/// use quickjs_runtime::jsutils::Script;
+/// use quickjs_runtime::quickjs_utils::promises::add_promise_reactions_native_q;
+/// use quickjs_runtime::quickjsvalueadapter::QuickJsValueAdapter;
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// let rt = QuickJsRuntimeBuilder::new().build();
+/// rt.loop_realm_sync(None, |q_js_rt, realm| {
+///     let calls = Rc::new(RefCell::new(vec![]));
+///     let (resolved_calls, finally_calls) = (calls.clone(), calls.clone());
+///     let prom = realm.eval(Script::new("native_reactions.js", "Promise.resolve(12);")).expect("script failed");
+///     add_promise_reactions_native_q(
+///         realm,
+///         &prom,
+///         Some(move |_realm: &_, res: QuickJsValueAdapter| resolved_calls.borrow_mut().push(format!("resolved with {}", res.to_i32()))),
+///         None::<fn(&_, _)>,
+///         Some(move |_realm: &_| finally_calls.borrow_mut().push("settled".to_string())),
+///     ).expect("could not add reactions");
+///     q_js_rt.run_pending_jobs_if_any();
+///     assert_eq!(*calls.borrow(), vec!["resolved with 12", "settled"]);
+/// });
+/// ```
+pub fn add_promise_reactions_native_q<R, C, F>(
+    q_ctx: &QuickJsRealmAdapter,
+    promise_obj_ref: &QuickJsValueAdapter,
+    on_resolve: Option<R>,
+    on_reject: Option<C>,
+    on_finally: Option<F>,
+) -> Result<(), JsError>
+where
+    R: Fn(&QuickJsRealmAdapter, QuickJsValueAdapter) + 'static,
+    C: Fn(&QuickJsRealmAdapter, QuickJsValueAdapter) + 'static,
+    F: Fn(&QuickJsRealmAdapter) + 'static,
+{
+    // finally is called from the then and catch functions so we don't need an extra derived promise
+    let on_finally = std::rc::Rc::new(on_finally);
+
+    let then_func = if on_resolve.is_some() || on_finally.is_some() {
+        let on_finally = on_finally.clone();
+        functions::new_function_q(
+            q_ctx,
+            "onResolve",
+            move |q_ctx, _this, args| {
+                if let Some(on_resolve) = on_resolve.as_ref() {
+                    on_resolve(q_ctx, args[0].clone());
+                }
+                if let Some(on_finally) = on_finally.as_ref() {
+                    on_finally(q_ctx);
+                }
+                Ok(quickjs_utils::new_undefined_ref())
+            },
+            1,
+        )?
+    } else {
+        quickjs_utils::new_undefined_ref()
+    };
+    let catch_func = if on_reject.is_some() || on_finally.is_some() {
+        functions::new_function_q(
+            q_ctx,
+            "onReject",
+            move |q_ctx, _this, args| {
+                if let Some(on_reject) = on_reject.as_ref() {
+                    on_reject(q_ctx, args[0].clone());
+                }
+                if let Some(on_finally) = on_finally.as_ref() {
+                    on_finally(q_ctx);
+                }
+                Ok(quickjs_utils::new_undefined_ref())
+            },
+            1,
+        )?
+    } else {
+        quickjs_utils::new_undefined_ref()
+    };
+
+    unsafe {
+        let native_promise = resolve_to_native_promise(q_ctx.context, promise_obj_ref)?;
+        functions::invoke_member_function(
+            q_ctx.context,
+            &native_promise,
+            "then",
+            &[then_func, catch_func],
+        )?;
+    }
+    Ok(())
+}
+
+/// create a derived promise which resolves with the result of the mapper applied to the resolution of the original promise
+/// rejections of the original promise are propagated unchanged, an Err returned by the mapper rejects the derived promise
+/// # Example
+/// ```rust
+/// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+/// use quickjs_runtime::jsutils::Script;
+/// use quickjs_runtime::quickjs_utils::primitives;
+/// use quickjs_runtime::quickjs_utils::promises::map_promise_q;
+/// let rt = QuickJsRuntimeBuilder::new().build();
+/// rt.loop_realm_sync(None, |_rt, realm| {
+///     let prom = realm.eval(Script::new("map_promise.js", "Promise.resolve(12);")).expect("script failed");
+///     let _mapped = map_promise_q(realm, &prom, |_realm, res| {
+///         Ok(primitives::from_i32(res.to_i32() * 2))
+///     }).expect("could not map promise");
+/// });
+/// ```
+pub fn map_promise_q<M>(
+    q_ctx: &QuickJsRealmAdapter,
+    promise_obj_ref: &QuickJsValueAdapter,
+    mapper: M,
+) -> Result<QuickJsValueAdapter, JsError>
+where
+    M: Fn(&QuickJsRealmAdapter, QuickJsValueAdapter) -> Result<QuickJsValueAdapter, JsError>
+        + 'static,
+{
+    // an Err returned by the function is thrown so then() rejects the derived promise with it
+    let then_func = functions::new_function_q(
+        q_ctx,
+        "mapPromise",
+        move |q_ctx, _this, args| mapper(q_ctx, args[0].clone()),
+        1,
+    )?;
+    unsafe {
+        let native_promise = resolve_to_native_promise(q_ctx.context, promise_obj_ref)?;
+        functions::invoke_member_function(q_ctx.context, &native_promise, "then", &[then_func])
+    }
+}
+
 #[allow(dead_code)]
 /// # Safety
 /// When passing a context pointer please make sure the corresponding QuickJsContext is still valid
@@ -200,7 +740,10 @@ pub unsafe fn add_promise_reactions(
     catch_func_obj_ref_opt: Option<QuickJsValueAdapter>,
     finally_func_obj_ref_opt: Option<QuickJsValueAdapter>,
 ) -> Result<(), JsError> {
-    debug_assert!(is_promise(context, promise_obj_ref));
+    debug_assert!(is_thenable(context, promise_obj_ref));
+    // thenables may not have catch and finally methods
+    let native_promise = resolve_to_native_promise(context, promise_obj_ref)?;
+    let promise_obj_ref = &native_promise;
 
     if let Some(then_func_obj_ref) = then_func_obj_ref_opt {
         functions::invoke_member_function(context, promise_obj_ref, "then", &[then_func_obj_ref])?;
@@ -227,49 +770,69 @@ pub unsafe fn add_promise_reactions(
 
 unsafe extern "C" fn promise_rejection_tracker(
     ctx: *mut q::JSContext,
-    _promise: q::JSValue,
+    promise: q::JSValue,
     reason: q::JSValue,
     is_handled: ::std::os::raw::c_int,
     _opaque: *mut ::std::os::raw::c_void,
 ) {
-    if is_handled == 0 {
-        let reason_ref = QuickJsValueAdapter::new(
-            ctx,
-            reason,
-            false,
-            false,
-            "promises::promise_rejection_tracker reason",
-        );
-        let reason_str_res = functions::call_to_string(ctx, &reason_ref);
-        QuickJsRuntimeAdapter::do_with(|rt| {
-            let realm = rt.get_quickjs_context(ctx);
-            let realm_id = realm.get_realm_id();
-            let stack = match get_stack(realm) {
-                Ok(s) => match s.to_string() {
-                    Ok(s) => s,
-                    Err(_) => "".to_string(),
-                },
-                Err(_) => "".to_string(),
-            };
-            match reason_str_res {
-                Ok(reason_str) => {
-                    log::error!(
-                        "[{}] unhandled promise rejection, reason: {}{}",
-                        realm_id,
-                        reason_str,
-                        stack
-                    );
-                }
-                Err(e) => {
-                    log::error!(
-                        "[{}] unhandled promise rejection, could not get reason: {}{}",
-                        realm_id,
-                        e,
-                        stack
-                    );
-                }
-            }
-        });
+    QuickJsRuntimeAdapter::do_with(|rt| {
+        let realm = rt.get_quickjs_context(ctx);
+        if let Some(handler) = rt.promise_rejection_handler.as_ref() {
+            let promise_ref = QuickJsValueAdapter::new(
+                ctx,
+                promise,
+                true,
+                true,
+                "promises::promise_rejection_tracker promise",
+            );
+            let reason_ref = QuickJsValueAdapter::new(
+                ctx,
+                reason,
+                true,
+                true,
+                "promises::promise_rejection_tracker reason",
+            );
+            handler(realm, promise_ref, reason_ref, is_handled != 0);
+        } else if is_handled == 0 {
+            log_unhandled_rejection(realm, reason);
+        }
+    });
+}
+
+unsafe fn log_unhandled_rejection(realm: &QuickJsRealmAdapter, reason: q::JSValue) {
+    let reason_ref = QuickJsValueAdapter::new(
+        realm.context,
+        reason,
+        false,
+        false,
+        "promises::promise_rejection_tracker reason",
+    );
+    let reason_str_res = functions::call_to_string(realm.context, &reason_ref);
+    let realm_id = realm.get_realm_id();
+    let stack = match get_stack(realm) {
+        Ok(s) => match s.to_string() {
+            Ok(s) => s,
+            Err(_) => "".to_string(),
+        },
+        Err(_) => "".to_string(),
+    };
+    match reason_str_res {
+        Ok(reason_str) => {
+            log::error!(
+                "[{}] unhandled promise rejection, reason: {}{}",
+                realm_id,
+                reason_str,
+                stack
+            );
+        }
+        Err(e) => {
+            log::error!(
+                "[{}] unhandled promise rejection, could not get reason: {}{}",
+                realm_id,
+                e,
+                stack
+            );
+        }
     }
 }
 
@@ -277,12 +840,24 @@ unsafe extern "C" fn promise_rejection_tracker(
 pub mod tests {
     use crate::builder::QuickJsRuntimeBuilder;
     use crate::facades::tests::init_test_rt;
-    use crate::jsutils::Script;
-    use crate::quickjs_utils::promises::{add_promise_reactions_q, is_promise_q, new_promise_q};
-    use crate::quickjs_utils::{functions, new_null_ref, primitives};
+    use crate::jsutils::promises::new_resolving_promise;
+    use crate::jsutils::{JsError, Script};
+    use crate::quickjs_utils::objects;
+    use crate::quickjs_utils::objects::set_property_q;
+    use crate::quickjs_utils::promises::{
+        add_promise_reactions_native_q, add_promise_reactions_q, all_q, all_settled_q,
+        await_promise_blocking_q, get_promise_state_q, is_promise_q, is_thenable_q, map_promise_q,
+        new_promise_q, promise_to_future_q, race_q, rejection_to_js_error,
+        resolve_to_native_promise_q, PromiseState,
+    };
+    use crate::quickjs_utils::{errors, functions, get_global_q, new_null_ref, primitives};
+    use crate::quickjsrealmadapter::QuickJsRealmAdapter;
     use crate::quickjsruntimeadapter::QuickJsRuntimeAdapter;
+    use crate::quickjsvalueadapter::QuickJsValueAdapter;
     use crate::values::JsValueFacade;
     use futures::executor::block_on;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
     use std::time::Duration;
 
     #[test]
@@ -384,6 +959,671 @@ pub mod tests {
         log::info!("< new_prom2");
     }
 
+    #[test]
+    fn test_resolve_cached_prom() {
+        let rt = init_test_rt();
+        let id = rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let prom = new_promise_q(q_ctx).expect("could not create promise");
+            let global = get_global_q(q_ctx);
+            set_property_q(q_ctx, &global, "testProm", &prom.get_promise_obj_ref())
+                .expect("could not set prop");
+            q_ctx
+                .eval(Script::new(
+                    "test_resolve_cached_prom.js",
+                    "globalThis.testProm.then((res) => {globalThis.testRes = res;});",
+                ))
+                .expect("script failed");
+            q_ctx.cache_promise(prom)
+        });
+        rt.exe_rt_task_in_event_loop(move |q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let prom = q_ctx
+                .consume_cached_promise(id)
+                .expect("promise was not cached");
+            prom.resolve_q(q_ctx, primitives::from_i32(12))
+                .expect("resolve failed");
+        });
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_resolve_cached_prom2.js", "globalThis.testRes;"),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_i32(), 12);
+    }
+
+    #[test]
+    fn test_resolve_clone() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let prom = new_promise_q(q_ctx).expect("could not create promise");
+            let prom_clone = prom.clone();
+            prom_clone
+                .resolve_q(q_ctx, primitives::from_i32(33))
+                .expect("resolve failed");
+            assert!(matches!(
+                prom.get_state(q_ctx).unwrap(),
+                PromiseState::Fulfilled(_)
+            ));
+
+            // resolve from script
+            let prom = new_promise_q(q_ctx).expect("could not create promise");
+            let func_ref = q_ctx
+                .eval(Script::new(
+                    "test_resolve_clone.js",
+                    "(function(resolve, reject){resolve(44);});",
+                ))
+                .expect("script failed");
+            functions::call_function_q(
+                q_ctx,
+                &func_ref,
+                &[
+                    prom.get_resolve_function_ref(),
+                    prom.get_reject_function_ref(),
+                ],
+                None,
+            )
+            .expect("function failed");
+            assert_eq!(prom.get_result(q_ctx).expect("no result").to_i32(), 44);
+        });
+    }
+
+    #[test]
+    fn test_promise_state() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let prom = new_promise_q(q_ctx).expect("could not create promise");
+            assert_eq!(prom.get_state(q_ctx).unwrap(), PromiseState::Pending);
+            assert!(prom.get_result(q_ctx).is_none());
+            prom.resolve_q(q_ctx, primitives::from_i32(71))
+                .expect("resolve failed");
+            assert!(matches!(
+                prom.get_state(q_ctx).unwrap(),
+                PromiseState::Fulfilled(_)
+            ));
+            let res = prom.get_result(q_ctx).expect("no result");
+            assert_eq!(res.to_i32(), 71);
+
+            let prom = new_promise_q(q_ctx).expect("could not create promise");
+            add_promise_reactions_q(
+                q_ctx,
+                &prom.get_promise_obj_ref(),
+                None,
+                Some(
+                    functions::new_function_q(q_ctx, "testCatch", |_, _, _| Ok(new_null_ref()), 1)
+                        .expect("could not create cb"),
+                ),
+                None,
+            )
+            .expect("could not add reactions");
+            prom.reject_q(q_ctx, primitives::from_i32(13))
+                .expect("reject failed");
+            assert!(matches!(
+                prom.get_state(q_ctx).unwrap(),
+                PromiseState::Rejected(_)
+            ));
+            let res = prom.get_result(q_ctx).expect("no result");
+            assert_eq!(res.to_i32(), 13);
+        });
+    }
+
+    #[test]
+    fn test_all() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let p1 = new_promise_q(q_ctx).expect("could not create promise");
+            let p2 = q_ctx
+                .eval(Script::new("test_all.js", "Promise.resolve(2);"))
+                .expect("script failed");
+            let all_prom =
+                all_q(q_ctx, vec![p1.get_promise_obj_ref(), p2]).expect("all failed");
+            assert!(is_promise_q(q_ctx, &all_prom));
+            let empty_prom = all_q(q_ctx, vec![]).expect("all failed");
+            let global = get_global_q(q_ctx);
+            set_property_q(q_ctx, &global, "allProm", &all_prom).expect("could not set prop");
+            set_property_q(q_ctx, &global, "emptyProm", &empty_prom).expect("could not set prop");
+            q_ctx
+                .eval(Script::new(
+                    "test_all2.js",
+                    "allProm.then((res) => {globalThis.allRes = res.join(',');}); emptyProm.then((res) => {globalThis.emptyRes = res.length;});",
+                ))
+                .expect("script failed");
+            p1.resolve_q(q_ctx, primitives::from_i32(1))
+                .expect("resolve failed");
+        });
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_all3.js", "allRes + '/' + emptyRes;"),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_str(), "1,2/0");
+    }
+
+    #[test]
+    fn test_all_resolving_promises() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let p1 = new_resolving_promise(q_ctx, || Ok(1), |realm, res| realm.create_i32(res))
+                .expect("could not create promise");
+            let p2 = new_resolving_promise(q_ctx, || Ok(2), |realm, res| realm.create_i32(res))
+                .expect("could not create promise");
+            let all_prom = all_q(q_ctx, vec![p1, p2, primitives::from_i32(3)]).expect("all failed");
+            let global = get_global_q(q_ctx);
+            set_property_q(q_ctx, &global, "allResProm", &all_prom).expect("could not set prop");
+            q_ctx
+                .eval(Script::new(
+                    "test_all_resolving_promises.js",
+                    "allResProm.then((res) => {globalThis.allResRes = res.join(',');});",
+                ))
+                .expect("script failed");
+        });
+        std::thread::sleep(Duration::from_millis(100));
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_all_resolving_promises2.js", "allResRes;"),
+            )
+            .expect("script failed");
+        assert_eq!(res.get_str(), "1,2,3");
+    }
+
+    #[test]
+    fn test_race_and_all_settled() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let p1 = new_promise_q(q_ctx).expect("could not create promise");
+            let p2 = new_promise_q(q_ctx).expect("could not create promise");
+            let p3 = q_ctx
+                .eval(Script::new("test_race.js", "Promise.resolve(3);"))
+                .expect("script failed");
+            let race_prom = race_q(q_ctx, vec![p1.get_promise_obj_ref(), p2.get_promise_obj_ref()])
+                .expect("race failed");
+            let settled_prom = all_settled_q(
+                q_ctx,
+                vec![p1.get_promise_obj_ref(), p2.get_promise_obj_ref(), p3],
+            )
+            .expect("allSettled failed");
+            let global = get_global_q(q_ctx);
+            set_property_q(q_ctx, &global, "raceProm", &race_prom).expect("could not set prop");
+            set_property_q(q_ctx, &global, "settledProm", &settled_prom)
+                .expect("could not set prop");
+            q_ctx
+                .eval(Script::new(
+                    "test_race2.js",
+                    "raceProm.catch((res) => {globalThis.raceRes = 'rejected:' + res;}); settledProm.then((res) => {globalThis.settledRes = JSON.stringify(res);});",
+                ))
+                .expect("script failed");
+            p2.reject_q(q_ctx, primitives::from_i32(2))
+                .expect("reject failed");
+            p1.resolve_q(q_ctx, primitives::from_i32(1))
+                .expect("resolve failed");
+        });
+        let res = rt
+            .eval_sync(
+                None,
+                Script::new("test_race3.js", "raceRes + '/' + settledRes;"),
+            )
+            .expect("script failed");
+        assert_eq!(
+            res.get_str(),
+            "rejected:2/[{\"status\":\"fulfilled\",\"value\":1},{\"status\":\"rejected\",\"reason\":2},{\"status\":\"fulfilled\",\"value\":3}]"
+        );
+    }
+
+    #[test]
+    fn test_promise_rejection_handler() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let rt = QuickJsRuntimeBuilder::new()
+            .set_promise_rejection_handler(move |realm, promise, reason, handled| {
+                assert!(is_promise_q(realm, &promise));
+                let reason_str =
+                    functions::call_to_string_q(realm, &reason).expect("could not convert reason");
+                tx.send(format!("{reason_str}:{handled}"))
+                    .expect("could not send");
+            })
+            .build();
+        rt.eval_sync(
+            None,
+            Script::new(
+                "test_promise_rejection_handler.js",
+                "globalThis.testRejProm = Promise.reject('poof'); 1;",
+            ),
+        )
+        .expect("script failed");
+        rt.eval_sync(
+            None,
+            Script::new(
+                "test_promise_rejection_handler2.js",
+                "testRejProm.catch(() => {}); 1;",
+            ),
+        )
+        .expect("script failed");
+        let res = rx
+            .recv_timeout(Duration::from_secs(1))
+            .expect("no rejection reported");
+        assert_eq!(res, "poof:false");
+        let res = rx
+            .recv_timeout(Duration::from_secs(1))
+            .expect("no handled rejection reported");
+        assert_eq!(res, "poof:true");
+    }
+
+    #[tokio::test]
+    async fn test_promise_to_future() {
+        let rt = init_test_rt();
+        let fut = rt
+            .loop_realm(None, |_rt, realm| {
+                let prom = realm
+                    .eval(Script::new(
+                        "test_promise_to_future.js",
+                        "new Promise((res) => {setTimeout(() => {res(123);}, 50);});",
+                    ))
+                    .expect("script failed");
+                promise_to_future_q(realm, &prom)
+            })
+            .await;
+        let res = tokio::spawn(fut)
+            .await
+            .expect("task failed")
+            .expect("promise failed");
+        assert_eq!(res.get_i32(), 123);
+
+        let fut = rt
+            .loop_realm(None, |_rt, realm| {
+                let prom = realm
+                    .eval(Script::new(
+                        "test_promise_to_future2.js",
+                        "new Promise((res, rej) => {setTimeout(() => {rej(new Error('poof'));}, 50);});",
+                    ))
+                    .expect("script failed");
+                promise_to_future_q(realm, &prom)
+            })
+            .await;
+        let err = fut.await.expect_err("promise should have been rejected");
+        assert_eq!(err.get_message(), "poof");
+    }
+
+    #[test]
+    fn test_await_promise_blocking() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let prom = q_ctx
+                .eval(Script::new(
+                    "test_await_promise_blocking.js",
+                    "(async function(){await null; await Promise.resolve(1); return 42;})();",
+                ))
+                .expect("script failed");
+            let res = await_promise_blocking_q(q_ctx, &prom, 100).expect("did not settle");
+            assert_eq!(res.expect("promise was rejected").to_i32(), 42);
+
+            let prom = q_ctx
+                .eval(Script::new(
+                    "test_await_promise_blocking2.js",
+                    "(async function(){await null; throw Error('oops');})();",
+                ))
+                .expect("script failed");
+            let reason = await_promise_blocking_q(q_ctx, &prom, 100)
+                .expect("did not settle")
+                .expect_err("promise was not rejected");
+            assert!(errors::is_error_q(q_ctx, &reason));
+
+            let prom = q_ctx
+                .eval(Script::new(
+                    "test_await_promise_blocking3.js",
+                    "new Promise(() => {});",
+                ))
+                .expect("script failed");
+            assert!(await_promise_blocking_q(q_ctx, &prom, 100).is_err());
+
+            let prom = q_ctx
+                .eval(Script::new(
+                    "test_await_promise_blocking4.js",
+                    "(async function(){await null; await null; return 1;})();",
+                ))
+                .expect("script failed");
+            assert!(await_promise_blocking_q(q_ctx, &prom, 0).is_err());
+        });
+    }
+
+    #[test]
+    fn test_map_promise() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let prom = q_ctx
+                .eval(Script::new(
+                    "test_map_promise.js",
+                    "(async function(){await null; return 3;})();",
+                ))
+                .expect("script failed");
+            let mapped = map_promise_q(q_ctx, &prom, |_q_ctx, res| {
+                Ok(primitives::from_i32(res.to_i32() * 2))
+            })
+            .expect("map failed");
+            let mapped = map_promise_q(q_ctx, &mapped, |q_ctx, res| {
+                primitives::from_string_q(q_ctx, format!("res={}", res.to_i32()).as_str())
+            })
+            .expect("map failed");
+            let res = await_promise_blocking_q(q_ctx, &mapped, 100)
+                .expect("did not settle")
+                .expect("promise was rejected");
+            assert_eq!(res.to_string().expect("not a string"), "res=6");
+
+            // mapper errors reject the derived promise
+            let prom = q_ctx
+                .eval(Script::new("test_map_promise2.js", "Promise.resolve(1);"))
+                .expect("script failed");
+            let mapped = map_promise_q(q_ctx, &prom, |_q_ctx, _res| {
+                Err(JsError::new_str("mapper failed"))
+            })
+            .expect("map failed");
+            let reason = await_promise_blocking_q(q_ctx, &mapped, 100)
+                .expect("did not settle")
+                .expect_err("promise was not rejected");
+            let err = rejection_to_js_error(q_ctx, &reason);
+            assert!(err.get_message().contains("mapper failed"));
+
+            // rejections are propagated unchanged
+            let prom = q_ctx
+                .eval(Script::new(
+                    "test_map_promise3.js",
+                    "Promise.reject(new TypeError('original'));",
+                ))
+                .expect("script failed");
+            let mapped = map_promise_q(q_ctx, &prom, |_q_ctx, res| Ok(res)).expect("map failed");
+            let reason = await_promise_blocking_q(q_ctx, &mapped, 100)
+                .expect("did not settle")
+                .expect_err("promise was not rejected");
+            let err = rejection_to_js_error(q_ctx, &reason);
+            assert_eq!(err.get_name(), "TypeError");
+            assert_eq!(err.get_message(), "original");
+        });
+    }
+
+    #[test]
+    fn test_reject_on_drop() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let func = functions::new_function_q(
+                q_ctx,
+                "makeAbandonedPromise",
+                |q_ctx, _this, _args| {
+                    let mut prom = new_promise_q(q_ctx)?;
+                    prom.set_reject_on_drop(true);
+                    // prom is dropped without being settled
+                    Ok(prom.get_promise_obj_ref())
+                },
+                0,
+            )
+            .expect("could not create function");
+            set_property_q(q_ctx, &get_global_q(q_ctx), "makeAbandonedPromise", &func)
+                .expect("could not set prop");
+            let prom = q_ctx
+                .eval(Script::new(
+                    "test_reject_on_drop.js",
+                    "globalThis.abandonedCaught = null; makeAbandonedPromise().catch((e) => {abandonedCaught = e.name; return 1;});",
+                ))
+                .expect("script failed");
+            let res = await_promise_blocking_q(q_ctx, &prom, 100)
+                .expect("did not settle")
+                .expect("promise was rejected");
+            assert_eq!(res.to_i32(), 1);
+            let caught = q_ctx
+                .eval(Script::new("test_reject_on_drop2.js", "abandonedCaught;"))
+                .expect("script failed");
+            assert_eq!(caught.to_string().unwrap(), "AbandonedError");
+
+            // settled promises and promises without the flag are not rejected
+            let prom = new_promise_q(q_ctx).expect("could not create promise");
+            let prom_obj = prom.get_promise_obj_ref();
+            drop(prom);
+            assert!(matches!(
+                get_promise_state_q(q_ctx, &prom_obj).unwrap(),
+                PromiseState::Pending
+            ));
+            let mut prom = new_promise_q(q_ctx).expect("could not create promise");
+            prom.set_reject_on_drop(true);
+            prom.resolve_q(q_ctx, primitives::from_i32(2))
+                .expect("resolve failed");
+            let prom_obj = prom.get_promise_obj_ref();
+            drop(prom);
+            assert!(matches!(
+                get_promise_state_q(q_ctx, &prom_obj).unwrap(),
+                PromiseState::Fulfilled(_)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_resolve_with_promise() {
+        let rt = init_test_rt();
+        let outer = rt.loop_realm_sync(None, |_rt, q_ctx| {
+            let outer = new_promise_q(q_ctx).expect("could not create promise");
+            let inner = new_resolving_promise(
+                q_ctx,
+                || {
+                    std::thread::sleep(Duration::from_millis(50));
+                    Ok(7)
+                },
+                |_realm, res| Ok(primitives::from_i32(res * 6)),
+            )
+            .expect("could not create promise");
+            assert!(outer
+                .resolve_with_promise_q(q_ctx, &primitives::from_i32(1))
+                .is_err());
+            outer
+                .resolve_with_promise_q(q_ctx, &inner)
+                .expect("could not adopt");
+            // the outer promise waits for the producer of the inner promise
+            assert!(matches!(
+                outer.get_state(q_ctx).unwrap(),
+                PromiseState::Pending
+            ));
+            q_ctx
+                .to_js_value_facade(&outer.get_promise_obj_ref())
+                .expect("could not convert")
+        });
+        let JsValueFacade::JsPromise { cached_promise } = outer else {
+            panic!("not a promise");
+        };
+        let res = cached_promise
+            .get_promise_result_sync()
+            .expect("promise timed out")
+            .expect("promise failed");
+        assert_eq!(res.get_i32(), 42);
+    }
+
+    #[test]
+    fn test_promise_reactions_native() {
+        struct DropCounter(Rc<Cell<usize>>);
+        impl Drop for DropCounter {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let calls = Rc::new(RefCell::new(vec![]));
+            let drops = Rc::new(Cell::new(0));
+
+            let resolved = new_promise_q(q_ctx).expect("could not create promise");
+            let rejected = new_promise_q(q_ctx).expect("could not create promise");
+            let resolved_obj = resolved.get_promise_obj_ref();
+            let rejected_obj = rejected.get_promise_obj_ref();
+            let baseline = (resolved_obj.get_ref_count(), rejected_obj.get_ref_count());
+
+            for prom in [&resolved_obj, &rejected_obj] {
+                let (c1, c2, c3) = (calls.clone(), calls.clone(), calls.clone());
+                let (d1, d2, d3) = (
+                    DropCounter(drops.clone()),
+                    DropCounter(drops.clone()),
+                    DropCounter(drops.clone()),
+                );
+                add_promise_reactions_native_q(
+                    q_ctx,
+                    prom,
+                    Some(
+                        move |_q_ctx: &QuickJsRealmAdapter, val: QuickJsValueAdapter| {
+                            let _d = &d1;
+                            c1.borrow_mut().push(format!("resolved:{}", val.to_i32()));
+                        },
+                    ),
+                    Some(
+                        move |_q_ctx: &QuickJsRealmAdapter, val: QuickJsValueAdapter| {
+                            let _d = &d2;
+                            c2.borrow_mut().push(format!("rejected:{}", val.to_i32()));
+                        },
+                    ),
+                    Some(move |_q_ctx: &QuickJsRealmAdapter| {
+                        let _d = &d3;
+                        c3.borrow_mut().push("finally".to_string());
+                    }),
+                )
+                .expect("could not add reactions");
+            }
+
+            resolved
+                .resolve_q(q_ctx, primitives::from_i32(1))
+                .expect("could not resolve");
+            rejected
+                .reject_q(q_ctx, primitives::from_i32(2))
+                .expect("could not reject");
+            q_js_rt.run_pending_jobs_if_any();
+            q_js_rt.gc();
+
+            assert_eq!(
+                *calls.borrow(),
+                vec!["resolved:1", "finally", "rejected:2", "finally"]
+            );
+            assert_eq!(drops.get(), 6);
+            assert_eq!(
+                (resolved_obj.get_ref_count(), rejected_obj.get_ref_count()),
+                baseline
+            );
+        });
+    }
+
+    #[test]
+    fn test_thenables() {
+        let rt = init_test_rt();
+        rt.create_context("test_thenables_realm")
+            .expect("could not create realm");
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let other_realm = q_js_rt
+                .get_realm("test_thenables_realm")
+                .expect("no such realm");
+
+            let foreign_prom = other_realm
+                .eval(Script::new("test_thenables.js", "Promise.resolve(1);"))
+                .expect("script failed");
+            assert!(is_promise_q(q_ctx, &foreign_prom));
+            assert!(is_thenable_q(q_ctx, &foreign_prom));
+
+            let thenable = q_ctx
+                .eval(Script::new(
+                    "test_thenables2.js",
+                    "({then: function(resolve) {resolve(7);}});",
+                ))
+                .expect("script failed");
+            assert!(!is_promise_q(q_ctx, &thenable));
+            assert!(is_thenable_q(q_ctx, &thenable));
+
+            let not_thenable = q_ctx
+                .eval(Script::new("test_thenables3.js", "({then: 12});"))
+                .expect("script failed");
+            assert!(!is_thenable_q(q_ctx, &not_thenable));
+            assert!(!is_thenable_q(q_ctx, &primitives::from_i32(3)));
+
+            let native = resolve_to_native_promise_q(q_ctx, &thenable).expect("could not wrap");
+            assert!(is_promise_q(q_ctx, &native));
+            let res = await_promise_blocking_q(q_ctx, &native, 10)
+                .expect("did not settle")
+                .expect("was rejected");
+            assert_eq!(res.to_i32(), 7);
+
+            let native = resolve_to_native_promise_q(q_ctx, &primitives::from_i32(3))
+                .expect("could not wrap");
+            let res = await_promise_blocking_q(q_ctx, &native, 10)
+                .expect("did not settle")
+                .expect("was rejected");
+            assert_eq!(res.to_i32(), 3);
+
+            // reactions may be added to a thenable which has no catch or finally
+            let global = get_global_q(q_ctx);
+            let then_func = functions::new_function_q(
+                q_ctx,
+                "testThen",
+                |q_ctx, _this, args| {
+                    let global = get_global_q(q_ctx);
+                    set_property_q(q_ctx, &global, "thenableRes", &args[0])?;
+                    Ok(new_null_ref())
+                },
+                1,
+            )
+            .expect("could not create func");
+            let catch_func =
+                functions::new_function_q(q_ctx, "testCatch", |_, _, _| Ok(new_null_ref()), 1)
+                    .expect("could not create func");
+            add_promise_reactions_q(q_ctx, &thenable, Some(then_func), Some(catch_func), None)
+                .expect("could not add reactions");
+            q_js_rt.run_pending_jobs_if_any();
+            let res = objects::get_property_q(q_ctx, &global, "thenableRes").expect("no res");
+            assert_eq!(res.to_i32(), 7);
+        });
+    }
+
+    #[test]
+    fn test_get_promise_state() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            q_ctx
+                .eval(Script::new(
+                    "test_get_promise_state.js",
+                    "globalThis.pendingProm = new Promise(() => {}); globalThis.resProm = Promise.resolve(1).then((v) => v + 1); globalThis.rejProm = Promise.resolve(1).then(() => {throw Error('poof');}); rejProm.catch(() => {});",
+                ))
+                .expect("script failed");
+        });
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let global = get_global_q(q_ctx);
+
+            let prom = objects::get_property_q(q_ctx, &global, "pendingProm").unwrap();
+            assert_eq!(
+                get_promise_state_q(q_ctx, &prom).unwrap(),
+                PromiseState::Pending
+            );
+
+            let prom = objects::get_property_q(q_ctx, &global, "resProm").unwrap();
+            match get_promise_state_q(q_ctx, &prom).unwrap() {
+                PromiseState::Fulfilled(val) => assert_eq!(val.to_i32(), 2),
+                state => panic!("unexpected state {state:?}"),
+            }
+
+            let prom = objects::get_property_q(q_ctx, &global, "rejProm").unwrap();
+            match get_promise_state_q(q_ctx, &prom).unwrap() {
+                PromiseState::Rejected(val) => {
+                    assert!(crate::quickjs_utils::errors::is_error_q(q_ctx, &val))
+                }
+                state => panic!("unexpected state {state:?}"),
+            }
+
+            assert!(get_promise_state_q(q_ctx, &primitives::from_i32(1)).is_err());
+        });
+    }
+
     #[test]
     fn test_promise_reactions() {
         log::info!("> test_promise_reactions");
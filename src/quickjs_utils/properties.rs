@@ -1,6 +1,7 @@
 use crate::jsutils::JsError;
 use crate::quickjs_utils::atoms;
 use crate::quickjs_utils::atoms::JSAtomRef;
+use crate::quickjsvalueadapter::QuickJsValueAdapter;
 use libquickjs_sys as q;
 use std::os::raw::c_int;
 
@@ -47,6 +48,18 @@ impl JSPropertyEnumRef {
         let atom = atom as q::JSAtom;
         unsafe { Ok(atoms::to_str(self.context, &atom)?.to_string()) }
     }
+    /// check if the property key at index is a Symbol instead of a string
+    pub fn is_symbol(&self, index: u32) -> bool {
+        let atom: *mut q::JSAtom = unsafe { self.get_atom_raw(index) };
+        let key_ref = QuickJsValueAdapter::new(
+            self.context,
+            unsafe { q::JS_AtomToValue(self.context, atom as q::JSAtom) },
+            false,
+            true,
+            "JSPropertyEnumRef::is_symbol",
+        );
+        key_ref.get_tag() == q::JS_TAG_SYMBOL as i64
+    }
     pub fn is_enumerable(&self, index: u32) -> bool {
         if index >= self.length {
             panic!("index out of bounds");
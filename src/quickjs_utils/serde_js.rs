@@ -0,0 +1,853 @@
+//! a serde Serializer and Deserializer which work directly on JS values
+//!
+//! this converts between types which implement Serialize/Deserialize and JS values without an intermediate serde_json::Value or JSON string
+//! # Example
+//! ```rust
+//! use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+//! use quickjs_runtime::jsutils::Script;
+//! use quickjs_runtime::quickjs_utils::serde_js::{from_js_value_q, to_js_value_q};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, PartialEq, Debug)]
+//! struct User {
+//!     name: String,
+//!     age: u32,
+//! }
+//!
+//! let rt = QuickJsRuntimeBuilder::new().build();
+//! rt.exe_rt_task_in_event_loop(|q_js_rt| {
+//!     let q_ctx = q_js_rt.get_main_realm();
+//!     let obj_ref = q_ctx.eval(Script::new("user.js", "({name: 'Harry', age: 42});")).unwrap();
+//!     let user: User = from_js_value_q(q_ctx, &obj_ref).unwrap();
+//!     assert_eq!(user, User { name: "Harry".to_string(), age: 42 });
+//!     let user_ref = to_js_value_q(q_ctx, &user).unwrap();
+//!     assert!(user_ref.is_object());
+//! });
+//! ```
+
+use crate::jsutils::{JsError, JsValueType};
+use crate::quickjs_utils;
+use crate::quickjs_utils::{arrays, bigints, dates, objects, primitives};
+use crate::quickjsrealmadapter::QuickJsRealmAdapter;
+use crate::quickjsvalueadapter::QuickJsValueAdapter;
+use serde::de::{DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::{de, ser, Serialize};
+
+/// the max depth of nested objects and arrays, deeper values result in an Err instead of a stack overflow
+pub const MAX_DEPTH: usize = 256;
+
+impl ser::Error for JsError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        JsError::new_string(msg.to_string())
+    }
+}
+
+impl de::Error for JsError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        JsError::new_string(msg.to_string())
+    }
+}
+
+/// convert a value which implements Serialize to a JS value
+pub fn to_js_value_q<T: Serialize + ?Sized>(
+    q_ctx: &QuickJsRealmAdapter,
+    value: &T,
+) -> Result<QuickJsValueAdapter, JsError> {
+    value.serialize(JsValueSerializer::new(q_ctx))
+}
+
+/// convert a JS value to a value which implements Deserialize
+pub fn from_js_value_q<T: DeserializeOwned>(
+    q_ctx: &QuickJsRealmAdapter,
+    value_ref: &QuickJsValueAdapter,
+) -> Result<T, JsError> {
+    T::deserialize(JsValueDeserializer::new(q_ctx, value_ref.clone()))
+}
+
+fn from_i64(i: i64) -> QuickJsValueAdapter {
+    match i32::try_from(i) {
+        Ok(i) => primitives::from_i32(i),
+        Err(_) => primitives::from_f64(i as f64),
+    }
+}
+
+fn from_u64(u: u64) -> QuickJsValueAdapter {
+    match i32::try_from(u) {
+        Ok(i) => primitives::from_i32(i),
+        Err(_) => primitives::from_f64(u as f64),
+    }
+}
+
+/// wrap a value in an object with a single property, this is how enum variants with data are represented
+fn wrap_variant(
+    q_ctx: &QuickJsRealmAdapter,
+    variant: &str,
+    value_ref: &QuickJsValueAdapter,
+) -> Result<QuickJsValueAdapter, JsError> {
+    let obj_ref = objects::create_object_q(q_ctx)?;
+    objects::set_property_q(q_ctx, &obj_ref, variant, value_ref)?;
+    Ok(obj_ref)
+}
+
+/// a serde Serializer which produces JS values
+/// structs and maps become objects, sequences and tuples become arrays and None or unit become null
+/// enum variants with data become an object with the variant name as single property (like serde_json does)
+pub struct JsValueSerializer<'a> {
+    q_ctx: &'a QuickJsRealmAdapter,
+}
+
+impl<'a> JsValueSerializer<'a> {
+    pub fn new(q_ctx: &'a QuickJsRealmAdapter) -> Self {
+        Self { q_ctx }
+    }
+}
+
+impl<'a> ser::Serializer for JsValueSerializer<'a> {
+    type Ok = QuickJsValueAdapter;
+    type Error = JsError;
+    type SerializeSeq = SeqSerializer<'a>;
+    type SerializeTuple = SeqSerializer<'a>;
+    type SerializeTupleStruct = SeqSerializer<'a>;
+    type SerializeTupleVariant = SeqSerializer<'a>;
+    type SerializeMap = MapSerializer<'a>;
+    type SerializeStruct = MapSerializer<'a>;
+    type SerializeStructVariant = MapSerializer<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(primitives::from_bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        Ok(primitives::from_i32(v as i32))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        Ok(primitives::from_i32(v as i32))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        Ok(primitives::from_i32(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(from_i64(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        Ok(primitives::from_i32(v as i32))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        Ok(primitives::from_i32(v as i32))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        Ok(from_u64(v as u64))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        Ok(from_u64(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        Ok(primitives::from_f64(v as f64))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        Ok(primitives::from_f64(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        primitives::from_string_q(self.q_ctx, v.to_string().as_str())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        primitives::from_string_q(self.q_ctx, v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        let arr_ref = arrays::create_array_q(self.q_ctx)?;
+        for (index, b) in (0_u32..).zip(v.iter()) {
+            arrays::set_element_q(
+                self.q_ctx,
+                &arr_ref,
+                index,
+                &primitives::from_i32(*b as i32),
+            )?;
+        }
+        Ok(arr_ref)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(quickjs_utils::new_null_ref())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(quickjs_utils::new_null_ref())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(quickjs_utils::new_null_ref())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        primitives::from_string_q(self.q_ctx, variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        let value_ref = value.serialize(JsValueSerializer::new(self.q_ctx))?;
+        wrap_variant(self.q_ctx, variant, &value_ref)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(SeqSerializer {
+            q_ctx: self.q_ctx,
+            arr_ref: arrays::create_array_q(self.q_ctx)?,
+            index: 0,
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Ok(SeqSerializer {
+            q_ctx: self.q_ctx,
+            arr_ref: arrays::create_array_q(self.q_ctx)?,
+            index: 0,
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(MapSerializer {
+            q_ctx: self.q_ctx,
+            obj_ref: objects::create_object_q(self.q_ctx)?,
+            key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(MapSerializer {
+            q_ctx: self.q_ctx,
+            obj_ref: objects::create_object_q(self.q_ctx)?,
+            key: None,
+            variant: Some(variant),
+        })
+    }
+}
+
+/// serializes sequences, tuples and tuple variants to an Array
+pub struct SeqSerializer<'a> {
+    q_ctx: &'a QuickJsRealmAdapter,
+    arr_ref: QuickJsValueAdapter,
+    index: u32,
+    variant: Option<&'static str>,
+}
+
+impl SeqSerializer<'_> {
+    fn add<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), JsError> {
+        let value_ref = value.serialize(JsValueSerializer::new(self.q_ctx))?;
+        arrays::set_element_q(self.q_ctx, &self.arr_ref, self.index, &value_ref)?;
+        self.index += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<QuickJsValueAdapter, JsError> {
+        match self.variant {
+            Some(variant) => wrap_variant(self.q_ctx, variant, &self.arr_ref),
+            None => Ok(self.arr_ref),
+        }
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer<'_> {
+    type Ok = QuickJsValueAdapter;
+    type Error = JsError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), JsError> {
+        self.add(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer<'_> {
+    type Ok = QuickJsValueAdapter;
+    type Error = JsError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), JsError> {
+        self.add(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer<'_> {
+    type Ok = QuickJsValueAdapter;
+    type Error = JsError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), JsError> {
+        self.add(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer<'_> {
+    type Ok = QuickJsValueAdapter;
+    type Error = JsError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), JsError> {
+        self.add(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+/// serializes maps, structs and struct variants to an Object
+pub struct MapSerializer<'a> {
+    q_ctx: &'a QuickJsRealmAdapter,
+    obj_ref: QuickJsValueAdapter,
+    key: Option<String>,
+    variant: Option<&'static str>,
+}
+
+impl MapSerializer<'_> {
+    fn add<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), JsError> {
+        let value_ref = value.serialize(JsValueSerializer::new(self.q_ctx))?;
+        objects::set_property_q(self.q_ctx, &self.obj_ref, key, &value_ref)
+    }
+
+    fn finish(self) -> Result<QuickJsValueAdapter, JsError> {
+        match self.variant {
+            Some(variant) => wrap_variant(self.q_ctx, variant, &self.obj_ref),
+            None => Ok(self.obj_ref),
+        }
+    }
+}
+
+impl ser::SerializeMap for MapSerializer<'_> {
+    type Ok = QuickJsValueAdapter;
+    type Error = JsError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), JsError> {
+        let key_ref = key.serialize(JsValueSerializer::new(self.q_ctx))?;
+        let key = if key_ref.is_string() {
+            key_ref.to_string()?
+        } else if key_ref.is_i32() {
+            key_ref.to_i32().to_string()
+        } else if key_ref.is_f64() {
+            key_ref.to_f64().to_string()
+        } else if key_ref.is_bool() {
+            key_ref.to_bool().to_string()
+        } else {
+            return Err(JsError::new_str(
+                "map keys must be strings, numbers or booleans",
+            ));
+        };
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), JsError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| JsError::new_str("serialize_value called before serialize_key"))?;
+        self.add(key.as_str(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer<'_> {
+    type Ok = QuickJsValueAdapter;
+    type Error = JsError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), JsError> {
+        self.add(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer<'_> {
+    type Ok = QuickJsValueAdapter;
+    type Error = JsError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), JsError> {
+        self.add(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+/// a serde Deserializer which reads JS values
+/// Dates are deserialized as their ISO-8601 string and integral Numbers as integers
+/// nesting is bounded by [MAX_DEPTH] so cyclic objects result in an Err
+pub struct JsValueDeserializer<'a> {
+    q_ctx: &'a QuickJsRealmAdapter,
+    value_ref: QuickJsValueAdapter,
+    depth: usize,
+}
+
+impl<'a> JsValueDeserializer<'a> {
+    pub fn new(q_ctx: &'a QuickJsRealmAdapter, value_ref: QuickJsValueAdapter) -> Self {
+        Self {
+            q_ctx,
+            value_ref,
+            depth: 0,
+        }
+    }
+
+    fn nested(&self, value_ref: QuickJsValueAdapter) -> Result<Self, JsError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(JsError::new_string(format!(
+                "value is nested deeper than {MAX_DEPTH} levels"
+            )));
+        }
+        Ok(Self {
+            q_ctx: self.q_ctx,
+            value_ref,
+            depth: self.depth + 1,
+        })
+    }
+}
+
+impl<'de> de::Deserializer<'de> for JsValueDeserializer<'_> {
+    type Error = JsError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.value_ref.is_big_int() {
+            let s = bigints::to_string_q(self.q_ctx, &self.value_ref)?;
+            return if let Ok(i) = s.parse::<i64>() {
+                visitor.visit_i64(i)
+            } else if let Ok(u) = s.parse::<u64>() {
+                visitor.visit_u64(u)
+            } else {
+                visitor.visit_string(s)
+            };
+        }
+        match self.value_ref.get_js_type() {
+            JsValueType::Null | JsValueType::Undefined => visitor.visit_unit(),
+            JsValueType::Boolean => visitor.visit_bool(self.value_ref.to_bool()),
+            JsValueType::I32 => visitor.visit_i32(self.value_ref.to_i32()),
+            JsValueType::F64 => {
+                let f = self.value_ref.to_f64();
+                if f.fract() == 0.0 && f.abs() <= 9_007_199_254_740_991f64 {
+                    visitor.visit_i64(f as i64)
+                } else {
+                    visitor.visit_f64(f)
+                }
+            }
+            JsValueType::String => visitor.visit_string(self.value_ref.to_string()?),
+            JsValueType::Array => {
+                let len = arrays::get_length_q(self.q_ctx, &self.value_ref)?;
+                visitor.visit_seq(SeqDeserializer {
+                    de: self,
+                    index: 0,
+                    len,
+                })
+            }
+            JsValueType::Object => {
+                if dates::is_date_q(self.q_ctx, &self.value_ref) {
+                    return visitor
+                        .visit_string(dates::to_iso_string_q(self.q_ctx, &self.value_ref)?);
+                }
+                let entries =
+                    objects::traverse_properties_q(self.q_ctx, &self.value_ref, |k, v| {
+                        Ok((k.to_string(), v.clone()))
+                    })?;
+                visitor.visit_map(MapDeserializer {
+                    de: self,
+                    entries: entries.into_iter(),
+                    value: None,
+                })
+            }
+            other => Err(JsError::new_string(format!(
+                "can not deserialize a {other}"
+            ))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.value_ref.is_null_or_undefined() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if self.value_ref.is_string() {
+            let variant = self.value_ref.to_string()?;
+            return visitor.visit_enum(EnumDeserializer {
+                de: self,
+                variant,
+                value: None,
+            });
+        }
+        if self.value_ref.get_js_type() == JsValueType::Object {
+            let mut entries =
+                objects::traverse_properties_q(self.q_ctx, &self.value_ref, |k, v| {
+                    Ok((k.to_string(), v.clone()))
+                })?;
+            if entries.len() == 1 {
+                let (variant, value_ref) = entries.remove(0);
+                return visitor.visit_enum(EnumDeserializer {
+                    de: self,
+                    variant,
+                    value: Some(value_ref),
+                });
+            }
+        }
+        Err(JsError::new_str(
+            "an enum must be a string or an object with a single property",
+        ))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct SeqDeserializer<'a> {
+    de: JsValueDeserializer<'a>,
+    index: u32,
+    len: u32,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqDeserializer<'_> {
+    type Error = JsError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        if self.index >= self.len {
+            return Ok(None);
+        }
+        let element_ref = arrays::get_element_q(self.de.q_ctx, &self.de.value_ref, self.index)?;
+        self.index += 1;
+        seed.deserialize(self.de.nested(element_ref)?).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some((self.len - self.index) as usize)
+    }
+}
+
+struct MapDeserializer<'a> {
+    de: JsValueDeserializer<'a>,
+    entries: std::vec::IntoIter<(String, QuickJsValueAdapter)>,
+    value: Option<QuickJsValueAdapter>,
+}
+
+impl<'de> de::MapAccess<'de> for MapDeserializer<'_> {
+    type Error = JsError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.entries.next() {
+            Some((key, value_ref)) => {
+                self.value = Some(value_ref);
+                seed.deserialize(IntoDeserializer::<JsError>::into_deserializer(key))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let value_ref = self
+            .value
+            .take()
+            .ok_or_else(|| JsError::new_str("next_value_seed called before next_key_seed"))?;
+        seed.deserialize(self.de.nested(value_ref)?)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct EnumDeserializer<'a> {
+    de: JsValueDeserializer<'a>,
+    variant: String,
+    value: Option<QuickJsValueAdapter>,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for EnumDeserializer<'a> {
+    type Error = JsError;
+    type Variant = VariantDeserializer<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variant =
+            seed.deserialize(IntoDeserializer::<JsError>::into_deserializer(self.variant))?;
+        let value = match self.value {
+            Some(value_ref) => Some(self.de.nested(value_ref)?),
+            None => None,
+        };
+        Ok((variant, VariantDeserializer { value }))
+    }
+}
+
+struct VariantDeserializer<'a> {
+    value: Option<JsValueDeserializer<'a>>,
+}
+
+fn missing_variant_value() -> JsError {
+    JsError::new_str("enum variant has no value")
+}
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer<'_> {
+    type Error = JsError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.value {
+            None => Ok(()),
+            Some(value) => <() as de::Deserialize>::deserialize(value),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        match self.value {
+            Some(value) => seed.deserialize(value),
+            None => Err(missing_variant_value()),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Some(value) => de::Deserializer::deserialize_seq(value, visitor),
+            None => Err(missing_variant_value()),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Some(value) => de::Deserializer::deserialize_map(value, visitor),
+            None => Err(missing_variant_value()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::facades::tests::init_test_rt;
+    use crate::jsutils::Script;
+    use crate::quickjs_utils::serde_js::{from_js_value_q, to_js_value_q};
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Role {
+        Admin,
+        Guest(String),
+        Custom { name: String, level: u8 },
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(rename_all = "camelCase")]
+    struct User {
+        first_name: String,
+        age: u32,
+        score: f64,
+        id: u64,
+        nick: Option<String>,
+        tags: Vec<String>,
+        pos: (i32, i32),
+        roles: Vec<Role>,
+        props: HashMap<String, i32>,
+    }
+
+    #[test]
+    fn test_deserialize_from_eval() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let obj_ref = q_ctx
+                .eval(Script::new(
+                    "test_deserialize_from_eval.js",
+                    "({firstName: 'Harry', age: 42, score: 1.5, id: 5000000000, nick: null, tags: ['a', 'b'], pos: [1, -2], roles: ['Admin', {Guest: 'x'}, {Custom: {name: 'c', level: 3}}], props: {p: 1}});",
+                ))
+                .expect("script failed");
+            let user: User = from_js_value_q(q_ctx, &obj_ref).expect("deserialize failed");
+            assert_eq!(
+                user,
+                User {
+                    first_name: "Harry".to_string(),
+                    age: 42,
+                    score: 1.5,
+                    id: 5_000_000_000,
+                    nick: None,
+                    tags: vec!["a".to_string(), "b".to_string()],
+                    pos: (1, -2),
+                    roles: vec![
+                        Role::Admin,
+                        Role::Guest("x".to_string()),
+                        Role::Custom {
+                            name: "c".to_string(),
+                            level: 3
+                        }
+                    ],
+                    props: HashMap::from([("p".to_string(), 1)]),
+                }
+            );
+
+            // round trip
+            let user_ref = to_js_value_q(q_ctx, &user).expect("serialize failed");
+            let user2: User = from_js_value_q(q_ctx, &user_ref).expect("deserialize failed");
+            assert_eq!(user, user2);
+
+            // wrong types
+            let obj_ref = q_ctx
+                .eval(Script::new(
+                    "test_deserialize_from_eval2.js",
+                    "({firstName: 12});",
+                ))
+                .expect("script failed");
+            assert!(from_js_value_q::<User>(q_ctx, &obj_ref).is_err());
+
+            // cyclic objects
+            let obj_ref = q_ctx
+                .eval(Script::new(
+                    "test_deserialize_from_eval3.js",
+                    "let cyclic = {}; cyclic.self = cyclic; cyclic;",
+                ))
+                .expect("script failed");
+            assert!(from_js_value_q::<serde_json::Value>(q_ctx, &obj_ref).is_err());
+        });
+    }
+
+    #[test]
+    fn test_serialize_to_js() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let role = Role::Custom {
+                name: "c".to_string(),
+                level: 3,
+            };
+            let role_ref = to_js_value_q(q_ctx, &role).expect("serialize failed");
+            let res = q_ctx
+                .invoke_function_by_name(&["JSON"], "stringify", &[role_ref])
+                .expect("stringify failed");
+            assert_eq!(
+                res.to_string().unwrap(),
+                r#"{"Custom":{"name":"c","level":3}}"#
+            );
+        });
+    }
+}
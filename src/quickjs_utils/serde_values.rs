@@ -0,0 +1,399 @@
+//! Utils for converting between serde_json::Value and JS values without going through a JSON string
+//!
+//! this module is only available with the serde_values feature, the conversion differs from
+//! [QuickJsRealmAdapter::value_adapter_to_serde_value] and [QuickJsRealmAdapter::serde_value_to_value_adapter] in these ways
+//! * Dates are converted to an ISO-8601 string (or epoch millis, see [JsonConversionOptions]) instead of an empty object
+//! * BigInts are converted to a Number instead of Null
+//! * integral f64 Numbers up to Number.MAX_SAFE_INTEGER are converted to an integer Value, integers beyond i32 survive a round trip as integers
+//! * objects and arrays nested deeper than [MAX_DEPTH] result in an Err instead of a stack overflow (e.g. for cyclic objects)
+
+use crate::jsutils::{JsError, JsValueType};
+use crate::quickjs_utils::{arrays, bigints, dates, objects, primitives};
+use crate::quickjsrealmadapter::QuickJsRealmAdapter;
+use crate::quickjsvalueadapter::QuickJsValueAdapter;
+use serde_json::Value;
+
+pub use crate::quickjs_utils::serde_js::MAX_DEPTH;
+
+/// Number.MAX_SAFE_INTEGER, integral Numbers up to this value are converted to an integer Value
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991f64;
+
+/// how Date objects are represented in a serde_json::Value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DateRepr {
+    /// an ISO-8601 string like "2011-10-05T14:48:07.123Z"
+    #[default]
+    IsoString,
+    /// a Number of milliseconds since the epoch
+    EpochMillis,
+}
+
+/// options for converting between serde_json::Value and JS values
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonConversionOptions {
+    /// how Dates are converted to a serde_json::Value, invalid Dates are always converted to Null
+    pub dates_as: DateRepr,
+    /// convert strings which look like an ISO-8601 date-time (e.g. "2011-10-05T14:48:07.123Z") to a Date when converting to a JS value
+    pub revive_dates: bool,
+}
+
+/// check if a string looks like an ISO-8601 date-time with a timezone, like the output of Date.prototype.toISOString()
+fn looks_like_iso_date(s: &str) -> bool {
+    let b = s.as_bytes();
+    let digits = |range: std::ops::Range<usize>| range.into_iter().all(|i| b[i].is_ascii_digit());
+    if b.len() < 20
+        || !digits(0..4)
+        || b[4] != b'-'
+        || !digits(5..7)
+        || b[7] != b'-'
+        || !digits(8..10)
+        || b[10] != b'T'
+        || !digits(11..13)
+        || b[13] != b':'
+        || !digits(14..16)
+        || b[16] != b':'
+        || !digits(17..19)
+    {
+        return false;
+    }
+    let mut rest = &b[19..];
+    if rest.first() == Some(&b'.') {
+        let frac_len = rest[1..].iter().take_while(|c| c.is_ascii_digit()).count();
+        if frac_len == 0 {
+            return false;
+        }
+        rest = &rest[1 + frac_len..];
+    }
+    match rest {
+        [b'Z'] => true,
+        [b'+' | b'-', h1, h2, b':', m1, m2] => [h1, h2, m1, m2].iter().all(|c| c.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+/// convert a serde_json::Value to a JS value
+/// integers which do not fit in an i32 are converted to a (f64) Number
+/// # Example
+/// ```rust
+/// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+/// use quickjs_runtime::quickjs_utils::serde_values::{from_serde_value_q, to_serde_value_q};
+/// use serde_json::json;
+/// let rt = QuickJsRuntimeBuilder::new().build();
+/// rt.exe_rt_task_in_event_loop(|q_js_rt| {
+///     let q_ctx = q_js_rt.get_main_realm();
+///     let input = json!({"a": [1, 2.5, "three", null, true]});
+///     let obj_ref = from_serde_value_q(q_ctx, &input).unwrap();
+///     assert_eq!(to_serde_value_q(q_ctx, &obj_ref).unwrap(), input);
+/// });
+/// ```
+pub fn from_serde_value_q(
+    q_ctx: &QuickJsRealmAdapter,
+    value: &Value,
+) -> Result<QuickJsValueAdapter, JsError> {
+    from_serde_value_with_options_q(q_ctx, value, &JsonConversionOptions::default())
+}
+
+/// convert a serde_json::Value to a JS value
+/// when options.revive_dates is true strings which look like an ISO-8601 date-time are converted to a Date
+pub fn from_serde_value_with_options_q(
+    q_ctx: &QuickJsRealmAdapter,
+    value: &Value,
+    options: &JsonConversionOptions,
+) -> Result<QuickJsValueAdapter, JsError> {
+    from_serde_value_depth(q_ctx, value, options, 0)
+}
+
+fn from_serde_value_depth(
+    q_ctx: &QuickJsRealmAdapter,
+    value: &Value,
+    options: &JsonConversionOptions,
+    depth: usize,
+) -> Result<QuickJsValueAdapter, JsError> {
+    if depth > MAX_DEPTH {
+        return Err(JsError::new_string(format!(
+            "value is nested deeper than {MAX_DEPTH} levels"
+        )));
+    }
+    match value {
+        Value::Null => Ok(crate::quickjs_utils::new_null_ref()),
+        Value::Bool(b) => Ok(primitives::from_bool(*b)),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64().and_then(|i| i32::try_from(i).ok()) {
+                Ok(primitives::from_i32(i))
+            } else {
+                // u64, i64 outside of the i32 range or f64
+                Ok(primitives::from_f64(n.as_f64().unwrap_or(f64::NAN)))
+            }
+        }
+        Value::String(s) => {
+            if options.revive_dates && looks_like_iso_date(s) {
+                if let Ok(date_ref) = dates::parse_iso_q(q_ctx, s) {
+                    return Ok(date_ref);
+                }
+            }
+            primitives::from_string_q(q_ctx, s.as_str())
+        }
+        Value::Array(a) => {
+            let arr_ref = arrays::create_array_q(q_ctx)?;
+            for (index, entry) in (0_u32..).zip(a.iter()) {
+                let entry_ref = from_serde_value_depth(q_ctx, entry, options, depth + 1)?;
+                arrays::set_element_q(q_ctx, &arr_ref, index, &entry_ref)?;
+            }
+            Ok(arr_ref)
+        }
+        Value::Object(o) => {
+            let obj_ref = objects::create_object_q(q_ctx)?;
+            for (name, entry) in o {
+                let entry_ref = from_serde_value_depth(q_ctx, entry, options, depth + 1)?;
+                objects::set_property_q(q_ctx, &obj_ref, name.as_str(), &entry_ref)?;
+            }
+            Ok(obj_ref)
+        }
+    }
+}
+
+/// convert a JS value to a serde_json::Value
+/// values which can not be represented in json (undefined, functions, NaN, Infinity) are converted to Null
+/// integral Numbers are converted to an integer Value
+/// Dates are converted to their ISO-8601 string and BigInts to a Number when they fit in an i64 or u64 (else to a String)
+pub fn to_serde_value_q(
+    q_ctx: &QuickJsRealmAdapter,
+    value_ref: &QuickJsValueAdapter,
+) -> Result<Value, JsError> {
+    to_serde_value_with_options_q(q_ctx, value_ref, &JsonConversionOptions::default())
+}
+
+/// convert a JS value to a serde_json::Value
+/// Dates are converted as specified by options.dates_as
+pub fn to_serde_value_with_options_q(
+    q_ctx: &QuickJsRealmAdapter,
+    value_ref: &QuickJsValueAdapter,
+    options: &JsonConversionOptions,
+) -> Result<Value, JsError> {
+    to_serde_value_depth(q_ctx, value_ref, options, 0)
+}
+
+fn to_serde_value_depth(
+    q_ctx: &QuickJsRealmAdapter,
+    value_ref: &QuickJsValueAdapter,
+    options: &JsonConversionOptions,
+    depth: usize,
+) -> Result<Value, JsError> {
+    if depth > MAX_DEPTH {
+        return Err(JsError::new_string(format!(
+            "value is nested deeper than {MAX_DEPTH} levels"
+        )));
+    }
+    if value_ref.is_big_int() {
+        // get_js_type does not detect BigInts
+        let s = bigints::to_string_q(q_ctx, value_ref)?;
+        return if let Ok(i) = s.parse::<i64>() {
+            Ok(Value::from(i))
+        } else if let Ok(u) = s.parse::<u64>() {
+            Ok(Value::from(u))
+        } else {
+            Ok(Value::from(s))
+        };
+    }
+    match value_ref.get_js_type() {
+        JsValueType::I32 => Ok(Value::from(value_ref.to_i32())),
+        JsValueType::F64 => {
+            let f = value_ref.to_f64();
+            if f.fract() == 0.0 && f.abs() <= MAX_SAFE_INTEGER {
+                // integers which did not fit in an i32
+                Ok(Value::from(f as i64))
+            } else {
+                Ok(serde_json::Number::from_f64(f)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null))
+            }
+        }
+        JsValueType::String => Ok(Value::from(value_ref.to_string()?)),
+        JsValueType::Boolean => Ok(Value::from(value_ref.to_bool())),
+        JsValueType::Array => {
+            let len = arrays::get_length_q(q_ctx, value_ref)?;
+            let mut arr = Vec::with_capacity(len as usize);
+            for index in 0..len {
+                let entry_ref = arrays::get_element_q(q_ctx, value_ref, index)?;
+                arr.push(to_serde_value_depth(q_ctx, &entry_ref, options, depth + 1)?);
+            }
+            Ok(Value::Array(arr))
+        }
+        JsValueType::Object => {
+            if dates::is_date_q(q_ctx, value_ref) {
+                let repr = match options.dates_as {
+                    DateRepr::IsoString => {
+                        dates::to_iso_string_q(q_ctx, value_ref).map(Value::from)
+                    }
+                    DateRepr::EpochMillis => dates::get_time_q(q_ctx, value_ref).map(|millis| {
+                        if millis.fract() == 0.0 {
+                            Value::from(millis as i64)
+                        } else {
+                            Value::from(millis)
+                        }
+                    }),
+                };
+                // invalid dates
+                return Ok(repr.unwrap_or(Value::Null));
+            }
+            let mut map = serde_json::Map::new();
+            objects::traverse_properties_q_mut(q_ctx, value_ref, |name, entry_ref| {
+                let entry = to_serde_value_depth(q_ctx, entry_ref, options, depth + 1)?;
+                map.insert(name.to_string(), entry);
+                Ok(())
+            })?;
+            Ok(Value::Object(map))
+        }
+        JsValueType::Null
+        | JsValueType::Undefined
+        | JsValueType::Function
+        | JsValueType::BigInt
+        | JsValueType::Promise
+        | JsValueType::Date
+        | JsValueType::Error => Ok(Value::Null),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::facades::tests::init_test_rt;
+    use crate::jsutils::Script;
+    use crate::quickjs_utils::dates;
+    use crate::quickjs_utils::serde_values::{
+        from_serde_value_q, from_serde_value_with_options_q, looks_like_iso_date, to_serde_value_q,
+        to_serde_value_with_options_q, DateRepr, JsonConversionOptions, MAX_DEPTH,
+    };
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_round_trip() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let input = json!({
+                "name": "test",
+                "nested": {"list": [1, -2, 3.5, {"deep": [null, false]}], "empty": {}},
+                "big": 5_000_000_000_i64,
+                "small": -5_000_000_000_i64,
+                "empty_list": []
+            });
+            let obj_ref = from_serde_value_q(q_ctx, &input).expect("from failed");
+            assert!(obj_ref.is_object());
+            let output = to_serde_value_q(q_ctx, &obj_ref).expect("to failed");
+            assert_eq!(output, input);
+
+            let js_ref = q_ctx
+                .eval(Script::new(
+                    "test_round_trip.js",
+                    "({d: new Date(1317826087123), n: NaN, u: undefined, f: function(){}, b: 12n, a: [1, [2, [3]]]});",
+                ))
+                .expect("script failed");
+            let output = to_serde_value_q(q_ctx, &js_ref).expect("to failed");
+            assert_eq!(
+                output,
+                json!({"d": "2011-10-05T14:48:07.123Z", "n": null, "u": null, "f": null, "b": 12, "a": [1, [2, [3]]]})
+            );
+        });
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let mut deep = Value::Null;
+            for _ in 0..(MAX_DEPTH + 2) {
+                deep = Value::Array(vec![deep]);
+            }
+            assert!(from_serde_value_q(q_ctx, &deep).is_err());
+
+            let js_ref = q_ctx
+                .eval(Script::new(
+                    "test_max_depth.js",
+                    "let o = {}; for (let i = 0; i < 1000; i++) { o = {o}; } o;",
+                ))
+                .expect("script failed");
+            assert!(to_serde_value_q(q_ctx, &js_ref).is_err());
+        });
+    }
+
+    #[test]
+    fn test_dates() {
+        assert!(looks_like_iso_date("1970-01-01T00:00:00.000Z"));
+        assert!(looks_like_iso_date("2011-10-05T14:48:07+02:00"));
+        assert!(!looks_like_iso_date("2011-10-05"));
+        assert!(!looks_like_iso_date("2011-10-05T14:48:07"));
+        assert!(!looks_like_iso_date("2011-10-05T14:48:07.Z"));
+        assert!(!looks_like_iso_date("on 2011-10-05T14:48:07Z"));
+
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let js_ref = q_ctx
+                .eval(Script::new(
+                    "test_dates.js",
+                    "({created: new Date(0), nested: [new Date(1000)], invalid: new Date(NaN)});",
+                ))
+                .expect("script failed");
+
+            let iso = to_serde_value_q(q_ctx, &js_ref).expect("to failed");
+            assert_eq!(
+                iso,
+                json!({"created": "1970-01-01T00:00:00.000Z", "nested": ["1970-01-01T00:00:01.000Z"], "invalid": null})
+            );
+            let millis_options = JsonConversionOptions {
+                dates_as: DateRepr::EpochMillis,
+                ..Default::default()
+            };
+            let millis = to_serde_value_with_options_q(q_ctx, &js_ref, &millis_options)
+                .expect("to failed");
+            assert_eq!(
+                millis,
+                json!({"created": 0, "nested": [1000], "invalid": null})
+            );
+
+            // without revive_dates the strings stay strings
+            let obj_ref = from_serde_value_q(q_ctx, &iso).expect("from failed");
+            let created_ref =
+                crate::quickjs_utils::objects::get_property_q(q_ctx, &obj_ref, "created")
+                    .expect("get failed");
+            assert!(created_ref.is_string());
+
+            let revive_options = JsonConversionOptions {
+                revive_dates: true,
+                ..Default::default()
+            };
+            let obj_ref =
+                from_serde_value_with_options_q(q_ctx, &iso, &revive_options).expect("from failed");
+            let created_ref =
+                crate::quickjs_utils::objects::get_property_q(q_ctx, &obj_ref, "created")
+                    .expect("get failed");
+            assert!(dates::is_date_q(q_ctx, &created_ref));
+            assert_eq!(dates::get_time_q(q_ctx, &created_ref).expect("get_time failed"), 0.0);
+            assert_eq!(to_serde_value_q(q_ctx, &obj_ref).expect("to failed"), iso);
+        });
+    }
+
+    #[test]
+    fn test_realm_conversion_unchanged() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let obj_ref = q_ctx
+                .eval(Script::new(
+                    "test_realm_conversion_unchanged.js",
+                    "({date: new Date(0), big: 12n, f: 2 ** 40});",
+                ))
+                .expect("script failed");
+            // the realm method converts Dates to an empty object, BigInts to null and keeps f64 Numbers as f64
+            assert_eq!(
+                q_ctx.value_adapter_to_serde_value(&obj_ref).unwrap(),
+                json!({"date": {}, "big": null, "f": 1099511627776.0})
+            );
+            assert_eq!(
+                to_serde_value_q(q_ctx, &obj_ref).unwrap(),
+                json!({"date": "1970-01-01T00:00:00.000Z", "big": 12, "f": 1099511627776u64})
+            );
+        });
+    }
+}
@@ -0,0 +1,235 @@
+//! Symbol utils, these methods can be used to create Symbols and get well-known Symbols like Symbol.iterator from rust
+//! see [MDN](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/Symbol) for more on Symbols
+
+use crate::jsutils::JsError;
+use crate::quickjs_utils::{functions, get_global, objects, primitives};
+use crate::quickjsrealmadapter::QuickJsRealmAdapter;
+use crate::quickjsvalueadapter::QuickJsValueAdapter;
+use libquickjs_sys as q;
+
+/// create a new Symbol
+/// when global is true the Symbol is created in (or fetched from) the global Symbol registry with Symbol.for(description)
+/// # Example
+/// ```rust
+/// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+/// use quickjs_runtime::quickjs_utils::symbols::{is_symbol, new_symbol_q};
+///
+/// let rt = QuickJsRuntimeBuilder::new().build();
+/// rt.exe_rt_task_in_event_loop(|q_js_rt| {
+///    let q_ctx = q_js_rt.get_main_realm();
+///    let my_symbol = new_symbol_q(q_ctx, "my_symbol", false).ok().unwrap();
+///    assert!(is_symbol(&my_symbol));
+/// });
+/// ```
+pub fn new_symbol_q(
+    q_ctx: &QuickJsRealmAdapter,
+    description: &str,
+    global: bool,
+) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { new_symbol(q_ctx.context, description, global) }
+}
+
+/// create a new Symbol
+/// when global is true the Symbol is created in (or fetched from) the global Symbol registry with Symbol.for(description)
+/// # Safety
+/// please ensure the passed JSContext is still valid
+pub unsafe fn new_symbol(
+    ctx: *mut q::JSContext,
+    description: &str,
+    global: bool,
+) -> Result<QuickJsValueAdapter, JsError> {
+    let symbol_func = get_symbol_function(ctx)?;
+    let description_ref = primitives::from_string(ctx, description)?;
+    if global {
+        functions::invoke_member_function(ctx, &symbol_func, "for", &[description_ref])
+    } else {
+        functions::call_function(ctx, &symbol_func, &[description_ref], None)
+    }
+}
+
+/// get a well-known Symbol by its name, e.g. "iterator" for Symbol.iterator
+pub fn get_well_known_symbol_q(
+    q_ctx: &QuickJsRealmAdapter,
+    name: &str,
+) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { get_well_known_symbol(q_ctx.context, name) }
+}
+
+/// get a well-known Symbol by its name, e.g. "iterator" for Symbol.iterator
+/// # Safety
+/// please ensure the passed JSContext is still valid
+pub unsafe fn get_well_known_symbol(
+    ctx: *mut q::JSContext,
+    name: &str,
+) -> Result<QuickJsValueAdapter, JsError> {
+    let symbol_func = get_symbol_function(ctx)?;
+    let symbol_ref = objects::get_property(ctx, &symbol_func, name)?;
+    if is_symbol(&symbol_ref) {
+        Ok(symbol_ref)
+    } else {
+        Err(JsError::new_string(format!(
+            "Symbol.{name} is not a well-known Symbol"
+        )))
+    }
+}
+
+/// get Symbol.iterator
+pub fn iterator_q(q_ctx: &QuickJsRealmAdapter) -> Result<QuickJsValueAdapter, JsError> {
+    get_well_known_symbol_q(q_ctx, "iterator")
+}
+
+/// get Symbol.asyncIterator
+pub fn async_iterator_q(q_ctx: &QuickJsRealmAdapter) -> Result<QuickJsValueAdapter, JsError> {
+    get_well_known_symbol_q(q_ctx, "asyncIterator")
+}
+
+/// get the description of a Symbol, returns None for Symbols created without a description
+pub fn get_description_q(
+    q_ctx: &QuickJsRealmAdapter,
+    symbol_ref: &QuickJsValueAdapter,
+) -> Result<Option<String>, JsError> {
+    unsafe { get_description(q_ctx.context, symbol_ref) }
+}
+
+/// get the description of a Symbol, returns None for Symbols created without a description
+/// # Safety
+/// please ensure the passed JSContext is still valid
+pub unsafe fn get_description(
+    ctx: *mut q::JSContext,
+    symbol_ref: &QuickJsValueAdapter,
+) -> Result<Option<String>, JsError> {
+    if !is_symbol(symbol_ref) {
+        return Err(JsError::new_str("value is not a Symbol"));
+    }
+    let description_ref = objects::get_property(ctx, symbol_ref, "description")?;
+    if description_ref.is_string() {
+        Ok(Some(primitives::to_string(ctx, &description_ref)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// check if a value is a Symbol
+pub fn is_symbol(value_ref: &QuickJsValueAdapter) -> bool {
+    value_ref.get_tag() == q::JS_TAG_SYMBOL as i64
+}
+
+unsafe fn get_symbol_function(ctx: *mut q::JSContext) -> Result<QuickJsValueAdapter, JsError> {
+    let global_ref = get_global(ctx);
+    objects::get_property(ctx, &global_ref, "Symbol")
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::facades::tests::init_test_rt;
+    use crate::jsutils::Script;
+    use crate::quickjs_utils::functions::new_function_q;
+    use crate::quickjs_utils::objects::{
+        create_object_q, get_property_by_symbol_q, set_property_by_symbol_q, set_property_q,
+    };
+    use crate::quickjs_utils::primitives::{from_bool, from_i32};
+    use crate::quickjs_utils::symbols::{
+        async_iterator_q, get_description_q, get_well_known_symbol_q, is_symbol, iterator_q,
+        new_symbol_q,
+    };
+    use crate::quickjs_utils::{get_global_q, new_undefined_ref};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_symbols() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let local_ref = new_symbol_q(q_ctx, "test_sym", false).expect("new failed");
+            let local_ref2 = new_symbol_q(q_ctx, "test_sym", false).expect("new failed");
+            let global_ref = new_symbol_q(q_ctx, "test_sym", true).expect("new failed");
+            assert!(is_symbol(&local_ref));
+            assert!(is_symbol(&global_ref));
+            assert!(!is_symbol(&from_i32(1)));
+            assert_eq!(
+                get_description_q(q_ctx, &local_ref).unwrap().as_deref(),
+                Some("test_sym")
+            );
+
+            let global = get_global_q(q_ctx);
+            set_property_q(q_ctx, &global, "local1", &local_ref).unwrap();
+            set_property_q(q_ctx, &global, "local2", &local_ref2).unwrap();
+            set_property_q(q_ctx, &global, "global1", &global_ref).unwrap();
+            let res = q_ctx
+                .eval(Script::new(
+                    "test_symbols.js",
+                    "JSON.stringify([local1 === local2, global1 === Symbol.for('test_sym'), Symbol.keyFor(local1)]);",
+                ))
+                .expect("script failed");
+            assert_eq!(res.to_string().unwrap(), "[false,true,null]");
+
+            let async_iter_ref = async_iterator_q(q_ctx).expect("no asyncIterator");
+            assert_eq!(
+                get_description_q(q_ctx, &async_iter_ref).unwrap().as_deref(),
+                Some("Symbol.asyncIterator")
+            );
+            assert!(get_well_known_symbol_q(q_ctx, "noSuchSymbol").is_err());
+
+            let obj_ref = create_object_q(q_ctx).unwrap();
+            set_property_by_symbol_q(q_ctx, &obj_ref, &local_ref, &from_i32(12)).unwrap();
+            let val_ref = get_property_by_symbol_q(q_ctx, &obj_ref, &local_ref).unwrap();
+            assert_eq!(val_ref.to_i32(), 12);
+            assert!(get_property_by_symbol_q(q_ctx, &obj_ref, &local_ref2)
+                .unwrap()
+                .is_undefined());
+            assert!(set_property_by_symbol_q(q_ctx, &obj_ref, &from_i32(1), &from_i32(1)).is_err());
+        });
+    }
+
+    #[test]
+    fn test_iterator() {
+        let rt = init_test_rt();
+        rt.exe_rt_task_in_event_loop(|q_js_rt| {
+            let q_ctx = q_js_rt.get_main_realm();
+            let host_ref = create_object_q(q_ctx).unwrap();
+            let iterator_func = new_function_q(
+                q_ctx,
+                "iterator",
+                |q_ctx, _this, _args| {
+                    let counter = Rc::new(Cell::new(0));
+                    let next_func = new_function_q(
+                        q_ctx,
+                        "next",
+                        move |q_ctx, _this, _args| {
+                            let result_ref = create_object_q(q_ctx)?;
+                            let current = counter.get();
+                            counter.set(current + 1);
+                            if current < 3 {
+                                set_property_q(q_ctx, &result_ref, "value", &from_i32(current))?;
+                                set_property_q(q_ctx, &result_ref, "done", &from_bool(false))?;
+                            } else {
+                                set_property_q(q_ctx, &result_ref, "value", &new_undefined_ref())?;
+                                set_property_q(q_ctx, &result_ref, "done", &from_bool(true))?;
+                            }
+                            Ok(result_ref)
+                        },
+                        0,
+                    )?;
+                    let iter_ref = create_object_q(q_ctx)?;
+                    set_property_q(q_ctx, &iter_ref, "next", &next_func)?;
+                    Ok(iter_ref)
+                },
+                0,
+            )
+            .unwrap();
+            let iterator_symbol = iterator_q(q_ctx).unwrap();
+            set_property_by_symbol_q(q_ctx, &host_ref, &iterator_symbol, &iterator_func).unwrap();
+
+            let global = get_global_q(q_ctx);
+            set_property_q(q_ctx, &global, "host", &host_ref).unwrap();
+            let res = q_ctx
+                .eval(Script::new(
+                    "test_iterator.js",
+                    "let vals = []; for (const v of host) { vals.push(v); } JSON.stringify([vals, [...host]]);",
+                ))
+                .expect("script failed");
+            assert_eq!(res.to_string().unwrap(), "[[0,1,2],[0,1,2]]");
+        });
+    }
+}
@@ -3,6 +3,7 @@
 //!
 //!
 use crate::jsutils::JsError;
+use crate::quickjs_utils::errors;
 use crate::quickjs_utils::get_constructor;
 use crate::quickjs_utils::objects::{
     construct_object, get_property, get_prototype_of, is_instance_of, is_instance_of_by_name,
@@ -111,6 +112,72 @@ pub unsafe fn new_array_buffer(
     Ok(obj_ref)
 }
 
+/// create a new ArrayBuffer which takes ownership of a Vec without copying its data
+/// the Vec is dropped when the ArrayBuffer is garbage collected (or detached)
+/// unlike with new_array_buffer_q the Vec can not be reclaimed, detach_array_buffer_buffer_q returns a copy of the data for this ArrayBuffer
+pub fn new_array_buffer_from_vec_q(
+    q_ctx: &QuickJsRealmAdapter,
+    data: Vec<u8>,
+) -> Result<QuickJsValueAdapter, JsError> {
+    unsafe { new_array_buffer_from_vec(q_ctx.context, data) }
+}
+
+/// create a new ArrayBuffer which takes ownership of a Vec without copying its data
+/// the Vec is dropped when the ArrayBuffer is garbage collected (or detached)
+/// # Safety
+/// please ensure that the relevant QuickjsRealmAdapter is not dropped while using this function or a result of this function
+pub unsafe fn new_array_buffer_from_vec(
+    ctx: *mut q::JSContext,
+    data: Vec<u8>,
+) -> Result<QuickJsValueAdapter, JsError> {
+    new_array_buffer_owned(ctx, data)
+}
+
+/// create a new ArrayBuffer backed by the bytes of buf, buf is boxed and passed to quickjs as opaque so free_owned_func can drop it
+unsafe fn new_array_buffer_owned<B: AsMut<[u8]> + 'static>(
+    ctx: *mut q::JSContext,
+    buf: B,
+) -> Result<QuickJsValueAdapter, JsError> {
+    log::trace!("new_array_buffer_owned");
+    let boxed = Box::into_raw(Box::new(buf));
+    // the heap allocation of the bytes does not move when the box is passed around
+    let bytes = (*boxed).as_mut();
+
+    #[cfg(target_pointer_width = "64")]
+    let length = bytes.len();
+    #[cfg(target_pointer_width = "32")]
+    let length = bytes.len() as u32;
+
+    let raw = q::JS_NewArrayBuffer(
+        ctx,
+        bytes.as_mut_ptr(),
+        length,
+        Some(free_owned_func::<B>),
+        boxed as _,
+        0,
+    );
+    let obj_ref =
+        QuickJsValueAdapter::new(ctx, raw, false, true, "typedarrays::new_array_buffer_owned");
+    if obj_ref.is_exception() {
+        // quickjs did not take ownership
+        drop(Box::from_raw(boxed));
+        return Err(JsError::new_str("Could not create array buffer"));
+    }
+    Ok(obj_ref)
+}
+
+unsafe extern "C" fn free_owned_func<B>(
+    _rt: *mut q::JSRuntime,
+    opaque: *mut ::std::os::raw::c_void,
+    ptr: *mut ::std::os::raw::c_void,
+) {
+    log::trace!("typedarrays::free_owned_func");
+    // quickjs calls this when detaching and again with a null ptr when a detached buffer is finalized
+    if !opaque.is_null() && !ptr.is_null() {
+        drop(Box::from_raw(opaque as *mut B));
+    }
+}
+
 pub fn is_array_buffer_q(q_ctx: &QuickJsRealmAdapter, buf: &QuickJsValueAdapter) -> bool {
     unsafe { is_array_buffer(q_ctx.context, buf) }
 }
@@ -122,6 +189,7 @@ pub unsafe fn is_array_buffer(ctx: *mut q::JSContext, buf: &QuickJsValueAdapter)
     buf.is_object() && is_instance_of_by_name(ctx, buf, "ArrayBuffer").unwrap_or(false)
 }
 
+/// check if a ref is a TypedArray (e.g. a Uint8Array)
 pub fn is_typed_array_q(q_ctx: &QuickJsRealmAdapter, arr: &QuickJsValueAdapter) -> bool {
     unsafe { is_typed_array(q_ctx.context, arr) }
 }
@@ -180,6 +248,7 @@ pub unsafe fn new_array_buffer_copy(
 }
 
 /// detach the array buffer and return it, after this the TypedArray is no longer usable in JS (or at least all items will return undefined)
+/// the Vec is only returned without copying for ArrayBuffers created with new_array_buffer_q, the data of other ArrayBuffers is copied before they are detached
 pub fn detach_array_buffer_buffer_q(
     q_ctx: &QuickJsRealmAdapter,
     array_buffer: &QuickJsValueAdapter,
@@ -188,6 +257,7 @@ pub fn detach_array_buffer_buffer_q(
 }
 
 /// detach the array buffer and return it, after this the TypedArray is no longer usable in JS (or at least all items will return undefined)
+/// the Vec is only returned without copying for ArrayBuffers created with new_array_buffer_q, the data of other ArrayBuffers is copied before they are detached
 /// # Safety
 /// please ensure that the relevant QuickjsRealmAdapter is not dropped while using this function or a result of this function
 pub unsafe fn detach_array_buffer_buffer(
@@ -198,7 +268,7 @@ pub unsafe fn detach_array_buffer_buffer(
 
     debug_assert!(is_array_buffer(ctx, array_buffer));
 
-    // check if vec is one we buffered, if not we create a new one from the slice we got from quickjs
+    // check if vec is one we buffered, if not we copy the data because the memory is owned by quickjs (or by a Vec passed to new_array_buffer_from_vec_q) and is freed when detaching
     // abuf->opaque seems impossible to get at, so we store the id ourselves as well
     let id_prop = get_property(ctx, array_buffer, "__buffer_id")?;
     let id_opt = if id_prop.is_i32() {
@@ -207,11 +277,14 @@ pub unsafe fn detach_array_buffer_buffer(
         None
     };
 
-    let v = if let Some(id) = id_opt {
+    let buffered = id_opt.and_then(|id| {
         BUFFERS.with(|rc| {
             let buffers = &mut *rc.borrow_mut();
-            buffers.remove(&id)
+            buffers.remove_opt(&id)
         })
+    });
+    let v = if let Some(v) = buffered {
+        v
     } else {
         #[cfg(target_pointer_width = "64")]
         let mut len: usize = 0;
@@ -219,8 +292,13 @@ pub unsafe fn detach_array_buffer_buffer(
         let mut len: u32 = 0;
 
         let ptr = q::JS_GetArrayBuffer(ctx, &mut len, *array_buffer.borrow_value());
+        if ptr.is_null() {
+            // quickjs throws a TypeError for detached buffers
+            return Err(QuickJsRealmAdapter::get_exception(ctx)
+                .unwrap_or_else(|| JsError::new_str("could not get ArrayBuffer data")));
+        }
 
-        Vec::from_raw_parts(ptr, len as _, len as _)
+        std::slice::from_raw_parts(ptr, len as _).to_vec()
     };
 
     q::JS_DetachArrayBuffer(ctx, *array_buffer.borrow_value());
@@ -278,6 +356,98 @@ pub unsafe fn get_array_buffer_buffer_copy(
     }
 }
 
+/// get a copy of the bytes of a TypedArray or an ArrayBuffer, for a TypedArray only the bytes in view of the array are copied
+/// returns an Err if the buffer was detached
+pub fn get_array_buffer_bytes_q(
+    q_ctx: &QuickJsRealmAdapter,
+    typed_array: &QuickJsValueAdapter,
+) -> Result<Vec<u8>, JsError> {
+    with_array_buffer_slice_q(q_ctx, typed_array, |bytes| bytes.to_vec())
+}
+
+/// call a consumer with the bytes of a TypedArray or an ArrayBuffer without copying them
+/// for a TypedArray the slice only contains the bytes in view of the array
+/// returns an Err if the buffer was detached
+pub fn with_array_buffer_slice_q<C, R>(
+    q_ctx: &QuickJsRealmAdapter,
+    typed_array: &QuickJsValueAdapter,
+    consumer: C,
+) -> Result<R, JsError>
+where
+    C: FnOnce(&[u8]) -> R,
+{
+    unsafe { with_array_buffer_slice(q_ctx.context, typed_array, consumer) }
+}
+
+/// call a consumer with the bytes of a TypedArray or an ArrayBuffer without copying them
+/// for a TypedArray the slice only contains the bytes in view of the array
+/// returns an Err if the buffer was detached
+/// # Safety
+/// please ensure that the relevant QuickjsRealmAdapter is not dropped while using this function or a result of this function
+pub unsafe fn with_array_buffer_slice<C, R>(
+    ctx: *mut q::JSContext,
+    typed_array: &QuickJsValueAdapter,
+    consumer: C,
+) -> Result<R, JsError>
+where
+    C: FnOnce(&[u8]) -> R,
+{
+    log::trace!("with_array_buffer_slice");
+
+    #[cfg(target_pointer_width = "64")]
+    let (mut offset, mut length, mut size): (usize, usize, usize) = (0, 0, 0);
+    #[cfg(target_pointer_width = "32")]
+    let (mut offset, mut length, mut size): (u32, u32, u32) = (0, 0, 0);
+
+    let is_buffer = is_array_buffer(ctx, typed_array);
+
+    // the buffer ref keeps the ArrayBuffer alive while the consumer runs
+    let buffer = if is_buffer {
+        typed_array.clone()
+    } else {
+        let raw = q::JS_GetTypedArrayBuffer(
+            ctx,
+            *typed_array.borrow_value(),
+            &mut offset,
+            &mut length,
+            std::ptr::null_mut(),
+        );
+        let buffer = QuickJsValueAdapter::new(
+            ctx,
+            raw,
+            false,
+            true,
+            "typedarrays::with_array_buffer_slice",
+        );
+        if buffer.is_exception() {
+            return Err(errors::get_exception(ctx)
+                .unwrap_or_else(|| JsError::new_str("not a TypedArray or ArrayBuffer")));
+        }
+        buffer
+    };
+
+    let ptr = q::JS_GetArrayBuffer(ctx, &mut size, *buffer.borrow_value());
+    if ptr.is_null() {
+        // e.g. the buffer was detached
+        return Err(errors::get_exception(ctx)
+            .unwrap_or_else(|| JsError::new_str("could not get ArrayBuffer data")));
+    }
+    if !is_buffer {
+        if offset + length > size {
+            return Err(JsError::new_str(
+                "TypedArray is out of bounds of its buffer",
+            ));
+        }
+        size = length;
+    }
+    let slice = if size == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(ptr.add(offset as _), size as _)
+    };
+    Ok(consumer(slice))
+}
+
 /// get the underlying ArrayBuffer of a TypedArray
 pub fn get_array_buffer_q(
     q_ctx: &QuickJsRealmAdapter,
@@ -372,6 +542,7 @@ pub mod tests {
         new_uint8_array_q,
     };
     use crate::values::{JsValueFacade, TypedArrayType};
+    use libquickjs_sys as q;
 
     use crate::facades::tests::init_test_rt;
     use crate::quickjs_utils::objects::set_property_q;
@@ -548,4 +719,180 @@ pub mod tests {
 
         thread::sleep(Duration::from_secs(1));
     }
+
+    #[test]
+    fn test_uint8_array_copy_length() {
+        let rt = init_test_rt();
+        rt.loop_realm_sync(None, |_rt, realm| {
+            let bytes: &[u8] = &[1, 2, 3, 4, 5];
+            let arr = new_uint8_array_copy_q(realm, bytes).expect("could not create array");
+            assert!(is_typed_array_q(realm, &arr));
+            assert!(!is_typed_array_q(realm, &new_undefined_ref()));
+            set_property_q(realm, &get_global_q(realm), "copiedBytes", &arr)
+                .expect("could not set prop");
+            let res = realm
+                .eval(Script::new(
+                    "test_uint8_array_copy_length.js",
+                    "copiedBytes instanceof Uint8Array ? copiedBytes.length * 10 + copiedBytes[4] : -1;",
+                ))
+                .expect("script failed");
+            assert_eq!(res.to_i32(), 55);
+        });
+    }
+
+    #[test]
+    fn test_array_buffer_from_vec() {
+        use crate::quickjs_utils::typedarrays::{
+            new_array_buffer_from_vec_q, new_array_buffer_owned,
+        };
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        struct DropFlag(Vec<u8>, Arc<AtomicBool>);
+        impl AsMut<[u8]> for DropFlag {
+            fn as_mut(&mut self) -> &mut [u8] {
+                self.0.as_mut()
+            }
+        }
+        impl Drop for DropFlag {
+            fn drop(&mut self) {
+                self.1.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let rt = crate::builder::QuickJsRuntimeBuilder::new().build();
+        let dropped = Arc::new(AtomicBool::new(false));
+        let dropped2 = dropped.clone();
+        let res = rt.loop_realm_sync(None, move |_rt, realm| {
+            let ab = new_array_buffer_from_vec_q(realm, vec![7, 8, 9]).expect("could not create");
+            assert!(is_array_buffer_q(realm, &ab));
+            let owned =
+                unsafe { new_array_buffer_owned(realm.context, DropFlag(vec![1, 2, 3], dropped2)) }
+                    .expect("could not create");
+            let global = get_global_q(realm);
+            set_property_q(realm, &global, "vecBuffer", &ab).expect("could not set prop");
+            set_property_q(realm, &global, "ownedBuffer", &owned).expect("could not set prop");
+            realm
+                .eval(Script::new(
+                    "test_array_buffer_from_vec.js",
+                    "new Uint8Array(vecBuffer)[1] * 10 + new Uint8Array(ownedBuffer)[2];",
+                ))
+                .expect("script failed")
+                .to_i32()
+        });
+        assert_eq!(res, 83);
+        assert!(!dropped.load(Ordering::SeqCst));
+        drop(rt);
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_detach_array_buffer_from_vec() {
+        use crate::quickjs_utils::typedarrays::{
+            detach_array_buffer_buffer_q, new_array_buffer_from_vec_q, new_array_buffer_owned,
+        };
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        struct DropCount(Vec<u8>, Arc<AtomicUsize>);
+        impl AsMut<[u8]> for DropCount {
+            fn as_mut(&mut self) -> &mut [u8] {
+                self.0.as_mut()
+            }
+        }
+        impl Drop for DropCount {
+            fn drop(&mut self) {
+                self.1.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let rt = crate::builder::QuickJsRuntimeBuilder::new().build();
+        let drops = Arc::new(AtomicUsize::new(0));
+        let drops2 = drops.clone();
+        rt.loop_realm_sync(None, move |_rt, realm| {
+            let ab = new_array_buffer_from_vec_q(realm, vec![7, 8, 9]).expect("could not create");
+            let detached = detach_array_buffer_buffer_q(realm, &ab).expect("detach failed");
+            assert_eq!(detached, vec![7, 8, 9]);
+            assert!(detach_array_buffer_buffer_q(realm, &ab).is_err());
+
+            let owned =
+                unsafe { new_array_buffer_owned(realm.context, DropCount(vec![1, 2], drops2)) }
+                    .expect("could not create");
+            let detached = detach_array_buffer_buffer_q(realm, &owned).expect("detach failed");
+            assert_eq!(detached, vec![1, 2]);
+
+            // buffers created in script are copied as well
+            let js_buffer = realm
+                .eval(Script::new(
+                    "test_detach_array_buffer_from_vec.js",
+                    "let jsBuffer = new Uint8Array([4, 5]).buffer; jsBuffer;",
+                ))
+                .expect("script failed");
+            let detached = detach_array_buffer_buffer_q(realm, &js_buffer).expect("detach failed");
+            assert_eq!(detached, vec![4, 5]);
+            let len = realm
+                .eval(Script::new("test_detached_len.js", "jsBuffer.byteLength;"))
+                .expect("script failed");
+            assert_eq!(len.to_i32(), 0);
+        });
+        drop(rt);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_array_buffer_bytes() {
+        use crate::quickjs_utils::typedarrays::{
+            get_array_buffer_bytes_q, with_array_buffer_slice_q,
+        };
+        let rt = init_test_rt();
+        rt.loop_realm_sync(None, |_rt, realm| {
+            let arr = realm
+                .eval(Script::new(
+                    "test_array_buffer_bytes.js",
+                    "const abBytes = new Uint8Array(8); for (let i = 0; i < 8; i++) {abBytes[i] = i * 2;}; abBytes;",
+                ))
+                .expect("script failed");
+            assert_eq!(
+                get_array_buffer_bytes_q(realm, &arr).expect("could not get bytes"),
+                vec![0, 2, 4, 6, 8, 10, 12, 14]
+            );
+            let sum = with_array_buffer_slice_q(realm, &arr, |bytes| {
+                bytes.iter().map(|b| *b as u32).sum::<u32>()
+            })
+            .expect("could not get slice");
+            assert_eq!(sum, 56);
+
+            // a view on part of the buffer
+            let view = realm
+                .eval(Script::new(
+                    "test_array_buffer_bytes2.js",
+                    "new Uint16Array(abBytes.buffer, 2, 2);",
+                ))
+                .expect("script failed");
+            assert_eq!(
+                get_array_buffer_bytes_q(realm, &view).expect("could not get bytes"),
+                vec![4, 6, 8, 10]
+            );
+            // the ArrayBuffer itself
+            let ab = get_array_buffer_q(realm, &view).expect("could not get buffer");
+            assert_eq!(
+                get_array_buffer_bytes_q(realm, &ab)
+                    .expect("could not get bytes")
+                    .len(),
+                8
+            );
+
+            let obj = realm
+                .eval(Script::new("test_array_buffer_bytes3.js", "({});"))
+                .expect("script failed");
+            assert!(get_array_buffer_bytes_q(realm, &obj).is_err());
+
+            // detached buffers result in an Err
+            let detached = new_uint8_array_copy_q(realm, &[1, 2, 3]).expect("could not create");
+            let detached_ab = get_array_buffer_q(realm, &detached).expect("could not get buffer");
+            unsafe { q::JS_DetachArrayBuffer(realm.context, *detached_ab.borrow_value()) };
+            assert!(get_array_buffer_bytes_q(realm, &detached).is_err());
+            assert!(get_array_buffer_bytes_q(realm, &detached_ab).is_err());
+        });
+    }
 }
@@ -5,7 +5,9 @@ use crate::quickjs_utils::typedarrays::{
     detach_array_buffer_buffer_q, get_array_buffer_buffer_copy_q, get_array_buffer_q,
     new_uint8_array_copy_q, new_uint8_array_q,
 };
-use crate::quickjs_utils::{arrays, errors, functions, get_global_q, json, new_null_ref, objects};
+use crate::quickjs_utils::{
+    arrays, compile, dates, errors, functions, get_global_q, json, modules, objects,
+};
 use crate::quickjsruntimeadapter::{make_cstring, QuickJsRuntimeAdapter};
 use crate::quickjsvalueadapter::{QuickJsValueAdapter, TAG_EXCEPTION};
 use crate::reflection::eventtarget::dispatch_event;
@@ -14,6 +16,7 @@ use crate::reflection::{new_instance, new_instance3, Proxy};
 use hirofa_utils::auto_id_map::AutoIdMap;
 
 use crate::jsutils::jsproxies::{JsProxy, JsProxyInstanceId};
+use crate::jsutils::promises::{PromiseToken, PromiseTokenState};
 use crate::jsutils::{JsError, JsValueType, Script};
 use crate::quickjs_utils::promises::QuickJsPromiseAdapter;
 use crate::values::{
@@ -23,13 +26,14 @@ use crate::values::{
 use libquickjs_sys as q;
 use serde_json::Value;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::ffi::CString;
 use std::future::Future;
 use std::i32;
 use std::os::raw::c_void;
 use std::rc::Rc;
 use std::sync::{Arc, Weak};
+use std::time::Duration;
 
 use crate::jsutils::promises::new_resolving_promise;
 use crate::jsutils::promises::new_resolving_promise_async;
@@ -63,14 +67,26 @@ type ProxyStaticEventListenerMaps = HashMap<
 pub struct QuickJsRealmAdapter {
     object_cache: RefCell<AutoIdMap<QuickJsValueAdapter>>,
     promise_cache: RefCell<AutoIdMap<QuickJsPromiseAdapter>>,
+    promise_tokens: RefCell<Vec<Weak<PromiseTokenState>>>,
     pub(crate) proxy_registry: RefCell<HashMap<String, Rc<Proxy>>>, // todo is this Rc needed or can we just borrow the Proxy when needed?
     pub(crate) proxy_constructor_refs: RefCell<HashMap<String, QuickJsValueAdapter>>,
     pub(crate) proxy_event_listeners: RefCell<ProxyEventListenerMaps>,
     pub(crate) proxy_static_event_listeners: RefCell<ProxyStaticEventListenerMaps>,
+    // paths of the modules evaluated with eval_module, these may be imported without a module loader
+    evaluated_modules: RefCell<HashSet<String>>,
+    // names of all modules loaded in this realm, in the order they finished loading
+    loaded_modules: RefCell<Vec<String>>,
+    // (importer, imported) pairs of resolved imports
+    module_dependencies: RefCell<Vec<(String, String)>>,
+    // (base path, module name) of the latest imports which no loader could resolve
+    modules_not_found: RefCell<VecDeque<(String, String)>>,
     pub id: String,
     pub context: *mut q::JSContext,
 }
 
+/// the max number of imports which could not be resolved which are remembered per realm
+const MAX_MODULES_NOT_FOUND: usize = 256;
+
 thread_local! {
     #[allow(clippy::box_collection)]
     static ID_REGISTRY: RefCell<HashMap<String, Box<String>>> = RefCell::new(HashMap::new());
@@ -148,6 +164,26 @@ impl QuickJsRealmAdapter {
             cache_map.clear();
         }
 
+        self.cancel_promise_tokens("realm was dropped");
+
+        // promises which are still waiting to be resolved from rust hold refs to the promise and its resolving functions
+        // those need to be freed while the context is still valid, a producer finishing later will just find no promise to resolve
+        let mut pending_promises = {
+            let promise_map = &mut *self.promise_cache.borrow_mut();
+            std::mem::replace(promise_map, AutoIdMap::new())
+        };
+        log::trace!(
+            "QuickJsContext:free {}, dropping {} cached promises",
+            self.id,
+            pending_promises.len()
+        );
+        pending_promises.clear();
+        QuickJsRuntimeAdapter::do_with(|q_js_rt| {
+            if let Some(stats) = q_js_rt.resolving_promise_stats.as_ref() {
+                stats.record_realm_dropped(self.id.as_str());
+            }
+        });
+
         let mut all_listeners = {
             let proxy_event_listeners: &mut ProxyEventListenerMaps =
                 &mut self.proxy_event_listeners.borrow_mut();
@@ -192,10 +228,15 @@ impl QuickJsRealmAdapter {
             context,
             object_cache: RefCell::new(AutoIdMap::new_with_max_size(i32::MAX as usize)),
             promise_cache: RefCell::new(AutoIdMap::new()),
+            promise_tokens: RefCell::new(vec![]),
             proxy_registry: RefCell::new(Default::default()),
             proxy_constructor_refs: RefCell::new(Default::default()),
             proxy_event_listeners: RefCell::new(Default::default()),
             proxy_static_event_listeners: RefCell::new(Default::default()),
+            evaluated_modules: RefCell::new(HashSet::new()),
+            loaded_modules: RefCell::new(vec![]),
+            module_dependencies: RefCell::new(vec![]),
+            modules_not_found: RefCell::new(VecDeque::new()),
         }
     }
     /// get the id of a QuickJsContext from a JSContext
@@ -222,6 +263,18 @@ impl QuickJsRealmAdapter {
         unsafe { Self::eval_ctx(self.context, script, None) }
     }
 
+    /// evaluate a script and interrupt it if it runs longer than timeout
+    /// an interrupted script results in an Err with name InterruptedError
+    pub fn eval_with_timeout(
+        &self,
+        script: Script,
+        timeout: Duration,
+    ) -> Result<QuickJsValueAdapter, JsError> {
+        QuickJsRuntimeAdapter::do_with(|q_js_rt| {
+            q_js_rt.run_with_timeout(timeout, || self.eval(script))
+        })
+    }
+
     pub fn eval_this(
         &self,
         script: Script,
@@ -288,8 +341,273 @@ impl QuickJsRealmAdapter {
     }
 
     /// evaluate a Module
+    /// the module may then be imported by other modules in this realm by its path, even if no module loader resolves that path
     pub fn eval_module(&self, script: Script) -> Result<QuickJsValueAdapter, JsError> {
-        unsafe { Self::eval_module_ctx(self.context, script) }
+        let path = script.get_path().to_string();
+        let res = unsafe { Self::eval_module_ctx(self.context, script) }?;
+        self.record_loaded_module(path.as_str());
+        self.evaluated_modules.borrow_mut().insert(path);
+        Ok(res)
+    }
+
+    /// get the names of all modules which were evaluated or loaded by a module loader in this realm
+    /// modules are listed in the order they finished loading, which is usually after the modules they import
+    pub fn loaded_module_names(&self) -> Vec<String> {
+        self.loaded_modules.borrow().clone()
+    }
+
+    /// get the (importer, imported) pairs of all imports which were resolved in this realm
+    /// the importer is the path of the importing module or script
+    pub fn module_dependency_graph(&self) -> Vec<(String, String)> {
+        self.module_dependencies.borrow().clone()
+    }
+
+    pub(crate) fn record_loaded_module(&self, name: &str) {
+        let loaded = &mut *self.loaded_modules.borrow_mut();
+        if !loaded.iter().any(|loaded_name| loaded_name == name) {
+            loaded.push(name.to_string());
+        }
+    }
+
+    pub(crate) fn record_module_dependency(&self, importer: &str, imported: &str) {
+        let dependencies = &mut *self.module_dependencies.borrow_mut();
+        if !dependencies
+            .iter()
+            .any(|(from, to)| from == importer && to == imported)
+        {
+            dependencies.push((importer.to_string(), imported.to_string()));
+        }
+    }
+
+    /// remove a module from the module cache (see [crate::builder::QuickJsRuntimeBuilder::module_cache]) and forget that this realm could not find it
+    /// so the module is loaded by the module loaders again, the module cache is shared by all realms
+    /// please note that QuickJS keeps every module which was loaded in a realm, so a realm which loaded the module keeps using the loaded version
+    /// returns true if the module was cached
+    pub fn invalidate_cached_module(&self, name: &str) -> bool {
+        let was_not_found = self.forget_module_not_found(name);
+        let was_cached =
+            QuickJsRuntimeAdapter::do_with(|q_js_rt| q_js_rt.remove_cached_module(name));
+        was_not_found || was_cached
+    }
+
+    /// invalidate all modules which were loaded in this realm and all imports which could not be found, see [QuickJsRealmAdapter::invalidate_cached_module]
+    pub fn invalidate_all_cached_modules(&self) {
+        QuickJsRuntimeAdapter::do_with(|q_js_rt| {
+            for name in &*self.loaded_modules.borrow() {
+                q_js_rt.remove_cached_module(name);
+            }
+        });
+        self.clear_modules_not_found();
+    }
+
+    /// forget that an import of a module could not be resolved, returns true if it was remembered
+    pub(crate) fn forget_module_not_found(&self, name: &str) -> bool {
+        let not_found = &mut *self.modules_not_found.borrow_mut();
+        let not_found_ct = not_found.len();
+        not_found.retain(|(_base, not_found_name)| not_found_name != name);
+        not_found.len() != not_found_ct
+    }
+
+    pub(crate) fn clear_modules_not_found(&self) {
+        self.modules_not_found.borrow_mut().clear();
+    }
+
+    /// check if an import was found to be missing before
+    pub(crate) fn is_module_not_found_cached(&self, base_path: &str, module_name: &str) -> bool {
+        self.modules_not_found
+            .borrow()
+            .iter()
+            .any(|(base, name)| base == base_path && name == module_name)
+    }
+
+    /// remember an import which could not be resolved, only the latest imports are remembered
+    pub(crate) fn cache_module_not_found(&self, base_path: &str, module_name: &str) {
+        let not_found = &mut *self.modules_not_found.borrow_mut();
+        if not_found.len() >= MAX_MODULES_NOT_FOUND {
+            not_found.pop_front();
+        }
+        not_found.push_back((base_path.to_string(), module_name.to_string()));
+    }
+
+    /// compile a script to bytecode which may be evaluated with [QuickJsRealmAdapter::eval_compiled_script]
+    /// the bytecode is sealed with a checksum (see [compile::seal_bytecode]) so it may be stored, e.g. on disk
+    pub fn compile_script(&self, script: Script) -> Result<Vec<u8>, JsError> {
+        let script = QuickJsRuntimeAdapter::pre_process(script)?;
+        let compiled = unsafe { compile::compile(self.context, script) }?;
+        Ok(compile::seal_bytecode(&unsafe {
+            compile::to_bytecode(self.context, &compiled)
+        }))
+    }
+
+    /// evaluate a script which was compiled with [QuickJsRealmAdapter::compile_script]
+    /// corrupt bytecode, bytecode of a module or of a different QuickJS version result in an Err
+    /// only evaluate bytecode you produced yourself, the checksum does not protect against crafted bytecode (see [compile::unseal_bytecode])
+    /// # Example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::jsutils::Script;
+    /// let rt = QuickJsRuntimeBuilder::new().build();
+    /// rt.exe_rt_task_in_event_loop(|q_js_rt| {
+    ///     let realm = q_js_rt.get_main_realm();
+    ///     let bytecode = realm.compile_script(Script::new("compiled.js", "6 * 7;")).expect("compile failed");
+    ///     let res = realm.eval_compiled_script(&bytecode).expect("eval failed");
+    ///     assert_eq!(res.to_i32(), 42);
+    /// });
+    /// ```
+    pub fn eval_compiled_script(&self, bytecode: &[u8]) -> Result<QuickJsValueAdapter, JsError> {
+        let compiled =
+            unsafe { compile::from_bytecode(self.context, compile::unseal_bytecode(bytecode)?) }?;
+        if !compiled.is_compiled_function() {
+            return Err(JsError::new_str("bytecode is not a compiled script"));
+        }
+        unsafe { compile::run_compiled_function(self.context, &compiled) }
+    }
+
+    /// compile a module to bytecode which may be evaluated with [QuickJsRealmAdapter::eval_compiled_module]
+    /// the bytecode is sealed with a checksum (see [compile::seal_bytecode]) so it may be stored, e.g. on disk
+    pub fn compile_module(&self, script: Script) -> Result<Vec<u8>, JsError> {
+        let script = QuickJsRuntimeAdapter::pre_process(script)?;
+        let compiled = unsafe { modules::compile_module(self.context, script) }?;
+        Ok(compile::seal_bytecode(&unsafe {
+            compile::to_bytecode(self.context, &compiled)
+        }))
+    }
+
+    /// evaluate a module which was compiled with [QuickJsRealmAdapter::compile_module], the module keeps the path it was compiled with
+    /// corrupt bytecode, bytecode of a script or of a different QuickJS version result in an Err
+    /// only evaluate bytecode you produced yourself, the checksum does not protect against crafted bytecode (see [compile::unseal_bytecode])
+    /// # Example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::jsutils::Script;
+    /// let rt = QuickJsRuntimeBuilder::new().build();
+    /// rt.exe_rt_task_in_event_loop(|q_js_rt| {
+    ///     let realm = q_js_rt.get_main_realm();
+    ///     let bytecode = realm.compile_module(Script::new("compiled.mes", "globalThis.compiled = 42;")).expect("compile failed");
+    ///     realm.eval_compiled_module(&bytecode).expect("eval failed");
+    /// });
+    /// ```
+    pub fn eval_compiled_module(&self, bytecode: &[u8]) -> Result<(), JsError> {
+        let compiled =
+            unsafe { compile::from_bytecode(self.context, compile::unseal_bytecode(bytecode)?) }?;
+        if !compiled.is_module() {
+            return Err(JsError::new_str("bytecode is not a compiled module"));
+        }
+        let path =
+            unsafe { modules::get_module_name(self.context, modules::get_module_def(&compiled)) }?;
+        unsafe { Self::eval_compiled_module_ctx(self.context, compiled, path.as_str()) }?;
+        self.record_loaded_module(path.as_str());
+        self.evaluated_modules.borrow_mut().insert(path);
+        Ok(())
+    }
+
+    /// check if a module with this path was evaluated with [QuickJsRealmAdapter::eval_module]
+    pub fn is_evaluated_module(&self, path: &str) -> bool {
+        self.evaluated_modules.borrow().contains(path)
+    }
+
+    /// evaluate a Module and return its namespace object which holds the exports of the module
+    /// a default export is available as the "default" property
+    ///
+    /// when using the bellard version of QuickJS the namespace is fetched with a dynamic import() of the module,
+    /// this runs the pending jobs of the runtime (like promise reactions of other scripts) until the import is settled
+    ///
+    /// like with [QuickJsRealmAdapter::eval_module] the module may then be imported by its path in this realm without a module loader
+    /// # Example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::jsutils::Script;
+    /// use quickjs_runtime::quickjs_utils::objects::get_property_q;
+    /// let rt = QuickJsRuntimeBuilder::new().build();
+    /// rt.exe_rt_task_in_event_loop(|q_js_rt| {
+    ///     let realm = q_js_rt.get_main_realm();
+    ///     let ns = realm.eval_module_exports(Script::new("exports.mes", "export const a = 1; export default 'b';")).expect("module failed");
+    ///     assert_eq!(get_property_q(realm, &ns, "a").unwrap().to_i32(), 1);
+    ///     assert_eq!(get_property_q(realm, &ns, "default").unwrap().to_string().unwrap(), "b");
+    /// });
+    /// ```
+    pub fn eval_module_exports(&self, script: Script) -> Result<QuickJsValueAdapter, JsError> {
+        self.eval_module_namespace(script)
+    }
+
+    #[cfg(feature = "quickjs-ng")]
+    fn eval_module_namespace(&self, script: Script) -> Result<QuickJsValueAdapter, JsError> {
+        let script = QuickJsRuntimeAdapter::pre_process(script)?;
+        let path = script.get_path().to_string();
+        let compiled_module = unsafe { modules::compile_module(self.context, script) }?;
+        let module = modules::get_module_def(&compiled_module);
+        unsafe { Self::eval_compiled_module_ctx(self.context, compiled_module, path.as_str()) }?;
+        self.record_loaded_module(path.as_str());
+        self.evaluated_modules.borrow_mut().insert(path.clone());
+        let ns = QuickJsValueAdapter::new(
+            self.context,
+            unsafe { q::JS_GetModuleNamespace(self.context, module) },
+            false,
+            true,
+            format!("eval_module_exports namespace of {path}").as_str(),
+        );
+        if ns.is_exception() {
+            return Err(
+                unsafe { Self::get_exception(self.context) }.unwrap_or_else(|| {
+                    JsError::new_string(format!("could not get exports of module {path}"))
+                }),
+            );
+        }
+        Ok(ns)
+    }
+
+    /// the bellard version of QuickJS has no api to get the namespace of a module, so the module is imported
+    /// with import() by a small helper function, pending jobs are run until that import is settled
+    #[cfg(feature = "bellard")]
+    fn eval_module_namespace(&self, script: Script) -> Result<QuickJsValueAdapter, JsError> {
+        use crate::quickjs_utils::promises::{get_promise_state_q, PromiseState};
+        let path = script.get_path().to_string();
+        self.eval_module(script)?;
+        let had_self_dependency = self
+            .module_dependencies
+            .borrow()
+            .iter()
+            .any(|(from, to)| from == &path && to == &path);
+        // the module is already loaded so importing it just resolves to its namespace
+        let import_func = self.eval(Script::new(path.as_str(), "(path) => import(path)"))?;
+        let promise = functions::call_function_q(
+            self,
+            &import_func,
+            &[from_string_q(self, path.as_str())?],
+            None,
+        )?;
+        QuickJsRuntimeAdapter::do_with(|q_js_rt| -> Result<(), JsError> {
+            while q_js_rt.has_pending_jobs()
+                && matches!(get_promise_state_q(self, &promise)?, PromiseState::Pending)
+            {
+                if let Err(e) = q_js_rt.run_pending_job() {
+                    log::error!("run_pending_job failed: {}", e);
+                }
+            }
+            Ok(())
+        })?;
+        // the helper imports from the path of the module, that is not a dependency of the module itself
+        if !had_self_dependency {
+            self.module_dependencies
+                .borrow_mut()
+                .retain(|(from, to)| from != &path || to != &path);
+        }
+        match get_promise_state_q(self, &promise)? {
+            PromiseState::Fulfilled(ns) => Ok(ns),
+            PromiseState::Rejected(err) => {
+                if errors::is_error_q(self, &err) {
+                    Err(unsafe { errors::error_to_js_error(self.context, &err) })
+                } else {
+                    Err(JsError::new_string(format!(
+                        "could not get exports of module {path}: {}",
+                        err.to_string()?
+                    )))
+                }
+            }
+            PromiseState::Pending => Err(JsError::new_string(format!(
+                "could not get exports of module {path}, the module did not finish evaluating"
+            ))),
+        }
     }
 
     /// # Safety
@@ -301,26 +619,35 @@ impl QuickJsRealmAdapter {
         log::debug!("q_js_rt.eval_module file {}", script.get_path());
 
         script = QuickJsRuntimeAdapter::pre_process(script)?;
+        let path = script.get_path().to_string();
 
-        let code_str = script.get_runnable_code();
-
-        let filename_c = make_cstring(script.get_path())?;
-        let code_c = make_cstring(code_str)?;
+        // compile first so we can set import.meta before the module is evaluated
+        let compiled_module = modules::compile_module(context, script)?;
+        Self::eval_compiled_module_ctx(context, compiled_module, path.as_str())
+    }
 
-        let value_raw = q::JS_Eval(
+    /// # Safety
+    /// when passing a context ptr please be sure that the corresponding QuickJsContext is still active
+    unsafe fn eval_compiled_module_ctx(
+        context: *mut q::JSContext,
+        compiled_module: QuickJsValueAdapter,
+        path: &str,
+    ) -> Result<QuickJsValueAdapter, JsError> {
+        modules::set_module_meta(
             context,
-            code_c.as_ptr(),
-            code_str.len() as _,
-            filename_c.as_ptr(),
-            q::JS_EVAL_TYPE_MODULE as i32,
-        );
+            modules::get_module_def(&compiled_module),
+            path,
+            true,
+        )?;
+
+        let value_raw = q::JS_EvalFunction(context, compiled_module.clone_value_incr_rc());
 
         let ret = QuickJsValueAdapter::new(
             context,
             value_raw,
             false,
             true,
-            format!("eval_module result of {}", script.get_path()).as_str(),
+            format!("eval_module result of {path}").as_str(),
         );
 
         log::trace!("evalled module yielded a {}", ret.borrow_value().tag);
@@ -702,8 +1029,8 @@ impl QuickJsRealmAdapter {
         object: &QuickJsValueAdapter,
         property_name: &str,
     ) -> Result<(), JsError> {
-        // todo impl a real delete_prop
-        objects::set_property_q(self, object, property_name, &new_null_ref())
+        objects::delete_property_q(self, object, property_name)?;
+        Ok(())
     }
 
     pub fn set_object_property(
@@ -888,6 +1215,33 @@ impl QuickJsRealmAdapter {
         map.remove_opt(&id)
     }
 
+    /// get a clone of a cached promise without removing it from the cache
+    pub fn get_cached_promise(&self, id: usize) -> Option<QuickJsPromiseAdapter> {
+        let map = &*self.promise_cache.borrow();
+        map.get(&id).cloned()
+    }
+
+    /// keep track of the token of a running producer so it can be cancelled when this realm is dropped
+    pub(crate) fn register_promise_token(&self, token: &PromiseToken) {
+        let tokens = &mut *self.promise_tokens.borrow_mut();
+        // forget tokens of producers which are done
+        tokens.retain(|t| t.strong_count() > 0);
+        tokens.push(token.downgrade());
+    }
+
+    /// cancel the tokens of all running producers of this realm
+    pub(crate) fn cancel_promise_tokens(&self, reason: &str) {
+        let tokens = std::mem::take(&mut *self.promise_tokens.borrow_mut());
+        for state in tokens.iter().filter_map(|t| t.upgrade()) {
+            PromiseToken::from_state(state).cancel(reason);
+        }
+    }
+
+    /// get the number of promises which are cached and not yet resolved from rust
+    pub fn get_cached_promise_count(&self) -> usize {
+        self.promise_cache.borrow().len()
+    }
+
     pub fn dispose_cached_object(&self, id: i32) {
         let _ = self.consume_cached_obj(id);
     }
@@ -1003,6 +1357,10 @@ impl QuickJsRealmAdapter {
                         buffer: self.copy_typed_array_buffer(js_value)?,
                         array_type: TypedArrayType::Uint8,
                     }
+                } else if dates::is_date_q(self, js_value) {
+                    JsValueFacade::Date {
+                        millis: dates::get_time_q(self, js_value).unwrap_or(f64::NAN),
+                    }
                 } else {
                     JsValueFacade::JsObject {
                         cached_object: CachedJsObjectRef::new(self, js_value.clone()),
@@ -1146,6 +1504,7 @@ impl QuickJsRealmAdapter {
             },
             JsValueFacade::JsonStr { json } => self.json_parse(json.as_str()),
             JsValueFacade::SerdeValue { value } => self.serde_value_to_value_adapter(value),
+            JsValueFacade::Date { millis } => dates::new_date_from_millis_q(self, millis),
         }
     }
 
@@ -1217,7 +1576,7 @@ impl QuickJsRealmAdapter {
             Value::String(s) => self.create_string(s.as_str()),
             Value::Array(a) => {
                 let arr = self.create_array()?;
-                for (x, aval) in (0_u32..).zip(a.into_iter()) {
+                for (x, aval) in (0_u32..).zip(a) {
                     let entry = self.serde_value_to_value_adapter(aval)?;
                     self.set_array_element(&arr, x, &entry)?;
                 }
@@ -1,25 +1,31 @@
 // store in thread_local
 
 use crate::facades::QuickjsRuntimeFacadeInner;
-use crate::jsutils::modules::{CompiledModuleLoader, NativeModuleLoader, ScriptModuleLoader};
+use crate::jsutils::modules::{
+    BytecodeCache, CompiledModuleLoader, NativeModuleLoader, ScriptModuleLoader,
+};
+use crate::jsutils::promises::ResolvingPromiseStats;
 use crate::jsutils::{JsError, Script, ScriptPreProcessor};
+use crate::quickjs_utils::compile;
 use crate::quickjs_utils::compile::from_bytecode;
 use crate::quickjs_utils::modules::{
     add_module_export, compile_module, get_module_def, get_module_name, new_module,
-    set_module_export,
+    set_module_export, set_module_meta,
 };
 use crate::quickjs_utils::runtime::new_class_id;
 use crate::quickjs_utils::{gc, interrupthandler, modules, promises};
 use crate::quickjsrealmadapter::QuickJsRealmAdapter;
+use crate::quickjsvalueadapter::QuickJsValueAdapter;
 use libquickjs_sys as q;
 use serde::Serialize;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::ffi::CString;
 use std::fmt::{Debug, Formatter};
 use std::os::raw::c_int;
 use std::panic;
 use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 
 /// this is the internal abstract loader which is used to actually load the modules
 pub trait ModuleLoader {
@@ -31,6 +37,15 @@ pub trait ModuleLoader {
         ref_path: &str,
         path: &str,
     ) -> Option<String>;
+    /// like normalize_path but a loader may return an Err if resolving the module failed, the Err is thrown in the importing script
+    fn try_normalize_path(
+        &self,
+        q_ctx: &QuickJsRealmAdapter,
+        ref_path: &str,
+        path: &str,
+    ) -> Result<Option<String>, JsError> {
+        Ok(self.normalize_path(q_ctx, ref_path, path))
+    }
     /// load the Module
     fn load_module(
         &self,
@@ -69,6 +84,28 @@ impl ScriptModuleLoaderAdapter {
     pub fn new(loader: Box<dyn ScriptModuleLoader>) -> Self {
         Self { inner: loader }
     }
+
+    fn read_cached_bytecode(
+        realm: &QuickJsRealmAdapter,
+        absolute_path: &str,
+        bytecode: &[u8],
+    ) -> Option<QuickJsValueAdapter> {
+        let res = compile::unseal_bytecode(bytecode)
+            .and_then(|bytecode| unsafe { from_bytecode(realm.context, bytecode) });
+        match res {
+            Ok(compiled_module) if compiled_module.is_module() => Some(compiled_module),
+            Ok(_) => {
+                log::debug!("cached bytecode of {absolute_path} is not a module, recompiling");
+                None
+            }
+            Err(err) => {
+                log::debug!(
+                    "could not read cached bytecode of {absolute_path}, recompiling: {err}"
+                );
+                None
+            }
+        }
+    }
 }
 
 impl ModuleLoader for CompiledModuleLoaderAdapter {
@@ -89,7 +126,9 @@ impl ModuleLoader for CompiledModuleLoaderAdapter {
         let bytes = self.inner.load_module(q_ctx, absolute_path);
 
         let compiled_module = unsafe { from_bytecode(q_ctx.context, &bytes)? };
-        Ok(get_module_def(&compiled_module))
+        let module = get_module_def(&compiled_module);
+        unsafe { set_module_meta(q_ctx.context, module, absolute_path, false)? };
+        Ok(module)
     }
 
     fn has_module(&self, q_ctx: &QuickJsRealmAdapter, absolute_path: &str) -> bool {
@@ -113,7 +152,22 @@ impl ModuleLoader for ScriptModuleLoaderAdapter {
         ref_path: &str,
         path: &str,
     ) -> Option<String> {
-        self.inner.normalize_path(realm, ref_path, path)
+        match self.inner.try_normalize_path(realm, ref_path, path) {
+            Ok(normalized) => normalized,
+            Err(e) => {
+                log::error!("could not normalize module {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    fn try_normalize_path(
+        &self,
+        realm: &QuickJsRealmAdapter,
+        ref_path: &str,
+        path: &str,
+    ) -> Result<Option<String>, JsError> {
+        self.inner.try_normalize_path(realm, ref_path, path)
     }
 
     fn load_module(
@@ -122,14 +176,57 @@ impl ModuleLoader for ScriptModuleLoaderAdapter {
         absolute_path: &str,
     ) -> Result<*mut q::JSModuleDef, JsError> {
         log::trace!("load_module");
-        let code = self.inner.load_module(realm, absolute_path);
-
-        let mut script = Script::new(absolute_path, code.as_str());
-        script = QuickJsRuntimeAdapter::pre_process(script)?;
+        if let Some(compiled_module) =
+            QuickJsRuntimeAdapter::do_with(|rt| rt.get_cached_module(absolute_path))
+                .and_then(|bytecode| Self::read_cached_bytecode(realm, absolute_path, &bytecode))
+        {
+            let module = get_module_def(&compiled_module);
+            unsafe { set_module_meta(realm.context, module, absolute_path, false)? };
+            return Ok(module);
+        }
+        let code = self.inner.try_load_module(realm, absolute_path)?;
+        let script = if modules::is_json_module(absolute_path) {
+            modules::json_module_script(absolute_path, code.as_str())?
+        } else {
+            QuickJsRuntimeAdapter::pre_process(Script::new(absolute_path, code.as_str()))?
+        };
         log::trace!("load_module / 2");
-        let compiled_module = unsafe { compile_module(realm.context, script)? };
+        let source_hash = compile::source_hash(script.get_runnable_code());
+        let cached_bytecode = QuickJsRuntimeAdapter::do_with(|rt| {
+            rt.bytecode_cache
+                .as_ref()
+                .and_then(|cache| cache.get(absolute_path, source_hash))
+        });
+        let compiled_module = match cached_bytecode
+            .and_then(|bytecode| Self::read_cached_bytecode(realm, absolute_path, &bytecode))
+        {
+            Some(compiled_module) => compiled_module,
+            None => {
+                let compiled_module = unsafe { compile_module(realm.context, script)? };
+                QuickJsRuntimeAdapter::do_with(|rt| {
+                    if let Some(cache) = rt.bytecode_cache.as_ref() {
+                        let bytecode =
+                            unsafe { compile::to_bytecode(realm.context, &compiled_module) };
+                        cache.put(
+                            absolute_path,
+                            source_hash,
+                            compile::seal_bytecode(&bytecode),
+                        );
+                    }
+                });
+                compiled_module
+            }
+        };
         log::trace!("load_module / 3");
-        Ok(get_module_def(&compiled_module))
+        QuickJsRuntimeAdapter::do_with(|rt| {
+            if rt.module_cache_enabled {
+                let bytecode = unsafe { compile::to_bytecode(realm.context, &compiled_module) };
+                rt.cache_module(absolute_path, compile::seal_bytecode(&bytecode));
+            }
+        });
+        let module = get_module_def(&compiled_module);
+        unsafe { set_module_meta(realm.context, module, absolute_path, false)? };
+        Ok(module)
     }
 
     fn has_module(&self, q_ctx: &QuickJsRealmAdapter, absolute_path: &str) -> bool {
@@ -204,6 +301,92 @@ impl ModuleLoader for NativeModuleLoaderAdapter {
     }
 }
 
+/// a native module which was registered by name, the exports are created by a factory when the module is loaded in a realm
+pub struct NamedNativeModuleAdapter {
+    name: String,
+    factory: modules::NativeModuleFactory,
+    // exports created in load_module, waiting to be set in init_module (by realm id and module ptr)
+    // init_module is never called for modules which fail to link, their exports are removed when the realm is dropped
+    #[allow(clippy::type_complexity)]
+    pending_exports: RefCell<HashMap<(String, usize), Vec<(String, QuickJsValueAdapter)>>>,
+}
+
+impl NamedNativeModuleAdapter {
+    pub fn new(name: &str, factory: modules::NativeModuleFactory) -> Self {
+        Self {
+            name: name.to_string(),
+            factory,
+            pending_exports: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// drop the exports which were created for a realm but never set because the module was not initialized
+    pub(crate) fn remove_pending_exports(&self, realm_id: &str) {
+        self.pending_exports
+            .borrow_mut()
+            .retain(|(id, _), _| id != realm_id);
+    }
+
+    #[cfg(test)]
+    pub(crate) fn pending_exports_count(&self) -> usize {
+        self.pending_exports.borrow().len()
+    }
+}
+
+impl ModuleLoader for NamedNativeModuleAdapter {
+    fn normalize_path(
+        &self,
+        _q_ctx: &QuickJsRealmAdapter,
+        _ref_path: &str,
+        path: &str,
+    ) -> Option<String> {
+        if self.name.eq(path) {
+            Some(path.to_string())
+        } else {
+            None
+        }
+    }
+
+    fn load_module(
+        &self,
+        q_ctx: &QuickJsRealmAdapter,
+        absolute_path: &str,
+    ) -> Result<*mut q::JSModuleDef, JsError> {
+        let exports = (self.factory)(q_ctx)?;
+
+        let module = unsafe { new_module(q_ctx.context, absolute_path, Some(native_module_init))? };
+        for (name, _val) in &exports {
+            unsafe { add_module_export(q_ctx.context, module, name.as_str())? }
+        }
+        self.pending_exports
+            .borrow_mut()
+            .insert((q_ctx.id.clone(), module as usize), exports);
+        Ok(module)
+    }
+
+    fn has_module(&self, _q_ctx: &QuickJsRealmAdapter, absolute_path: &str) -> bool {
+        self.name.eq(absolute_path)
+    }
+
+    unsafe fn init_module(
+        &self,
+        q_ctx: &QuickJsRealmAdapter,
+        module: *mut q::JSModuleDef,
+    ) -> Result<(), JsError> {
+        let exports = self
+            .pending_exports
+            .borrow_mut()
+            .remove(&(q_ctx.id.clone(), module as usize))
+            .ok_or_else(|| {
+                JsError::new_string(format!("native module {} was not loaded", self.name))
+            })?;
+        for (name, val) in exports {
+            set_module_export(q_ctx.context, module, name.as_str(), val)?;
+        }
+        Ok(())
+    }
+}
+
 unsafe extern "C" fn native_module_init(
     ctx: *mut q::JSContext,
     module: *mut q::JSModuleDef,
@@ -263,11 +446,24 @@ pub struct QuickJsRuntimeAdapter {
     pub(crate) context_init_hooks: RefCell<ContextInitHooks>,
     script_module_loaders: Vec<ScriptModuleLoaderAdapter>,
     native_module_loaders: Vec<NativeModuleLoaderAdapter>,
+    pub(crate) named_native_modules: Vec<NamedNativeModuleAdapter>,
     compiled_module_loaders: Vec<CompiledModuleLoaderAdapter>,
     // script preprocs just preproc the input code, typescript transpiler will be special option which is run as last preproc
     pub(crate) script_pre_processors: Vec<Box<dyn ScriptPreProcessor + Send>>,
+    pub(crate) bytecode_cache: Option<Box<dyn BytecodeCache + Send>>,
     #[allow(clippy::type_complexity)]
     pub(crate) interrupt_handler: Option<Box<dyn Fn(&QuickJsRuntimeAdapter) -> bool>>,
+    pub(crate) promise_rejection_handler: Option<promises::PromiseRejectionHandler>,
+    #[cfg(feature = "console")]
+    pub(crate) console_handler: Option<crate::features::console::ConsoleHandler>,
+    pub(crate) module_cache_enabled: bool,
+    module_root: Option<String>,
+    // sealed bytecode of script modules by normalized path
+    module_cache: RefCell<HashMap<String, Vec<u8>>>,
+    pub(crate) resolving_promise_stats: Option<Arc<ResolvingPromiseStats>>,
+    // set while a script is evaluated with a timeout
+    eval_deadline: Cell<Option<Instant>>,
+    eval_deadline_exceeded: Cell<bool>,
 }
 
 thread_local! {
@@ -414,7 +610,19 @@ impl QuickJsRuntimeAdapter {
     pub(crate) fn pre_process(mut script: Script) -> Result<Script, JsError> {
         Self::do_with(|q_js_rt| {
             for pp in &q_js_rt.script_pre_processors {
-                pp.process(&mut script)?;
+                pp.process(&mut script).map_err(|e| {
+                    // make sure the error tells which script failed
+                    let path = script.get_path();
+                    if e.get_message().contains(path) {
+                        e
+                    } else {
+                        JsError::new(
+                            e.get_name().to_string(),
+                            format!("could not pre-process {path}: {}", e.get_message()),
+                            e.get_stack().to_string(),
+                        )
+                    }
+                })?;
             }
             #[cfg(feature = "typescript")]
             crate::typescript::transpile_serverside(q_js_rt, &mut script)?;
@@ -470,6 +678,9 @@ impl QuickJsRuntimeAdapter {
         log::debug!("QuickJsRuntime::drop_context: {}", id);
 
         QuickJsRuntimeAdapter::do_with(|rt| {
+            for named_native_module in &rt.named_native_modules {
+                named_native_module.remove_pending_exports(id);
+            }
             let q_ctx = rt.get_context(id);
             log::trace!("QuickJsRuntime::q_ctx.free: {}", id);
             q_ctx.free();
@@ -541,9 +752,20 @@ impl QuickJsRuntimeAdapter {
             context_init_hooks: RefCell::new(vec![]),
             script_module_loaders: vec![],
             native_module_loaders: vec![],
+            named_native_modules: vec![],
             compiled_module_loaders: vec![],
             script_pre_processors: vec![],
+            bytecode_cache: None,
             interrupt_handler: None,
+            promise_rejection_handler: None,
+            #[cfg(feature = "console")]
+            console_handler: None,
+            module_cache_enabled: false,
+            module_root: None,
+            module_cache: RefCell::new(HashMap::new()),
+            resolving_promise_stats: None,
+            eval_deadline: Cell::new(None),
+            eval_deadline_exceeded: Cell::new(false),
         };
 
         modules::set_module_loader(&q_rt);
@@ -555,6 +777,8 @@ impl QuickJsRuntimeAdapter {
         q_rt
     }
 
+    /// set the interrupt handler of this runtime, this replaces a handler set by [crate::builder::QuickJsRuntimeBuilder::set_interrupt_handler]
+    /// the handler is polled during script execution, returning true aborts the running script
     pub fn set_interrupt_handler<I: Fn(&QuickJsRuntimeAdapter) -> bool + 'static>(
         &mut self,
         interrupt_handler: I,
@@ -564,6 +788,55 @@ impl QuickJsRuntimeAdapter {
         self
     }
 
+    /// run a consumer (which evaluates a script) and interrupt the script if it runs longer than timeout
+    /// an interrupted script results in an Err with name InterruptedError
+    /// if this is called while another timeout is active the earliest deadline is used
+    pub fn run_with_timeout<R, C: FnOnce() -> Result<R, JsError>>(
+        &self,
+        timeout: Duration,
+        consumer: C,
+    ) -> Result<R, JsError> {
+        interrupthandler::init(self);
+        let previous_deadline = self.eval_deadline.get();
+        let deadline = Instant::now() + timeout;
+        self.eval_deadline.set(Some(
+            previous_deadline.map_or(deadline, |p| p.min(deadline)),
+        ));
+        let res = consumer();
+        self.eval_deadline.set(previous_deadline);
+        let exceeded = self.eval_deadline_exceeded.replace(false);
+        match res {
+            Err(_) if exceeded => Err(JsError::new(
+                "InterruptedError".to_string(),
+                format!("script was interrupted because it ran longer than {timeout:?}"),
+                "".to_string(),
+            )),
+            res => res,
+        }
+    }
+
+    /// check if the deadline of [QuickJsRuntimeAdapter::run_with_timeout] has passed, this is called by the interrupt handler
+    pub(crate) fn is_eval_deadline_exceeded(&self) -> bool {
+        match self.eval_deadline.get() {
+            Some(deadline) if Instant::now() >= deadline => {
+                self.eval_deadline_exceeded.set(true);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// set a handler which is called for unhandled promise rejections and for rejections which are handled late
+    pub fn set_promise_rejection_handler<
+        H: Fn(&QuickJsRealmAdapter, QuickJsValueAdapter, QuickJsValueAdapter, bool) + 'static,
+    >(
+        &mut self,
+        handler: H,
+    ) -> &mut Self {
+        promises::set_promise_rejection_handler(self, Box::new(handler));
+        self
+    }
+
     pub fn add_script_module_loader(&mut self, sml: ScriptModuleLoaderAdapter) {
         self.script_module_loaders.push(sml);
     }
@@ -576,6 +849,99 @@ impl QuickJsRuntimeAdapter {
         self.native_module_loaders.push(nml);
     }
 
+    /// register a native module which can be imported by name, the factory is called to create the exports when the module is imported in a realm
+    /// # Example
+    /// ```rust
+    /// use quickjs_runtime::builder::QuickJsRuntimeBuilder;
+    /// use quickjs_runtime::jsutils::Script;
+    /// let rt = QuickJsRuntimeBuilder::new().build();
+    /// rt.exe_task_in_event_loop(|| {
+    ///     quickjs_runtime::quickjsruntimeadapter::QuickJsRuntimeAdapter::do_with_mut(|q_js_rt| {
+    ///         q_js_rt.register_native_module("host:answer", |realm| {
+    ///             Ok(vec![("answer".to_string(), realm.create_i32(42)?)])
+    ///         });
+    ///     });
+    /// });
+    /// rt.eval_module_sync(None, Script::new("answer.mjs", "import {answer} from 'host:answer';\nconsole.log(answer);")).expect("module failed");
+    /// ```
+    pub fn register_native_module<
+        F: Fn(&QuickJsRealmAdapter) -> Result<Vec<(String, QuickJsValueAdapter)>, JsError> + 'static,
+    >(
+        &mut self,
+        name: &str,
+        factory: F,
+    ) -> &mut Self {
+        modules::register_native_module(self, name, Box::new(factory));
+        self
+    }
+
+    /// enable or disable caching of compiled script modules by their normalized path
+    /// when enabled a script module loader will only be called once for a module, even if it is imported in multiple realms
+    /// imports which could not be resolved are remembered per realm so loaders are not asked again
+    /// please note that QuickJS always keeps a loaded module per realm, invalidating the cache only affects realms which did not load the module yet
+    pub fn set_module_cache_enabled(&mut self, enabled: bool) -> &mut Self {
+        self.module_cache_enabled = enabled;
+        if !enabled {
+            self.clear_module_cache();
+        }
+        self
+    }
+
+    /// set a root path for modules, imports which resolve to a path outside of the root (like '../../etc/passwd') throw a ReferenceError
+    /// bare specifiers (like 'lodash') are not checked, see [crate::quickjs_utils::modules::normalize_module_path]
+    pub fn set_module_root(&mut self, root: Option<String>) -> &mut Self {
+        self.module_root = root;
+        self
+    }
+
+    /// get the root path for modules, see [QuickJsRuntimeAdapter::set_module_root]
+    pub fn get_module_root(&self) -> Option<&str> {
+        self.module_root.as_deref()
+    }
+
+    /// remove a single module from the module cache, returns true if the module was cached
+    /// this also makes all realms forget that the module could not be found, see [QuickJsRealmAdapter::invalidate_cached_module]
+    pub fn invalidate_cached_module(&self, absolute_path: &str) -> bool {
+        for realm in self.contexts.values() {
+            realm.forget_module_not_found(absolute_path);
+        }
+        self.remove_cached_module(absolute_path)
+    }
+
+    /// remove all modules from the module cache, this also makes all realms forget which modules could not be found
+    pub fn clear_module_cache(&self) {
+        for realm in self.contexts.values() {
+            realm.clear_modules_not_found();
+        }
+        self.module_cache.borrow_mut().clear();
+    }
+
+    pub(crate) fn remove_cached_module(&self, absolute_path: &str) -> bool {
+        self.module_cache
+            .borrow_mut()
+            .remove(absolute_path)
+            .is_some()
+    }
+
+    /// check if a module is in the module cache
+    pub fn is_module_cached(&self, absolute_path: &str) -> bool {
+        self.module_cache.borrow().contains_key(absolute_path)
+    }
+
+    fn get_cached_module(&self, absolute_path: &str) -> Option<Vec<u8>> {
+        if self.module_cache_enabled {
+            self.module_cache.borrow().get(absolute_path).cloned()
+        } else {
+            None
+        }
+    }
+
+    fn cache_module(&self, absolute_path: &str, bytecode: Vec<u8>) {
+        self.module_cache
+            .borrow_mut()
+            .insert(absolute_path.to_string(), bytecode);
+    }
+
     pub fn get_main_realm(&self) -> &QuickJsRealmAdapter {
         // todo store this somewhere so we don't need a lookup in the map every time
         self.get_context("__main__")
@@ -585,6 +951,12 @@ impl QuickJsRuntimeAdapter {
     where
         C: Fn(&dyn ModuleLoader) -> Option<R>,
     {
+        for loader in &self.named_native_modules {
+            let res = consumer(loader);
+            if res.is_some() {
+                return res;
+            }
+        }
         for loader in &self.compiled_module_loaders {
             let res = consumer(loader);
             if res.is_some() {
@@ -611,6 +983,11 @@ impl QuickJsRuntimeAdapter {
         gc(self);
     }
 
+    /// set the number of bytes which may be allocated before the garbage collector is run automatically
+    pub fn set_gc_threshold(&self, bytes: usize) {
+        unsafe { q::JS_SetGCThreshold(self.runtime, bytes) }
+    }
+
     pub fn do_with<C, R>(task: C) -> R
     where
         C: FnOnce(&QuickJsRuntimeAdapter) -> R,
@@ -724,9 +1101,21 @@ impl QuickJsRuntimeAdapter {
         let realm = self.get_main_realm();
         for loader in &self.script_module_loaders {
             let i = &loader.inner;
-            if let Some(normalized) = i.normalize_path(realm, ref_path, path) {
-                let code = i.load_module(realm, normalized.as_str());
-                return Some(Script::new(normalized.as_str(), code.as_str()));
+            let normalized = match i.try_normalize_path(realm, ref_path, path) {
+                Ok(normalized) => normalized,
+                Err(e) => {
+                    log::error!("could not normalize module {}: {}", path, e);
+                    return None;
+                }
+            };
+            if let Some(normalized) = normalized {
+                return match i.try_load_module(realm, normalized.as_str()) {
+                    Ok(code) => Some(Script::new(normalized.as_str(), code.as_str())),
+                    Err(e) => {
+                        log::error!("could not load module {}: {}", normalized, e);
+                        None
+                    }
+                };
             }
         }
 
@@ -739,6 +1128,10 @@ impl Drop for QuickJsRuntimeAdapter {
         // drop contexts first, should be done when Dropping EsRuntime?
         log::trace!("drop QuickJsRuntime, dropping contexts");
 
+        // exports of native modules which failed to link still hold values of their realm
+        for named_native_module in &self.named_native_modules {
+            named_native_module.pending_exports.borrow_mut().clear();
+        }
         self.contexts.clear();
         log::trace!("drop QuickJsRuntime, after dropping contexts");
 
@@ -820,6 +1213,9 @@ pub mod tests {
 
     use crate::jsutils::modules::ScriptModuleLoader;
     use crate::jsutils::Script;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
 
     struct FooScriptModuleLoader {}
     impl ScriptModuleLoader for FooScriptModuleLoader {
@@ -852,6 +1248,179 @@ pub mod tests {
         });
     }
 
+    #[test]
+    fn test_module_cache() {
+        struct CountingModuleLoader {
+            loads: Arc<AtomicUsize>,
+        }
+        impl ScriptModuleLoader for CountingModuleLoader {
+            fn normalize_path(
+                &self,
+                _realm: &QuickJsRealmAdapter,
+                _ref_path: &str,
+                path: &str,
+            ) -> Option<String> {
+                Some(path.to_string())
+            }
+
+            fn load_module(&self, _realm: &QuickJsRealmAdapter, _absolute_path: &str) -> String {
+                self.loads.fetch_add(1, Ordering::SeqCst);
+                "export const counted = 1;".to_string()
+            }
+        }
+
+        let loads = Arc::new(AtomicUsize::new(0));
+        let rt = QuickJsRuntimeBuilder::new()
+            .script_module_loader(CountingModuleLoader {
+                loads: loads.clone(),
+            })
+            .module_cache(true)
+            .build();
+
+        let import = |realm: Option<&str>, name: &str| {
+            rt.eval_module_sync(
+                realm,
+                Script::new(
+                    name,
+                    "import {counted} from 'counted.mes';\nconsole.log(counted);",
+                ),
+            )
+            .expect("module failed");
+        };
+        import(None, "test_module_cache1.mes");
+        import(None, "test_module_cache2.mes");
+        import(Some("test_module_cache_realm"), "test_module_cache3.mes");
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        let was_cached = rt.loop_sync(|rt| {
+            assert!(rt.is_module_cached("counted.mes"));
+            rt.invalidate_cached_module("counted.mes")
+        });
+        assert!(was_cached);
+        import(Some("test_module_cache_realm2"), "test_module_cache4.mes");
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+
+        rt.loop_sync(|rt| rt.clear_module_cache());
+        import(Some("test_module_cache_realm3"), "test_module_cache5.mes");
+        assert_eq!(loads.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_module_cache_invalidation() {
+        struct MutableModuleLoader {
+            sources: Arc<Mutex<HashMap<String, String>>>,
+            failed_lookups: Arc<AtomicUsize>,
+        }
+        impl ScriptModuleLoader for MutableModuleLoader {
+            fn normalize_path(
+                &self,
+                _realm: &QuickJsRealmAdapter,
+                _ref_path: &str,
+                path: &str,
+            ) -> Option<String> {
+                if self.sources.lock().unwrap().contains_key(path) {
+                    Some(path.to_string())
+                } else {
+                    self.failed_lookups.fetch_add(1, Ordering::SeqCst);
+                    None
+                }
+            }
+
+            fn load_module(&self, _realm: &QuickJsRealmAdapter, absolute_path: &str) -> String {
+                self.sources
+                    .lock()
+                    .unwrap()
+                    .get(absolute_path)
+                    .cloned()
+                    .unwrap_or_default()
+            }
+        }
+
+        let sources = Arc::new(Mutex::new(HashMap::new()));
+        sources.lock().unwrap().insert(
+            "versioned.mes".to_string(),
+            "export const version = 1;".to_string(),
+        );
+        let failed_lookups = Arc::new(AtomicUsize::new(0));
+        let rt = QuickJsRuntimeBuilder::new()
+            .script_module_loader(MutableModuleLoader {
+                sources: sources.clone(),
+                failed_lookups: failed_lookups.clone(),
+            })
+            .module_cache(true)
+            .build();
+
+        let import_version = |realm: &str| -> i32 {
+            rt.eval_module_sync(
+                Some(realm),
+                Script::new(
+                    "test_module_cache_invalidation.mes",
+                    "import {version} from 'versioned.mes';\nglobalThis.importedVersion = version;",
+                ),
+            )
+            .expect("module failed");
+            rt.eval_sync(Some(realm), Script::new("version.js", "importedVersion;"))
+                .expect("script failed")
+                .get_i32()
+        };
+
+        assert_eq!(import_version("cache_realm1"), 1);
+        sources.lock().unwrap().insert(
+            "versioned.mes".to_string(),
+            "export const version = 2;".to_string(),
+        );
+        // the cached source is still served
+        assert_eq!(import_version("cache_realm2"), 1);
+        assert!(rt.invalidate_cached_module("versioned.mes"));
+        assert_eq!(import_version("cache_realm3"), 2);
+
+        // missing modules are remembered per realm until invalidated
+        let import_missing = |realm: &str| {
+            rt.eval_module_sync(
+                Some(realm),
+                Script::new(
+                    "test_module_cache_missing.mes",
+                    "import {late} from 'late.mes';\nglobalThis.late = late;",
+                ),
+            )
+        };
+        assert!(import_missing("cache_realm4").is_err());
+        let lookup_ct = failed_lookups.load(Ordering::SeqCst);
+        assert!(import_missing("cache_realm4").is_err());
+        assert_eq!(failed_lookups.load(Ordering::SeqCst), lookup_ct);
+        assert!(import_missing("cache_realm5").is_err());
+        assert_eq!(failed_lookups.load(Ordering::SeqCst), lookup_ct + 1);
+
+        sources
+            .lock()
+            .unwrap()
+            .insert("late.mes".to_string(), "export const late = 3;".to_string());
+        assert!(import_missing("cache_realm4").is_err());
+        assert!(rt.loop_realm_sync(Some("cache_realm4"), |_rt, realm| {
+            realm.invalidate_cached_module("late.mes")
+        }));
+        import_missing("cache_realm4").expect("module failed");
+        assert!(import_missing("cache_realm5").is_err());
+        rt.clear_module_cache(Some("cache_realm5"));
+        import_missing("cache_realm5").expect("module failed");
+
+        // clearing the module cache of a realm invalidates the modules it loaded
+        sources.lock().unwrap().insert(
+            "versioned.mes".to_string(),
+            "export const version = 3;".to_string(),
+        );
+        assert_eq!(import_version("cache_realm6"), 2);
+        rt.clear_module_cache(Some("cache_realm3"));
+        assert_eq!(import_version("cache_realm7"), 3);
+
+        sources.lock().unwrap().insert(
+            "versioned.mes".to_string(),
+            "export const version = 4;".to_string(),
+        );
+        rt.loop_sync(|rt| rt.clear_module_cache());
+        assert_eq!(import_version("cache_realm8"), 4);
+    }
+
     #[test]
     fn test_script_load() {
         log::debug!("testing1");
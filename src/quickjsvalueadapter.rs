@@ -192,7 +192,7 @@ impl QuickJsValueAdapter {
         self.is_null() || self.is_undefined()
     }
 
-    /// return true if the wrapped value represents a JS null value
+    /// return true if the wrapped value represents a JS undefined value
     pub fn is_undefined(&self) -> bool {
         unsafe { q::JS_IsUndefined(self.value) }
     }
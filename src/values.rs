@@ -1,5 +1,6 @@
 use crate::facades::QuickjsRuntimeFacadeInner;
 use crate::jsutils::{JsError, JsValueType};
+use crate::quickjs_utils::dates;
 use crate::quickjsrealmadapter::QuickJsRealmAdapter;
 use crate::quickjsvalueadapter::QuickJsValueAdapter;
 use crate::reflection::JsProxyInstanceId;
@@ -14,7 +15,7 @@ use std::fmt::{Debug, Formatter};
 use std::pin::Pin;
 use std::sync::atomic::{AtomicU16, Ordering};
 use std::sync::{Arc, Weak};
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use string_cache::DefaultAtom;
 
 pub struct CachedJsObjectRef {
@@ -456,6 +457,10 @@ pub enum JsValueFacade {
     SerdeValue {
         value: serde_json::Value,
     },
+    // a Date, millis is NaN for an Invalid Date
+    Date {
+        millis: f64,
+    },
     Null,
     Undefined,
 }
@@ -512,6 +517,16 @@ impl JsValueFacade {
             func: Arc::new(Box::new(function)),
         }
     }
+    /// create a new Date from a timestamp (milliseconds since the epoch)
+    pub fn new_date(millis: f64) -> Self {
+        Self::Date { millis }
+    }
+    /// create a new Date from a SystemTime, anything below millisecond precision is truncated
+    pub fn new_date_from_system_time(time: SystemTime) -> Self {
+        Self::Date {
+            millis: dates::system_time_to_millis(time),
+        }
+    }
     /// create a new promise with a producer which will run async in a threadpool
     pub fn new_promise<R, P, M>(producer: P) -> Self
     where
@@ -543,6 +558,9 @@ impl JsValueFacade {
     pub fn is_js_array(&self) -> bool {
         matches!(self, JsValueFacade::JsArray { .. })
     }
+    pub fn is_date(&self) -> bool {
+        matches!(self, JsValueFacade::Date { .. })
+    }
 
     pub fn get_i32(&self) -> i32 {
         match self {
@@ -568,6 +586,26 @@ impl JsValueFacade {
             }
         }
     }
+    /// get the timestamp of a Date, NaN for an Invalid Date
+    pub fn get_date_millis(&self) -> f64 {
+        match self {
+            JsValueFacade::Date { millis } => *millis,
+            _ => {
+                panic!("Not a Date");
+            }
+        }
+    }
+    /// get a Date as SystemTime, None for an Invalid Date
+    pub fn get_date_system_time(&self) -> Option<SystemTime> {
+        let millis = self.get_date_millis();
+        if millis.is_nan() {
+            None
+        } else if millis < 0f64 {
+            UNIX_EPOCH.checked_sub(Duration::from_millis(-millis as u64))
+        } else {
+            UNIX_EPOCH.checked_add(Duration::from_millis(millis as u64))
+        }
+    }
     pub fn get_str(&self) -> &str {
         match self {
             JsValueFacade::String { val } => val,
@@ -607,6 +645,7 @@ impl JsValueFacade {
             JsValueFacade::ProxyInstance { .. } => JsValueType::Object,
             JsValueFacade::TypedArray { .. } => JsValueType::Object,
             JsValueFacade::JsonStr { .. } => JsValueType::Object,
+            JsValueFacade::Date { .. } => JsValueType::Date,
             JsValueFacade::SerdeValue { value } => match value {
                 serde_json::Value::Null => JsValueType::Null,
                 serde_json::Value::Bool(_) => JsValueType::Boolean,
@@ -689,6 +728,7 @@ impl JsValueFacade {
             JsValueFacade::TypedArray { .. } => "TypedArray".to_string(),
             JsValueFacade::JsonStr { json } => format!("JsonStr: '{json}'"),
             JsValueFacade::SerdeValue { value } => format!("Serde value: {value}"),
+            JsValueFacade::Date { millis } => format!("Date: {millis}"),
         }
     }
     pub async fn to_serde_value(&self) -> Result<serde_json::Value, JsError> {
@@ -712,6 +752,9 @@ impl JsValueFacade {
             JsValueFacade::TypedArray { .. } => Ok(Value::Null),
             JsValueFacade::JsonStr { json } => Ok(serde_json::from_str(json).unwrap()),
             JsValueFacade::SerdeValue { value } => Ok(value.clone()),
+            JsValueFacade::Date { millis } => Ok(dates::millis_to_iso_string(*millis)
+                .map(Value::from)
+                .unwrap_or(Value::Null)),
         }
     }
     pub async fn to_json_string(&self) -> Result<String, JsError> {
@@ -735,6 +778,9 @@ impl JsValueFacade {
             JsValueFacade::TypedArray { .. } => Ok("[]".to_string()),
             JsValueFacade::JsonStr { json } => Ok(json.clone()),
             JsValueFacade::SerdeValue { value } => Ok(serde_json::to_string(value).unwrap()),
+            JsValueFacade::Date { millis } => Ok(dates::millis_to_iso_string(*millis)
+                .map(|iso| format!("'{iso}'"))
+                .unwrap_or_else(|| "null".to_string())),
         }
     }
 }
@@ -785,6 +831,12 @@ impl JsValueConvertable for String {
     }
 }
 
+impl JsValueConvertable for SystemTime {
+    fn to_js_value_facade(self) -> JsValueFacade {
+        JsValueFacade::new_date_from_system_time(self)
+    }
+}
+
 impl JsValueConvertable for Vec<u8> {
     fn to_js_value_facade(self) -> JsValueFacade {
         JsValueFacade::TypedArray {